@@ -1,285 +1,12133 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::address_lookup_table;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
 use anchor_lang::solana_program::clock::Clock;
+use anchor_lang::solana_program::compute_units::sol_remaining_compute_units;
+use anchor_lang::solana_program::epoch_schedule::EpochSchedule;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::keccak;
+use anchor_lang::solana_program::program::{invoke, invoke_signed};
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::solana_program::sysvar::instructions::{
+    self as instructions_sysvar, load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::system_program::{self, CreateAccount, Transfer};
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{
+    self, Burn, CloseAccount, InitializeAccount3, Mint, MintTo, TokenAccount, TokenInterface,
+    TransferChecked,
+};
 
 declare_id!("Dq38DoFThxyXXrgz57DNvL8iCAgQyKwJ88fNGKWZpGzY");
 
+/// Squads v4 multisig program, the target of `propose_squads_action` payloads.
+pub const SQUADS_PROGRAM_ID: Pubkey = pubkey!("SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf");
+
 #[program]
 pub mod agora_governor {
     use super::*;
 
+    /// Sets up a governor with `proposal_types` as its initial types, so proposals can
+    /// be created straight away; more can be added with `add_proposal_type`.
     pub fn initialize(
         ctx: Context<Initialize>,
         voting_delay: u64,
         voting_period: u64,
         proposal_threshold: u64,
+        timelock_delay: u64,
+        proposal_types: Vec<ProposalType>,
     ) -> Result<()> {
         let governor = &mut ctx.accounts.governor;
         governor.admin = *ctx.accounts.admin.key;
         governor.manager = *ctx.accounts.manager.key;
+        governor.guardian = *ctx.accounts.guardian.key;
+        governor.governing_mint = ctx.accounts.governing_mint.key();
+        governor.total_supply = ctx.accounts.governing_mint.supply;
         governor.voting_delay = voting_delay;
         governor.voting_period = voting_period;
         governor.proposal_threshold = proposal_threshold;
+        governor.timelock_delay = timelock_delay;
+        governor.authority_bump = ctx.bumps.governor_authority;
+        governor.treasury_bump = ctx.bumps.treasury;
+        governor.apply_defaults();
         governor.proposal_count = 0;
+        require!(
+            proposal_types.len() <= Governor::MAX_PROPOSAL_TYPES,
+            GovernorError::TooManyProposalTypes
+        );
+        for proposal_type in &proposal_types {
+            proposal_type.validate(governor.risk_tier_multipliers.len())?;
+        }
+        governor.active_proposal_counts = vec![0; proposal_types.len()];
+        governor.proposal_types = proposal_types;
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn create_proposal(
         ctx: Context<CreateProposal>,
         description: String,
         proposal_type: u8,
+        actions: Vec<ProposalAction>,
+        supersedes: Option<u64>,
+        depends_on: Option<u64>,
+        instructions: Vec<ProposalInstruction>,
+        quorum_override: Option<u16>,
     ) -> Result<()> {
-        let governor = &mut ctx.accounts.governor;
-        let proposal = &mut ctx.accounts.proposal;
-        let clock = Clock::get()?;
+        require!(!ctx.accounts.governor.hashed_proposal_ids, GovernorError::ProposalIdModeMismatch);
+        ctx.accounts.governor.check_quorum_override(proposal_type, quorum_override)?;
+        ctx.accounts.proposal.quorum_override = quorum_override;
+        let accounts = ProposalCreation {
+            proposal_info: ctx.accounts.proposal.to_account_info(),
+            governor: &mut ctx.accounts.governor,
+            proposal: &mut ctx.accounts.proposal,
+            proposal_id: None,
+            proposal_bump: ctx.bumps.proposal,
+            proposer: &ctx.accounts.proposer,
+            proposer_balance: ctx.accounts.proposer_token_account.amount,
+            proposer_delegation: &ctx.accounts.proposer_delegation,
+            proposer_checkpoints: ctx.accounts.proposer_checkpoints.as_deref(),
+            superseded_proposal: ctx.accounts.superseded_proposal.as_deref(),
+            proposal_digest: &ctx.accounts.proposal_digest,
+            treasury: &ctx.accounts.treasury,
+            system_program: &ctx.accounts.system_program,
+            delegations: ctx.remaining_accounts,
+            treasury_vaults: TreasuryRegistry::vaults_of(&ctx.accounts.treasury_registry),
+        };
+        open_proposal(accounts, description, proposal_type, actions, supersedes, depends_on, instructions)
+    }
 
+    /// `create_proposal` for governors with `hashed_proposal_ids`: the proposal's id
+    /// is `Proposal::hashed_id` of its content rather than the next counter value, so
+    /// clients can derive it up front and the same content can't be proposed twice.
+    /// Ids order nothing, so hashed proposals neither supersede nor depend on others.
+    pub fn create_hashed_proposal(
+        ctx: Context<CreateHashedProposal>,
+        proposal_id: u64,
+        description: String,
+        proposal_type: u8,
+        actions: Vec<ProposalAction>,
+        instructions: Vec<ProposalInstruction>,
+        quorum_override: Option<u16>,
+    ) -> Result<()> {
+        let governor_key = ctx.accounts.governor.key();
+        require!(ctx.accounts.governor.hashed_proposal_ids, GovernorError::ProposalIdModeMismatch);
+        ctx.accounts.governor.check_quorum_override(proposal_type, quorum_override)?;
+
+        let proposal_info = ctx.accounts.proposal.to_account_info();
+        let space = 8 + Proposal::LEN + Proposal::payload_len(&instructions);
+        let id = proposal_id.to_le_bytes();
+        let proposal_seeds: &[&[u8]] = &[b"proposal", governor_key.as_ref(), &id, &[ctx.bumps.proposal]];
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                CreateAccount { from: ctx.accounts.proposer.to_account_info(), to: proposal_info.clone() },
+                &[proposal_seeds],
+            ),
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            &crate::ID,
+        )?;
+        let mut proposal = Box::new(Proposal::try_deserialize_unchecked(&mut &proposal_info.try_borrow_data()?[..])?);
+        proposal.quorum_override = quorum_override;
+
+        let accounts = ProposalCreation {
+            proposal_info: proposal_info.clone(),
+            governor: &mut ctx.accounts.governor,
+            proposal: &mut proposal,
+            proposal_id: Some(proposal_id),
+            proposal_bump: ctx.bumps.proposal,
+            proposer: &ctx.accounts.proposer,
+            proposer_balance: ctx.accounts.proposer_token_account.amount,
+            proposer_delegation: &ctx.accounts.proposer_delegation,
+            proposer_checkpoints: ctx.accounts.proposer_checkpoints.as_deref(),
+            superseded_proposal: None,
+            proposal_digest: &ctx.accounts.proposal_digest,
+            treasury: &ctx.accounts.treasury,
+            system_program: &ctx.accounts.system_program,
+            delegations: ctx.remaining_accounts,
+            treasury_vaults: TreasuryRegistry::vaults_of(&ctx.accounts.treasury_registry),
+        };
+        open_proposal(accounts, description, proposal_type, actions, None, None, instructions)?;
         require!(
-            governor.get_votes(&ctx.accounts.proposer.key(), clock.slot) >= governor.proposal_threshold
-                || ctx.accounts.proposer.key() == &governor.manager,
-            GovernorError::InsufficientProposerVotes
+            proposal_id
+                == Proposal::hashed_id(&governor_key, proposal_type, &proposal.content_hash, &proposal.compute_payload_hash()?),
+            GovernorError::InvalidProposalId
+        );
+        proposal.try_serialize(&mut &mut proposal_info.try_borrow_mut_data()?[..])?;
+        Ok(())
+    }
+
+    /// Creates a proposal whose payload upgrades `program` from `buffer` through the
+    /// upgradeable loader, refunding the buffer's lamports to `spill`. The payload is
+    /// built on-chain so it cannot be mis-encoded. Both the program's upgrade authority
+    /// and the buffer's authority must be the governor authority PDA.
+    pub fn propose_program_upgrade(
+        ctx: Context<ProposeProgramUpgrade>,
+        description: String,
+        proposal_type: u8,
+        program: Pubkey,
+        spill: Pubkey,
+    ) -> Result<()> {
+        require!(!ctx.accounts.governor.hashed_proposal_ids, GovernorError::ProposalIdModeMismatch);
+        let governor_key = ctx.accounts.governor.key();
+        let authority = Pubkey::create_program_address(
+            &ctx.accounts.governor.authority_seeds(&governor_key),
+            &crate::ID,
+        )
+        .map_err(|_| GovernorError::InvalidUpgradeBuffer)?;
+        require!(
+            buffer_authority(&ctx.accounts.buffer.try_borrow_data()?) == Some(authority),
+            GovernorError::InvalidUpgradeBuffer
+        );
+        let upgrade = ProposalInstruction::from_instruction(
+            0,
+            bpf_loader_upgradeable::upgrade(&program, &ctx.accounts.buffer.key(), &authority, &spill),
+        );
+
+        let accounts = ProposalCreation {
+            proposal_info: ctx.accounts.proposal.to_account_info(),
+            governor: &mut ctx.accounts.governor,
+            proposal: &mut ctx.accounts.proposal,
+            proposal_id: None,
+            proposal_bump: ctx.bumps.proposal,
+            proposer: &ctx.accounts.proposer,
+            proposer_balance: ctx.accounts.proposer_token_account.amount,
+            proposer_delegation: &ctx.accounts.proposer_delegation,
+            proposer_checkpoints: ctx.accounts.proposer_checkpoints.as_deref(),
+            superseded_proposal: None,
+            proposal_digest: &ctx.accounts.proposal_digest,
+            treasury: &ctx.accounts.treasury,
+            system_program: &ctx.accounts.system_program,
+            delegations: ctx.remaining_accounts,
+            treasury_vaults: &[],
+        };
+        open_proposal(accounts, description, proposal_type, vec![], None, None, vec![upgrade])
+    }
+
+    /// Creates a proposal whose payload creates a vault transaction carrying
+    /// `transaction_message` on a Squads v4 `multisig`, then proposes and approves it.
+    /// The governor authority acts as an ordinary member: it must hold the Initiate
+    /// and Vote permissions, and the treasury pays the Squads accounts' rent. The
+    /// transaction index is the multisig's next one at creation, so another member's
+    /// transaction landing first makes the payload fail.
+    pub fn propose_squads_action(
+        ctx: Context<ProposeSquadsAction>,
+        description: String,
+        proposal_type: u8,
+        vault_index: u8,
+        transaction_message: Vec<u8>,
+    ) -> Result<()> {
+        require!(!ctx.accounts.governor.hashed_proposal_ids, GovernorError::ProposalIdModeMismatch);
+        let governor_key = ctx.accounts.governor.key();
+        let authority = Pubkey::create_program_address(
+            &ctx.accounts.governor.authority_seeds(&governor_key),
+            &crate::ID,
+        )
+        .map_err(|_| GovernorError::InvalidSquadsMultisig)?;
+        let (transaction_index, permissions) =
+            squads_multisig_state(&ctx.accounts.multisig.try_borrow_data()?, &authority)
+                .ok_or(GovernorError::InvalidSquadsMultisig)?;
+        require!(
+            permissions & SQUADS_INITIATE_VOTE == SQUADS_INITIATE_VOTE,
+            GovernorError::NotSquadsMember
         );
+        let instructions = squads_action_instructions(
+            &ctx.accounts.multisig.key(),
+            transaction_index + 1,
+            &authority,
+            &ctx.accounts.treasury.key(),
+            vault_index,
+            transaction_message,
+        )
+        .into_iter()
+        .enumerate()
+        .map(|(index, instruction)| ProposalInstruction::from_instruction(index as u16, instruction))
+        .collect();
 
-        let proposal_type_info = governor.proposal_types.get(&proposal_type).ok_or(GovernorError::InvalidProposalType)?;
+        let accounts = ProposalCreation {
+            proposal_info: ctx.accounts.proposal.to_account_info(),
+            governor: &mut ctx.accounts.governor,
+            proposal: &mut ctx.accounts.proposal,
+            proposal_id: None,
+            proposal_bump: ctx.bumps.proposal,
+            proposer: &ctx.accounts.proposer,
+            proposer_balance: ctx.accounts.proposer_token_account.amount,
+            proposer_delegation: &ctx.accounts.proposer_delegation,
+            proposer_checkpoints: ctx.accounts.proposer_checkpoints.as_deref(),
+            superseded_proposal: None,
+            proposal_digest: &ctx.accounts.proposal_digest,
+            treasury: &ctx.accounts.treasury,
+            system_program: &ctx.accounts.system_program,
+            delegations: ctx.remaining_accounts,
+            treasury_vaults: &[],
+        };
+        open_proposal(accounts, description, proposal_type, vec![], None, None, instructions)
+    }
+
+    /// Creates a proposal in the Draft state. Its content stays editable by the
+    /// proposer until `activate_proposal` freezes it and starts the schedule.
+    pub fn create_proposal_draft(
+        ctx: Context<CreateProposalDraft>,
+        description: String,
+        proposal_type: u8,
+        actions: Vec<ProposalAction>,
+        supersedes: Option<u64>,
+        depends_on: Option<u64>,
+    ) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
+        require!(!governor.hashed_proposal_ids, GovernorError::ProposalIdModeMismatch);
+        governor.validate_new_proposal(proposal_type, &actions)?;
+        proposal.fee_paid = charge_proposal_fee(
+            governor,
+            &ctx.accounts.proposer,
+            &ctx.accounts.treasury,
+            &ctx.accounts.system_program,
+        )?;
 
         proposal.id = governor.proposal_count;
+        proposal.governor = governor.key();
+        proposal.bump = ctx.bumps.proposal;
         proposal.proposer = *ctx.accounts.proposer.key;
+        Proposal::validate_description(&description)?;
         proposal.description = description;
         proposal.proposal_type = proposal_type;
-        proposal.start_block = clock.slot + governor.voting_delay;
-        proposal.end_block = proposal.start_block + governor.voting_period;
+        proposal.actions = actions;
+        proposal.set_supersedes(supersedes, ctx.accounts.superseded_proposal.as_deref())?;
+        proposal.set_depends_on(depends_on)?;
+        proposal.state = ProposalState::Draft;
+        proposal.created_slot = clock.slot;
+        proposal.start_block = 0;
+        proposal.end_block = 0;
+        proposal.eta = 0;
+        proposal.outcome = None;
         proposal.executed = false;
         proposal.canceled = false;
 
+        governor.hold_proposal_type(proposal.proposal_type);
         governor.proposal_count += 1;
 
+        Ok(())
+    }
+
+    pub fn update_proposal_draft(
+        ctx: Context<UpdateProposalDraft>,
+        _proposal_id: u64,
+        description: String,
+        proposal_type: u8,
+        actions: Vec<ProposalAction>,
+    ) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+
+        require!(proposal.state == ProposalState::Draft, GovernorError::ProposalNotDraft);
+        governor.validate_new_proposal(proposal_type, &actions)?;
+
+        Proposal::validate_description(&description)?;
+        proposal.description = description;
+        governor.release_proposal_type(proposal.proposal_type);
+        governor.hold_proposal_type(proposal_type);
+        proposal.proposal_type = proposal_type;
+        proposal.actions = actions;
+
+        Ok(())
+    }
+
+    /// Appends one instruction to a draft's executable payload, growing the proposal
+    /// account to fit. Indices must be contiguous starting from zero, so a payload too
+    /// large for one transaction can be uploaded over several.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_proposal_instruction(
+        ctx: Context<AddProposalInstruction>,
+        _proposal_id: u64,
+        ix_index: u16,
+        program_id: Pubkey,
+        accounts: Vec<AccountMetaData>,
+        data: Vec<u8>,
+        self_call: bool,
+        hold_up_slots: u64,
+        kind: InstructionKind,
+    ) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+
+        require!(proposal.state == ProposalState::Draft, GovernorError::ProposalNotDraft);
+        require!(proposal.external_actions == 0, GovernorError::ExternalActionsWithPayload);
+        let proposal_type_info = proposal.type_info(&governor.proposal_types)?;
+        require!(proposal_type_info.binding, GovernorError::NonBindingProposal);
+
+        let next_index = proposal.instructions.len();
+        require!(ix_index as usize >= next_index, GovernorError::DuplicateInstructionIndex);
+        require!(ix_index as usize == next_index, GovernorError::InstructionIndexOutOfOrder);
+        require!(next_index < Proposal::MAX_INSTRUCTIONS, GovernorError::TooManyInstructions);
+
+        let payload_bytes = proposal.payload_bytes as usize
+            + ProposalInstruction::serialized_len(accounts.len(), data.len());
+        require!(payload_bytes <= Proposal::MAX_PAYLOAD_BYTES, GovernorError::PayloadTooLarge);
+        proposal.payload_bytes = payload_bytes as u32;
+
+        proposal.instructions.push(ProposalInstruction {
+            index: ix_index,
+            program_id,
+            accounts,
+            data,
+            self_call,
+            hold_up_slots,
+            kind,
+        });
+
+        Ok(())
+    }
+
+    /// Removes an instruction from a draft's payload. The list is compacted: later
+    /// instructions shift down one index so indices stay contiguous from zero.
+    pub fn remove_proposal_instruction(
+        ctx: Context<RemoveProposalInstruction>,
+        _proposal_id: u64,
+        ix_index: u16,
+    ) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+
+        require!(proposal.state == ProposalState::Draft, GovernorError::ProposalNotDraft);
+        require!(
+            (ix_index as usize) < proposal.instructions.len(),
+            GovernorError::InvalidInstructionIndex
+        );
+
+        let removed = proposal.instructions.remove(ix_index as usize);
+        proposal.payload_bytes -= removed.size() as u32;
+        for (index, instruction) in proposal.instructions.iter_mut().enumerate().skip(ix_index as usize) {
+            instruction.index = index as u16;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces an instruction of a draft's payload in place, keeping its index.
+    #[allow(clippy::too_many_arguments)]
+    pub fn replace_proposal_instruction(
+        ctx: Context<ReplaceProposalInstruction>,
+        _proposal_id: u64,
+        ix_index: u16,
+        program_id: Pubkey,
+        accounts: Vec<AccountMetaData>,
+        data: Vec<u8>,
+        self_call: bool,
+        hold_up_slots: u64,
+        kind: InstructionKind,
+    ) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+
+        require!(proposal.state == ProposalState::Draft, GovernorError::ProposalNotDraft);
+        let replaced_size = proposal
+            .instructions
+            .get(ix_index as usize)
+            .ok_or(GovernorError::InvalidInstructionIndex)?
+            .size();
+
+        let payload_bytes = proposal.payload_bytes as usize - replaced_size
+            + ProposalInstruction::serialized_len(accounts.len(), data.len());
+        require!(payload_bytes <= Proposal::MAX_PAYLOAD_BYTES, GovernorError::PayloadTooLarge);
+
+        proposal.instructions[ix_index as usize] = ProposalInstruction {
+            index: ix_index,
+            program_id,
+            accounts,
+            data,
+            self_call,
+            hold_up_slots,
+            kind,
+        };
+        proposal.payload_bytes = payload_bytes as u32;
+
+        Ok(())
+    }
+
+    /// Creates the zero-copy `ProposalActions` account of a draft, for more actions
+    /// than fit in `Proposal::actions`. Proposer only.
+    pub fn create_proposal_actions(ctx: Context<CreateProposalActions>, _proposal_id: u64) -> Result<()> {
+        require!(ctx.accounts.proposal.state == ProposalState::Draft, GovernorError::ProposalNotDraft);
+
+        let mut proposal_actions = ctx.accounts.proposal_actions.load_init()?;
+        proposal_actions.proposal = ctx.accounts.proposal.key();
+        proposal_actions.bump = ctx.bumps.proposal_actions;
+
+        Ok(())
+    }
+
+    /// Appends `actions` to a draft's `ProposalActions` account. They run after the
+    /// proposal's own actions and are frozen once the draft is activated. A draft
+    /// keeps its actions in one place or carries a payload, not both. Proposer only.
+    pub fn append_proposal_actions(
+        ctx: Context<AppendProposalActions>,
+        _proposal_id: u64,
+        actions: Vec<ProposalAction>,
+    ) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+
+        require!(proposal.state == ProposalState::Draft, GovernorError::ProposalNotDraft);
+        require!(proposal.instructions.is_empty(), GovernorError::ExternalActionsWithPayload);
+        let proposal_type_info = proposal.type_info(&governor.proposal_types)?;
+        require!(proposal_type_info.binding, GovernorError::NonBindingProposal);
+
+        let mut proposal_actions = ctx.accounts.proposal_actions.load_mut()?;
+        proposal_actions.append(&actions)?;
+        proposal.external_actions = proposal_actions.len;
+
+        Ok(())
+    }
+
+    /// Freezes a draft's content, checks the proposer threshold and starts the
+    /// proposal's lifecycle from the current slot.
+    pub fn activate_proposal(ctx: Context<ActivateProposal>, _proposal_id: u64) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
+        require!(proposal.state == ProposalState::Draft, GovernorError::ProposalNotDraft);
+        governor.check_programs_allowed(proposal.proposal_type, &proposal.instructions)?;
+        governor.check_spending_caps(
+            &governor.key(),
+            proposal.proposal_type,
+            &proposal.instructions,
+            TreasuryRegistry::vaults_of(&ctx.accounts.treasury_registry),
+        )?;
+        governor.check_blackout(&proposal.proposer, clock.slot)?;
+        governor.check_proposer_threshold(
+            &governor.key(),
+            &proposal.proposer,
+            proposal.proposal_type,
+            ctx.accounts.proposer_token_account.amount,
+            &ctx.accounts.proposer_delegation,
+            ctx.remaining_accounts,
+            clock.slot,
+            ctx.accounts.proposer_checkpoints.as_deref(),
+        )?;
+
+        proposal.activate(governor, clock.slot)?;
+        governor.check_resubmission_cooldown(
+            &governor.key(),
+            &proposal.content_hash,
+            &ctx.accounts.proposal_digest,
+            clock.slot,
+        )?;
+        // Taken only now: reallocating a draft would refund lamports above its rent.
+        proposal.deposit = take_proposal_deposit(
+            governor,
+            &ctx.accounts.proposer,
+            &proposal.to_account_info(),
+            &ctx.accounts.system_program,
+        )?;
+
         emit!(ProposalCreated {
             proposal_id: proposal.id,
             proposer: proposal.proposer,
             start_block: proposal.start_block,
             end_block: proposal.end_block,
             description: proposal.description.clone(),
-            proposal_type,
+            proposal_type: proposal.proposal_type,
+            supersedes: proposal.supersedes,
+            depends_on: proposal.depends_on,
+            payload_hash: proposal.compute_payload_hash()?,
+            fee_paid: proposal.fee_paid,
         });
+        emit_compat_proposal(governor, proposal, clock.unix_timestamp)?;
 
         Ok(())
     }
 
-    pub fn cast_vote(
-        ctx: Context<CastVote>,
-        proposal_id: u64,
-        support: bool,
+    /// Closes an abandoned draft, and its `ProposalActions` account if passed, and
+    /// returns their rent to the proposer.
+    pub fn close_proposal_draft(ctx: Context<CloseProposalDraft>, _proposal_id: u64) -> Result<()> {
+        require!(
+            ctx.accounts.proposal.state == ProposalState::Draft,
+            GovernorError::ProposalNotDraft
+        );
+        require!(
+            ctx.accounts.proposal.external_actions == 0 || ctx.accounts.proposal_actions.is_some(),
+            GovernorError::ProposalActionsRequired
+        );
+        ctx.accounts.governor.release_proposal_type(ctx.accounts.proposal.proposal_type);
+        Ok(())
+    }
+
+    /// Creates a proposal in the Sponsoring state without a threshold check. It gets
+    /// voting timing only once sponsors have pooled enough weight to activate it.
+    pub fn draft_proposal(
+        ctx: Context<CreateProposal>,
+        description: String,
+        proposal_type: u8,
+        actions: Vec<ProposalAction>,
+        supersedes: Option<u64>,
+        depends_on: Option<u64>,
+        instructions: Vec<ProposalInstruction>,
     ) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
+        require!(!governor.hashed_proposal_ids, GovernorError::ProposalIdModeMismatch);
+        governor.validate_new_proposal(proposal_type, &actions)?;
+        governor.check_programs_allowed(proposal_type, &instructions)?;
+        governor.check_spending_caps(
+            &governor.key(),
+            proposal_type,
+            &instructions,
+            TreasuryRegistry::vaults_of(&ctx.accounts.treasury_registry),
+        )?;
+        proposal.fee_paid = charge_proposal_fee(
+            governor,
+            &ctx.accounts.proposer,
+            &ctx.accounts.treasury,
+            &ctx.accounts.system_program,
+        )?;
+        proposal.deposit = take_proposal_deposit(
+            governor,
+            &ctx.accounts.proposer,
+            &proposal.to_account_info(),
+            &ctx.accounts.system_program,
+        )?;
+
+        proposal.id = governor.proposal_count;
+        proposal.governor = governor.key();
+        proposal.bump = ctx.bumps.proposal;
+        proposal.proposer = *ctx.accounts.proposer.key;
+        Proposal::validate_description(&description)?;
+        proposal.description = description;
+        proposal.proposal_type = proposal_type;
+        proposal.actions = actions;
+        proposal.set_supersedes(supersedes, ctx.accounts.superseded_proposal.as_deref())?;
+        proposal.set_depends_on(depends_on)?;
+        proposal.payload_bytes = Proposal::validate_payload(&instructions)? as u32;
+        proposal.instructions = instructions;
+        proposal.state = ProposalState::Sponsoring;
+        proposal.created_slot = clock.slot;
+        proposal.sponsor_weight = governor.get_votes(
+            &governor.key(),
+            &proposal.proposer,
+            ctx.accounts.proposer_token_account.amount,
+            &ctx.accounts.proposer_delegation,
+            ctx.remaining_accounts,
+        )?;
+        proposal.start_block = 0;
+        proposal.end_block = 0;
+        proposal.eta = 0;
+        proposal.outcome = None;
+        proposal.executed = false;
+        proposal.canceled = false;
+
+        governor.hold_proposal_type(proposal.proposal_type);
+        governor.proposal_count += 1;
+
+        emit!(ProposalDrafted {
+            governor: governor.key(),
+            proposal_id: proposal.id,
+            proposer: proposal.proposer,
+            sponsorship_deadline: clock.slot + governor.sponsorship_window,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Adds the sponsor's weight, snapshotted at the draft's creation slot, to a
+    /// Sponsoring proposal and lists the sponsor on it; see `Proposal::add_sponsor`.
+    /// The receipt PDA also stops a wallet from sponsoring twice.
+    pub fn sponsor_proposal(ctx: Context<SponsorProposal>, _proposal_id: u64) -> Result<()> {
         let governor = &ctx.accounts.governor;
         let proposal = &mut ctx.accounts.proposal;
-        let vote = &mut ctx.accounts.vote;
+        let sponsor = ctx.accounts.sponsor.key();
         let clock = Clock::get()?;
 
+        require!(proposal.state == ProposalState::Sponsoring, GovernorError::ProposalNotSponsoring);
         require!(
-            clock.slot >= proposal.start_block && clock.slot <= proposal.end_block,
-            GovernorError::VotingPeriodInactive
+            clock.slot <= proposal.created_slot + governor.sponsorship_window,
+            GovernorError::SponsorshipWindowClosed
         );
+        let weight = governor.get_votes(
+            &governor.key(),
+            &sponsor,
+            ctx.accounts.sponsor_token_account.amount,
+            &ctx.accounts.sponsor_delegation,
+            ctx.remaining_accounts,
+        )?;
+        proposal.add_sponsor(sponsor, weight)?;
 
-        let voter_weight = governor.get_votes(&ctx.accounts.voter.key(), proposal.start_block);
-
-        vote.voter = *ctx.accounts.voter.key;
-        vote.proposal_id = proposal_id;
-        vote.support = support;
-        vote.weight = voter_weight;
-
-        if support {
-            proposal.for_votes += voter_weight;
-        } else {
-            proposal.against_votes += voter_weight;
-        }
+        let receipt = &mut ctx.accounts.sponsor_receipt;
+        receipt.proposal = proposal.key();
+        receipt.sponsor = sponsor;
+        receipt.weight = weight;
+        receipt.bump = ctx.bumps.sponsor_receipt;
 
-        emit!(VoteCast {
-            voter: vote.voter,
-            proposal_id,
-            support,
-            weight: voter_weight,
+        emit!(ProposalSponsored {
+            governor: governor.key(),
+            proposal_id: proposal.id,
+            sponsor,
+            weight,
+            total_sponsor_weight: proposal.sponsor_weight,
+            unix_timestamp: clock.unix_timestamp,
         });
 
         Ok(())
     }
 
-    pub fn execute_proposal(ctx: Context<ExecuteProposal>, proposal_id: u64) -> Result<()> {
+    /// Starts the voting schedule of a sponsored proposal once its pooled weight meets
+    /// the proposal threshold. Permissionless.
+    pub fn activate_sponsored_proposal(
+        ctx: Context<ActivateSponsoredProposal>,
+        _proposal_id: u64,
+    ) -> Result<()> {
         let governor = &ctx.accounts.governor;
         let proposal = &mut ctx.accounts.proposal;
         let clock = Clock::get()?;
 
-        require!(!proposal.executed, GovernorError::ProposalAlreadyExecuted);
-        require!(!proposal.canceled, GovernorError::ProposalCanceled);
-        require!(clock.slot > proposal.end_block, GovernorError::VotingPeriodActive);
+        require!(proposal.state == ProposalState::Sponsoring, GovernorError::ProposalNotSponsoring);
+        governor.check_blackout(&proposal.proposer, clock.slot)?;
+        let threshold = governor.proposal_threshold_for(proposal.proposal_type)?;
+        require!(proposal.sponsor_weight >= threshold, GovernorError::InsufficientProposerVotes);
+
+        proposal.activate(governor, clock.slot)?;
+        governor.check_resubmission_cooldown(
+            &governor.key(),
+            &proposal.content_hash,
+            &ctx.accounts.proposal_digest,
+            clock.slot,
+        )?;
 
-        let proposal_type_info = governor.proposal_types.get(&proposal.proposal_type).unwrap();
-        let quorum = (governor.total_supply * proposal_type_info.quorum as u64) / 10_000;
-        let approval_threshold = (proposal.for_votes * 10_000) / (proposal.for_votes + proposal.against_votes);
+        emit!(ProposalCreated {
+            proposal_id: proposal.id,
+            proposer: proposal.proposer,
+            start_block: proposal.start_block,
+            end_block: proposal.end_block,
+            description: proposal.description.clone(),
+            proposal_type: proposal.proposal_type,
+            supersedes: proposal.supersedes,
+            depends_on: proposal.depends_on,
+            payload_hash: proposal.compute_payload_hash()?,
+            fee_paid: proposal.fee_paid,
+        });
+        emit!(DraftPromoted {
+            governor: governor.key(),
+            proposal_id: proposal.id,
+            proposer: proposal.proposer,
+            sponsor_weight: proposal.sponsor_weight,
+            sponsors: proposal.sponsors.clone(),
+            threshold,
+            unix_timestamp: clock.unix_timestamp,
+        });
+        emit_compat_proposal(governor, proposal, clock.unix_timestamp)?;
+
+        Ok(())
+    }
+
+    /// Closes a draft that failed to gather enough sponsorship before its window
+    /// ended, returning rent to the proposer. Permissionless.
+    pub fn close_expired_draft(ctx: Context<CloseExpiredDraft>, _proposal_id: u64) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let proposal = &ctx.accounts.proposal;
+        let clock = Clock::get()?;
 
+        require!(proposal.state == ProposalState::Sponsoring, GovernorError::ProposalNotSponsoring);
         require!(
-            proposal.for_votes + proposal.against_votes >= quorum,
-            GovernorError::QuorumNotReached
+            clock.slot > proposal.created_slot + governor.sponsorship_window
+                && proposal.sponsor_weight < governor.proposal_threshold_for(proposal.proposal_type)?,
+            GovernorError::DraftNotExpired
         );
+        governor.release_proposal_type(proposal.proposal_type);
+
+        Ok(())
+    }
+
+    /// Replaces the description of a Pending proposal before voting starts, e.g. to fix
+    /// a typo. `new_content_hash` must match the recomputed content hash, so the
+    /// proposer commits to exactly what voters will see.
+    pub fn edit_proposal(
+        ctx: Context<EditProposal>,
+        _proposal_id: u64,
+        new_description: String,
+        new_content_hash: [u8; 32],
+    ) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
         require!(
-            approval_threshold >= proposal_type_info.approval_threshold as u64,
-            GovernorError::ApprovalThresholdNotMet
+            proposal.state == ProposalState::Pending && clock.slot < proposal.start_block,
+            GovernorError::ProposalNotEditable
         );
+        require!(
+            (proposal.edit_count as u64) < governor.max_proposal_edits,
+            GovernorError::TooManyEdits
+        );
+        Proposal::validate_description(&new_description)?;
 
-        // TODO: Execute proposal logic here
-        // This would typically involve calling other instructions or programs
-
-        proposal.executed = true;
+        proposal.description = new_description;
+        proposal.content_hash = proposal.compute_content_hash()?;
+        require!(proposal.content_hash == new_content_hash, GovernorError::ContentHashMismatch);
+        proposal.edit_count += 1;
 
-        emit!(ProposalExecuted { proposal_id });
+        emit!(ProposalEdited {
+            governor: governor.key(),
+            proposal_id: proposal.id,
+            content_hash: proposal.content_hash,
+            edit_count: proposal.edit_count,
+            unix_timestamp: clock.unix_timestamp,
+        });
 
         Ok(())
     }
 
-    // TODO: Add more instructions for other functionalities like canceling proposals, 
-    // setting proposal types, updating governor settings, etc.
-}
+    /// Records weight-based community endorsement of a proposal in its Endorsement
+    /// phase. Reaching the type's endorsement quorum starts the voting delay.
+    /// Endorsing is not voting: the same wallet can still vote once voting opens.
+    pub fn endorse_proposal(ctx: Context<EndorseProposal>, _proposal_id: u64) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let endorser = ctx.accounts.endorser.key();
+        let clock = Clock::get()?;
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(init, payer = admin, space = 8 + Governor::LEN)]
-    pub governor: Account<'info, Governor>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    pub manager: AccountInfo<'info>,
-    pub system_program: Program<'info, System>,
-}
+        require!(proposal.state == ProposalState::Endorsement, GovernorError::ProposalNotInEndorsement);
+        require!(clock.slot <= proposal.endorsement_end, GovernorError::EndorsementPeriodEnded);
 
-#[derive(Accounts)]
-pub struct CreateProposal<'info> {
-    #[account(mut)]
-    pub governor: Account<'info, Governor>,
-    #[account(init, payer = proposer, space = 8 + Proposal::LEN)]
-    pub proposal: Account<'info, Proposal>,
-    #[account(mut)]
-    pub proposer: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        let weight = governor.get_votes(
+            &governor.key(),
+            &endorser,
+            ctx.accounts.endorser_token_account.amount,
+            &ctx.accounts.endorser_delegation,
+            ctx.remaining_accounts,
+        )?;
+        proposal.endorsement_weight += weight;
 
-#[derive(Accounts)]
-pub struct CastVote<'info> {
-    #[account(mut)]
-    pub governor: Account<'info, Governor>,
-    #[account(mut)]
-    pub proposal: Account<'info, Proposal>,
-    #[account(init, payer = voter, space = 8 + Vote::LEN)]
-    pub vote: Account<'info, Vote>,
-    #[account(mut)]
-    pub voter: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        let receipt = &mut ctx.accounts.endorsement_receipt;
+        receipt.proposal = proposal.key();
+        receipt.endorser = endorser;
+        receipt.weight = weight;
+        receipt.bump = ctx.bumps.endorsement_receipt;
 
-#[derive(Accounts)]
-pub struct ExecuteProposal<'info> {
-    #[account(mut)]
-    pub governor: Account<'info, Governor>,
-    #[account(mut)]
-    pub proposal: Account<'info, Proposal>,
-    pub executor: Signer<'info>,
-}
+        emit!(ProposalEndorsed {
+            governor: governor.key(),
+            proposal_id: proposal.id,
+            endorser,
+            weight,
+            total_endorsement_weight: proposal.endorsement_weight,
+            unix_timestamp: clock.unix_timestamp,
+        });
 
-#[account]
-pub struct Governor {
-    pub admin: Pubkey,
-    pub manager: Pubkey,
-    pub voting_delay: u64,
-    pub voting_period: u64,
-    pub proposal_threshold: u64,
-    pub proposal_count: u64,
-    pub total_supply: u64,
-    pub proposal_types: Vec<ProposalType>,
-}
+        let proposal_type_info = proposal.type_info(&governor.proposal_types)?;
+        let endorsement_quorum =
+            (governor.total_supply * proposal_type_info.endorsement_quorum_bps as u64) / 10_000;
+        if proposal.endorsement_weight >= endorsement_quorum {
+            proposal.state = ProposalState::Pending;
+            proposal.schedule(governor, clock.slot)?;
 
-#[account]
-pub struct Proposal {
-    pub id: u64,
-    pub proposer: Pubkey,
-    pub description: String,
-    pub proposal_type: u8,
-    pub start_block: u64,
-    pub end_block: u64,
-    pub for_votes: u64,
-    pub against_votes: u64,
-    pub executed: bool,
-    pub canceled: bool,
-}
+            emit!(EndorsementQuorumReached {
+                governor: governor.key(),
+                proposal_id: proposal.id,
+                start_block: proposal.start_block,
+                end_block: proposal.end_block,
+                unix_timestamp: clock.unix_timestamp,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn cast_vote<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CastVote<'info>>,
+        proposal_id: u64,
+        support: bool,
+    ) -> Result<()> {
+        record_vote(ctx, proposal_id, support, None)
+    }
+
+    /// Casts a vote with a rationale of at most `Governor::max_reason_length` bytes.
+    /// The reason is always emitted in `VoteCast`; it is also stored on the `Vote`
+    /// account, at the voter's expense, only with `Governor::store_reasons` set.
+    pub fn cast_vote_with_reason<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CastVote<'info>>,
+        proposal_id: u64,
+        support: bool,
+        reason: String,
+    ) -> Result<()> {
+        require!(
+            reason.len() <= ctx.accounts.governor.max_reason_length as usize,
+            GovernorError::ReasonTooLong
+        );
+        record_vote(ctx, proposal_id, support, Some(reason))
+    }
+
+    /// Commits a hidden vote on a commit-reveal proposal during its voting period.
+    /// `commitment` is `VoteCommitment::hash(support, salt)`; the voter's weight is
+    /// fixed now but counts only once `reveal_vote` opens the commitment.
+    pub fn commit_vote(ctx: Context<CommitVote>, proposal_id: u64, commitment: [u8; 32]) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let proposal = &ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
+        require!(proposal.reveal_end != 0, GovernorError::NotCommitReveal);
+        proposal.check_voting_open(clock.slot)?;
+        governor.check_top_level_vote(&ctx.accounts.instructions)?;
+
+        let current_weight = governor.get_votes(
+            &governor.key(),
+            &ctx.accounts.voter.key(),
+            token_balance(&ctx.accounts.voter_token_account),
+            &ctx.accounts.voter_delegation,
+            ctx.remaining_accounts,
+        )?;
+        if ctx.accounts.voter_token_account.is_none() {
+            require!(current_weight > 0, GovernorError::InsufficientVotingPower);
+        }
+        let (weight, _) = governor.vote_weight(
+            proposal,
+            ctx.accounts.voter_checkpoints.as_deref(),
+            current_weight,
+        )?;
+        require!(weight >= governor.min_voting_weight, GovernorError::WeightBelowMinimum);
+
+        let vote_commitment = &mut ctx.accounts.vote_commitment;
+        vote_commitment.governor = governor.key();
+        vote_commitment.proposal_id = proposal_id;
+        vote_commitment.voter = ctx.accounts.voter.key();
+        vote_commitment.commitment = commitment;
+        vote_commitment.weight = weight;
+        vote_commitment.revealed = false;
+        vote_commitment.bump = ctx.bumps.vote_commitment;
+
+        emit!(VoteCommitted {
+            governor: governor.key(),
+            proposal_id,
+            voter: vote_commitment.voter,
+            weight,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Opens a commitment from `commit_vote` during the proposal's reveal window,
+    /// after voting ends, and adds its weight to the tally. Commitments never
+    /// revealed are not counted. Permissionless: knowing the salt is enough.
+    pub fn reveal_vote(
+        ctx: Context<RevealVote>,
+        proposal_id: u64,
+        support: bool,
+        salt: [u8; 32],
+    ) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let vote_commitment = &mut ctx.accounts.vote_commitment;
+        let clock = Clock::get()?;
+
+        require!(
+            !proposal.is_terminal()
+                && clock.slot > proposal.end_block
+                && clock.slot <= proposal.reveal_end,
+            GovernorError::RevealWindowInactive
+        );
+        require!(!vote_commitment.revealed, GovernorError::VoteAlreadyRevealed);
+        require!(
+            VoteCommitment::hash(support, &salt) == vote_commitment.commitment,
+            GovernorError::CommitmentMismatch
+        );
+        vote_commitment.revealed = true;
+
+        emit!(VoteRevealed {
+            governor: governor.key(),
+            proposal_id,
+            voter: vote_commitment.voter,
+            support,
+            weight: vote_commitment.weight,
+            unix_timestamp: clock.unix_timestamp,
+        });
+        count_vote(
+            governor,
+            proposal,
+            support,
+            vote_commitment.weight,
+            vote_commitment.weight,
+            &clock,
+        )
+    }
+
+    /// Records whether a proposal passed once voting has ended. Permissionless, and
+    /// the only terminal step for non-binding proposals. Only a proposal still in its
+    /// endorsement phase or Pending can be finalized; queueing records the outcome too.
+    pub fn finalize_proposal(ctx: Context<FinalizeProposal>, _proposal_id: u64) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
+        require!(!proposal.canceled, GovernorError::ProposalCanceled);
+        require!(proposal.outcome.is_none(), GovernorError::ProposalAlreadyFinalized);
+        require!(
+            matches!(proposal.state, ProposalState::Pending | ProposalState::Endorsement),
+            GovernorError::ProposalAlreadyFinalized
+        );
+
+        let held = proposal.holds_type();
+        let mut defeat_reason = None;
+        let state = if proposal.state == ProposalState::Endorsement {
+            require!(clock.slot > proposal.endorsement_end, GovernorError::EndorsementPeriodActive);
+            ProposalState::EndorsementFailed
+        } else {
+            require!(proposal.is_activated(), GovernorError::ProposalNotActivated);
+            require!(clock.slot > proposal.tally_end(), GovernorError::VotingPeriodActive);
+
+            let proposal_type_info = proposal.type_info(&governor.proposal_types)?;
+            let quorum_votes = governor.quorum_votes(proposal_type_info, proposal.quorum_override)?;
+            defeat_reason = proposal.defeat_reason(proposal_type_info, quorum_votes);
+            if defeat_reason.is_none() {
+                ProposalState::Succeeded
+            } else {
+                ProposalState::Defeated
+            }
+        };
+        proposal.outcome = Some(state);
+        proposal.state = state;
+        proposal.deposit_forfeited = defeat_reason == Some(DefeatReason::QuorumNotReached);
+        governor.settle_proposal_type(proposal, held);
+        append_history(
+            &mut ctx.accounts.history,
+            governor.key(),
+            ctx.bumps.history,
+            proposal,
+            clock.slot,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+        )?;
+
+        let digest = &mut ctx.accounts.proposal_digest;
+        digest.governor = governor.key();
+        digest.content_hash = proposal.content_hash;
+        digest.proposal_id = proposal.id;
+        if state == ProposalState::Defeated {
+            digest.defeated_slot = Some(clock.slot);
+        }
+        digest.bump = ctx.bumps.proposal_digest;
+
+        emit!(ProposalFinalized {
+            governor: governor.key(),
+            proposal_id: proposal.id,
+            state,
+            for_votes: proposal.for_votes,
+            against_votes: proposal.against_votes,
+            unix_timestamp: clock.unix_timestamp,
+        });
+        if let Some(reason) = defeat_reason {
+            emit!(ProposalDefeated {
+                governor: governor.key(),
+                proposal_id: proposal.id,
+                for_votes: proposal.for_votes,
+                against_votes: proposal.against_votes,
+                reason,
+                unix_timestamp: clock.unix_timestamp,
+            });
+        }
+        emit_compat_proposal(governor, proposal, clock.unix_timestamp)?;
+
+        Ok(())
+    }
+
+    /// Cancels a proposal that has not been executed. The proposer, manager or guardian
+    /// may cancel; see `Governor::cancel_before_active_only` for the proposer's window.
+    pub fn cancel_proposal(ctx: Context<CancelProposal>, _proposal_id: u64) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let canceler = ctx.accounts.canceler.key();
+        let clock = Clock::get()?;
+
+        require!(!proposal.executed, GovernorError::ProposalAlreadyExecuted);
+        require!(!proposal.canceled, GovernorError::ProposalCanceled);
+
+        if canceler != governor.manager && canceler != governor.guardian {
+            require_keys_eq!(canceler, proposal.proposer, GovernorError::Unauthorized);
+            let voting_started = proposal.is_activated() && clock.slot >= proposal.start_block;
+            require!(
+                !(governor.cancel_before_active_only && voting_started),
+                GovernorError::CancelWindowClosed
+            );
+        }
+
+        let held = proposal.holds_type();
+        proposal.canceled = true;
+        proposal.state = ProposalState::Canceled;
+        governor.settle_proposal_type(proposal, held);
+
+        emit!(ProposalCanceled {
+            governor: governor.key(),
+            proposal_id: proposal.id,
+            canceled_by: canceler,
+            slot: clock.slot,
+            unix_timestamp: clock.unix_timestamp,
+            reason: CancelReason::Manual,
+        });
+        emit_compat_proposal(governor, proposal, clock.unix_timestamp)?;
+
+        Ok(())
+    }
+
+    /// Stops a Queued proposal during its timelock. Manager or guardian only. Payload
+    /// instructions not yet run by `execute_transaction` can never run afterwards.
+    pub fn cancel_queued_proposal(ctx: Context<CancelProposal>, proposal_id: u64) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let canceler = ctx.accounts.canceler.key();
+        let clock = Clock::get()?;
+
+        require!(
+            canceler == governor.manager || canceler == governor.guardian,
+            GovernorError::Unauthorized
+        );
+        require!(!proposal.executed, GovernorError::ProposalAlreadyExecuted);
+        require!(!proposal.canceled, GovernorError::ProposalCanceled);
+        require!(proposal.state == ProposalState::Queued, GovernorError::ProposalNotQueued);
+
+        let held = proposal.holds_type();
+        proposal.eta = 0;
+        proposal.canceled = true;
+        proposal.state = ProposalState::Canceled;
+        governor.settle_proposal_type(proposal, held);
+
+        emit!(QueuedProposalCanceled {
+            governor: governor.key(),
+            proposal_id,
+            canceled_by: canceler,
+            executed_instructions: proposal.executed_instructions,
+            total_instructions: proposal.instructions.len() as u16,
+            unix_timestamp: clock.unix_timestamp,
+        });
+        emit_compat_proposal(governor, proposal, clock.unix_timestamp)?;
+
+        Ok(())
+    }
+
+    /// Freezes execution of a proposal that has finished voting, pending an
+    /// investigation, without canceling it. The tally, finalization and queueing
+    /// are unaffected; every execution path fails until `unblock_execution`.
+    /// `reason_hash` points at the off-chain rationale. Guardian or manager only.
+    pub fn block_execution(ctx: Context<BlockExecution>, proposal_id: u64, reason_hash: [u8; 32]) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let blocker = ctx.accounts.blocker.key();
+        let clock = Clock::get()?;
+
+        require!(
+            blocker == governor.manager || blocker == governor.guardian,
+            GovernorError::Unauthorized
+        );
+        require!(!proposal.execution_blocked, GovernorError::ExecutionBlocked);
+        require!(!proposal.executed, GovernorError::ProposalAlreadyExecuted);
+        require!(!proposal.canceled, GovernorError::ProposalCanceled);
+        proposal.check_not_defeated()?;
+        require!(proposal.is_activated(), GovernorError::ProposalNotActivated);
+        require!(clock.slot > proposal.tally_end(), GovernorError::VotingPeriodActive);
+        require!(!proposal.is_terminal(), GovernorError::ProposalNotBlockable);
+
+        proposal.execution_blocked = true;
+
+        emit!(ProposalExecutionBlocked {
+            governor: governor.key(),
+            proposal_id,
+            blocked_by: blocker,
+            reason_hash,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Lifts a block set by `block_execution`. The proposal resumes from whatever
+    /// state it is in: one that expired meanwhile stays Expired. Guardian or manager
+    /// only.
+    pub fn unblock_execution(ctx: Context<BlockExecution>, proposal_id: u64) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let unblocker = ctx.accounts.blocker.key();
+
+        require!(
+            unblocker == governor.manager || unblocker == governor.guardian,
+            GovernorError::Unauthorized
+        );
+        require!(proposal.execution_blocked, GovernorError::ExecutionNotBlocked);
+
+        proposal.execution_blocked = false;
+
+        emit!(ProposalExecutionUnblocked {
+            governor: governor.key(),
+            proposal_id,
+            unblocked_by: unblocker,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Cancels a proposal still waiting for its voting period `MAX_PENDING_SLOTS`
+    /// after creation. Bounded settings never allow this; it only cleans up after a
+    /// misconfigured delay. Permissionless.
+    pub fn expire_pending(ctx: Context<ExpirePending>, _proposal_id: u64) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
+        require!(
+            proposal.state == ProposalState::Pending
+                && clock.slot < proposal.start_block
+                && clock.slot >= proposal.created_slot.saturating_add(Proposal::MAX_PENDING_SLOTS),
+            GovernorError::ProposalNotStale
+        );
+
+        let held = proposal.holds_type();
+        proposal.canceled = true;
+        proposal.state = ProposalState::Canceled;
+        governor.settle_proposal_type(proposal, held);
+
+        emit!(ProposalCanceled {
+            governor: governor.key(),
+            proposal_id: proposal.id,
+            canceled_by: ctx.accounts.payer.key(),
+            slot: clock.slot,
+            unix_timestamp: clock.unix_timestamp,
+            reason: CancelReason::StalePending,
+        });
+        emit_compat_proposal(governor, proposal, clock.unix_timestamp)?;
+
+        Ok(())
+    }
+
+    /// Moves a proposal that can no longer matter to Expired. Permissionless. Accepts a
+    /// Draft older than `draft_ttl_slots`, a proposal in its voting period that
+    /// cannot reach quorum even if all power that can still vote does, or a queued proposal
+    /// past its `expires_at`, even if partially executed.
+    pub fn expire_proposal(ctx: Context<ExpireProposal>, _proposal_id: u64) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+        let held = proposal.holds_type();
+
+        if proposal.state == ProposalState::Queued {
+            require!(
+                proposal.check_execution_deadline(clock.slot).is_err(),
+                GovernorError::ProposalNotExpirable
+            );
+            proposal.state = ProposalState::Expired;
+            proposal.outcome = Some(ProposalState::Expired);
+            governor.settle_proposal_type(proposal, held);
+
+            emit!(ProposalExecutionExpired {
+                governor: governor.key(),
+                proposal_id: proposal.id,
+                expires_at: proposal.expires_at,
+                unexecuted_instructions: proposal.instructions.len() as u16
+                    - proposal.executed_instructions,
+                unix_timestamp: clock.unix_timestamp,
+            });
+            emit_compat_proposal(governor, proposal, clock.unix_timestamp)?;
+            return Ok(());
+        }
+
+        let reason = if proposal.state == ProposalState::Draft {
+            require!(
+                governor.draft_ttl_slots > 0
+                    && clock.slot >= proposal.created_slot.saturating_add(governor.draft_ttl_slots),
+                GovernorError::ProposalNotExpirable
+            );
+            ExpiryReason::DraftTtl
+        } else {
+            require!(
+                proposal.state == ProposalState::Pending
+                    && clock.slot >= proposal.start_block
+                    && clock.slot <= proposal.end_block,
+                GovernorError::ProposalNotExpirable
+            );
+            proposal.take_supply_snapshot(governor.total_supply);
+            let proposal_type_info = proposal.type_info(&governor.proposal_types)?;
+            require!(
+                proposal.quorum_unreachable(
+                    proposal_type_info,
+                    governor.quorum_votes(proposal_type_info, proposal.quorum_override)?,
+                )?,
+                GovernorError::ProposalNotExpirable
+            );
+            ExpiryReason::QuorumUnreachable
+        };
+        proposal.state = ProposalState::Expired;
+        proposal.outcome = Some(ProposalState::Expired);
+        governor.settle_proposal_type(proposal, held);
+
+        emit!(ProposalExpiredEarly {
+            governor: governor.key(),
+            proposal_id: proposal.id,
+            reason,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Moves the deposit of a proposal finalized as failing quorum from its account to
+    /// the treasury. Permissionless.
+    pub fn sweep_forfeited_deposit(ctx: Context<SweepForfeitedDeposit>, _proposal_id: u64) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+
+        require!(proposal.deposit_forfeited, GovernorError::DepositNotForfeited);
+        require!(!proposal.deposit_swept, GovernorError::DepositAlreadySwept);
+        proposal.deposit_swept = true;
+        let amount = proposal.deposit;
+        proposal.sub_lamports(amount)?;
+        ctx.accounts.treasury.add_lamports(amount)?;
+
+        emit!(DepositForfeited {
+            governor: ctx.accounts.governor.key(),
+            proposal_id: proposal.id,
+            proposer: proposal.proposer,
+            amount,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Closes an Expired proposal and returns its rent to the proposer. Permissionless.
+    pub fn close_expired_proposal(
+        ctx: Context<CloseExpiredProposal>,
+        _proposal_id: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.proposal.state == ProposalState::Expired,
+            GovernorError::ProposalNotExpirable
+        );
+        require!(ctx.accounts.proposal.unclaimed_stakes == 0, GovernorError::StakesUnclaimed);
+        Ok(())
+    }
+
+    pub fn queue_proposal(ctx: Context<QueueProposal>, _proposal_id: u64) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
+        require!(!proposal.executed, GovernorError::ProposalAlreadyExecuted);
+        require!(!proposal.canceled, GovernorError::ProposalCanceled);
+        require!(proposal.is_activated(), GovernorError::ProposalNotActivated);
+        require!(proposal.eta == 0, GovernorError::ProposalAlreadyQueued);
+        require!(clock.slot > proposal.tally_end(), GovernorError::VotingPeriodActive);
+
+        let proposal_type_info = proposal.type_info(&governor.proposal_types)?;
+        require!(proposal_type_info.binding, GovernorError::NonBindingProposal);
+        proposal.check_succeeded(proposal_type_info, governor.quorum_votes(proposal_type_info, proposal.quorum_override)?)?;
+        proposal.check_dependency(ctx.accounts.dependency.as_deref())?;
+
+        let deadline_slots = proposal_type_info.execution_deadline_slots(governor.execution_deadline_slots);
+        proposal.eta = clock.slot + governor.timelock_delay;
+        proposal.expires_at = if deadline_slots > 0 {
+            proposal.eta + deadline_slots
+        } else {
+            0
+        };
+        proposal.outcome = Some(ProposalState::Succeeded);
+        proposal.state = ProposalState::Queued;
+
+        if let (Some(timelock_key), false) = (governor.timelock, proposal.instructions.is_empty()) {
+            let timelock = ctx
+                .accounts
+                .timelock
+                .as_mut()
+                .filter(|timelock| timelock.key() == timelock_key)
+                .ok_or(GovernorError::InvalidTimelock)?;
+            let governor_key = governor.key();
+            let authority = Pubkey::create_program_address(&governor.authority_seeds(&governor_key), &crate::ID)
+                .map_err(|_| GovernorError::InvalidTimelock)?;
+            let hash = proposal.timelock_operation_hash()?;
+            let eta = timelock.queue(&authority, hash, governor.timelock_delay, clock.slot)?;
+
+            emit!(TimelockOperationQueued {
+                timelock: timelock_key,
+                proposer: authority,
+                hash,
+                eta,
+                unix_timestamp: clock.unix_timestamp,
+            });
+        }
+
+        emit!(ProposalQueued {
+            governor: governor.key(),
+            proposal_id: proposal.id,
+            eta: proposal.eta,
+            executable_at: proposal.executable_at(),
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Executes a queued proposal once its timelock has passed. Permissionless: the
+    /// executor receives `Governor::execution_reward` from the treasury when the
+    /// treasury can afford it.
+    pub fn execute_proposal<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteProposal<'info>>,
+        proposal_id: u64,
+    ) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
+        require!(!proposal.executed, GovernorError::ProposalAlreadyExecuted);
+        require!(!proposal.canceled, GovernorError::ProposalCanceled);
+        proposal.check_not_defeated()?;
+        require!(proposal.is_activated(), GovernorError::ProposalNotActivated);
+        require!(clock.slot > proposal.tally_end(), GovernorError::VotingPeriodActive);
+        require!(proposal.eta != 0, GovernorError::ProposalNotQueued);
+        require!(clock.slot >= proposal.eta, GovernorError::TimelockNotExpired);
+        proposal.check_execution_deadline(clock.slot)?;
+        require!(!proposal.execution_blocked, GovernorError::ExecutionBlocked);
+        require!(proposal.instructions.is_empty(), GovernorError::ProposalHasPayload);
+
+        let proposal_type_info = proposal.type_info(&governor.proposal_types)?;
+        require!(proposal_type_info.binding, GovernorError::NonBindingProposal);
+        proposal.check_executable(
+            proposal_type_info,
+            governor.quorum_votes(proposal_type_info, proposal.quorum_override)?,
+            governor.total_supply,
+            governor.clamp_to_supply,
+        )?;
+        proposal.check_dependency(ctx.accounts.dependency.as_deref())?;
+
+        let actions = proposal.all_actions(ctx.accounts.proposal_actions.as_ref())?;
+        execute_actions(
+            governor,
+            &actions,
+            SupplyAccounts {
+                governing_mint: &mut ctx.accounts.governing_mint,
+                governor_authority: &ctx.accounts.governor_authority,
+                token_program: &ctx.accounts.token_program,
+                token_accounts: ctx.remaining_accounts,
+            },
+            clock.unix_timestamp,
+        )?;
+
+        let held = proposal.holds_type();
+        proposal.executed = true;
+        proposal.state = ProposalState::Executed;
+        governor.settle_proposal_type(proposal, held);
+        append_history(
+            &mut ctx.accounts.history,
+            governor.key(),
+            ctx.bumps.history,
+            proposal,
+            clock.slot,
+            &ctx.accounts.executor,
+            &ctx.accounts.system_program,
+        )?;
+
+        let reward_paid = pay_execution_reward(
+            governor,
+            &ctx.accounts.treasury,
+            &ctx.accounts.executor,
+            &ctx.accounts.system_program,
+        )?;
+
+        emit!(ProposalExecuted {
+            proposal_id,
+            executor: ctx.accounts.executor.key(),
+            reward: reward_paid,
+        });
+        emit_compat_proposal(governor, proposal, clock.unix_timestamp)?;
+
+        Ok(())
+    }
+
+    /// Executes a finalized, succeeded proposal immediately, skipping the queue and
+    /// the timelock. Only the guardian may call this, only for proposal types that
+    /// allow it, and only when the tally clears the governor's supermajority bar:
+    /// `emergency_bypass_threshold_bps` approval and `emergency_quorum_bps` turnout.
+    /// A proposal with a payload is instead queued with `eta` set to the current
+    /// slot and released from any external timelock, so `execute_transaction` can
+    /// run it at once; instruction hold-ups still apply. Emits `TimelockBypassed`.
+    pub fn emergency_execute<'info>(
+        ctx: Context<'_, '_, 'info, 'info, EmergencyExecute<'info>>,
+        proposal_id: u64,
+    ) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
+        require!(!proposal.executed, GovernorError::ProposalAlreadyExecuted);
+        require!(!proposal.canceled, GovernorError::ProposalCanceled);
+        require!(proposal.is_activated(), GovernorError::ProposalNotActivated);
+        require!(clock.slot > proposal.tally_end(), GovernorError::VotingPeriodActive);
+        require!(!proposal.execution_blocked, GovernorError::ExecutionBlocked);
+        require!(!proposal.timelock_bypassed, GovernorError::ProposalAlreadyQueued);
+        require!(
+            proposal.outcome == Some(ProposalState::Succeeded),
+            GovernorError::ProposalNotFinalized
+        );
+
+        let proposal_type_info = proposal.type_info(&governor.proposal_types)?;
+        require!(proposal_type_info.binding, GovernorError::NonBindingProposal);
+        require!(
+            proposal_type_info.emergency_bypass_allowed,
+            GovernorError::EmergencyBypassNotAllowed
+        );
+        proposal.check_emergency_bypass(
+            governor.emergency_bypass_threshold_bps,
+            governor.emergency_quorum_bps,
+            governor.total_supply,
+        )?;
+        proposal.check_executable(
+            proposal_type_info,
+            governor.quorum_votes(proposal_type_info, proposal.quorum_override)?,
+            governor.total_supply,
+            governor.clamp_to_supply,
+        )?;
+        proposal.check_dependency(ctx.accounts.dependency.as_deref())?;
+
+        if proposal.instructions.is_empty() {
+            let actions = proposal.all_actions(ctx.accounts.proposal_actions.as_ref())?;
+            execute_actions(
+                governor,
+                &actions,
+                SupplyAccounts {
+                    governing_mint: &mut ctx.accounts.governing_mint,
+                    governor_authority: &ctx.accounts.governor_authority,
+                    token_program: &ctx.accounts.token_program,
+                    token_accounts: ctx.remaining_accounts,
+                },
+                clock.unix_timestamp,
+            )?;
+
+            let held = proposal.holds_type();
+            proposal.executed = true;
+            proposal.state = ProposalState::Executed;
+            governor.settle_proposal_type(proposal, held);
+            append_history(
+                &mut ctx.accounts.history,
+                governor.key(),
+                ctx.bumps.history,
+                proposal,
+                clock.slot,
+                &ctx.accounts.guardian,
+                &ctx.accounts.system_program,
+            )?;
+
+            emit!(EmergencyExecuted {
+                governor: governor.key(),
+                proposal_id,
+                guardian: ctx.accounts.guardian.key(),
+                eta: proposal.eta,
+                slot: clock.slot,
+                unix_timestamp: clock.unix_timestamp,
+            });
+        } else {
+            // The payload runs through `execute_transaction`, one CPI per instruction.
+            let deadline_slots = proposal_type_info.execution_deadline_slots(governor.execution_deadline_slots);
+            proposal.eta = clock.slot;
+            proposal.expires_at = if deadline_slots > 0 {
+                clock.slot + deadline_slots
+            } else {
+                0
+            };
+            proposal.state = ProposalState::Queued;
+            proposal.timelock_bypassed = true;
+
+            emit!(ProposalQueued {
+                governor: governor.key(),
+                proposal_id,
+                eta: proposal.eta,
+                executable_at: proposal.executable_at(),
+                unix_timestamp: clock.unix_timestamp,
+            });
+        }
+        emit!(TimelockBypassed {
+            governor: governor.key(),
+            proposal_id,
+            guardian: ctx.accounts.guardian.key(),
+            for_votes: proposal.for_votes,
+            against_votes: proposal.against_votes,
+            total_supply: governor.total_supply,
+            skipped_timelock_slots: governor.timelock_delay,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Runs instruction `ix_index` of a queued proposal's payload once its timelock and
+    /// the instruction's hold-up have passed, signed by the governor authority PDA and
+    /// the lamport treasury. A system transfer out of the treasury must leave it
+    /// rent-exempt and emits `TreasuryTransfer`; an SPL token transfer must come from
+    /// an account owned by the authority. Instructions run strictly in order. The
+    /// remaining accounts are the instruction's accounts, matching the stored metas,
+    /// followed by the program to invoke, any token accounts named by `Mint` or
+    /// `Burn` actions and the `SpendingLimit` of a limited mint the instruction
+    /// moves out of the treasury. Completing the last one applies the proposal's actions,
+    /// marks it executed and pays the executor `Governor::execution_reward` as
+    /// `execute_proposal` does. While the CPI runs the governor is flagged as executing and
+    /// rejects every mutating instruction; an instruction targeting this program must
+    /// be stored with `self_call` set and runs without the flag. Permissionless.
+    pub fn execute_transaction<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteTransaction<'info>>,
+        _proposal_id: u64,
+        ix_index: u16,
+    ) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
+        check_payload_executable(governor, proposal, ctx.accounts.dependency.as_deref(), clock.slot)?;
+        check_timelock_ready(governor, proposal, ctx.accounts.timelock.as_ref(), clock.slot)?;
+        require!(
+            ix_index == proposal.executed_instructions,
+            GovernorError::InstructionIndexOutOfOrder
+        );
+        let account_count = proposal
+            .instructions
+            .get(ix_index as usize)
+            .ok_or(GovernorError::InvalidInstructionIndex)?
+            .account_count();
+        require!(
+            ctx.remaining_accounts.len() > account_count,
+            GovernorError::InvalidTransactionAccounts
+        );
+        let (instruction_infos, action_infos) = ctx.remaining_accounts.split_at(account_count + 1);
+        check_execution_hook(governor, proposal, ctx.accounts.execution_hook.as_deref())?;
+        run_payload_instruction(governor, proposal, instruction_infos, ctx.remaining_accounts, &clock)?;
+
+        finish_payload(
+            governor,
+            proposal,
+            SupplyAccounts {
+                governing_mint: &mut ctx.accounts.governing_mint,
+                governor_authority: &ctx.accounts.governor_authority,
+                token_program: &ctx.accounts.token_program,
+                token_accounts: action_infos,
+            },
+            CompletionAccounts {
+                history: &mut ctx.accounts.history,
+                history_bump: ctx.bumps.history,
+                treasury: &ctx.accounts.treasury,
+                executor: &ctx.accounts.executor,
+                system_program: &ctx.accounts.system_program,
+            },
+            &clock,
+        )?;
+        release_timelock_operation(governor, proposal, ctx.accounts.timelock.as_mut(), clock.unix_timestamp)
+    }
+
+    /// Runs the pending instructions of a queued proposal's payload in order, as
+    /// `execute_transaction` would one by one. The remaining accounts hold, for each
+    /// instruction in turn, its stored metas' accounts followed by its program, so
+    /// each group's length is the stored `accounts.len() + 1`; after the last
+    /// instruction come any token accounts named by `Mint` or `Burn` actions and the
+    /// `SpendingLimit` accounts of limited mints the payload moves. After
+    /// the first instruction the call stops early, keeping its progress, when the
+    /// accounts for the next one are missing, its hold-up has not passed or fewer
+    /// than `Proposal::BATCH_COMPUTE_RESERVE` compute units remain; a later call
+    /// resumes there. Permissionless.
+    pub fn execute_all_transactions<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteTransaction<'info>>,
+        _proposal_id: u64,
+    ) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
+        check_payload_executable(governor, proposal, ctx.accounts.dependency.as_deref(), clock.slot)?;
+        check_timelock_ready(governor, proposal, ctx.accounts.timelock.as_ref(), clock.slot)?;
+
+        let mut remaining = ctx.remaining_accounts;
+        let mut executed = 0;
+        while let Some(stored) = proposal.instructions.get(proposal.executed_instructions as usize) {
+            let group_len = stored.account_count() + 1;
+            if executed > 0
+                && (remaining.len() < group_len
+                    || clock.slot < proposal.eta.saturating_add(stored.hold_up_slots)
+                    || sol_remaining_compute_units() < Proposal::BATCH_COMPUTE_RESERVE)
+            {
+                break;
+            }
+            require!(remaining.len() >= group_len, GovernorError::InvalidTransactionAccounts);
+            let (instruction_infos, rest) = remaining.split_at(group_len);
+            check_execution_hook(governor, proposal, ctx.accounts.execution_hook.as_deref())?;
+            run_payload_instruction(governor, proposal, instruction_infos, ctx.remaining_accounts, &clock)?;
+            remaining = rest;
+            executed += 1;
+        }
+
+        finish_payload(
+            governor,
+            proposal,
+            SupplyAccounts {
+                governing_mint: &mut ctx.accounts.governing_mint,
+                governor_authority: &ctx.accounts.governor_authority,
+                token_program: &ctx.accounts.token_program,
+                token_accounts: remaining,
+            },
+            CompletionAccounts {
+                history: &mut ctx.accounts.history,
+                history_bump: ctx.bumps.history,
+                treasury: &ctx.accounts.treasury,
+                executor: &ctx.accounts.executor,
+                system_program: &ctx.accounts.system_program,
+            },
+            &clock,
+        )?;
+        release_timelock_operation(governor, proposal, ctx.accounts.timelock.as_mut(), clock.unix_timestamp)
+    }
+
+    /// Creates an address lookup table owned by the governor authority and fills it
+    /// with the governor, the proposal and every account and program in the stored
+    /// payload, so a payload too wide for one transaction's account list can still
+    /// run through `execute_all_transactions`. The table's address is recorded on
+    /// the proposal. Proposer only, once activation has frozen the payload. The
+    /// runtime resolves table entries before the program runs, so execution checks
+    /// them against the stored metas like any other account, with or without a table.
+    pub fn create_payload_lookup_table(
+        ctx: Context<CreatePayloadLookupTable>,
+        _proposal_id: u64,
+        recent_slot: u64,
+    ) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
+        require!(proposal.is_activated(), GovernorError::ProposalNotActivated);
+        require!(!proposal.executed, GovernorError::ProposalAlreadyExecuted);
+        require!(!proposal.canceled, GovernorError::ProposalCanceled);
+        require!(proposal.lookup_table.is_none(), GovernorError::LookupTableAlreadyCreated);
+
+        let governor_key = governor.key();
+        let authority = ctx.accounts.governor_authority.key();
+        let proposer = ctx.accounts.proposer.key();
+        let (create, lookup_table) =
+            address_lookup_table::instruction::create_lookup_table(authority, proposer, recent_slot);
+        require_keys_eq!(lookup_table, ctx.accounts.lookup_table.key(), GovernorError::InvalidLookupTable);
+        let mut addresses = vec![governor_key, proposal.key()];
+        for address in proposal.payload_addresses() {
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+        let extend = address_lookup_table::instruction::extend_lookup_table(
+            lookup_table,
+            authority,
+            Some(proposer),
+            addresses.clone(),
+        );
+
+        let infos = [
+            ctx.accounts.lookup_table.to_account_info(),
+            ctx.accounts.governor_authority.to_account_info(),
+            ctx.accounts.proposer.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.address_lookup_table_program.to_account_info(),
+        ];
+        invoke_signed(&create, &infos, &[])?;
+        invoke_signed(&extend, &infos, &[&governor.authority_seeds(&governor_key)])?;
+        proposal.lookup_table = Some(lookup_table);
+
+        emit!(PayloadLookupTableCreated {
+            governor: governor_key,
+            proposal_id: proposal.id,
+            lookup_table,
+            addresses: addresses.len() as u16,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Records that instruction `ix_index` of a queued proposal's payload failed with
+    /// `error_code`, marking it `InstructionStatus::Failed`. A failing CPI aborts its
+    /// whole transaction, so `execute_transaction` cannot write the failure itself;
+    /// the manager passes the code the failed transaction returned: the custom code
+    /// of a `ProgramError::Custom`, otherwise `u64::from(ProgramError)`. The
+    /// instruction must be the next one due and stays retryable: a later successful
+    /// `execute_transaction` marks it executed, keeping the code as its last error.
+    /// Manager only.
+    pub fn record_transaction_failure(
+        ctx: Context<RecordTransactionFailure>,
+        proposal_id: u64,
+        ix_index: u16,
+        error_code: u64,
+    ) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
+        require!(error_code != 0, GovernorError::InvalidErrorCode);
+        check_next_instruction_due(proposal, ix_index, clock.slot)?;
+
+        proposal.instruction_error_codes[ix_index as usize] = error_code;
+
+        emit!(TransactionExecutionResult {
+            governor: governor.key(),
+            proposal_id,
+            ix_index,
+            success: false,
+            error_code,
+            compute_consumed_estimate: 0,
+            unix_timestamp: clock.unix_timestamp,
+        });
+        emit!(InstructionStatusChanged {
+            governor: governor.key(),
+            proposal_id,
+            ix_index,
+            status: proposal.instruction_status(ix_index),
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Skips instruction `ix_index` of a queued proposal's payload so the rest can
+    /// run, for an instruction that can never succeed. It must be the next one due
+    /// and past its timelock and hold-up. A failed `execute_transaction` leaves the
+    /// instruction pending, so transient failures are retried rather than skipped.
+    /// The last error recorded by `record_transaction_failure` is kept. Skipping the
+    /// last instruction completes the proposal like executing it would;
+    /// the remaining accounts are then any token accounts named by `Mint` or `Burn`
+    /// actions. Manager only.
+    pub fn mark_transaction_skipped<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MarkTransactionSkipped<'info>>,
+        proposal_id: u64,
+        ix_index: u16,
+    ) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
+        check_next_instruction_due(proposal, ix_index, clock.slot)?;
+
+        proposal.skipped_instructions |= 1 << ix_index;
+        proposal.executed_instructions += 1;
+
+        emit!(TransactionSkipped {
+            governor: governor.key(),
+            proposal_id,
+            ix_index,
+            skipped_by: ctx.accounts.manager.key(),
+            unix_timestamp: clock.unix_timestamp,
+        });
+        emit!(InstructionStatusChanged {
+            governor: governor.key(),
+            proposal_id,
+            ix_index,
+            status: InstructionStatus::Skipped,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        finish_payload(
+            governor,
+            proposal,
+            SupplyAccounts {
+                governing_mint: &mut ctx.accounts.governing_mint,
+                governor_authority: &ctx.accounts.governor_authority,
+                token_program: &ctx.accounts.token_program,
+                token_accounts: ctx.remaining_accounts,
+            },
+            CompletionAccounts {
+                history: &mut ctx.accounts.history,
+                history_bump: ctx.bumps.history,
+                treasury: &ctx.accounts.treasury,
+                executor: &ctx.accounts.manager,
+                system_program: &ctx.accounts.system_program,
+            },
+            &clock,
+        )
+    }
+
+    /// Refreshes `Governor::total_supply` from the governing mint. Permissionless.
+    pub fn sync_total_supply(ctx: Context<SyncTotalSupply>) -> Result<()> {
+        let supply = ctx.accounts.governing_mint.supply;
+        record_total_supply(&mut ctx.accounts.governor, supply, Clock::get()?.unix_timestamp);
+        Ok(())
+    }
+
+    /// Creates the governor authority's associated token account for `mint`, from
+    /// which payloads can transfer. Works for both token programs. Permissionless.
+    pub fn create_treasury_token_account(_ctx: Context<CreateTreasuryTokenAccount>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Registers the governor authority's associated token account for `mint` as a
+    /// treasury vault, creating the account unless it exists. Registered vaults are
+    /// listed in the governor's `TreasuryRegistry`, which grows by one entry up to
+    /// `TreasuryRegistry::MAX_VAULTS`. Signed by the manager or, as a self-call from a
+    /// proposal's payload, by the governor authority.
+    pub fn create_treasury_vault(ctx: Context<CreateTreasuryVault>) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let registry = &mut ctx.accounts.treasury_registry;
+        let creator = ctx.accounts.creator.key();
+        let mint = ctx.accounts.mint.key();
+        let vault = ctx.accounts.vault.key();
+
+        require!(
+            creator == ctx.accounts.governor_authority.key() || creator == governor.manager,
+            GovernorError::Unauthorized
+        );
+        if registry.governor == Pubkey::default() {
+            registry.governor = governor.key();
+            registry.bump = ctx.bumps.treasury_registry;
+        }
+        require!(
+            !registry.vaults.iter().any(|entry| entry.mint == mint),
+            GovernorError::TreasuryVaultExists
+        );
+        require!(
+            registry.vaults.len() < TreasuryRegistry::MAX_VAULTS,
+            GovernorError::TreasuryRegistryFull
+        );
+
+        let registry_info = registry.to_account_info();
+        grow_account(
+            &registry_info,
+            registry_info.data_len() + TreasuryVault::LEN,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+        )?;
+        registry.vaults.push(TreasuryVault { mint, address: vault });
+
+        emit!(TreasuryVaultCreated {
+            governor: governor.key(),
+            mint,
+            vault,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Closes the empty treasury vault for `mint`, returning its rent, and the rent of
+    /// its registry entry, to the lamport treasury. Only the governor authority can
+    /// sign, so this runs as a self-call from a proposal's payload.
+    pub fn close_treasury_vault(ctx: Context<CloseTreasuryVault>) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let registry = &mut ctx.accounts.treasury_registry;
+        let mint = ctx.accounts.mint.key();
+        let vault = ctx.accounts.vault.key();
+
+        require!(ctx.accounts.vault.amount == 0, GovernorError::TreasuryVaultNotEmpty);
+        let position = registry
+            .vaults
+            .iter()
+            .position(|entry| entry.mint == mint && entry.address == vault)
+            .ok_or(GovernorError::TreasuryVaultNotRegistered)?;
+        registry.vaults.remove(position);
+
+        let governor_key = governor.key();
+        token_interface::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.vault.to_account_info(),
+                destination: ctx.accounts.treasury.to_account_info(),
+                authority: ctx.accounts.governor_authority.to_account_info(),
+            },
+            &[&governor.authority_seeds(&governor_key)],
+        ))?;
+
+        let registry_info = registry.to_account_info();
+        let new_len = registry_info.data_len() - TreasuryVault::LEN;
+        let surplus = registry_info
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(new_len));
+        registry_info.realloc(new_len, false)?;
+        registry_info.sub_lamports(surplus)?;
+        ctx.accounts.treasury.add_lamports(surplus)?;
+
+        emit!(TreasuryVaultClosed {
+            governor: governor_key,
+            mint,
+            vault,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Deposits `amount` lamports into the treasury and records it in `TreasuryStats`.
+    /// Plain transfers to the treasury still work but are not counted.
+    pub fn deposit_to_treasury(ctx: Context<DepositToTreasury>, amount: u64) -> Result<()> {
+        let clock = Clock::get()?;
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.depositor.to_account_info(),
+                    to: ctx.accounts.treasury.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let stats = &mut ctx.accounts.treasury_stats;
+        stats.governor = ctx.accounts.governor.key();
+        stats.bump = ctx.bumps.treasury_stats;
+        stats.record(TreasuryStats::NATIVE_MINT, amount);
+
+        emit!(TreasuryDeposit {
+            governor: ctx.accounts.governor.key(),
+            from: ctx.accounts.depositor.key(),
+            mint_or_native: TreasuryStats::NATIVE_MINT,
+            amount,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Deposits `amount` tokens into the governor authority's token account for the
+    /// mint and records it in `TreasuryStats`.
+    pub fn deposit_token_to_treasury(ctx: Context<DepositTokenToTreasury>, amount: u64) -> Result<()> {
+        let clock = Clock::get()?;
+
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.depositor_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.treasury_token_account.to_account_info(),
+                    authority: ctx.accounts.depositor.to_account_info(),
+                },
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        let mint = ctx.accounts.mint.key();
+        let stats = &mut ctx.accounts.treasury_stats;
+        stats.governor = ctx.accounts.governor.key();
+        stats.bump = ctx.bumps.treasury_stats;
+        stats.record(mint, amount);
+
+        emit!(TreasuryDeposit {
+            governor: ctx.accounts.governor.key(),
+            from: ctx.accounts.depositor.key(),
+            mint_or_native: mint,
+            amount,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the treasury's lamports followed by the balance of each governor
+    /// authority token account passed as a remaining account.
+    pub fn get_treasury_balances(ctx: Context<GetTreasuryBalances>) -> Result<Vec<TreasuryBalance>> {
+        let authority = ctx.accounts.governor_authority.key();
+
+        let mut balances = vec![TreasuryBalance {
+            mint_or_native: TreasuryStats::NATIVE_MINT,
+            amount: ctx.accounts.treasury.lamports(),
+        }];
+        for info in ctx.remaining_accounts {
+            require!(
+                *info.owner == anchor_spl::token::ID || *info.owner == anchor_spl::token_2022::ID,
+                GovernorError::InvalidTreasuryTokenAccount
+            );
+            let token_account = TokenAccount::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+            require_keys_eq!(token_account.owner, authority, GovernorError::InvalidTreasuryTokenAccount);
+            balances.push(TreasuryBalance {
+                mint_or_native: token_account.mint,
+                amount: token_account.amount,
+            });
+        }
+        Ok(balances)
+    }
+
+    /// Returns the treasury's lamports and emits them in a `TreasuryBalanceSnapshot`,
+    /// together with the balance of `treasury_token_account` when one is passed.
+    pub fn get_treasury_balance(ctx: Context<GetTreasuryBalance>) -> Result<u64> {
+        let lamports = ctx.accounts.treasury.lamports();
+
+        emit!(TreasuryBalanceSnapshot {
+            governor: ctx.accounts.governor.key(),
+            treasury: ctx.accounts.treasury.key(),
+            lamports,
+            token: ctx.accounts.treasury_token_account.as_ref().map(|token_account| TreasuryBalance {
+                mint_or_native: token_account.mint,
+                amount: token_account.amount,
+            }),
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(lamports)
+    }
+
+    /// Creates a recurring proposal template. Every `interval` slots, `crank_schedule`
+    /// can instantiate one proposal from it.
+    pub fn create_proposal_schedule(
+        ctx: Context<CreateProposalSchedule>,
+        schedule_id: u64,
+        proposal_type: u8,
+        description_hash: [u8; 32],
+        actions: Vec<ProposalAction>,
+        instructions: Vec<ProposalInstruction>,
+        interval: u64,
+    ) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let schedule = &mut ctx.accounts.schedule;
+        let clock = Clock::get()?;
+
+        governor.validate_new_proposal(proposal_type, &actions)?;
+        governor.check_programs_allowed(proposal_type, &instructions)?;
+        governor.check_spending_caps(
+            &governor.key(),
+            proposal_type,
+            &instructions,
+            TreasuryRegistry::vaults_of(&ctx.accounts.treasury_registry),
+        )?;
+        require!(interval > 0, GovernorError::ParameterOutOfBounds);
+        let payload_bytes = Proposal::validate_payload(&instructions)?;
+
+        schedule.governor = governor.key();
+        schedule.schedule_id = schedule_id;
+        schedule.proposal_type = proposal_type;
+        governor.hold_proposal_type(proposal_type);
+        schedule.description_hash = description_hash;
+        schedule.actions = actions;
+        schedule.interval = interval;
+        schedule.next_run_slot = clock.slot + interval;
+        schedule.instance_count = 0;
+        schedule.paused = false;
+        schedule.bump = ctx.bumps.schedule;
+        schedule.payload_bytes = payload_bytes as u32;
+        schedule.instructions = instructions;
+
+        Ok(())
+    }
+
+    /// Instantiates the next proposal from a schedule once it is due. Permissionless.
+    /// The schedule is the proposer, so no proposer threshold applies. Intervals missed
+    /// while nobody cranked are skipped, so each interval yields at most one proposal.
+    pub fn crank_schedule(ctx: Context<CrankSchedule>, _schedule_id: u64) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let schedule = &mut ctx.accounts.schedule;
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
+        require!(!governor.hashed_proposal_ids, GovernorError::ProposalIdModeMismatch);
+        require!(!schedule.paused, GovernorError::SchedulePaused);
+        require!(clock.slot >= schedule.next_run_slot, GovernorError::ScheduleNotDue);
+        governor.check_blackout(&schedule.key(), clock.slot)?;
+        let elapsed_intervals = (clock.slot - schedule.next_run_slot) / schedule.interval + 1;
+        schedule.next_run_slot += elapsed_intervals * schedule.interval;
+        schedule.instance_count += 1;
+
+        governor.validate_new_proposal(schedule.proposal_type, &schedule.actions)?;
+        governor.check_programs_allowed(schedule.proposal_type, &schedule.instructions)?;
+        governor.check_spending_caps(
+            &governor.key(),
+            schedule.proposal_type,
+            &schedule.instructions,
+            TreasuryRegistry::vaults_of(&ctx.accounts.treasury_registry),
+        )?;
+
+        proposal.id = governor.proposal_count;
+        proposal.governor = governor.key();
+        proposal.bump = ctx.bumps.proposal;
+        proposal.proposer = schedule.key();
+        proposal.description = schedule.description();
+        proposal.proposal_type = schedule.proposal_type;
+        proposal.actions = schedule.actions.clone();
+        proposal.payload_bytes = schedule.payload_bytes;
+        proposal.instructions = schedule.instructions.clone();
+        proposal.created_slot = clock.slot;
+        proposal.activate(governor, clock.slot)?;
+        proposal.eta = 0;
+        proposal.outcome = None;
+        proposal.executed = false;
+        proposal.canceled = false;
+
+        governor.hold_proposal_type(proposal.proposal_type);
+        governor.proposal_count += 1;
+
+        emit!(ProposalCreated {
+            proposal_id: proposal.id,
+            proposer: proposal.proposer,
+            start_block: proposal.start_block,
+            end_block: proposal.end_block,
+            description: proposal.description.clone(),
+            proposal_type: proposal.proposal_type,
+            supersedes: None,
+            depends_on: None,
+            payload_hash: proposal.compute_payload_hash()?,
+            fee_paid: proposal.fee_paid,
+        });
+        emit_compat_proposal(governor, proposal, clock.unix_timestamp)?;
+
+        Ok(())
+    }
+
+    pub fn set_schedule_paused(
+        ctx: Context<UpdateProposalSchedule>,
+        _schedule_id: u64,
+        paused: bool,
+    ) -> Result<()> {
+        ctx.accounts.schedule.paused = paused;
+        Ok(())
+    }
+
+    /// Deletes a schedule and returns its rent to the manager. Proposals it already
+    /// created are unaffected.
+    pub fn cancel_schedule(ctx: Context<CancelProposalSchedule>, _schedule_id: u64) -> Result<()> {
+        ctx.accounts.governor.release_proposal_type(ctx.accounts.schedule.proposal_type);
+        Ok(())
+    }
+
+    /// Starts handing the admin role to `new_admin`, who must accept it with
+    /// `accept_admin`. Naming the governor authority PDA puts every admin-only
+    /// instruction behind proposal execution. Replaces any pending transfer. Admin
+    /// only, and never after `lock_admin`.
+    pub fn transfer_admin(ctx: Context<AdminUpdateGovernor>, new_admin: Pubkey) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+
+        require!(!governor.admin_locked, GovernorError::AdminTransferLocked);
+        governor.pending_admin = Some(new_admin);
+
+        emit!(AdminTransferStarted {
+            governor: governor.key(),
+            admin: governor.admin,
+            pending_admin: new_admin,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Completes a transfer started by `transfer_admin`. Signed by the pending admin:
+    /// for the governor authority PDA, as a self-call from a proposal's payload.
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let new_admin = ctx.accounts.new_admin.key();
+
+        let previous_admin = std::mem::replace(&mut governor.admin, new_admin);
+        governor.pending_admin = None;
+
+        emit!(AdminTransferred {
+            governor: governor.key(),
+            previous_admin,
+            new_admin,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Permanently keeps the admin role with the governor authority PDA, which must
+    /// already hold it: `transfer_admin` is rejected from then on and any pending
+    /// transfer is dropped. Runs as a self-call from a proposal's payload.
+    pub fn lock_admin(ctx: Context<AdminUpdateGovernor>) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let governor_key = governor.key();
+
+        let authority = Pubkey::create_program_address(&governor.authority_seeds(&governor_key), &crate::ID)
+            .map_err(|_| GovernorError::InvalidTransactionAccounts)?;
+        require_keys_eq!(governor.admin, authority, GovernorError::AdminNotGovernorAuthority);
+        governor.admin_locked = true;
+        governor.pending_admin = None;
+
+        emit!(AdminLocked {
+            governor: governor_key,
+            admin: authority,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Routes the governor's payloads through `timelock`, or stops doing so with
+    /// `None`. While set, `queue_proposal` registers each payload there and its
+    /// transactions only run once the operation is ready. Admin only.
+    pub fn set_timelock(ctx: Context<AdminUpdateGovernor>, timelock: Option<Pubkey>) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        governor.timelock = timelock;
+
+        emit!(GovernorTimelockSet {
+            governor: governor.key(),
+            timelock,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets or clears the program consulted through the `execution_hook` interface
+    /// before each payload instruction runs. Admin only.
+    pub fn set_execution_hook(ctx: Context<AdminUpdateGovernor>, execution_hook: Option<Pubkey>) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        governor.execution_hook = execution_hook;
+
+        emit!(ExecutionHookSet {
+            governor: governor.key(),
+            execution_hook,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Adds a window of slots, `[start_slot, end_slot)`, during which no new proposal
+    /// can start its lifecycle. Voting, finalization and execution are unaffected.
+    /// Windows that have already ended are pruned; overlapping windows are rejected.
+    pub fn add_blackout_window(
+        ctx: Context<AdminUpdateGovernor>,
+        start_slot: u64,
+        end_slot: u64,
+    ) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let clock = Clock::get()?;
+
+        require!(start_slot < end_slot, GovernorError::InvalidBlackoutWindow);
+        governor.blackout_windows.retain(|window| window.end_slot > clock.slot);
+        require!(
+            governor
+                .blackout_windows
+                .iter()
+                .all(|window| end_slot <= window.start_slot || start_slot >= window.end_slot),
+            GovernorError::InvalidBlackoutWindow
+        );
+        require!(
+            governor.blackout_windows.len() < Governor::MAX_BLACKOUT_WINDOWS,
+            GovernorError::TooManyBlackoutWindows
+        );
+
+        let window = BlackoutWindow { start_slot, end_slot };
+        let position = governor
+            .blackout_windows
+            .iter()
+            .position(|other| other.start_slot > start_slot)
+            .unwrap_or(governor.blackout_windows.len());
+        governor.blackout_windows.insert(position, window);
+
+        emit!(BlackoutWindowAdded {
+            governor: governor.key(),
+            start_slot,
+            end_slot,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Removes the blackout window starting at `start_slot`.
+    pub fn remove_blackout_window(ctx: Context<AdminUpdateGovernor>, start_slot: u64) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let clock = Clock::get()?;
+
+        let position = governor
+            .blackout_windows
+            .iter()
+            .position(|window| window.start_slot == start_slot)
+            .ok_or(GovernorError::InvalidBlackoutWindow)?;
+        let window = governor.blackout_windows.remove(position);
+
+        emit!(BlackoutWindowRemoved {
+            governor: governor.key(),
+            start_slot: window.start_slot,
+            end_slot: window.end_slot,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the delay before voting opens on proposals created from now on; existing
+    /// proposals keep the schedule they were given.
+    pub fn set_voting_delay(ctx: Context<AdminUpdateGovernor>, voting_delay: u64) -> Result<()> {
+        admin_set_parameter(&mut ctx.accounts.governor, ParamKey::VotingDelay, voting_delay)
+    }
+
+    /// Sets the voting period of proposals created from now on; existing proposals
+    /// keep the schedule they were given.
+    pub fn set_voting_period(ctx: Context<AdminUpdateGovernor>, voting_period: u64) -> Result<()> {
+        admin_set_parameter(&mut ctx.accounts.governor, ParamKey::VotingPeriod, voting_period)
+    }
+
+    /// Sets the voting power needed to create a proposal.
+    pub fn set_proposal_threshold(ctx: Context<AdminUpdateGovernor>, proposal_threshold: u64) -> Result<()> {
+        admin_set_parameter(&mut ctx.accounts.governor, ParamKey::ProposalThreshold, proposal_threshold)
+    }
+
+    pub fn set_blackout_manager_exempt(ctx: Context<AdminUpdateGovernor>, exempt: bool) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let clock = Clock::get()?;
+
+        let old = std::mem::replace(&mut governor.blackout_manager_exempt, exempt);
+
+        emit!(GovernorSettingsUpdated {
+            governor: governor.key(),
+            field: "blackout_manager_exempt".to_string(),
+            old: old as u64,
+            new: exempt as u64,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Strips `address` of all voting weight, including weight delegated to others,
+    /// e.g. for a sanctioned holder or an exploiter sitting on stolen tokens.
+    pub fn block_address(ctx: Context<AdminUpdateGovernor>, address: Pubkey) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let clock = Clock::get()?;
+
+        require!(!governor.is_blocked(&address), GovernorError::AddressAlreadyBlocked);
+        require!(
+            governor.blocklist.len() < Governor::MAX_BLOCKLIST_LEN,
+            GovernorError::BlocklistFull
+        );
+        governor.blocklist.push(address);
+
+        emit!(AddressBlocked {
+            governor: governor.key(),
+            address,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn unblock_address(ctx: Context<AdminUpdateGovernor>, address: Pubkey) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let clock = Clock::get()?;
+
+        let position = governor
+            .blocklist
+            .iter()
+            .position(|blocked| *blocked == address)
+            .ok_or(GovernorError::AddressNotBlocked)?;
+        governor.blocklist.swap_remove(position);
+
+        emit!(AddressUnblocked {
+            governor: governor.key(),
+            address,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Replaces the quorum multipliers per risk tier, e.g. `[10_000, 30_000]` for
+    /// treasury proposals needing 3x the quorum of routine ones. Every proposal
+    /// type's tier must remain defined.
+    pub fn set_risk_tier_multipliers(
+        ctx: Context<AdminUpdateGovernor>,
+        multipliers: Vec<u16>,
+    ) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let clock = Clock::get()?;
+
+        require!(
+            !multipliers.is_empty()
+                && multipliers.len() <= Governor::MAX_RISK_TIERS
+                && multipliers.iter().all(|multiplier| *multiplier > 0),
+            GovernorError::InvalidRiskTier
+        );
+        require!(
+            governor
+                .proposal_types
+                .iter()
+                .all(|proposal_type| (proposal_type.risk_tier as usize) < multipliers.len()),
+            GovernorError::InvalidRiskTier
+        );
+        governor.risk_tier_multipliers = multipliers.clone();
+
+        emit!(RiskTierMultipliersUpdated {
+            governor: governor.key(),
+            multipliers,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Appends `proposal_type` under the next type id, growing the governor account
+    /// at the admin's expense. It is validated like the types passed to `initialize`.
+    /// Admin only, like `remove_proposal_type`.
+    pub fn add_proposal_type(ctx: Context<AddProposalType>, proposal_type: ProposalType) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+
+        require!(
+            governor.proposal_types.len() < Governor::MAX_PROPOSAL_TYPES,
+            GovernorError::TooManyProposalTypes
+        );
+        proposal_type.validate(governor.risk_tier_multipliers.len())?;
+
+        let governor_info = governor.to_account_info();
+        grow_account(
+            &governor_info,
+            governor_info.data_len() + proposal_type.try_to_vec()?.len() + 4,
+            &ctx.accounts.admin,
+            &ctx.accounts.system_program,
+        )?;
+        let proposal_type_id = governor.proposal_types.len() as u8;
+        governor.active_proposal_counts.push(0);
+        emit!(ProposalTypeAdded {
+            governor: governor.key(),
+            proposal_type: proposal_type_id,
+            name: proposal_type.name.clone(),
+            quorum: proposal_type.quorum,
+            approval_threshold: proposal_type.approval_threshold,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+        governor.proposal_types.push(proposal_type);
+
+        Ok(())
+    }
+
+    /// Replaces the settings of proposal type `proposal_type_id`, allowlist included,
+    /// after validating them like `add_proposal_type`. The governor account is resized
+    /// to fit: the admin pays for growth and gets the rent freed by shrinking. Activated
+    /// proposals keep their type snapshot; drafts and schedules are checked against
+    /// the new settings when they are activated or instantiated. Admin only.
+    pub fn update_proposal_type(
+        ctx: Context<UpdateProposalType>,
+        proposal_type_id: u8,
+        proposal_type: ProposalType,
+    ) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let index = proposal_type_id as usize;
+
+        require!(index < governor.proposal_types.len(), GovernorError::InvalidProposalType);
+        require!(!governor.proposal_types[index].removed, GovernorError::ProposalTypeRetired);
+        proposal_type.validate(governor.risk_tier_multipliers.len())?;
+
+        let old_len = governor.proposal_types[index].try_to_vec()?.len();
+        let new_len = proposal_type.try_to_vec()?.len();
+        let governor_info = governor.to_account_info();
+        let mut refund = 0;
+        if new_len > old_len {
+            grow_account(
+                &governor_info,
+                governor_info.data_len() + (new_len - old_len),
+                &ctx.accounts.admin,
+                &ctx.accounts.system_program,
+            )?;
+        } else if new_len < old_len {
+            let account_len = governor_info.data_len() - (old_len - new_len);
+            refund = governor_info
+                .lamports()
+                .saturating_sub(Rent::get()?.minimum_balance(account_len));
+            governor_info.realloc(account_len, false)?;
+            governor_info.sub_lamports(refund)?;
+            ctx.accounts.admin.add_lamports(refund)?;
+        }
+
+        emit!(ProposalTypeUpdated {
+            governor: governor.key(),
+            proposal_type: proposal_type_id,
+            name: proposal_type.name.clone(),
+            quorum: proposal_type.quorum,
+            approval_threshold: proposal_type.approval_threshold,
+            allowed_programs: proposal_type.allowed_programs.clone(),
+            refund,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+        governor.proposal_types[index] = proposal_type;
+
+        Ok(())
+    }
+
+    /// Retires proposal type `proposal_type_id`: the entry is tombstoned with
+    /// `ProposalType::removed` rather than deleted, so the indices held by finished
+    /// proposals and `GovernorHistory` records keep pointing at the right type. New
+    /// proposals can no longer use it, and no proposal in progress or schedule may
+    /// still hold it. Its program allowlist and spending caps are dropped, shrinking
+    /// the governor account and refunding the freed rent to the admin. Admin only.
+    pub fn remove_proposal_type(ctx: Context<RemoveProposalType>, proposal_type_id: u8) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let index = proposal_type_id as usize;
+
+        require!(index < governor.proposal_types.len(), GovernorError::InvalidProposalType);
+        require!(!governor.proposal_types[index].removed, GovernorError::ProposalTypeRetired);
+        require!(
+            governor.active_proposal_counts.get(index).copied().unwrap_or(0) == 0,
+            GovernorError::ProposalTypeInUse
+        );
+        let proposal_type = &mut governor.proposal_types[index];
+        let old_len = proposal_type.try_to_vec()?.len();
+        proposal_type.removed = true;
+        proposal_type.allowed_programs = vec![];
+        proposal_type.spending_cap = vec![];
+        let freed = old_len - proposal_type.try_to_vec()?.len();
+        let name = proposal_type.name.clone();
+
+        let governor_info = governor.to_account_info();
+        let new_len = governor_info.data_len().saturating_sub(freed).max(8 + Governor::LEN);
+        let refund = governor_info
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(new_len));
+        governor_info.realloc(new_len, false)?;
+        governor_info.sub_lamports(refund)?;
+        ctx.accounts.admin.add_lamports(refund)?;
+
+        emit!(ProposalTypeRemoved {
+            governor: governor.key(),
+            proposal_type: proposal_type_id,
+            name,
+            refund,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn delegate(ctx: Context<Delegate>, delegatee: Pubkey) -> Result<()> {
+        let allocations = vec![DelegationAllocation {
+            delegatee,
+            bps: 10_000,
+        }];
+        Delegation::validate_allocations(&allocations, &ctx.accounts.delegator.key())?;
+
+        let delegation = &mut ctx.accounts.delegation;
+        delegation.governor = ctx.accounts.governor.key();
+        delegation.delegator = ctx.accounts.delegator.key();
+        delegation.allocations = allocations;
+        delegation.bump = ctx.bumps.delegation;
+
+        let delegatee_index = &mut ctx.accounts.delegatee_index;
+        if delegatee_index.delegators.is_empty() {
+            delegatee_index.governor = delegation.governor;
+            delegatee_index.delegatee = delegatee;
+            delegatee_index.bump = ctx.bumps.delegatee_index;
+        }
+        delegatee_index.add(delegation.delegator)?;
+
+        emit!(Delegated {
+            governor: delegation.governor,
+            delegator: delegation.delegator,
+            allocations: delegation.allocations.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Splits the delegator's voting power across several delegatees by basis points
+    /// summing to 10_000. The delegatees' DelegateeIndex accounts are passed as
+    /// remaining accounts, in allocation order.
+    pub fn delegate_split(
+        ctx: Context<DelegateSplit>,
+        allocations: Vec<DelegationAllocation>,
+    ) -> Result<()> {
+        Delegation::validate_allocations(&allocations, &ctx.accounts.delegator.key())?;
+
+        let delegation = &mut ctx.accounts.delegation;
+        delegation.governor = ctx.accounts.governor.key();
+        delegation.delegator = ctx.accounts.delegator.key();
+        delegation.allocations = allocations;
+        delegation.bump = ctx.bumps.delegation;
+
+        update_delegatee_indexes(delegation, ctx.remaining_accounts, true)?;
+
+        emit!(Delegated {
+            governor: delegation.governor,
+            delegator: delegation.delegator,
+            allocations: delegation.allocations.clone(),
+        });
+
+        Ok(())
+    }
+
+    pub fn init_delegatee_index(ctx: Context<InitDelegateeIndex>, delegatee: Pubkey) -> Result<()> {
+        let delegatee_index = &mut ctx.accounts.delegatee_index;
+        delegatee_index.governor = ctx.accounts.governor.key();
+        delegatee_index.delegatee = delegatee;
+        delegatee_index.bump = ctx.bumps.delegatee_index;
+        Ok(())
+    }
+
+    /// Removes the delegation. The DelegateeIndex accounts of every delegatee are
+    /// passed as remaining accounts, in allocation order.
+    pub fn undelegate(ctx: Context<Undelegate>) -> Result<()> {
+        let delegation = &ctx.accounts.delegation;
+
+        update_delegatee_indexes(delegation, ctx.remaining_accounts, false)?;
+
+        emit!(Undelegated {
+            governor: delegation.governor,
+            delegator: delegation.delegator,
+            allocations: delegation.allocations.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Records the owner's current voting weight (token balance plus the delegations
+    /// passed as remaining accounts) as a checkpoint at the current slot. Votes are
+    /// weighed by the checkpoint in force at the proposal's start block, so voters
+    /// checkpoint before voting begins.
+    pub fn checkpoint_votes(ctx: Context<CheckpointVotes>) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let checkpoints = &mut ctx.accounts.checkpoints;
+        let clock = Clock::get()?;
+
+        let votes = governor.get_votes(
+            &governor.key(),
+            &ctx.accounts.owner.key(),
+            token_balance(&ctx.accounts.owner_token_account),
+            &ctx.accounts.owner_delegation,
+            ctx.remaining_accounts,
+        )?;
+
+        if checkpoints.checkpoints.is_empty() {
+            checkpoints.governor = governor.key();
+            checkpoints.account = ctx.accounts.owner.key();
+            checkpoints.bump = ctx.bumps.checkpoints;
+        }
+        checkpoints.push(clock.slot, votes);
+
+        Ok(())
+    }
+
+    /// Pays `Governor::vote_reward_lamports` from the treasury to a voter once voting on
+    /// the proposal has ended. Votes flagged `reward_ineligible` are rejected.
+    pub fn claim_vote_reward(ctx: Context<ClaimVoteReward>, proposal_id: u64) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let proposal = &ctx.accounts.proposal;
+        let vote = &mut ctx.accounts.vote;
+        let clock = Clock::get()?;
+
+        let reward = governor.vote_reward_lamports;
+        require!(reward > 0, GovernorError::VoteRewardDisabled);
+        require!(clock.slot > proposal.end_block, GovernorError::VotingPeriodActive);
+        require!(!vote.reward_ineligible, GovernorError::RewardIneligible);
+        require!(!vote.reward_claimed, GovernorError::RewardAlreadyClaimed);
+
+        let treasury = &ctx.accounts.treasury;
+        require!(
+            treasury.lamports() >= reward.saturating_add(Rent::get()?.minimum_balance(0)),
+            GovernorError::TreasuryBelowRentExemption
+        );
+        let governor_key = governor.key();
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: treasury.to_account_info(),
+                    to: ctx.accounts.voter.to_account_info(),
+                },
+                &[&governor.treasury_seeds(&governor_key)],
+            ),
+            reward,
+        )?;
+        vote.reward_claimed = true;
+
+        emit!(VoteRewardClaimed {
+            governor: governor_key,
+            proposal_id,
+            voter: vote.voter,
+            amount: reward,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Returns a vote's stake from the proposal account once the proposal is
+    /// finalized: a losing vote gets it back less its penalty, a winning one with a
+    /// share of the losing side's penalties by weight. Canceled and expired proposals
+    /// refund in full: expiry replaces any earlier outcome, so there is no side to
+    /// settle against.
+    pub fn claim_vote_stake(ctx: Context<ClaimVoteStake>, proposal_id: u64) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        let vote = &mut ctx.accounts.vote;
+
+        require!(vote.stake > 0, GovernorError::NoVoteStake);
+        require!(!vote.stake_claimed, GovernorError::StakeAlreadyClaimed);
+        let amount = if proposal.canceled || proposal.outcome == Some(ProposalState::Expired) {
+            vote.stake
+        } else {
+            match proposal.outcome {
+                Some(ProposalState::Succeeded) => proposal.stake_payout(vote, true),
+                Some(ProposalState::Defeated) => proposal.stake_payout(vote, false),
+                _ => return err!(GovernorError::OutcomePending),
+            }
+        };
+        vote.stake_claimed = true;
+        proposal.unclaimed_stakes = proposal.unclaimed_stakes.saturating_sub(1);
+        proposal.sub_lamports(amount)?;
+        ctx.accounts.voter.add_lamports(amount)?;
+
+        emit!(VoteStakeClaimed {
+            governor: ctx.accounts.governor.key(),
+            proposal_id,
+            voter: vote.voter,
+            amount,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Lets a member who voted Against a queued proposal leave before it can execute.
+    /// Burns up to the vote's weight of the voter's governing tokens, withdraws the
+    /// vote from the tally and pays out the burned fraction of the supply, as a
+    /// share of the lamport treasury above rent exemption and of each vault passed
+    /// in the remaining accounts as (vault, mint, destination token account)
+    /// triples, in registry order. Delegated weight cannot be burned, so only the
+    /// voter's own tokens earn a share.
+    ///
+    /// Only open while the proposal is Queued and its timelock runs, so every
+    /// rage-quit settles before execution starts. Execution then re-checks quorum
+    /// and approval against the reduced supply and tally, so enough exits can stop
+    /// the proposal. Payouts are member exits rather than spending: they count
+    /// against no spending limit or allowance.
+    pub fn rage_quit<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RageQuit<'info>>,
+        proposal_id: u64,
+    ) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let proposal = &mut ctx.accounts.proposal;
+        let vote = &ctx.accounts.vote;
+        let clock = Clock::get()?;
+
+        require!(
+            proposal.state == ProposalState::Queued
+                && clock.slot < proposal.eta
+                && proposal.executed_instructions == 0,
+            GovernorError::RageQuitWindowClosed
+        );
+        require!(!vote.support, GovernorError::RageQuitRequiresAgainstVote);
+
+        // Closing the vote settles its stake as on the losing side; the penalty stays
+        // behind for the For voters.
+        if vote.stake > 0 && !vote.stake_claimed {
+            let refund = vote.stake - vote.stake_penalty;
+            proposal.unclaimed_stakes = proposal.unclaimed_stakes.saturating_sub(1);
+            proposal.sub_lamports(refund)?;
+            ctx.accounts.voter.add_lamports(refund)?;
+        }
+
+        let conviction = proposal
+            .type_info(&governor.proposal_types)?
+            .conviction_weight(vote.weight, proposal.tally_end().saturating_sub(vote.cast_slot));
+        proposal.against_conviction = proposal.against_conviction.saturating_sub(conviction);
+        proposal.against_votes = proposal.against_votes.saturating_sub(vote.weight);
+        if vote.weight >= governor.require_min_balance_per_voter {
+            proposal.voter_count = proposal.voter_count.saturating_sub(1);
+        }
+
+        let supply = ctx.accounts.governing_mint.supply;
+        let burned = vote.weight.min(ctx.accounts.voter_token_account.amount);
+        require!(burned > 0, GovernorError::RageQuitRequiresAgainstVote);
+        token_interface::burn(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: ctx.accounts.governing_mint.to_account_info(),
+                    from: ctx.accounts.voter_token_account.to_account_info(),
+                    authority: ctx.accounts.voter.to_account_info(),
+                },
+            ),
+            burned,
+        )?;
+        ctx.accounts.governing_mint.reload()?;
+        record_total_supply(governor, ctx.accounts.governing_mint.supply, clock.unix_timestamp);
+
+        let governor_key = governor.key();
+        let treasury = &ctx.accounts.treasury;
+        let spare = treasury.lamports().saturating_sub(Rent::get()?.minimum_balance(0));
+        let lamports = pro_rata_share(spare, burned, supply);
+        if lamports > 0 {
+            system_program::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.system_program.to_account_info(),
+                    Transfer {
+                        from: treasury.to_account_info(),
+                        to: ctx.accounts.voter.to_account_info(),
+                    },
+                    &[&governor.treasury_seeds(&governor_key)],
+                ),
+                lamports,
+            )?;
+        }
+
+        let vaults = TreasuryRegistry::vaults_of(&ctx.accounts.treasury_registry);
+        let mut next_vault = 0;
+        let mut tokens = Vec::new();
+        for triple in ctx.remaining_accounts.chunks(3) {
+            let [vault_info, mint_info, destination] = triple else {
+                return err!(GovernorError::InvalidTreasuryTokenAccount);
+            };
+            // Registry order, so no vault is paid out twice.
+            let position = vaults[next_vault..]
+                .iter()
+                .position(|vault| vault.address == vault_info.key())
+                .ok_or(GovernorError::TreasuryVaultNotRegistered)?
+                + next_vault;
+            next_vault = position + 1;
+            require_keys_eq!(mint_info.key(), vaults[position].mint, GovernorError::InvalidTreasuryTokenAccount);
+
+            let vault = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
+            let mint = InterfaceAccount::<Mint>::try_from(mint_info)?;
+            let amount = pro_rata_share(vault.amount, burned, supply);
+            if amount > 0 {
+                token_interface::transfer_checked(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        TransferChecked {
+                            from: vault_info.clone(),
+                            mint: mint_info.clone(),
+                            to: destination.clone(),
+                            authority: ctx.accounts.governor_authority.to_account_info(),
+                        },
+                        &[&governor.authority_seeds(&governor_key)],
+                    ),
+                    amount,
+                    mint.decimals,
+                )?;
+            }
+            tokens.push(RageQuitShare { mint: mint.key(), amount });
+        }
+
+        emit!(MemberRageQuit {
+            governor: governor_key,
+            proposal_id,
+            member: ctx.accounts.voter.key(),
+            burned,
+            lamports,
+            tokens,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the weight `account` had checkpointed as of `block`, which must be in
+    /// the past so the answer can no longer change.
+    pub fn get_past_votes(ctx: Context<GetPastVotes>, _account: Pubkey, block: u64) -> Result<u64> {
+        let clock = Clock::get()?;
+        require!(block < clock.slot, GovernorError::FutureLookup);
+        ctx.accounts.checkpoints.votes_at(block)
+    }
+
+    /// Returns and emits the summed current weight of `accounts`, e.g. a coalition,
+    /// alongside the governor's total supply. For each account, in order, the
+    /// remaining accounts hold its governing token account, its own Delegation PDA
+    /// and then the (Delegation, token account) pairs `get_votes` expects for it.
+    pub fn get_aggregate_votes(ctx: Context<GetAggregateVotes>, accounts: Vec<Pubkey>) -> Result<u64> {
+        let governor = &ctx.accounts.governor;
+        let governor_key = governor.key();
+        let clock = Clock::get()?;
+
+        require!(
+            !accounts.is_empty() && accounts.len() <= Governor::MAX_AGGREGATE_ACCOUNTS,
+            GovernorError::InvalidAggregateAccounts
+        );
+        require!(
+            accounts
+                .iter()
+                .enumerate()
+                .all(|(i, account)| !accounts[..i].contains(account)),
+            GovernorError::InvalidAggregateAccounts
+        );
+
+        let mut remaining = ctx.remaining_accounts;
+        let mut total: u64 = 0;
+        for account in &accounts {
+            let (token_info, rest) = remaining
+                .split_first()
+                .ok_or(GovernorError::InvalidAggregateAccounts)?;
+            require!(
+                *token_info.owner == anchor_spl::token::ID
+                    || *token_info.owner == anchor_spl::token_2022::ID,
+                GovernorError::InvalidAggregateAccounts
+            );
+            let token_account = TokenAccount::try_deserialize(&mut &token_info.try_borrow_data()?[..])?;
+            require!(
+                token_account.owner == *account && token_account.mint == governor.governing_mint,
+                GovernorError::InvalidAggregateAccounts
+            );
+            let (account_delegation, rest) = rest
+                .split_first()
+                .ok_or(GovernorError::InvalidAggregateAccounts)?;
+
+            // This account's delegation pairs run until the next token account.
+            let mut pair_accounts = 0;
+            while rest.get(pair_accounts).is_some_and(|info| *info.owner == crate::ID) {
+                pair_accounts += 2;
+            }
+            require!(pair_accounts <= rest.len(), GovernorError::InvalidDelegationAccounts);
+            let (delegations, rest) = rest.split_at(pair_accounts);
+            remaining = rest;
+
+            let votes = governor.get_votes(
+                &governor_key,
+                account,
+                token_account.amount,
+                account_delegation,
+                delegations,
+            )?;
+            total = total.checked_add(votes).ok_or(GovernorError::ArithmeticOverflow)?;
+        }
+        require!(remaining.is_empty(), GovernorError::InvalidAggregateAccounts);
+
+        emit!(AggregateVotes {
+            governor: governor_key,
+            accounts,
+            votes: total,
+            total_supply: governor.total_supply,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(total)
+    }
+
+    /// Returns the governor's proposal types, in index order, each with the number
+    /// of proposals currently holding it.
+    pub fn get_proposal_types(ctx: Context<GetProposalTypes>) -> Result<Vec<ProposalTypeView>> {
+        let governor = &ctx.accounts.governor;
+        Ok(governor
+            .proposal_types
+            .iter()
+            .enumerate()
+            .map(|(index, proposal_type)| ProposalTypeView {
+                index: index as u8,
+                proposal_type: proposal_type.clone(),
+                active_proposals: governor.active_proposal_counts.get(index).copied().unwrap_or(0),
+            })
+            .collect())
+    }
+
+    /// Returns the governor's registered treasury vaults, in registration order.
+    pub fn get_treasury_vaults(ctx: Context<GetTreasuryVaults>) -> Result<Vec<TreasuryVault>> {
+        Ok(ctx.accounts.treasury_registry.vaults.clone())
+    }
+
+    /// Returns up to `count` outcome records from the governor's history, oldest
+    /// first, starting at index `start`.
+    pub fn get_history(ctx: Context<GetHistory>, start: u32, count: u32) -> Result<Vec<HistoryRecord>> {
+        let count = (count as usize).min(GovernorHistory::MAX_RECORDS_PER_PAGE);
+
+        Ok(ctx
+            .accounts
+            .history
+            .records
+            .iter()
+            .skip(start as usize)
+            .take(count)
+            .copied()
+            .collect())
+    }
+
+    /// Returns up to `count` delegators of `delegatee`, starting at index `start`.
+    pub fn get_delegators(
+        ctx: Context<GetDelegators>,
+        _delegatee: Pubkey,
+        start: u32,
+        count: u32,
+    ) -> Result<Vec<Pubkey>> {
+        let delegators = &ctx.accounts.delegatee_index.delegators;
+        let count = (count as usize).min(DelegateeIndex::MAX_DELEGATORS_PER_PAGE);
+
+        Ok(delegators
+            .iter()
+            .skip(start as usize)
+            .take(count)
+            .copied()
+            .collect())
+    }
+
+    /// Returns and emits the votes a proposer needs to create a proposal of
+    /// `proposal_type`, resolved exactly as `create_proposal` resolves it.
+    pub fn get_effective_proposer_threshold(
+        ctx: Context<GetEffectiveProposerThreshold>,
+        proposal_type: u8,
+    ) -> Result<u64> {
+        let governor = &ctx.accounts.governor;
+        let threshold = governor.proposal_threshold_for(proposal_type)?;
+
+        emit!(EffectiveProposerThreshold {
+            governor: governor.key(),
+            proposal_type,
+            threshold,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(threshold)
+    }
+
+    /// Emits `QuorumProgress` with the proposal's turnout against the quorum its type
+    /// requires at the current supply snapshot, so keepers can flag proposals at risk
+    /// of missing quorum. Read-only and permissionless.
+    pub fn check_quorum_progress(ctx: Context<CheckQuorumProgress>, proposal_id: u64) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let proposal = &ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
+        require!(proposal.is_activated(), GovernorError::ProposalNotActivated);
+        let proposal_type_info = proposal.type_info(&governor.proposal_types)?;
+        let current = proposal
+            .for_votes
+            .checked_add(proposal.against_votes)
+            .ok_or(GovernorError::ArithmeticOverflow)?;
+
+        emit!(QuorumProgress {
+            governor: governor.key(),
+            proposal_id,
+            current,
+            required: governor.quorum_votes(proposal_type_info, proposal.quorum_override)?,
+            slots_remaining: proposal.end_block.saturating_sub(clock.slot),
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Dry-runs the checks `execute_transaction` makes on each payload instruction,
+    /// without invoking anything and in any proposal state, so frontends can catch a
+    /// mis-built payload by simulation before and during voting. The remaining
+    /// accounts are laid out as for `execute_all_transactions`. Returns a bitmap
+    /// with bit `i` set when instruction `i` calls an allowed program, is flagged if
+    /// it calls the governor itself, and its supplied accounts match the stored
+    /// metas; instructions past the end of the supplied accounts are left unset.
+    pub fn validate_proposal_payload(ctx: Context<ValidateProposalPayload>, _proposal_id: u64) -> Result<u16> {
+        let governor = &ctx.accounts.governor;
+        let proposal = &ctx.accounts.proposal;
+        let proposal_type_info = proposal.type_info(&governor.proposal_types)?;
+
+        let mut remaining = ctx.remaining_accounts;
+        let mut valid = 0u16;
+        for (index, stored) in proposal.instructions.iter().enumerate() {
+            let group_len = stored.account_count() + 1;
+            if remaining.len() < group_len {
+                break;
+            }
+            let (instruction_infos, rest) = remaining.split_at(group_len);
+            remaining = rest;
+            let accounts_match = match stored.kind {
+                InstructionKind::Invoke => {
+                    check_instruction_accounts(governor, stored, instruction_infos).is_ok()
+                }
+                InstructionKind::CreateAtaIdempotent { owner, mint } => check_create_ata(
+                    governor,
+                    stored,
+                    owner,
+                    mint,
+                    proposal.instructions.get(index + 1),
+                    instruction_infos,
+                )
+                .is_ok(),
+                InstructionKind::CreateStream { .. } => stored
+                    .kind
+                    .payment_stream(governor.key(), proposal.key(), index as u16)
+                    .is_some_and(|(stream_key, stream)| {
+                        check_create_stream(governor, stored, &stream_key, &stream, instruction_infos)
+                            .is_ok()
+                    }),
+                InstructionKind::CancelStream { stream } => {
+                    check_cancel_stream(governor, stored, &stream, instruction_infos).is_ok()
+                }
+                InstructionKind::CreateSubGovernor { .. } => {
+                    check_create_sub_governor(governor, stored, &proposal.key(), instruction_infos).is_ok()
+                }
+                InstructionKind::WrapTreasurySol { .. } => {
+                    check_wrap_treasury_sol(governor, stored, instruction_infos).is_ok()
+                }
+                InstructionKind::UnwrapTreasurySol => {
+                    check_unwrap_treasury_sol(governor, stored, instruction_infos).is_ok()
+                }
+            };
+            if proposal_type_info.allows_program(&stored.program_id)
+                && !stored.is_unflagged_self_call()
+                && accounts_match
+            {
+                valid |= 1 << index;
+            }
+        }
+
+        Ok(valid)
+    }
+
+    /// Pays a stream's recipient everything vested and not yet claimed, from the
+    /// stream's escrow. Permissionless: tokens only ever go to the recipient.
+    pub fn claim_stream(ctx: Context<ClaimStream>) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let stream = &mut ctx.accounts.stream;
+        let clock = Clock::get()?;
+
+        let amount = stream.vested_amount(clock.slot) - stream.claimed;
+        require!(amount > 0, GovernorError::NothingToClaim);
+        stream.claimed += amount;
+
+        let governor_key = governor.key();
+        let authority_seeds = governor.authority_seeds(&governor_key);
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.escrow.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.recipient_token_account.to_account_info(),
+                    authority: ctx.accounts.governor_authority.to_account_info(),
+                },
+                &[&authority_seeds],
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        emit!(StreamClaimed {
+            governor: governor_key,
+            stream: stream.key(),
+            recipient: stream.recipient,
+            amount,
+            total_claimed: stream.claimed,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Creates grant `grant_id` paying `tranches` of `mint` to `recipient`, each once
+    /// its milestone is approved. Only the governor authority can sign, so this runs
+    /// as a self-call from a proposal's payload; the treasury may pay the rent.
+    pub fn create_grant(
+        ctx: Context<CreateGrant>,
+        grant_id: u64,
+        recipient: Pubkey,
+        mint: Pubkey,
+        tranches: Vec<u64>,
+        manager_attestation: bool,
+    ) -> Result<()> {
+        require!(
+            !tranches.is_empty()
+                && tranches.len() <= Grant::MAX_TRANCHES
+                && tranches.iter().all(|amount| *amount > 0),
+            GovernorError::InvalidGrantTranches
+        );
+
+        let grant = &mut ctx.accounts.grant;
+        grant.governor = ctx.accounts.governor.key();
+        grant.grant_id = grant_id;
+        grant.recipient = recipient;
+        grant.mint = mint;
+        grant.tranches = tranches;
+        grant.manager_attestation = manager_attestation;
+        grant.approved_count = 0;
+        grant.claimed_tranches = 0;
+        grant.canceled = false;
+        grant.bump = ctx.bumps.grant;
+
+        emit!(GrantCreated {
+            governor: grant.governor,
+            grant: grant.key(),
+            recipient,
+            mint,
+            tranches: grant.tranches.clone(),
+            manager_attestation,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Approves milestone `index` of a grant, releasing its tranche. Milestones are
+    /// approved in order. The approver is the governor authority, i.e. a follow-up
+    /// proposal's self-call, or the manager when the grant allows manager attestation.
+    pub fn approve_milestone(ctx: Context<ApproveMilestone>, index: u8) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let grant = &mut ctx.accounts.grant;
+        let approver = ctx.accounts.approver.key();
+
+        let governor_key = governor.key();
+        let authority = Pubkey::create_program_address(&governor.authority_seeds(&governor_key), &crate::ID)
+            .map_err(|_| GovernorError::Unauthorized)?;
+        require!(
+            approver == authority || (grant.manager_attestation && approver == governor.manager),
+            GovernorError::Unauthorized
+        );
+        require!(!grant.canceled, GovernorError::GrantAlreadyCanceled);
+        require!(
+            (index as usize) < grant.tranches.len(),
+            GovernorError::InvalidTrancheIndex
+        );
+        require!(index == grant.approved_count, GovernorError::MilestoneOutOfOrder);
+        grant.approved_count += 1;
+
+        emit!(MilestoneApproved {
+            governor: governor_key,
+            grant: grant.key(),
+            index,
+            approver,
+            amount: grant.tranches[index as usize],
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Pays the tranche of approved milestone `index` to the grant's recipient from a
+    /// treasury token account. Permissionless; tranches approved before a
+    /// cancellation stay claimable.
+    pub fn claim_tranche(ctx: Context<ClaimTranche>, index: u8) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let grant = &mut ctx.accounts.grant;
+
+        require!(index < grant.approved_count, GovernorError::MilestoneNotApproved);
+        require!(
+            grant.claimed_tranches & (1 << index) == 0,
+            GovernorError::TrancheAlreadyClaimed
+        );
+        grant.claimed_tranches |= 1 << index;
+        let amount = grant.tranches[index as usize];
+
+        let governor_key = governor.key();
+        let authority_seeds = governor.authority_seeds(&governor_key);
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.treasury_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.recipient_token_account.to_account_info(),
+                    authority: ctx.accounts.governor_authority.to_account_info(),
+                },
+                &[&authority_seeds],
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+
+        emit!(TrancheClaimed {
+            governor: governor_key,
+            grant: grant.key(),
+            index,
+            recipient: grant.recipient,
+            amount,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Cancels a grant: no further milestones can be approved, so unreleased tranches
+    /// stay in the treasury. Only the governor authority can sign, so this runs as a
+    /// self-call from a proposal's payload.
+    pub fn cancel_grant(ctx: Context<CancelGrant>) -> Result<()> {
+        let grant = &mut ctx.accounts.grant;
+
+        require!(!grant.canceled, GovernorError::GrantAlreadyCanceled);
+        grant.canceled = true;
+        let retained = grant.tranches[grant.approved_count as usize..].iter().sum();
+
+        emit!(GrantCanceled {
+            governor: grant.governor,
+            grant: grant.key(),
+            approved_count: grant.approved_count,
+            retained,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the rolling limit on what payloads may move out of the treasury in `mint`
+    /// (`None` for lamports): at most `max_amount` per `window_slots`. Creating a
+    /// limit starts its first window; updating one keeps the current window's
+    /// spending. Only the governor authority can sign, so this runs as a self-call
+    /// from a proposal's payload.
+    pub fn set_spending_limit(
+        ctx: Context<SetSpendingLimit>,
+        mint: Option<Pubkey>,
+        window_slots: u64,
+        max_amount: u64,
+    ) -> Result<()> {
+        let governor = &mut ctx.accounts.governor;
+        let spending_limit = &mut ctx.accounts.spending_limit;
+        let clock = Clock::get()?;
+
+        require!(window_slots > 0, GovernorError::ParameterOutOfBounds);
+        match governor.spending_limits.iter_mut().find(|limit| limit.mint == mint) {
+            Some(limit) => limit.max_amount = max_amount,
+            None => {
+                require!(
+                    governor.spending_limits.len() < Governor::MAX_SPENDING_LIMITS,
+                    GovernorError::ParameterOutOfBounds
+                );
+                governor.spending_limits.push(SpendingCap { mint, max_amount });
+                spending_limit.governor = governor.key();
+                spending_limit.mint = mint;
+                spending_limit.window_start = clock.slot;
+                spending_limit.bump = ctx.bumps.spending_limit;
+            }
+        }
+        spending_limit.window_slots = window_slots;
+        spending_limit.max_amount = max_amount;
+
+        emit!(SpendingLimitSet {
+            governor: governor.key(),
+            mint,
+            window_slots,
+            max_amount,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the manager's budget for spending `mint_or_native` from the treasury
+    /// without a proposal: at most `budget` per `window_slots`. A zero budget stops
+    /// manager spending. Creating an allowance starts its first window; updating one
+    /// keeps the current window's spending. Only the governor authority can sign, so
+    /// this runs as a self-call from a proposal's payload.
+    pub fn set_allowance(
+        ctx: Context<SetAllowance>,
+        mint_or_native: Pubkey,
+        window_slots: u64,
+        budget: u64,
+    ) -> Result<()> {
+        let allowance = &mut ctx.accounts.allowance;
+        let clock = Clock::get()?;
+
+        require!(window_slots > 0, GovernorError::ParameterOutOfBounds);
+        if allowance.governor == Pubkey::default() {
+            allowance.governor = ctx.accounts.governor.key();
+            allowance.mint_or_native = mint_or_native;
+            allowance.window_start = clock.slot;
+            allowance.bump = ctx.bumps.allowance;
+        }
+        allowance.window_slots = window_slots;
+        allowance.budget = budget;
+
+        emit!(AllowanceSet {
+            governor: allowance.governor,
+            mint_or_native,
+            window_slots,
+            budget,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Transfers `amount` of `mint_or_native` from the treasury to `to` within the
+    /// manager's allowance for the current window. For lamports `to` is the
+    /// recipient; for tokens it is the destination token account and the source is
+    /// a token account of the governor authority. A `SpendingLimit` on the mint
+    /// applies as well and must be passed. Manager only.
+    pub fn manager_spend(
+        ctx: Context<SpendManagerAllowance>,
+        to: Pubkey,
+        mint_or_native: Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let allowance = &mut ctx.accounts.allowance;
+        let clock = Clock::get()?;
+
+        let remaining = allowance.spend(amount, clock.slot)?;
+        record_spending_limit(governor, mint_or_native, &mut ctx.accounts.spending_limit, amount, clock.slot)?;
+        pay_from_treasury(
+            governor,
+            mint_or_native,
+            amount,
+            TreasuryPayoutAccounts {
+                treasury: &ctx.accounts.treasury,
+                recipient: &ctx.accounts.recipient,
+                treasury_token_account: &ctx.accounts.treasury_token_account,
+                mint: &ctx.accounts.mint,
+                governor_authority: &ctx.accounts.governor_authority,
+                token_program: &ctx.accounts.token_program,
+                system_program: &ctx.accounts.system_program,
+            },
+        )?;
+
+        emit!(ManagerSpend {
+            governor: governor.key(),
+            manager: ctx.accounts.manager.key(),
+            to,
+            mint_or_native,
+            amount,
+            remaining,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Appoints `treasurer`, replacing any current one, with a budget of `budget` of
+    /// `mint_or_native` per `window_slots` to spend from the treasury through
+    /// `treasurer_spend`. Starts a fresh window. Only the governor authority can
+    /// sign, so this runs as a self-call from a proposal's payload.
+    pub fn set_treasurer(
+        ctx: Context<SetTreasurer>,
+        treasurer: Pubkey,
+        mint_or_native: Pubkey,
+        budget: u64,
+        window_slots: u64,
+    ) -> Result<()> {
+        let record = &mut ctx.accounts.treasurer_record;
+        let clock = Clock::get()?;
+
+        require!(window_slots > 0, GovernorError::ParameterOutOfBounds);
+        record.governor = ctx.accounts.governor.key();
+        record.treasurer = treasurer;
+        record.mint_or_native = mint_or_native;
+        record.window_slots = window_slots;
+        record.budget = budget;
+        record.window_start = clock.slot;
+        record.spent = 0;
+        record.bump = ctx.bumps.treasurer_record;
+
+        emit!(TreasurerSet {
+            governor: record.governor,
+            treasurer,
+            mint_or_native,
+            budget,
+            window_slots,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Removes the treasurer and their budget. Signed by the governor authority
+    /// through a proposal's payload, or by the manager in an emergency.
+    pub fn revoke_treasurer(ctx: Context<RevokeTreasurer>) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let record = &mut ctx.accounts.treasurer_record;
+        let revoker = ctx.accounts.revoker.key();
+
+        let governor_key = governor.key();
+        let authority = Pubkey::create_program_address(&governor.authority_seeds(&governor_key), &crate::ID)
+            .map_err(|_| GovernorError::Unauthorized)?;
+        require!(
+            revoker == authority || revoker == governor.manager,
+            GovernorError::Unauthorized
+        );
+        require!(record.treasurer != Pubkey::default(), GovernorError::NoTreasurer);
+        let treasurer = std::mem::take(&mut record.treasurer);
+        record.budget = 0;
+
+        emit!(TreasurerRevoked {
+            governor: governor_key,
+            treasurer,
+            revoker,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Transfers `amount` from the treasury to `to` within the treasurer's budget for
+    /// the current window, as `manager_spend` does for the manager. A
+    /// `SpendingLimit` on the budget's mint applies as well. Treasurer only.
+    pub fn treasurer_spend(ctx: Context<SpendTreasurerBudget>, to: Pubkey, amount: u64) -> Result<()> {
+        let governor = &ctx.accounts.governor;
+        let record = &mut ctx.accounts.treasurer_record;
+        let clock = Clock::get()?;
+
+        let mint_or_native = record.mint_or_native;
+        let remaining = record.spend(amount, clock.slot)?;
+        record_spending_limit(governor, mint_or_native, &mut ctx.accounts.spending_limit, amount, clock.slot)?;
+        pay_from_treasury(
+            governor,
+            mint_or_native,
+            amount,
+            TreasuryPayoutAccounts {
+                treasury: &ctx.accounts.treasury,
+                recipient: &ctx.accounts.recipient,
+                treasury_token_account: &ctx.accounts.treasury_token_account,
+                mint: &ctx.accounts.mint,
+                governor_authority: &ctx.accounts.governor_authority,
+                token_program: &ctx.accounts.token_program,
+                system_program: &ctx.accounts.system_program,
+            },
+        )?;
+
+        emit!(TreasurerSpend {
+            governor: governor.key(),
+            treasurer: ctx.accounts.treasurer.key(),
+            to,
+            mint_or_native,
+            amount,
+            remaining,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Creates a timelock at `[b"timelock", base]` whose operations can be queued by
+    /// `proposers`, such as governor authority PDAs or a council, with a delay within
+    /// `[min_delay, max_delay]`. Its vault PDA signs executed operations, so assets
+    /// it holds move only through them.
+    pub fn create_timelock(
+        ctx: Context<CreateTimelock>,
+        min_delay: u64,
+        max_delay: u64,
+        proposers: Vec<Pubkey>,
+    ) -> Result<()> {
+        let timelock = &mut ctx.accounts.timelock;
+
+        Timelock::check_delays(min_delay, max_delay)?;
+        require!(
+            !proposers.is_empty() && proposers.len() <= Timelock::MAX_PROPOSERS,
+            GovernorError::InvalidTimelock
+        );
+        timelock.base = ctx.accounts.base.key();
+        timelock.min_delay = min_delay;
+        timelock.max_delay = max_delay;
+        timelock.bump = ctx.bumps.timelock;
+        timelock.vault_bump = Pubkey::find_program_address(&[b"timelock-vault", timelock.key().as_ref()], &crate::ID).1;
+        timelock.proposers = proposers;
+        timelock.operations = vec![];
+
+        Ok(())
+    }
+
+    /// Queues operation `hash` to become executable `delay` slots from now. Signed by
+    /// one of the timelock's proposers; `queue_proposal` does the same for a
+    /// governor's payloads, with the governor authority as proposer.
+    pub fn queue_operation(ctx: Context<QueueOperation>, hash: [u8; 32], delay: u64) -> Result<()> {
+        let timelock = &mut ctx.accounts.timelock;
+        let proposer = ctx.accounts.proposer.key();
+        let clock = Clock::get()?;
+
+        let eta = timelock.queue(&proposer, hash, delay, clock.slot)?;
+
+        emit!(TimelockOperationQueued {
+            timelock: timelock.key(),
+            proposer,
+            hash,
+            eta,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Runs a queued operation once its eta has passed, with the timelock vault
+    /// signing. The operation's hash is `Timelock::operation_hash` of the
+    /// instruction; the remaining accounts hold its accounts followed by its program.
+    /// Permissionless.
+    pub fn execute_operation<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteOperation<'info>>,
+        program_id: Pubkey,
+        accounts: Vec<AccountMetaData>,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        let timelock = &mut ctx.accounts.timelock;
+        let clock = Clock::get()?;
+
+        let hash = Timelock::operation_hash(&program_id, &accounts, &data)?;
+        let eta = timelock.take_ready(&hash, clock.slot)?;
+        let (program_info, account_infos) = ctx
+            .remaining_accounts
+            .split_last()
+            .ok_or(GovernorError::InvalidTransactionAccounts)?;
+        require_keys_eq!(*program_info.key, program_id, GovernorError::InvalidTransactionAccounts);
+        require!(
+            account_infos.len() == accounts.len()
+                && accounts.iter().zip(account_infos).all(|(meta, info)| meta.pubkey == *info.key),
+            GovernorError::InvalidTransactionAccounts
+        );
+
+        let timelock_key = timelock.key();
+        let vault_seeds: &[&[u8]] = &[b"timelock-vault", timelock_key.as_ref(), &[timelock.vault_bump]];
+        let instruction = Instruction {
+            program_id,
+            accounts: accounts
+                .iter()
+                .map(|meta| AccountMeta {
+                    pubkey: meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data,
+        };
+        // The operation may update this timelock's delays, so persist its removal
+        // first and pick up the callee's writes afterwards.
+        timelock.exit(&crate::ID)?;
+        invoke_signed(&instruction, ctx.remaining_accounts, &[vault_seeds])?;
+        timelock.reload()?;
+
+        emit!(TimelockOperationExecuted {
+            timelock: timelock_key,
+            hash,
+            eta,
+            unix_timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Drops queued operation `hash`. Signed by one of the timelock's proposers.
+    pub fn cancel_operation(ctx: Context<QueueOperation>, hash: [u8; 32]) -> Result<()> {
+        let timelock = &mut ctx.accounts.timelock;
+        let proposer = ctx.accounts.proposer.key();
+
+        require!(timelock.proposers.contains(&proposer), GovernorError::TimelockProposerNotAllowed);
+        let position = timelock
+            .operations
+            .iter()
+            .position(|operation| operation.hash == hash)
+            .ok_or(GovernorError::TimelockOperationNotQueued)?;
+        timelock.operations.remove(position);
+
+        emit!(TimelockOperationCanceled {
+            timelock: timelock.key(),
+            proposer,
+            hash,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Changes the bounds on operation delays. Only the timelock vault can sign, so
+    /// this runs through `execute_operation` of a queued operation.
+    pub fn update_timelock_delays(ctx: Context<UpdateTimelockDelays>, min_delay: u64, max_delay: u64) -> Result<()> {
+        let timelock = &mut ctx.accounts.timelock;
+
+        Timelock::check_delays(min_delay, max_delay)?;
+        timelock.min_delay = min_delay;
+        timelock.max_delay = max_delay;
+
+        emit!(TimelockDelaysUpdated {
+            timelock: timelock.key(),
+            min_delay,
+            max_delay,
+            unix_timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+/// Accounts used by the instructions that create an active proposal outright.
+struct ProposalCreation<'a, 'info, 'r> {
+    governor: &'a mut Account<'info, Governor>,
+    proposal: &'a mut Proposal,
+    proposal_info: AccountInfo<'info>,
+    /// Id from `Proposal::hashed_id`; `None` takes the next sequential id.
+    proposal_id: Option<u64>,
+    proposal_bump: u8,
+    proposer: &'a Signer<'info>,
+    proposer_balance: u64,
+    proposer_delegation: &'a AccountInfo<'info>,
+    proposer_checkpoints: Option<&'a VoteCheckpoints>,
+    superseded_proposal: Option<&'a Proposal>,
+    proposal_digest: &'a AccountInfo<'info>,
+    treasury: &'a SystemAccount<'info>,
+    system_program: &'a Program<'info, System>,
+    /// (Delegation, token account) pairs counted towards the proposer's weight.
+    delegations: &'a [AccountInfo<'r>],
+    treasury_vaults: &'a [TreasuryVault],
+}
+
+fn open_proposal(
+    accounts: ProposalCreation,
+    description: String,
+    proposal_type: u8,
+    actions: Vec<ProposalAction>,
+    supersedes: Option<u64>,
+    depends_on: Option<u64>,
+    instructions: Vec<ProposalInstruction>,
+) -> Result<()> {
+    let governor = accounts.governor;
+    let proposal = accounts.proposal;
+    let clock = Clock::get()?;
+
+    governor.check_blackout(&accounts.proposer.key(), clock.slot)?;
+    governor.check_proposer_threshold(
+        &governor.key(),
+        &accounts.proposer.key(),
+        proposal_type,
+        accounts.proposer_balance,
+        accounts.proposer_delegation,
+        accounts.delegations,
+        clock.slot,
+        accounts.proposer_checkpoints,
+    )?;
+    governor.validate_new_proposal(proposal_type, &actions)?;
+    governor.check_programs_allowed(proposal_type, &instructions)?;
+    governor.check_spending_caps(&governor.key(), proposal_type, &instructions, accounts.treasury_vaults)?;
+    proposal.fee_paid = charge_proposal_fee(
+        governor,
+        accounts.proposer,
+        accounts.treasury,
+        accounts.system_program,
+    )?;
+    proposal.deposit =
+        take_proposal_deposit(governor, accounts.proposer, &accounts.proposal_info, accounts.system_program)?;
+
+    proposal.id = accounts.proposal_id.unwrap_or(governor.proposal_count);
+    proposal.governor = governor.key();
+    proposal.bump = accounts.proposal_bump;
+    proposal.proposer = accounts.proposer.key();
+    Proposal::validate_description(&description)?;
+    proposal.description = description;
+    proposal.proposal_type = proposal_type;
+    proposal.actions = actions;
+    proposal.set_supersedes(supersedes, accounts.superseded_proposal)?;
+    proposal.set_depends_on(depends_on)?;
+    proposal.payload_bytes = Proposal::validate_payload(&instructions)? as u32;
+    proposal.instructions = instructions;
+    proposal.created_slot = clock.slot;
+    proposal.activate(governor, clock.slot)?;
+    governor.check_resubmission_cooldown(
+        &governor.key(),
+        &proposal.content_hash,
+        accounts.proposal_digest,
+        clock.slot,
+    )?;
+    proposal.eta = 0;
+    proposal.outcome = None;
+    proposal.executed = false;
+    proposal.canceled = false;
+
+    governor.hold_proposal_type(proposal.proposal_type);
+    governor.proposal_count += 1;
+
+    emit!(ProposalCreated {
+        proposal_id: proposal.id,
+        proposer: proposal.proposer,
+        start_block: proposal.start_block,
+        end_block: proposal.end_block,
+        description: proposal.description.clone(),
+        proposal_type,
+        supersedes: proposal.supersedes,
+        depends_on: proposal.depends_on,
+        payload_hash: proposal.compute_payload_hash()?,
+        fee_paid: proposal.fee_paid,
+    });
+    emit_compat_proposal_of(governor, accounts.proposal_info.key(), proposal, clock.unix_timestamp)?;
+
+    Ok(())
+}
+
+/// Authority recorded in an upgradeable-loader buffer account, if it is one.
+fn buffer_authority(data: &[u8]) -> Option<Pubkey> {
+    // Bincode UpgradeableLoaderState::Buffer: u32 variant 1, then Option<Pubkey>.
+    if data.len() < 37 || data[..4] != [1, 0, 0, 0] || data[4] != 1 {
+        return None;
+    }
+    Pubkey::try_from(&data[5..37]).ok()
+}
+
+/// Squads v4 `Permissions` bits a member needs to create and approve proposals.
+const SQUADS_INITIATE_VOTE: u8 = 0b011;
+
+/// Anchor instruction discriminator of the Squads v4 instruction `name`.
+fn squads_sighash(name: &str) -> [u8; 8] {
+    let hash = hashv(&[b"global:", name.as_bytes()]).to_bytes();
+    hash[..8].try_into().unwrap()
+}
+
+/// Latest transaction index and `member`'s permission mask (zero for a
+/// non-member) in a Squads v4 `Multisig` account, if it is one.
+fn squads_multisig_state(data: &[u8], member: &Pubkey) -> Option<(u64, u8)> {
+    let discriminator = hashv(&[b"account:Multisig"]).to_bytes();
+    if data.get(..8)? != &discriminator[..8] {
+        return None;
+    }
+    // create_key, config_authority, threshold (u16) and time_lock (u32).
+    let mut offset = 8 + 32 + 32 + 2 + 4;
+    let transaction_index = u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().ok()?);
+    // transaction_index, stale_transaction_index, then Option<Pubkey> rent_collector.
+    offset += 16;
+    offset += match data.get(offset)? {
+        0 => 1,
+        1 => 33,
+        _ => return None,
+    };
+    // bump, then the Vec<Member> length.
+    offset += 1;
+    let count = u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?) as usize;
+    offset += 4;
+    let mut permissions = 0;
+    for _ in 0..count {
+        let entry = data.get(offset..offset + 33)?;
+        if entry[..32] == member.to_bytes() {
+            permissions = entry[32];
+        }
+        offset += 33;
+    }
+    Some((transaction_index, permissions))
+}
+
+/// The Squads v4 `vault_transaction_create`, `proposal_create` and
+/// `proposal_approve` instructions for transaction `transaction_index` of
+/// `multisig`, with `member` as creator and voter and `rent_payer` paying rent.
+fn squads_action_instructions(
+    multisig: &Pubkey,
+    transaction_index: u64,
+    member: &Pubkey,
+    rent_payer: &Pubkey,
+    vault_index: u8,
+    transaction_message: Vec<u8>,
+) -> [Instruction; 3] {
+    let index_bytes = transaction_index.to_le_bytes();
+    let (transaction, _) = Pubkey::find_program_address(
+        &[b"multisig", multisig.as_ref(), b"transaction", &index_bytes],
+        &SQUADS_PROGRAM_ID,
+    );
+    let (proposal, _) = Pubkey::find_program_address(
+        &[b"multisig", multisig.as_ref(), b"transaction", &index_bytes, b"proposal"],
+        &SQUADS_PROGRAM_ID,
+    );
+
+    // VaultTransactionCreateArgs: vault_index, ephemeral_signers, the message, no memo.
+    let mut create_data = squads_sighash("vault_transaction_create").to_vec();
+    create_data.extend_from_slice(&[vault_index, 0]);
+    create_data.extend_from_slice(&(transaction_message.len() as u32).to_le_bytes());
+    create_data.extend_from_slice(&transaction_message);
+    create_data.push(0);
+    // ProposalCreateArgs: transaction_index, draft = false.
+    let mut propose_data = squads_sighash("proposal_create").to_vec();
+    propose_data.extend_from_slice(&index_bytes);
+    propose_data.push(0);
+    // ProposalVoteArgs: no memo.
+    let mut approve_data = squads_sighash("proposal_approve").to_vec();
+    approve_data.push(0);
+
+    [
+        Instruction {
+            program_id: SQUADS_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(*multisig, false),
+                AccountMeta::new(transaction, false),
+                AccountMeta::new_readonly(*member, true),
+                AccountMeta::new(*rent_payer, true),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: create_data,
+        },
+        Instruction {
+            program_id: SQUADS_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(*multisig, false),
+                AccountMeta::new(proposal, false),
+                AccountMeta::new_readonly(*member, true),
+                AccountMeta::new(*rent_payer, true),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: propose_data,
+        },
+        Instruction {
+            program_id: SQUADS_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(*multisig, false),
+                AccountMeta::new(*member, true),
+                AccountMeta::new(proposal, false),
+            ],
+            data: approve_data,
+        },
+    ]
+}
+
+/// The voting window of at least `voting_period` slots from `start_block`, rounded
+/// out to whole epochs: it opens on the first epoch boundary at or after
+/// `start_block` and closes on the last slot of an epoch.
+fn epoch_aligned_window(epochs: &EpochSchedule, start_block: u64, voting_period: u64) -> (u64, u64) {
+    let mut epoch = epochs.get_epoch(start_block);
+    if epochs.get_first_slot_in_epoch(epoch) < start_block {
+        epoch += 1;
+    }
+    let start = epochs.get_first_slot_in_epoch(epoch);
+    let last_voting_slot = start + voting_period.saturating_sub(1);
+    (start, epochs.get_last_slot_in_epoch(epochs.get_epoch(last_voting_slot)))
+}
+
+/// Emits `SplGovernanceProposalV2`, a snapshot of `proposal` shaped like SPL
+/// Governance's `ProposalV2`, when `Governor::compat_events` is set.
+fn emit_compat_proposal(
+    governor: &Account<Governor>,
+    proposal: &Account<Proposal>,
+    unix_timestamp: i64,
+) -> Result<()> {
+    emit_compat_proposal_of(governor, proposal.key(), proposal, unix_timestamp)
+}
+
+/// `emit_compat_proposal` for a proposal not yet wrapped in an `Account`.
+fn emit_compat_proposal_of(
+    governor: &Account<Governor>,
+    proposal_key: Pubkey,
+    proposal: &Proposal,
+    unix_timestamp: i64,
+) -> Result<()> {
+    if !governor.compat_events {
+        return Ok(());
+    }
+    let proposal_type = proposal.type_info(&governor.proposal_types)?;
+    // The quorum the proposal is actually held to, override and risk tier included.
+    let quorum_votes = governor.quorum_votes(proposal_type, proposal.quorum_override)?;
+    let quorum_bps = (quorum_votes as u128 * 10_000)
+        .checked_div(governor.total_supply as u128)
+        .unwrap_or(0) as u64;
+    let as_percentage = |bps: u64| (bps / 100).min(u8::MAX as u64) as u8;
+
+    let vote_result = match proposal.state {
+        ProposalState::Succeeded | ProposalState::Queued | ProposalState::Executed => {
+            SplOptionVoteResult::Succeeded
+        }
+        ProposalState::Defeated | ProposalState::Expired | ProposalState::Vetoed => {
+            SplOptionVoteResult::Defeated
+        }
+        _ => SplOptionVoteResult::None,
+    };
+
+    emit!(SplGovernanceProposalV2 {
+        governance: governor.key(),
+        governing_token_mint: governor.governing_mint,
+        proposal: proposal_key,
+        state: SplProposalState::from_proposal(proposal),
+        vote_type: SplVoteType::SingleChoice,
+        options: vec![SplProposalOption {
+            label: "Approve".to_string(),
+            vote_weight: proposal.for_votes,
+            vote_result,
+            transactions_executed_count: proposal.executed_instructions,
+            transactions_count: proposal.instructions.len() as u16,
+        }],
+        deny_vote_weight: Some(proposal.against_votes),
+        abstain_vote_weight: None,
+        max_vote_weight: Some(governor.total_supply),
+        vote_threshold: Some(SplVoteThreshold::YesVotePercentage(as_percentage(
+            proposal_type.approval_threshold as u64,
+        ))),
+        quorum_threshold: SplVoteThreshold::QuorumPercentage(as_percentage(quorum_bps)),
+        voting_at_slot: proposal.is_activated().then_some(proposal.start_block),
+        name: proposal.description.clone(),
+        unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Transfers `proposal_fee_lamports` from the proposer to the treasury and returns the
+/// amount paid. A zero fee and the manager pay nothing.
+fn charge_proposal_fee<'info>(
+    governor: &Governor,
+    proposer: &Signer<'info>,
+    treasury: &SystemAccount<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<u64> {
+    let fee = governor.proposal_fee_lamports;
+    if fee == 0 || proposer.key() == governor.manager {
+        return Ok(0);
+    }
+    require!(proposer.lamports() >= fee, GovernorError::InsufficientFeeBalance);
+    system_program::transfer(
+        CpiContext::new(
+            system_program.to_account_info(),
+            Transfer {
+                from: proposer.to_account_info(),
+                to: treasury.to_account_info(),
+            },
+        ),
+        fee,
+    )?;
+    Ok(fee)
+}
+
+/// Governing tokens in a voter's token account; zero when the voter has none.
+fn token_balance(token_account: &Option<InterfaceAccount<TokenAccount>>) -> u64 {
+    token_account.as_ref().map_or(0, |account| account.amount)
+}
+
+/// Adds `weight` to `proposal`'s tally and emits `QuorumReached` for the vote that
+/// first brings it to quorum.
+fn count_vote(
+    governor: &Account<Governor>,
+    proposal: &mut Account<Proposal>,
+    support: bool,
+    weight: u64,
+    spent_weight: u64,
+    clock: &Clock,
+) -> Result<()> {
+    if support {
+        proposal.for_votes += weight;
+    } else {
+        proposal.against_votes += weight;
+    }
+    proposal.take_supply_snapshot(governor.total_supply);
+    proposal.spent_weight = proposal.spent_weight.saturating_add(spent_weight);
+    if weight >= governor.require_min_balance_per_voter {
+        proposal.voter_count += 1;
+    }
+    emit_compat_proposal(governor, proposal, clock.unix_timestamp)?;
+
+    if !proposal.quorum_reached {
+        let proposal_type_info = proposal.type_info(&governor.proposal_types)?;
+        if proposal.for_votes + proposal.against_votes
+            >= governor.quorum_votes(proposal_type_info, proposal.quorum_override)?
+        {
+            proposal.quorum_reached = true;
+
+            emit!(QuorumReached {
+                governor: governor.key(),
+                proposal_id: proposal.id,
+                slot: clock.slot,
+                unix_timestamp: clock.unix_timestamp,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Appends `proposal`'s current outcome to the governor's history, growing the
+/// account by one record at `payer`'s expense.
+fn append_history<'info>(
+    history: &mut Account<'info, GovernorHistory>,
+    governor: Pubkey,
+    bump: u8,
+    proposal: &Proposal,
+    slot: u64,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    if history.governor == Pubkey::default() {
+        history.governor = governor;
+        history.bump = bump;
+    }
+
+    let history_info = history.to_account_info();
+    grow_account(&history_info, history_info.data_len() + HistoryRecord::LEN, payer, system_program)?;
+
+    history.records.push(HistoryRecord {
+        proposal_id: proposal.id,
+        proposal_type: proposal.proposal_type,
+        for_votes: proposal.for_votes,
+        against_votes: proposal.against_votes,
+        outcome: proposal.state,
+        slot,
+    });
+    Ok(())
+}
+
+/// Records the voter's `Vote` for `cast_vote` and `cast_vote_with_reason`.
+fn record_vote<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CastVote<'info>>,
+    proposal_id: u64,
+    support: bool,
+    reason: Option<String>,
+) -> Result<()> {
+    let governor = &ctx.accounts.governor;
+    let proposal = &mut ctx.accounts.proposal;
+    let vote = &mut ctx.accounts.vote;
+    let clock = Clock::get()?;
+
+    require!(proposal.reveal_end == 0, GovernorError::CommitRevealRequired);
+    proposal.check_voting_open(clock.slot)?;
+    governor.check_top_level_vote(&ctx.accounts.instructions)?;
+
+    let current_weight = governor.get_votes(
+        &governor.key(),
+        &ctx.accounts.voter.key(),
+        token_balance(&ctx.accounts.voter_token_account),
+        &ctx.accounts.voter_delegation,
+        ctx.remaining_accounts,
+    )?;
+    if ctx.accounts.voter_token_account.is_none() {
+        require!(current_weight > 0, GovernorError::InsufficientVotingPower);
+    }
+    let (voter_weight, spent_weight) = governor.vote_weight(
+        proposal,
+        ctx.accounts.voter_checkpoints.as_deref(),
+        current_weight,
+    )?;
+    require!(voter_weight >= governor.min_voting_weight, GovernorError::WeightBelowMinimum);
+
+    vote.governor = governor.key();
+    vote.voter = *ctx.accounts.voter.key;
+    vote.proposal_id = proposal_id;
+    vote.support = support;
+    vote.weight = voter_weight;
+    vote.cast_slot = clock.slot;
+    vote.bump = ctx.bumps.vote;
+
+    let activity = &mut ctx.accounts.voter_activity;
+    if activity.votes_in_window == 0
+        || clock.slot >= activity.window_start.saturating_add(governor.vote_window_slots)
+    {
+        activity.governor = governor.key();
+        activity.voter = vote.voter;
+        activity.window_start = clock.slot;
+        activity.votes_in_window = 0;
+        activity.bump = ctx.bumps.voter_activity;
+    }
+    activity.votes_in_window += 1;
+    vote.reward_ineligible = governor.max_votes_per_window != 0
+        && activity.votes_in_window > governor.max_votes_per_window;
+
+    if governor.store_reasons {
+        if let Some(reason) = &reason {
+            grow_account(
+                &vote.to_account_info(),
+                8 + Vote::LEN + reason.len(),
+                &ctx.accounts.voter,
+                &ctx.accounts.system_program,
+            )?;
+            vote.reason = reason.clone();
+        }
+    }
+
+    if governor.loser_penalty_bps > 0 && governor.vote_stake_lamports > 0 {
+        let stake = governor.vote_stake_lamports;
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.voter.to_account_info(),
+                    to: proposal.to_account_info(),
+                },
+            ),
+            stake,
+        )?;
+        vote.stake = stake;
+        vote.stake_penalty = pro_rata_share(stake, governor.loser_penalty_bps as u64, 10_000);
+        if support {
+            proposal.for_stake_weight += voter_weight;
+            proposal.for_stake_penalties += vote.stake_penalty;
+        } else {
+            proposal.against_stake_weight += voter_weight;
+            proposal.against_stake_penalties += vote.stake_penalty;
+        }
+        proposal.unclaimed_stakes += 1;
+    }
+
+    emit!(VoteCast {
+        voter: vote.voter,
+        proposal_id,
+        support,
+        weight: voter_weight,
+        reward_ineligible: vote.reward_ineligible,
+        reason,
+    });
+    let conviction = proposal
+        .type_info(&governor.proposal_types)?
+        .conviction_weight(voter_weight, proposal.tally_end().saturating_sub(clock.slot));
+    if support {
+        proposal.for_conviction += conviction;
+    } else {
+        proposal.against_conviction += conviction;
+    }
+    count_vote(governor, proposal, support, voter_weight, spent_weight, &clock)
+}
+
+/// Reallocates `info` to `new_len` bytes, first topping its lamports up to rent
+/// exemption at the new size from `payer`.
+fn grow_account<'info>(
+    info: &AccountInfo<'info>,
+    new_len: usize,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    let shortfall = Rent::get()?.minimum_balance(new_len).saturating_sub(info.lamports());
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                Transfer {
+                    from: payer.to_account_info(),
+                    to: info.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    info.realloc(new_len, false)?;
+    Ok(())
+}
+
+/// Transfers `proposal_deposit_lamports` from the proposer into the proposal account
+/// and returns the amount escrowed. A zero deposit and the manager pay nothing.
+fn take_proposal_deposit<'info>(
+    governor: &Governor,
+    proposer: &Signer<'info>,
+    proposal: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<u64> {
+    let deposit = governor.proposal_deposit_lamports;
+    if deposit == 0 || proposer.key() == governor.manager {
+        return Ok(0);
+    }
+    require!(proposer.lamports() >= deposit, GovernorError::InsufficientFeeBalance);
+    system_program::transfer(
+        CpiContext::new(
+            system_program.to_account_info(),
+            Transfer {
+                from: proposer.to_account_info(),
+                to: proposal.clone(),
+            },
+        ),
+        deposit,
+    )?;
+    Ok(deposit)
+}
+
+/// Checks that must pass before any instruction of `proposal`'s payload may run.
+fn check_payload_executable(
+    governor: &Governor,
+    proposal: &Proposal,
+    dependency: Option<&Proposal>,
+    slot: u64,
+) -> Result<()> {
+    require!(!proposal.executed, GovernorError::ProposalAlreadyExecuted);
+    require!(!proposal.canceled, GovernorError::ProposalCanceled);
+    proposal.check_not_defeated()?;
+    require!(proposal.state == ProposalState::Queued, GovernorError::ProposalNotQueued);
+    require!(slot >= proposal.eta, GovernorError::TimelockNotExpired);
+    proposal.check_execution_deadline(slot)?;
+    require!(!proposal.execution_blocked, GovernorError::ExecutionBlocked);
+
+    let proposal_type_info = proposal.type_info(&governor.proposal_types)?;
+    require!(proposal_type_info.binding, GovernorError::NonBindingProposal);
+    proposal.check_executable(
+        proposal_type_info,
+        governor.quorum_votes(proposal_type_info, proposal.quorum_override)?,
+        governor.total_supply,
+        governor.clamp_to_supply,
+    )?;
+    proposal.check_dependency(dependency)
+}
+
+/// For a governor with a timelock, checks that `proposal`'s payload was
+/// registered there by `queue_proposal` and that the operation is ready, unless
+/// `emergency_execute` released it.
+fn check_timelock_ready(
+    governor: &Governor,
+    proposal: &Proposal,
+    timelock: Option<&Account<Timelock>>,
+    slot: u64,
+) -> Result<()> {
+    let (Some(timelock_key), false) = (governor.timelock, proposal.timelock_bypassed) else {
+        return Ok(());
+    };
+    let timelock = timelock
+        .filter(|timelock| timelock.key() == timelock_key)
+        .ok_or(GovernorError::InvalidTimelock)?;
+    let hash = proposal.timelock_operation_hash()?;
+    let operation = timelock
+        .operations
+        .iter()
+        .find(|operation| operation.hash == hash)
+        .ok_or(GovernorError::TimelockOperationNotQueued)?;
+    require!(slot >= operation.eta, GovernorError::TimelockOperationNotReady);
+    Ok(())
+}
+
+/// Removes a fully executed proposal's operation from the governor's timelock.
+fn release_timelock_operation(
+    governor: &Governor,
+    proposal: &Proposal,
+    timelock: Option<&mut Account<Timelock>>,
+    unix_timestamp: i64,
+) -> Result<()> {
+    let (Some(timelock_key), Some(timelock), true) = (governor.timelock, timelock, proposal.executed) else {
+        return Ok(());
+    };
+    let hash = proposal.timelock_operation_hash()?;
+    if let Some(position) = timelock.operations.iter().position(|operation| operation.hash == hash) {
+        let operation = timelock.operations.remove(position);
+        emit!(TimelockOperationExecuted {
+            timelock: timelock_key,
+            hash,
+            eta: operation.eta,
+            unix_timestamp,
+        });
+    }
+    Ok(())
+}
+
+/// Checks `infos`, one payload instruction's accounts followed by its program,
+/// against the stored metas: same count and keys, writable where the meta is, and
+/// signing where the meta is unless the signer is the governor authority or
+/// treasury PDA, which sign through `invoke_signed`. Returns those two PDAs.
+fn check_instruction_accounts(
+    governor: &Account<Governor>,
+    stored: &ProposalInstruction,
+    infos: &[AccountInfo],
+) -> Result<(Pubkey, Pubkey)> {
+    let governor_key = governor.key();
+    let authority = Pubkey::create_program_address(&governor.authority_seeds(&governor_key), &crate::ID)
+        .map_err(|_| GovernorError::InvalidTransactionAccounts)?;
+    let treasury = Pubkey::create_program_address(&governor.treasury_seeds(&governor_key), &crate::ID)
+        .map_err(|_| GovernorError::InvalidTransactionAccounts)?;
+    let (program_info, account_infos) = infos
+        .split_last()
+        .ok_or(GovernorError::InvalidTransactionAccounts)?;
+    require!(
+        account_infos.len() == stored.accounts.len(),
+        GovernorError::InvalidTransactionAccounts
+    );
+    require_keys_eq!(
+        *program_info.key,
+        stored.program_id,
+        GovernorError::InvalidTransactionAccounts
+    );
+    for (meta, info) in stored.accounts.iter().zip(account_infos) {
+        require_keys_eq!(*info.key, meta.pubkey, GovernorError::InvalidTransactionAccounts);
+        require!(
+            (!meta.is_writable || info.is_writable)
+                && (!meta.is_signer
+                    || info.is_signer
+                    || meta.pubkey == authority
+                    || meta.pubkey == treasury),
+            GovernorError::InvalidTransactionAccounts
+        );
+    }
+    Ok((authority, treasury))
+}
+
+/// Checks `infos` for a `CreateAtaIdempotent` instruction: the payer, the associated
+/// token account of `owner` for `mint` under the mint's token program, `owner`,
+/// `mint`, the system and token programs, then the associated token program. The
+/// payer must be the treasury under `fund_ata_from_treasury` and a signer otherwise,
+/// and `next` must be a token transfer into the associated token account.
+fn check_create_ata(
+    governor: &Account<Governor>,
+    stored: &ProposalInstruction,
+    owner: Pubkey,
+    mint: Pubkey,
+    next: Option<&ProposalInstruction>,
+    infos: &[AccountInfo],
+) -> Result<()> {
+    let [payer, ata, owner_info, mint_info, system_program_info, token_program, ata_program] = infos
+    else {
+        return err!(GovernorError::InvalidTransactionAccounts);
+    };
+    require!(
+        stored.program_id == anchor_spl::associated_token::ID
+            && *ata_program.key == anchor_spl::associated_token::ID
+            && *owner_info.key == owner
+            && *mint_info.key == mint
+            && *system_program_info.key == system_program::ID
+            && (*token_program.key == anchor_spl::token::ID
+                || *token_program.key == anchor_spl::token_2022::ID)
+            && mint_info.owner == token_program.key,
+        GovernorError::InvalidTransactionAccounts
+    );
+    let address = anchor_spl::associated_token::get_associated_token_address_with_program_id(
+        &owner,
+        &mint,
+        token_program.key,
+    );
+    require_keys_eq!(*ata.key, address, GovernorError::InvalidTransactionAccounts);
+    if governor.fund_ata_from_treasury {
+        let governor_key = governor.key();
+        let treasury = Pubkey::create_program_address(&governor.treasury_seeds(&governor_key), &crate::ID)
+            .map_err(|_| GovernorError::InvalidTransactionAccounts)?;
+        require_keys_eq!(*payer.key, treasury, GovernorError::InvalidTransactionAccounts);
+    } else {
+        require!(payer.is_signer, GovernorError::InvalidTransactionAccounts);
+    }
+    require!(
+        next.and_then(ProposalInstruction::token_transfer_destination) == Some(address),
+        GovernorError::AtaTransferMismatch
+    );
+    Ok(())
+}
+
+/// Creates the associated token account checked by `check_create_ata`, signing for
+/// the treasury in case it is the payer.
+fn create_ata_idempotent<'info>(governor: &Account<'info, Governor>, infos: &[AccountInfo<'info>]) -> Result<()> {
+    let governor_key = governor.key();
+    let treasury_seeds = governor.treasury_seeds(&governor_key);
+    anchor_spl::associated_token::create_idempotent(CpiContext::new_with_signer(
+        infos[6].clone(),
+        anchor_spl::associated_token::Create {
+            payer: infos[0].clone(),
+            associated_token: infos[1].clone(),
+            authority: infos[2].clone(),
+            mint: infos[3].clone(),
+            system_program: infos[4].clone(),
+            token_program: infos[5].clone(),
+        },
+        &[&treasury_seeds],
+    ))?;
+    if governor.fund_ata_from_treasury {
+        require!(
+            infos[0].lamports() >= Rent::get()?.minimum_balance(0),
+            GovernorError::TreasuryBelowRentExemption
+        );
+    }
+    Ok(())
+}
+
+/// Checks the accounts shared by the wSOL payload kinds: the treasury, the governor
+/// authority's associated token account for the native mint, the authority and the
+/// token program, which must be the stored `program_id`.
+fn check_treasury_wsol(
+    governor: &Account<Governor>,
+    stored: &ProposalInstruction,
+    treasury: &AccountInfo,
+    wsol: &AccountInfo,
+    governor_authority: &AccountInfo,
+    token_program: &AccountInfo,
+) -> Result<()> {
+    let governor_key = governor.key();
+    let authority = Pubkey::create_program_address(&governor.authority_seeds(&governor_key), &crate::ID)
+        .map_err(|_| GovernorError::InvalidTransactionAccounts)?;
+    let treasury_key = Pubkey::create_program_address(&governor.treasury_seeds(&governor_key), &crate::ID)
+        .map_err(|_| GovernorError::InvalidTransactionAccounts)?;
+    require!(
+        stored.program_id == anchor_spl::token::ID
+            && *token_program.key == anchor_spl::token::ID
+            && *treasury.key == treasury_key
+            && *governor_authority.key == authority,
+        GovernorError::InvalidTransactionAccounts
+    );
+    let address = anchor_spl::associated_token::get_associated_token_address_with_program_id(
+        &authority,
+        &anchor_spl::token::spl_token::native_mint::ID,
+        &anchor_spl::token::ID,
+    );
+    require_keys_eq!(*wsol.key, address, GovernorError::InvalidWrappedSolAccount);
+    Ok(())
+}
+
+/// Checks `infos` for a `WrapTreasurySol` instruction: the treasury, the authority's
+/// wSOL account, the authority, the native mint, the system and associated token
+/// programs, then the token program.
+fn check_wrap_treasury_sol(governor: &Account<Governor>, stored: &ProposalInstruction, infos: &[AccountInfo]) -> Result<()> {
+    let [treasury, wsol, governor_authority, mint, system_program_info, ata_program, token_program] = infos else {
+        return err!(GovernorError::InvalidTransactionAccounts);
+    };
+    check_treasury_wsol(governor, stored, treasury, wsol, governor_authority, token_program)?;
+    require!(
+        *mint.key == anchor_spl::token::spl_token::native_mint::ID
+            && *system_program_info.key == system_program::ID
+            && *ata_program.key == anchor_spl::associated_token::ID,
+        GovernorError::InvalidTransactionAccounts
+    );
+    Ok(())
+}
+
+/// Checks `infos` for an `UnwrapTreasurySol` instruction: the treasury, the
+/// authority's wSOL account, the authority, then the token program.
+fn check_unwrap_treasury_sol(governor: &Account<Governor>, stored: &ProposalInstruction, infos: &[AccountInfo]) -> Result<()> {
+    let [treasury, wsol, governor_authority, token_program] = infos else {
+        return err!(GovernorError::InvalidTransactionAccounts);
+    };
+    check_treasury_wsol(governor, stored, treasury, wsol, governor_authority, token_program)
+}
+
+/// Moves `amount` lamports from the treasury into the authority's wSOL account,
+/// creating it from the treasury if needed, and syncs its token balance.
+fn wrap_treasury_sol<'info>(
+    governor: &Account<'info, Governor>,
+    proposal_id: u64,
+    amount: u64,
+    infos: &[AccountInfo<'info>],
+    clock: &Clock,
+) -> Result<()> {
+    let [treasury, wsol, governor_authority, mint, system_program_info, ata_program, token_program] = infos else {
+        return err!(GovernorError::InvalidTransactionAccounts);
+    };
+    let governor_key = governor.key();
+    let treasury_seeds = governor.treasury_seeds(&governor_key);
+    anchor_spl::associated_token::create_idempotent(CpiContext::new_with_signer(
+        ata_program.clone(),
+        anchor_spl::associated_token::Create {
+            payer: treasury.clone(),
+            associated_token: wsol.clone(),
+            authority: governor_authority.clone(),
+            mint: mint.clone(),
+            system_program: system_program_info.clone(),
+            token_program: token_program.clone(),
+        },
+        &[&treasury_seeds],
+    ))?;
+    system_program::transfer(
+        CpiContext::new_with_signer(
+            system_program_info.clone(),
+            system_program::Transfer { from: treasury.clone(), to: wsol.clone() },
+            &[&treasury_seeds],
+        ),
+        amount,
+    )?;
+    anchor_spl::token::sync_native(CpiContext::new(
+        token_program.clone(),
+        anchor_spl::token::SyncNative { account: wsol.clone() },
+    ))?;
+    require!(
+        treasury.lamports() >= Rent::get()?.minimum_balance(0),
+        GovernorError::TreasuryBelowRentExemption
+    );
+
+    emit!(TreasurySolWrapped {
+        governor: governor_key,
+        proposal_id,
+        wsol_account: *wsol.key,
+        amount,
+        unix_timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Closes the authority's wSOL account, returning its tokens and rent to the
+/// treasury as lamports.
+fn unwrap_treasury_sol<'info>(
+    governor: &Account<'info, Governor>,
+    proposal_id: u64,
+    infos: &[AccountInfo<'info>],
+    clock: &Clock,
+) -> Result<()> {
+    let [treasury, wsol, governor_authority, token_program] = infos else {
+        return err!(GovernorError::InvalidTransactionAccounts);
+    };
+    let amount = wsol.lamports();
+    let governor_key = governor.key();
+    let authority_seeds = governor.authority_seeds(&governor_key);
+    anchor_spl::token::close_account(CpiContext::new_with_signer(
+        token_program.clone(),
+        anchor_spl::token::CloseAccount {
+            account: wsol.clone(),
+            destination: treasury.clone(),
+            authority: governor_authority.clone(),
+        },
+        &[&authority_seeds],
+    ))?;
+
+    emit!(TreasurySolUnwrapped {
+        governor: governor_key,
+        proposal_id,
+        wsol_account: *wsol.key,
+        amount,
+        unix_timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Checks the accounts shared by the stream payload kinds: the mint, a treasury
+/// token account of the governor authority for it, the authority and the mint's
+/// token program. Returns the authority.
+fn check_stream_token_accounts(
+    governor: &Account<Governor>,
+    mint: &Pubkey,
+    mint_info: &AccountInfo,
+    treasury_token_account: &AccountInfo,
+    governor_authority: &AccountInfo,
+    token_program: &AccountInfo,
+) -> Result<Pubkey> {
+    let governor_key = governor.key();
+    let authority = Pubkey::create_program_address(&governor.authority_seeds(&governor_key), &crate::ID)
+        .map_err(|_| GovernorError::InvalidTransactionAccounts)?;
+    require!(
+        *mint_info.key == *mint
+            && *governor_authority.key == authority
+            && (*token_program.key == anchor_spl::token::ID
+                || *token_program.key == anchor_spl::token_2022::ID)
+            && mint_info.owner == token_program.key
+            && treasury_token_account.owner == token_program.key,
+        GovernorError::InvalidTransactionAccounts
+    );
+    let treasury_account = TokenAccount::try_deserialize(&mut &treasury_token_account.try_borrow_data()?[..])?;
+    require!(
+        treasury_account.owner == authority && treasury_account.mint == *mint,
+        GovernorError::InvalidTreasuryTokenAccount
+    );
+    Ok(authority)
+}
+
+/// Checks `infos` for a `CreateStream` instruction creating `stream` at
+/// `stream_key`: a signing payer, the stream and escrow addresses, the token
+/// accounts checked by `check_stream_token_accounts`, the system program, then the
+/// governor program.
+fn check_create_stream(
+    governor: &Account<Governor>,
+    stored: &ProposalInstruction,
+    stream_key: &Pubkey,
+    stream: &PaymentStream,
+    infos: &[AccountInfo],
+) -> Result<()> {
+    let [payer, stream_info, escrow, mint, treasury_token_account, governor_authority, token_program, system_program_info, program] =
+        infos
+    else {
+        return err!(GovernorError::InvalidTransactionAccounts);
+    };
+    require!(
+        stream.total_amount > 0 && stream.start_slot < stream.end_slot,
+        GovernorError::InvalidStreamTerms
+    );
+    require!(
+        stored.program_id == crate::ID
+            && *program.key == crate::ID
+            && payer.is_signer
+            && *stream_info.key == *stream_key
+            && *escrow.key == stream.escrow
+            && *system_program_info.key == system_program::ID,
+        GovernorError::InvalidTransactionAccounts
+    );
+    check_stream_token_accounts(
+        governor,
+        &stream.mint,
+        mint,
+        treasury_token_account,
+        governor_authority,
+        token_program,
+    )?;
+    Ok(())
+}
+
+/// Creates the stream and escrow checked by `check_create_stream`, paid by the
+/// payer, and moves `total_amount` from the treasury token account into the escrow.
+fn create_stream<'info>(
+    governor: &Account<'info, Governor>,
+    proposal_id: u64,
+    stream_key: &Pubkey,
+    stream: &PaymentStream,
+    infos: &[AccountInfo<'info>],
+    clock: &Clock,
+) -> Result<()> {
+    let [payer, stream_info, escrow, mint, treasury_token_account, governor_authority, token_program, system_program_info, _] =
+        infos
+    else {
+        return err!(GovernorError::InvalidTransactionAccounts);
+    };
+    let rent = Rent::get()?;
+    let index = stream.index.to_le_bytes();
+    let stream_seeds: &[&[u8]] = &[b"stream", stream.proposal.as_ref(), &index, &[stream.bump]];
+    system_program::create_account(
+        CpiContext::new_with_signer(
+            system_program_info.clone(),
+            CreateAccount { from: payer.clone(), to: stream_info.clone() },
+            &[stream_seeds],
+        ),
+        rent.minimum_balance(8 + PaymentStream::LEN),
+        (8 + PaymentStream::LEN) as u64,
+        &crate::ID,
+    )?;
+    stream.try_serialize(&mut &mut stream_info.try_borrow_mut_data()?[..])?;
+
+    let escrow_seeds: &[&[u8]] = &[b"stream-escrow", stream_key.as_ref(), &[stream.escrow_bump]];
+    system_program::create_account(
+        CpiContext::new_with_signer(
+            system_program_info.clone(),
+            CreateAccount { from: payer.clone(), to: escrow.clone() },
+            &[escrow_seeds],
+        ),
+        rent.minimum_balance(PaymentStream::ESCROW_LEN),
+        PaymentStream::ESCROW_LEN as u64,
+        token_program.key,
+    )?;
+    token_interface::initialize_account3(CpiContext::new(
+        token_program.clone(),
+        InitializeAccount3 {
+            account: escrow.clone(),
+            mint: mint.clone(),
+            authority: governor_authority.clone(),
+        },
+    ))?;
+
+    let governor_key = governor.key();
+    let authority_seeds = governor.authority_seeds(&governor_key);
+    let decimals = Mint::try_deserialize(&mut &mint.try_borrow_data()?[..])?.decimals;
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            TransferChecked {
+                from: treasury_token_account.clone(),
+                mint: mint.clone(),
+                to: escrow.clone(),
+                authority: governor_authority.clone(),
+            },
+            &[&authority_seeds],
+        ),
+        stream.total_amount,
+        decimals,
+    )?;
+
+    emit!(StreamCreated {
+        governor: governor_key,
+        proposal_id,
+        stream: *stream_key,
+        recipient: stream.recipient,
+        mint: stream.mint,
+        total_amount: stream.total_amount,
+        start_slot: stream.start_slot,
+        end_slot: stream.end_slot,
+        unix_timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Checks `infos` for a `CancelStream` instruction: the governor's uncanceled
+/// stream at `stream_key`, its escrow, the token accounts checked by
+/// `check_stream_token_accounts`, then the governor program. Returns the stream.
+fn check_cancel_stream(
+    governor: &Account<Governor>,
+    stored: &ProposalInstruction,
+    stream_key: &Pubkey,
+    infos: &[AccountInfo],
+) -> Result<PaymentStream> {
+    let [stream_info, escrow, mint, treasury_token_account, governor_authority, token_program, program] = infos
+    else {
+        return err!(GovernorError::InvalidTransactionAccounts);
+    };
+    require!(
+        stored.program_id == crate::ID
+            && *program.key == crate::ID
+            && *stream_info.key == *stream_key
+            && *stream_info.owner == crate::ID,
+        GovernorError::InvalidTransactionAccounts
+    );
+    let stream = PaymentStream::try_deserialize(&mut &stream_info.try_borrow_data()?[..])?;
+    require!(
+        stream.governor == governor.key() && *escrow.key == stream.escrow,
+        GovernorError::InvalidStream
+    );
+    require!(stream.canceled_at.is_none(), GovernorError::StreamAlreadyCanceled);
+    check_stream_token_accounts(
+        governor,
+        &stream.mint,
+        mint,
+        treasury_token_account,
+        governor_authority,
+        token_program,
+    )?;
+    Ok(stream)
+}
+
+/// Stops `stream` vesting at the current slot and returns its unvested tokens from
+/// the escrow to the treasury token account. What already vested stays claimable.
+fn cancel_stream<'info>(
+    governor: &Account<'info, Governor>,
+    proposal_id: u64,
+    mut stream: PaymentStream,
+    infos: &[AccountInfo<'info>],
+    clock: &Clock,
+) -> Result<()> {
+    let [stream_info, escrow, mint, treasury_token_account, governor_authority, token_program, _] = infos
+    else {
+        return err!(GovernorError::InvalidTransactionAccounts);
+    };
+    stream.canceled_at = Some(clock.slot);
+    let returned = stream.total_amount - stream.vested_amount(clock.slot);
+    stream.try_serialize(&mut &mut stream_info.try_borrow_mut_data()?[..])?;
+
+    let governor_key = governor.key();
+    let authority_seeds = governor.authority_seeds(&governor_key);
+    let decimals = Mint::try_deserialize(&mut &mint.try_borrow_data()?[..])?.decimals;
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            TransferChecked {
+                from: escrow.clone(),
+                mint: mint.clone(),
+                to: treasury_token_account.clone(),
+                authority: governor_authority.clone(),
+            },
+            &[&authority_seeds],
+        ),
+        returned,
+        decimals,
+    )?;
+
+    emit!(StreamCanceled {
+        governor: governor_key,
+        proposal_id,
+        stream: *stream_info.key,
+        returned,
+        unix_timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Checks `infos` for a `CreateSubGovernor` instruction: the executor paying for
+/// the new governor, its address, a token mint, this governor's treasury, the
+/// child's treasury, the system program, then the governor program. Returns the
+/// child's address, its bump and its proposal types, decoded from the stored data.
+fn check_create_sub_governor(
+    governor: &Account<Governor>,
+    stored: &ProposalInstruction,
+    proposal: &Pubkey,
+    infos: &[AccountInfo],
+) -> Result<(Pubkey, u8, Vec<ProposalType>)> {
+    let [payer, child, mint, treasury, child_treasury, system_program_info, program] = infos else {
+        return err!(GovernorError::InvalidTransactionAccounts);
+    };
+    let InstructionKind::CreateSubGovernor { config } = &stored.kind else {
+        return err!(GovernorError::InvalidTransactionAccounts);
+    };
+    let governor_key = governor.key();
+    let authority = Pubkey::create_program_address(&governor.authority_seeds(&governor_key), &crate::ID)
+        .map_err(|_| GovernorError::InvalidTransactionAccounts)?;
+    let parent_treasury = Pubkey::create_program_address(&governor.treasury_seeds(&governor_key), &crate::ID)
+        .map_err(|_| GovernorError::InvalidTransactionAccounts)?;
+    let (child_key, bump) = SubGovernorConfig::address(&authority, proposal, stored.index);
+    require!(
+        stored.program_id == crate::ID
+            && *program.key == crate::ID
+            && payer.is_signer
+            && *child.key == child_key
+            && *mint.key == config.governing_mint
+            && (*mint.owner == anchor_spl::token::ID || *mint.owner == anchor_spl::token_2022::ID)
+            && *treasury.key == parent_treasury
+            && *child_treasury.key == Pubkey::find_program_address(&[b"treasury", child_key.as_ref()], &crate::ID).0
+            && *system_program_info.key == system_program::ID,
+        GovernorError::InvalidTransactionAccounts
+    );
+    let proposal_types = Vec::<ProposalType>::try_from_slice(&stored.data)
+        .map_err(|_| GovernorError::InvalidProposalType)?;
+    for proposal_type in &proposal_types {
+        proposal_type.validate(1)?;
+    }
+    Ok((child_key, bump, proposal_types))
+}
+
+/// Creates the child governor checked by `check_create_sub_governor`, paid by the
+/// payer, with this governor's authority as admin, and moves the budget from this
+/// governor's treasury into the child's.
+fn create_sub_governor<'info>(
+    governor: &Account<'info, Governor>,
+    proposal: &Account<'info, Proposal>,
+    stored: &ProposalInstruction,
+    config: &SubGovernorConfig,
+    (child_key, bump, proposal_types): (Pubkey, u8, Vec<ProposalType>),
+    infos: &[AccountInfo<'info>],
+    clock: &Clock,
+) -> Result<()> {
+    let [payer, child, mint, treasury, child_treasury, system_program_info, _] = infos else {
+        return err!(GovernorError::InvalidTransactionAccounts);
+    };
+    let governor_key = governor.key();
+    let authority = Pubkey::create_program_address(&governor.authority_seeds(&governor_key), &crate::ID)
+        .map_err(|_| GovernorError::InvalidTransactionAccounts)?;
+
+    let mut sub_governor = Governor {
+        admin: authority,
+        manager: config.manager,
+        guardian: config.guardian,
+        governing_mint: config.governing_mint,
+        voting_delay: config.voting_delay,
+        voting_period: config.voting_period,
+        proposal_threshold: config.proposal_threshold,
+        total_supply: Mint::try_deserialize(&mut &mint.try_borrow_data()?[..])?.supply,
+        timelock_delay: config.timelock_delay,
+        authority_bump: Pubkey::find_program_address(&[b"governor-authority", child_key.as_ref()], &crate::ID).1,
+        treasury_bump: Pubkey::find_program_address(&[b"treasury", child_key.as_ref()], &crate::ID).1,
+        active_proposal_counts: vec![0; proposal_types.len()],
+        proposal_types,
+        ..Governor::default()
+    };
+    sub_governor.apply_defaults();
+    let space = Governor::space_with_types(&sub_governor.proposal_types);
+
+    let proposal_key = proposal.key();
+    let index = stored.index.to_le_bytes();
+    let child_seeds: &[&[u8]] = &[b"sub-governor", authority.as_ref(), proposal_key.as_ref(), &index, &[bump]];
+    system_program::create_account(
+        CpiContext::new_with_signer(
+            system_program_info.clone(),
+            CreateAccount { from: payer.clone(), to: child.clone() },
+            &[child_seeds],
+        ),
+        Rent::get()?.minimum_balance(space),
+        space as u64,
+        &crate::ID,
+    )?;
+    sub_governor.try_serialize(&mut &mut child.try_borrow_mut_data()?[..])?;
+
+    if config.budget_lamports > 0 {
+        require!(
+            treasury.lamports() >= config.budget_lamports.saturating_add(Rent::get()?.minimum_balance(0)),
+            GovernorError::TreasuryBelowRentExemption
+        );
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                system_program_info.clone(),
+                Transfer { from: treasury.clone(), to: child_treasury.clone() },
+                &[&governor.treasury_seeds(&governor_key)],
+            ),
+            config.budget_lamports,
+        )?;
+    }
+
+    emit!(SubGovernorCreated {
+        governor: governor_key,
+        proposal_id: proposal.id,
+        sub_governor: child_key,
+        governing_mint: config.governing_mint,
+        proposal_types: sub_governor.proposal_types.len() as u8,
+        budget_lamports: config.budget_lamports,
+        unix_timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Interface of execution hooks: programs a governor lets veto each payload
+/// instruction before it runs, e.g. to block transfers to sanctioned addresses. A
+/// hook implements `check_execution`, shaped like an Anchor instruction of that
+/// name, taking the governor and proposal as read-only accounts and
+/// `CheckExecution` as arguments. Returning an error vetoes the instruction and
+/// aborts the transaction. `noop_execution_hook` is a reference hook approving
+/// everything.
+pub mod execution_hook {
+    use super::*;
+
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+    pub struct CheckExecution {
+        pub proposal_id: u64,
+        /// Index of the instruction in the proposal's payload.
+        pub index: u16,
+        /// Program the instruction invokes.
+        pub program_id: Pubkey,
+        /// The Borsh-serialized `ProposalInstruction`.
+        pub instruction: Vec<u8>,
+    }
+
+    /// Anchor discriminator of `check_execution`.
+    pub fn discriminator() -> [u8; 8] {
+        hashv(&[b"global:check_execution"]).to_bytes()[..8].try_into().unwrap()
+    }
+
+    /// The `check_execution` call to `hook` for `args`.
+    pub fn check_execution(hook: Pubkey, governor: Pubkey, proposal: Pubkey, args: &CheckExecution) -> Result<Instruction> {
+        let mut data = discriminator().to_vec();
+        args.serialize(&mut data)?;
+        Ok(Instruction {
+            program_id: hook,
+            accounts: vec![AccountMeta::new_readonly(governor, false), AccountMeta::new_readonly(proposal, false)],
+            data,
+        })
+    }
+}
+
+/// Asks the governor's execution hook, if it has one, to approve the proposal's
+/// next payload instruction. `hook` must be the hook program.
+fn check_execution_hook<'info>(
+    governor: &mut Account<'info, Governor>,
+    proposal: &Account<'info, Proposal>,
+    hook: Option<&AccountInfo<'info>>,
+) -> Result<()> {
+    let Some(hook_program) = governor.execution_hook else {
+        return Ok(());
+    };
+    let hook = hook.ok_or(GovernorError::InvalidExecutionHook)?;
+    require_keys_eq!(*hook.key, hook_program, GovernorError::InvalidExecutionHook);
+    let index = proposal.executed_instructions;
+    let stored = proposal
+        .instructions
+        .get(index as usize)
+        .ok_or(GovernorError::InvalidInstructionIndex)?;
+    let instruction = execution_hook::check_execution(
+        hook_program,
+        governor.key(),
+        proposal.key(),
+        &execution_hook::CheckExecution {
+            proposal_id: proposal.id,
+            index,
+            program_id: stored.program_id,
+            instruction: stored.try_to_vec()?,
+        },
+    )?;
+    // Persist the guard so the hook can't reenter the governor.
+    governor.execution_in_progress = true;
+    governor.exit(&crate::ID)?;
+    invoke(&instruction, &[governor.to_account_info(), proposal.to_account_info(), hook.clone()])?;
+    governor.execution_in_progress = false;
+    Ok(())
+}
+
+/// Runs the proposal's next payload instruction and emits a successful
+/// `TransactionExecutionResult` with the compute units it took. A failing
+/// instruction aborts the transaction; `record_transaction_failure` records it.
+fn run_payload_instruction<'info>(
+    governor: &mut Account<'info, Governor>,
+    proposal: &mut Account<'info, Proposal>,
+    infos: &[AccountInfo<'info>],
+    limit_infos: &'info [AccountInfo<'info>],
+    clock: &Clock,
+) -> Result<()> {
+    let ix_index = proposal.executed_instructions;
+    let compute_before = sol_remaining_compute_units();
+    invoke_payload_instruction(governor, proposal, infos, limit_infos, clock)?;
+
+    emit!(TransactionExecutionResult {
+        governor: governor.key(),
+        proposal_id: proposal.id,
+        ix_index,
+        success: true,
+        error_code: 0,
+        compute_consumed_estimate: compute_before.saturating_sub(sol_remaining_compute_units()),
+        unix_timestamp: clock.unix_timestamp,
+    });
+    emit!(InstructionStatusChanged {
+        governor: governor.key(),
+        proposal_id: proposal.id,
+        ix_index,
+        status: InstructionStatus::Executed,
+        unix_timestamp: clock.unix_timestamp,
+    });
+    Ok(())
+}
+
+/// Runs `proposal`'s next payload instruction by CPI, signed by the governor authority
+/// and the treasury. `infos` are the instruction's accounts, matching the stored
+/// metas, followed by the program to invoke.
+fn invoke_payload_instruction<'info>(
+    governor: &mut Account<'info, Governor>,
+    proposal: &mut Account<'info, Proposal>,
+    infos: &[AccountInfo<'info>],
+    limit_infos: &'info [AccountInfo<'info>],
+    clock: &Clock,
+) -> Result<()> {
+    let ix_index = proposal.executed_instructions;
+    let stored = proposal
+        .instructions
+        .get(ix_index as usize)
+        .ok_or(GovernorError::InvalidInstructionIndex)?
+        .clone();
+    require!(
+        clock.slot >= proposal.eta.saturating_add(stored.hold_up_slots),
+        GovernorError::TimelockNotExpired
+    );
+    let proposal_type_info = proposal.type_info(&governor.proposal_types)?;
+    require!(
+        proposal_type_info.allows_program(&stored.program_id),
+        GovernorError::ProgramNotAllowed
+    );
+    // The type's caps are fixed at activation, but spending limits and the vault
+    // registry may have changed since.
+    let vaults = if proposal_type_info.spending_cap.is_empty() && governor.spending_limits.is_empty() {
+        vec![]
+    } else {
+        registered_vaults(&governor.key(), limit_infos)?
+    };
+    governor.check_spending_caps_of(&governor.key(), proposal_type_info, &proposal.instructions, &vaults)?;
+    record_spending(governor, &stored, limit_infos, &vaults, clock.slot)?;
+    if let Some((stream_key, stream)) = stored.kind.payment_stream(governor.key(), proposal.key(), ix_index) {
+        check_create_stream(governor, &stored, &stream_key, &stream, infos)?;
+        proposal.executed_instructions += 1;
+        return create_stream(governor, proposal.id, &stream_key, &stream, infos, clock);
+    }
+    if let InstructionKind::CancelStream { stream } = stored.kind {
+        let payment_stream = check_cancel_stream(governor, &stored, &stream, infos)?;
+        proposal.executed_instructions += 1;
+        return cancel_stream(governor, proposal.id, payment_stream, infos, clock);
+    }
+    if let InstructionKind::CreateSubGovernor { config } = &stored.kind {
+        let child = check_create_sub_governor(governor, &stored, &proposal.key(), infos)?;
+        proposal.executed_instructions += 1;
+        return create_sub_governor(governor, proposal, &stored, config, child, infos, clock);
+    }
+    if let InstructionKind::WrapTreasurySol { amount } = stored.kind {
+        check_wrap_treasury_sol(governor, &stored, infos)?;
+        proposal.executed_instructions += 1;
+        return wrap_treasury_sol(governor, proposal.id, amount, infos, clock);
+    }
+    if stored.kind == InstructionKind::UnwrapTreasurySol {
+        check_unwrap_treasury_sol(governor, &stored, infos)?;
+        proposal.executed_instructions += 1;
+        return unwrap_treasury_sol(governor, proposal.id, infos, clock);
+    }
+    require!(!stored.is_unflagged_self_call(), GovernorError::SelfCallNotFlagged);
+    let self_call = stored.program_id == crate::ID;
+    if let InstructionKind::CreateAtaIdempotent { owner, mint } = stored.kind {
+        check_create_ata(
+            governor,
+            &stored,
+            owner,
+            mint,
+            proposal.instructions.get(ix_index as usize + 1),
+            infos,
+        )?;
+        proposal.executed_instructions += 1;
+        return create_ata_idempotent(governor, infos);
+    }
+    let (authority, treasury) = check_instruction_accounts(governor, &stored, infos)?;
+    governor.execution_in_progress = !self_call;
+    proposal.executed_instructions += 1;
+
+    let governor_key = governor.key();
+    let authority_seeds = governor.authority_seeds(&governor_key);
+    let treasury_seeds = governor.treasury_seeds(&governor_key);
+    let account_infos = &infos[..stored.accounts.len()];
+
+    let instruction = Instruction {
+        program_id: stored.program_id,
+        accounts: stored
+            .accounts
+            .iter()
+            .map(|meta| AccountMeta {
+                pubkey: meta.pubkey,
+                is_signer: meta.is_signer,
+                is_writable: meta.is_writable,
+            })
+            .collect(),
+        data: stored.data.clone(),
+    };
+    let treasury_transfer = stored
+        .system_transfer()
+        .filter(|(from, _, _)| *from == treasury);
+    if let Some(source) = stored.token_transfer_source() {
+        let source_info = account_infos
+            .iter()
+            .find(|info| *info.key == source)
+            .ok_or(GovernorError::InvalidTransactionAccounts)?;
+        let source_account = TokenAccount::try_deserialize(&mut &source_info.try_borrow_data()?[..])?;
+        require_keys_eq!(source_account.owner, authority, GovernorError::InvalidTreasuryTokenAccount);
+    }
+    // Persist the guard and the advanced index first: a reentrant call must see
+    // them, and anything the callee writes is reloaded afterwards.
+    governor.exit(&crate::ID)?;
+    proposal.exit(&crate::ID)?;
+    invoke_signed(&instruction, infos, &[&authority_seeds, &treasury_seeds])?;
+    governor.reload()?;
+    proposal.reload()?;
+    governor.execution_in_progress = false;
+
+    if let Some((_, to, lamports)) = treasury_transfer {
+        let treasury_info = account_infos
+            .iter()
+            .find(|info| *info.key == treasury)
+            .ok_or(GovernorError::InvalidTransactionAccounts)?;
+        require!(
+            treasury_info.lamports() >= Rent::get()?.minimum_balance(0),
+            GovernorError::TreasuryBelowRentExemption
+        );
+
+        emit!(TreasuryTransfer {
+            governor: governor_key,
+            proposal_id: proposal.id,
+            to,
+            lamports,
+            unix_timestamp: clock.unix_timestamp,
+        });
+    }
+
+    emit!(TransactionExecuted {
+        governor: governor_key,
+        proposal_id: proposal.id,
+        ix_index,
+        unix_timestamp: clock.unix_timestamp,
+    });
+    Ok(())
+}
+
+/// Accounts paying out of the treasury for `manager_spend` and `treasurer_spend`.
+/// The token accounts are only needed for token payouts.
+struct TreasuryPayoutAccounts<'a, 'info> {
+    treasury: &'a SystemAccount<'info>,
+    recipient: &'a UncheckedAccount<'info>,
+    treasury_token_account: &'a Option<InterfaceAccount<'info, TokenAccount>>,
+    mint: &'a Option<InterfaceAccount<'info, Mint>>,
+    governor_authority: &'a Option<UncheckedAccount<'info>>,
+    token_program: &'a Option<Interface<'info, TokenInterface>>,
+    system_program: &'a Program<'info, System>,
+}
+
+/// Records `amount` against the governor's `SpendingLimit` on `mint_or_native`, if it
+/// has one, which must then be passed as `spending_limit`.
+fn record_spending_limit(
+    governor: &Governor,
+    mint_or_native: Pubkey,
+    spending_limit: &mut Option<Account<SpendingLimit>>,
+    amount: u64,
+    slot: u64,
+) -> Result<()> {
+    let mint = (mint_or_native != TreasuryStats::NATIVE_MINT).then_some(mint_or_native);
+    if governor.spending_limits.iter().any(|limit| limit.mint == mint) {
+        spending_limit
+            .as_mut()
+            .ok_or(GovernorError::SpendingLimitAccountMissing)?
+            .record(amount, slot)?;
+    }
+    Ok(())
+}
+
+/// Transfers `amount` of `mint_or_native` to the recipient: lamports from the
+/// treasury, which must stay rent-exempt, or tokens from a token account of the
+/// governor authority.
+fn pay_from_treasury(
+    governor: &Account<Governor>,
+    mint_or_native: Pubkey,
+    amount: u64,
+    accounts: TreasuryPayoutAccounts,
+) -> Result<()> {
+    let governor_key = governor.key();
+    if mint_or_native == TreasuryStats::NATIVE_MINT {
+        require!(
+            accounts.treasury.lamports() >= amount.saturating_add(Rent::get()?.minimum_balance(0)),
+            GovernorError::TreasuryBelowRentExemption
+        );
+        return system_program::transfer(
+            CpiContext::new_with_signer(
+                accounts.system_program.to_account_info(),
+                Transfer {
+                    from: accounts.treasury.to_account_info(),
+                    to: accounts.recipient.to_account_info(),
+                },
+                &[&governor.treasury_seeds(&governor_key)],
+            ),
+            amount,
+        );
+    }
+
+    let (Some(source), Some(mint), Some(authority), Some(token_program)) = (
+        accounts.treasury_token_account,
+        accounts.mint,
+        accounts.governor_authority,
+        accounts.token_program,
+    ) else {
+        return err!(GovernorError::InvalidTreasuryTokenAccount);
+    };
+    require!(
+        source.owner == authority.key() && source.mint == mint_or_native,
+        GovernorError::InvalidTreasuryTokenAccount
+    );
+    require_keys_eq!(mint.key(), mint_or_native, GovernorError::InvalidTreasuryTokenAccount);
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: source.to_account_info(),
+                mint: mint.to_account_info(),
+                to: accounts.recipient.to_account_info(),
+                authority: authority.to_account_info(),
+            },
+            &[&governor.authority_seeds(&governor_key)],
+        ),
+        amount,
+        mint.decimals,
+    )
+}
+
+/// Vaults of the governor's `TreasuryRegistry` when it is among `infos`; none when
+/// it is missing, so unattributed transfers stay unattributed.
+fn registered_vaults<'info>(governor: &Pubkey, infos: &'info [AccountInfo<'info>]) -> Result<Vec<TreasuryVault>> {
+    let (address, _) = Pubkey::find_program_address(&[b"treasury-registry", governor.as_ref()], &crate::ID);
+    match infos.iter().find(|info| *info.key == address) {
+        Some(info) => Ok(Account::<TreasuryRegistry>::try_from(info)?.vaults.clone()),
+        None => Ok(vec![]),
+    }
+}
+
+/// The `burned` fraction of `supply`, applied to `balance`, rounded down.
+fn pro_rata_share(balance: u64, burned: u64, supply: u64) -> u64 {
+    if supply == 0 {
+        return 0;
+    }
+    (balance as u128 * burned.min(supply) as u128 / supply as u128) as u64
+}
+
+/// Moves a spending window forward to the one containing `slot` once
+/// `window_slots` have passed since it began, clearing `spent`. Windows stay
+/// aligned to the first one.
+fn roll_window(window_start: &mut u64, spent: &mut u64, window_slots: u64, slot: u64) {
+    let elapsed = slot.saturating_sub(*window_start);
+    if elapsed >= window_slots {
+        *window_start = slot - elapsed % window_slots;
+        *spent = 0;
+    }
+}
+
+/// Records the treasury outflow of payload instruction `stored`, if any, against
+/// its mint's `SpendingLimit`, looked up by address in `limit_infos`.
+fn record_spending<'info>(
+    governor: &Account<'info, Governor>,
+    stored: &ProposalInstruction,
+    limit_infos: &'info [AccountInfo<'info>],
+    vaults: &[TreasuryVault],
+    slot: u64,
+) -> Result<()> {
+    if governor.spending_limits.is_empty() {
+        return Ok(());
+    }
+    let governor_key = governor.key();
+    let authority = Pubkey::create_program_address(&governor.authority_seeds(&governor_key), &crate::ID)
+        .map_err(|_| GovernorError::InvalidTransactionAccounts)?;
+    let treasury = Pubkey::create_program_address(&governor.treasury_seeds(&governor_key), &crate::ID)
+        .map_err(|_| GovernorError::InvalidTransactionAccounts)?;
+    let Some(outflow) = TreasuryOutflow::decode(stored, &authority, &treasury, vaults) else {
+        return Ok(());
+    };
+    let mint = outflow.mint();
+    if !governor.spending_limits.iter().any(|limit| limit.mint == mint) {
+        return Ok(());
+    }
+
+    let (address, _) = Pubkey::find_program_address(
+        &[b"spending-limit", governor_key.as_ref(), mint.unwrap_or_default().as_ref()],
+        &crate::ID,
+    );
+    let limit_info = limit_infos
+        .iter()
+        .find(|info| *info.key == address)
+        .ok_or(GovernorError::SpendingLimitAccountMissing)?;
+    let mut spending_limit = Account::<SpendingLimit>::try_from(limit_info)?;
+    spending_limit.record(outflow.amount(), slot)?;
+    spending_limit.exit(&crate::ID)
+}
+
+/// Checks that instruction `ix_index` of a queued, unblocked proposal is the next
+/// one due and past its timelock and hold-up, for the manager to record a failure
+/// or skip it.
+fn check_next_instruction_due(proposal: &Proposal, ix_index: u16, slot: u64) -> Result<()> {
+    require!(!proposal.executed, GovernorError::ProposalAlreadyExecuted);
+    require!(!proposal.canceled, GovernorError::ProposalCanceled);
+    proposal.check_not_defeated()?;
+    require!(proposal.state == ProposalState::Queued, GovernorError::ProposalNotQueued);
+    proposal.check_execution_deadline(slot)?;
+    require!(!proposal.execution_blocked, GovernorError::ExecutionBlocked);
+    require!(
+        ix_index == proposal.executed_instructions,
+        GovernorError::InstructionIndexOutOfOrder
+    );
+    let stored = proposal
+        .instructions
+        .get(ix_index as usize)
+        .ok_or(GovernorError::InvalidInstructionIndex)?;
+    require!(
+        slot >= proposal.eta.saturating_add(stored.hold_up_slots),
+        GovernorError::TimelockNotExpired
+    );
+    Ok(())
+}
+
+/// Once every payload instruction has run or been skipped, applies the proposal's
+/// actions, marks it executed, records it in the governor's history and pays the
+/// execution reward.
+fn finish_payload<'info>(
+    governor: &mut Account<'info, Governor>,
+    proposal: &mut Account<'info, Proposal>,
+    supply: SupplyAccounts<'_, 'info>,
+    completion: CompletionAccounts<'_, 'info>,
+    clock: &Clock,
+) -> Result<()> {
+    if (proposal.executed_instructions as usize) < proposal.instructions.len() {
+        return Ok(());
+    }
+    execute_actions(governor, &proposal.actions, supply, clock.unix_timestamp)?;
+
+    let held = proposal.holds_type();
+    proposal.executed = true;
+    proposal.state = ProposalState::Executed;
+    governor.settle_proposal_type(proposal, held);
+    append_history(
+        completion.history,
+        governor.key(),
+        completion.history_bump,
+        proposal,
+        clock.slot,
+        completion.executor,
+        completion.system_program,
+    )?;
+
+    let reward = pay_execution_reward(
+        governor,
+        completion.treasury,
+        completion.executor,
+        completion.system_program,
+    )?;
+
+    emit!(ProposalExecuted {
+        proposal_id: proposal.id,
+        executor: completion.executor.key(),
+        reward,
+    });
+    emit_compat_proposal(governor, proposal, clock.unix_timestamp)
+}
+
+/// Accounts `finish_payload` needs to record a completed payload; the executor pays
+/// for the history record and receives the execution reward.
+struct CompletionAccounts<'a, 'info> {
+    history: &'a mut Account<'info, GovernorHistory>,
+    history_bump: u8,
+    treasury: &'a SystemAccount<'info>,
+    executor: &'a Signer<'info>,
+    system_program: &'a Program<'info, System>,
+}
+
+/// Pays `Governor::execution_reward` from the treasury to `executor` and returns the
+/// lamports paid. Best effort: an underfunded treasury pays nothing rather than
+/// blocking execution.
+fn pay_execution_reward<'info>(
+    governor: &Account<'info, Governor>,
+    treasury: &SystemAccount<'info>,
+    executor: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<u64> {
+    let reward = governor.execution_reward;
+    let affordable = treasury.lamports() >= reward.saturating_add(Rent::get()?.minimum_balance(0));
+    if reward == 0 || !affordable {
+        return Ok(0);
+    }
+    let governor_key = governor.key();
+    system_program::transfer(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            Transfer {
+                from: treasury.to_account_info(),
+                to: executor.to_account_info(),
+            },
+            &[&governor.treasury_seeds(&governor_key)],
+        ),
+        reward,
+    )?;
+    Ok(reward)
+}
+
+/// Accounts used by `Mint` and `Burn` actions. The mint, authority and token program
+/// are optional on the execute instructions; token accounts are looked up by address.
+struct SupplyAccounts<'a, 'info> {
+    governing_mint: &'a mut Option<InterfaceAccount<'info, Mint>>,
+    governor_authority: &'a Option<UncheckedAccount<'info>>,
+    token_program: &'a Option<Interface<'info, TokenInterface>>,
+    token_accounts: &'a [AccountInfo<'info>],
+}
+
+/// Applies `key` as a `ParameterChange` action would, for the admin setters.
+fn admin_set_parameter(governor: &mut Account<Governor>, key: ParamKey, value: u64) -> Result<()> {
+    key.validate(value)?;
+    let old = governor.set_parameter(key, value)?;
+    emit!(GovernorSettingsUpdated {
+        governor: governor.key(),
+        field: key.name().to_string(),
+        old,
+        new: value,
+        unix_timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+fn execute_actions<'info>(
+    governor: &mut Account<'info, Governor>,
+    actions: &[ProposalAction],
+    mut supply: SupplyAccounts<'_, 'info>,
+    unix_timestamp: i64,
+) -> Result<()> {
+    for action in actions {
+        match action {
+            ProposalAction::ParameterChange { key, value } => {
+                let old = governor.set_parameter(*key, *value)?;
+                emit!(GovernorSettingsUpdated {
+                    governor: governor.key(),
+                    field: key.name().to_string(),
+                    old,
+                    new: *value,
+                    unix_timestamp,
+                });
+            }
+            ProposalAction::Mint { to, amount } => {
+                let to_info = supply.token_account(to)?;
+                let (mint, authority, token_program) = supply.accounts()?;
+                require!(
+                    mint.mint_authority == COption::Some(authority.key()),
+                    GovernorError::MintAuthorityNotGovernor
+                );
+                let governor_key = governor.key();
+                token_interface::mint_to(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        MintTo {
+                            mint: mint.to_account_info(),
+                            to: to_info,
+                            authority: authority.to_account_info(),
+                        },
+                        &[&governor.authority_seeds(&governor_key)],
+                    ),
+                    *amount,
+                )?;
+                mint.reload()?;
+                record_total_supply(governor, mint.supply, unix_timestamp);
+            }
+            ProposalAction::Burn { from, amount } => {
+                let from_info = supply.token_account(from)?;
+                let (mint, authority, token_program) = supply.accounts()?;
+                let from_account = TokenAccount::try_deserialize(&mut &from_info.try_borrow_data()?[..])?;
+                require_keys_eq!(
+                    from_account.owner,
+                    authority.key(),
+                    GovernorError::InvalidTreasuryTokenAccount
+                );
+                let governor_key = governor.key();
+                token_interface::burn(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        Burn {
+                            mint: mint.to_account_info(),
+                            from: from_info,
+                            authority: authority.to_account_info(),
+                        },
+                        &[&governor.authority_seeds(&governor_key)],
+                    ),
+                    *amount,
+                )?;
+                mint.reload()?;
+                record_total_supply(governor, mint.supply, unix_timestamp);
+            }
+        }
+    }
+    Ok(())
+}
+
+impl<'a, 'info> SupplyAccounts<'a, 'info> {
+    #[allow(clippy::type_complexity)]
+    fn accounts(
+        &mut self,
+    ) -> Result<(
+        &mut InterfaceAccount<'info, Mint>,
+        &UncheckedAccount<'info>,
+        &Interface<'info, TokenInterface>,
+    )> {
+        match (
+            self.governing_mint.as_mut(),
+            self.governor_authority.as_ref(),
+            self.token_program.as_ref(),
+        ) {
+            (Some(mint), Some(authority), Some(token_program)) => Ok((mint, authority, token_program)),
+            _ => err!(GovernorError::MissingSupplyAccounts),
+        }
+    }
+
+    fn token_account(&self, address: &Pubkey) -> Result<AccountInfo<'info>> {
+        self.token_accounts
+            .iter()
+            .find(|info| info.key == address)
+            .cloned()
+            .ok_or_else(|| error!(GovernorError::MissingSupplyAccounts))
+    }
+}
+
+/// Sets `total_supply` to the mint's current `supply`, so quorum tracks mints and burns.
+fn record_total_supply(governor: &mut Account<Governor>, supply: u64, unix_timestamp: i64) {
+    let old = std::mem::replace(&mut governor.total_supply, supply);
+    if old != supply {
+        emit!(TotalSupplySynced {
+            governor: governor.key(),
+            old,
+            new: supply,
+            unix_timestamp,
+        });
+    }
+}
+
+fn update_delegatee_indexes(
+    delegation: &Delegation,
+    index_infos: &[AccountInfo],
+    add: bool,
+) -> Result<()> {
+    require!(
+        index_infos.len() == delegation.allocations.len(),
+        GovernorError::InvalidDelegateeIndexAccounts
+    );
+    for (allocation, info) in delegation.allocations.iter().zip(index_infos) {
+        let (expected, _) = Pubkey::find_program_address(
+            &[
+                b"delegatee-index",
+                delegation.governor.as_ref(),
+                allocation.delegatee.as_ref(),
+            ],
+            &crate::ID,
+        );
+        require_keys_eq!(*info.key, expected, GovernorError::InvalidDelegateeIndexAccounts);
+        require!(
+            info.is_writable && *info.owner == crate::ID,
+            GovernorError::InvalidDelegateeIndexAccounts
+        );
+
+        let mut index = DelegateeIndex::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        if add {
+            index.add(delegation.delegator)?;
+        } else {
+            index.delegators.retain(|delegator| *delegator != delegation.delegator);
+        }
+        index.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(
+    voting_delay: u64,
+    voting_period: u64,
+    proposal_threshold: u64,
+    timelock_delay: u64,
+    proposal_types: Vec<ProposalType>
+)]
+pub struct Initialize<'info> {
+    #[account(init, payer = admin, space = Governor::space_with_types(&proposal_types))]
+    pub governor: Account<'info, Governor>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub manager: AccountInfo<'info>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub guardian: AccountInfo<'info>,
+    pub governing_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: PDA the governor signs with; only its bump is recorded.
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump)]
+    pub governor_authority: UncheckedAccount<'info>,
+    #[account(seeds = [b"treasury", governor.key().as_ref()], bump)]
+    pub treasury: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    description: String,
+    proposal_type: u8,
+    actions: Vec<ProposalAction>,
+    supersedes: Option<u64>,
+    depends_on: Option<u64>,
+    instructions: Vec<ProposalInstruction>
+)]
+pub struct CreateProposal<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + Proposal::LEN + Proposal::payload_len(&instructions),
+        seeds = [b"proposal", governor.key().as_ref(), &governor.proposal_count.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    #[account(
+        constraint = proposer_token_account.owner == proposer.key()
+            && proposer_token_account.mint == governor.governing_mint
+            @ GovernorError::InvalidVotingTokenAccount
+    )]
+    pub proposer_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// Required when `supersedes` is set.
+    pub superseded_proposal: Option<Account<'info, Proposal>>,
+    /// CHECK: ProposalDigest PDA of the new content, checked in `check_resubmission_cooldown`
+    pub proposal_digest: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"treasury", governor.key().as_ref()], bump)]
+    pub treasury: SystemAccount<'info>,
+    /// The governor's treasury registry, if it has one; transfers out of registered
+    /// vaults then count against caps and limits on their mint.
+    #[account(has_one = governor)]
+    pub treasury_registry: Option<Account<'info, TreasuryRegistry>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: The proposer's Delegation PDA, which need not exist; read by `get_votes`.
+    #[account(seeds = [b"delegation", governor.key().as_ref(), proposer.key().as_ref()], bump)]
+    pub proposer_delegation: UncheckedAccount<'info>,
+    /// The proposer's VoteCheckpoints PDA, read by `check_proposer_threshold`; the
+    /// manager may omit it.
+    #[account(
+        seeds = [b"checkpoints", governor.key().as_ref(), proposer.key().as_ref()],
+        bump = proposer_checkpoints.bump
+    )]
+    pub proposer_checkpoints: Option<Account<'info, VoteCheckpoints>>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct CreateHashedProposal<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    /// CHECK: Created by the instruction at the address of `proposal_id`, which must
+    /// not be taken yet.
+    #[account(
+        mut,
+        seeds = [b"proposal", governor.key().as_ref(), &proposal_id.to_le_bytes()],
+        bump,
+        constraint = proposal.data_is_empty() @ GovernorError::ProposalIdTaken
+    )]
+    pub proposal: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    #[account(
+        constraint = proposer_token_account.owner == proposer.key()
+            && proposer_token_account.mint == governor.governing_mint
+            @ GovernorError::InvalidVotingTokenAccount
+    )]
+    pub proposer_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: ProposalDigest PDA of the new content, checked in `check_resubmission_cooldown`
+    pub proposal_digest: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"treasury", governor.key().as_ref()], bump)]
+    pub treasury: SystemAccount<'info>,
+    /// The governor's treasury registry, if it has one; transfers out of registered
+    /// vaults then count against caps and limits on their mint.
+    #[account(has_one = governor)]
+    pub treasury_registry: Option<Account<'info, TreasuryRegistry>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: The proposer's Delegation PDA, which need not exist; read by `get_votes`.
+    #[account(seeds = [b"delegation", governor.key().as_ref(), proposer.key().as_ref()], bump)]
+    pub proposer_delegation: UncheckedAccount<'info>,
+    /// The proposer's VoteCheckpoints PDA, read by `check_proposer_threshold`; the
+    /// manager may omit it.
+    #[account(
+        seeds = [b"checkpoints", governor.key().as_ref(), proposer.key().as_ref()],
+        bump = proposer_checkpoints.bump
+    )]
+    pub proposer_checkpoints: Option<Account<'info, VoteCheckpoints>>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeProgramUpgrade<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + Proposal::LEN + ProposalInstruction::PROGRAM_UPGRADE_LEN,
+        seeds = [b"proposal", governor.key().as_ref(), &governor.proposal_count.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    #[account(
+        constraint = proposer_token_account.owner == proposer.key()
+            && proposer_token_account.mint == governor.governing_mint
+            @ GovernorError::InvalidVotingTokenAccount
+    )]
+    pub proposer_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Buffer holding the new bytecode; its authority is checked in the handler.
+    #[account(owner = bpf_loader_upgradeable::ID @ GovernorError::InvalidUpgradeBuffer)]
+    pub buffer: UncheckedAccount<'info>,
+    /// CHECK: ProposalDigest PDA of the new content, checked in `check_resubmission_cooldown`
+    pub proposal_digest: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"treasury", governor.key().as_ref()], bump)]
+    pub treasury: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: The proposer's Delegation PDA, which need not exist; read by `get_votes`.
+    #[account(seeds = [b"delegation", governor.key().as_ref(), proposer.key().as_ref()], bump)]
+    pub proposer_delegation: UncheckedAccount<'info>,
+    /// The proposer's VoteCheckpoints PDA, read by `check_proposer_threshold`; the
+    /// manager may omit it.
+    #[account(
+        seeds = [b"checkpoints", governor.key().as_ref(), proposer.key().as_ref()],
+        bump = proposer_checkpoints.bump
+    )]
+    pub proposer_checkpoints: Option<Account<'info, VoteCheckpoints>>,
+}
+
+#[derive(Accounts)]
+#[instruction(description: String, proposal_type: u8, vault_index: u8, transaction_message: Vec<u8>)]
+pub struct ProposeSquadsAction<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + Proposal::LEN + ProposalInstruction::squads_action_len(transaction_message.len()),
+        seeds = [b"proposal", governor.key().as_ref(), &governor.proposal_count.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    #[account(
+        constraint = proposer_token_account.owner == proposer.key()
+            && proposer_token_account.mint == governor.governing_mint
+            @ GovernorError::InvalidVotingTokenAccount
+    )]
+    pub proposer_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Squads multisig; its layout and the authority's membership are checked in the handler.
+    #[account(owner = SQUADS_PROGRAM_ID @ GovernorError::InvalidSquadsMultisig)]
+    pub multisig: UncheckedAccount<'info>,
+    /// CHECK: ProposalDigest PDA of the new content, checked in `check_resubmission_cooldown`
+    pub proposal_digest: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"treasury", governor.key().as_ref()], bump)]
+    pub treasury: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: The proposer's Delegation PDA, which need not exist; read by `get_votes`.
+    #[account(seeds = [b"delegation", governor.key().as_ref(), proposer.key().as_ref()], bump)]
+    pub proposer_delegation: UncheckedAccount<'info>,
+    /// The proposer's VoteCheckpoints PDA, read by `check_proposer_threshold`; the
+    /// manager may omit it.
+    #[account(
+        seeds = [b"checkpoints", governor.key().as_ref(), proposer.key().as_ref()],
+        bump = proposer_checkpoints.bump
+    )]
+    pub proposer_checkpoints: Option<Account<'info, VoteCheckpoints>>,
+}
+
+#[derive(Accounts)]
+pub struct CreateProposalDraft<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + Proposal::LEN,
+        seeds = [b"proposal", governor.key().as_ref(), &governor.proposal_count.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    /// Required when `supersedes` is set.
+    pub superseded_proposal: Option<Account<'info, Proposal>>,
+    #[account(mut, seeds = [b"treasury", governor.key().as_ref()], bump)]
+    pub treasury: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct UpdateProposalDraft<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId,
+        has_one = proposer @ GovernorError::Unauthorized
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    pub proposer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    proposal_id: u64,
+    ix_index: u16,
+    program_id: Pubkey,
+    accounts: Vec<AccountMetaData>,
+    data: Vec<u8>
+)]
+pub struct AddProposalInstruction<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId,
+        has_one = proposer @ GovernorError::Unauthorized,
+        realloc = proposal.to_account_info().data_len()
+            + ProposalInstruction::serialized_len(accounts.len(), data.len()),
+        realloc::payer = proposer,
+        realloc::zero = false
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64, ix_index: u16)]
+pub struct RemoveProposalInstruction<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId,
+        has_one = proposer @ GovernorError::Unauthorized,
+        realloc = proposal.to_account_info().data_len()
+            - proposal.instructions.get(ix_index as usize).map_or(0, ProposalInstruction::size),
+        realloc::payer = proposer,
+        realloc::zero = false
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    proposal_id: u64,
+    ix_index: u16,
+    program_id: Pubkey,
+    accounts: Vec<AccountMetaData>,
+    data: Vec<u8>
+)]
+pub struct ReplaceProposalInstruction<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId,
+        has_one = proposer @ GovernorError::Unauthorized,
+        realloc = proposal.to_account_info().data_len()
+            - proposal.instructions.get(ix_index as usize).map_or(0, ProposalInstruction::size)
+            + ProposalInstruction::serialized_len(accounts.len(), data.len()),
+        realloc::payer = proposer,
+        realloc::zero = false
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct CreateProposalActions<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId,
+        has_one = proposer @ GovernorError::Unauthorized
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + ProposalActions::LEN,
+        seeds = [b"proposal-actions", proposal.key().as_ref()],
+        bump
+    )]
+    pub proposal_actions: AccountLoader<'info, ProposalActions>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct AppendProposalActions<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId,
+        has_one = proposer @ GovernorError::Unauthorized
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(
+        mut,
+        constraint = proposal_actions.load()?.proposal == proposal.key() @ GovernorError::InvalidProposalId
+    )]
+    pub proposal_actions: AccountLoader<'info, ProposalActions>,
+    pub proposer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ActivateProposal<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId,
+        has_one = proposer @ GovernorError::Unauthorized
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    #[account(
+        constraint = proposer_token_account.owner == proposer.key()
+            && proposer_token_account.mint == governor.governing_mint
+            @ GovernorError::InvalidVotingTokenAccount
+    )]
+    pub proposer_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: ProposalDigest PDA of the draft's content, checked in `check_resubmission_cooldown`
+    pub proposal_digest: UncheckedAccount<'info>,
+    /// The governor's treasury registry, if it has one; transfers out of registered
+    /// vaults then count against caps and limits on their mint.
+    #[account(has_one = governor)]
+    pub treasury_registry: Option<Account<'info, TreasuryRegistry>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: The proposer's Delegation PDA, which need not exist; read by `get_votes`.
+    #[account(seeds = [b"delegation", governor.key().as_ref(), proposer.key().as_ref()], bump)]
+    pub proposer_delegation: UncheckedAccount<'info>,
+    /// The proposer's VoteCheckpoints PDA, read by `check_proposer_threshold`; the
+    /// manager may omit it.
+    #[account(
+        seeds = [b"checkpoints", governor.key().as_ref(), proposer.key().as_ref()],
+        bump = proposer_checkpoints.bump
+    )]
+    pub proposer_checkpoints: Option<Account<'info, VoteCheckpoints>>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct CloseProposalDraft<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        close = proposer,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId,
+        has_one = proposer @ GovernorError::Unauthorized
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    /// The draft's `ProposalActions` account, closed along with it; required when
+    /// `proposal.external_actions` is non-zero.
+    #[account(
+        mut,
+        close = proposer,
+        constraint = proposal_actions.load()?.proposal == proposal.key() @ GovernorError::InvalidProposalId
+    )]
+    pub proposal_actions: Option<AccountLoader<'info, ProposalActions>>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct SponsorProposal<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(
+        init,
+        payer = sponsor,
+        space = 8 + SponsorReceipt::LEN,
+        seeds = [b"sponsor", proposal.key().as_ref(), sponsor.key().as_ref()],
+        bump
+    )]
+    pub sponsor_receipt: Account<'info, SponsorReceipt>,
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+    #[account(
+        constraint = sponsor_token_account.owner == sponsor.key()
+            && sponsor_token_account.mint == governor.governing_mint
+            @ GovernorError::InvalidVotingTokenAccount
+    )]
+    pub sponsor_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: The sponsor's Delegation PDA, which need not exist; read by `get_votes`.
+    #[account(seeds = [b"delegation", governor.key().as_ref(), sponsor.key().as_ref()], bump)]
+    pub sponsor_delegation: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ActivateSponsoredProposal<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    /// CHECK: ProposalDigest PDA of the draft's content, checked in `check_resubmission_cooldown`
+    pub proposal_digest: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct CloseExpiredDraft<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        close = proposer,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId,
+        has_one = proposer
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    /// CHECK: Rent destination, validated against `proposal.proposer`
+    #[account(mut)]
+    pub proposer: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct EditProposal<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId,
+        has_one = proposer @ GovernorError::Unauthorized
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    pub proposer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct EndorseProposal<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(
+        init,
+        payer = endorser,
+        space = 8 + EndorsementReceipt::LEN,
+        seeds = [b"endorsement", proposal.key().as_ref(), endorser.key().as_ref()],
+        bump
+    )]
+    pub endorsement_receipt: Account<'info, EndorsementReceipt>,
+    #[account(mut)]
+    pub endorser: Signer<'info>,
+    #[account(
+        constraint = endorser_token_account.owner == endorser.key()
+            && endorser_token_account.mint == governor.governing_mint
+            @ GovernorError::InvalidVotingTokenAccount
+    )]
+    pub endorser_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: The endorser's Delegation PDA, which need not exist; read by `get_votes`.
+    #[account(seeds = [b"delegation", governor.key().as_ref(), endorser.key().as_ref()], bump)]
+    pub endorser_delegation: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CastVote<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(mut, has_one = governor @ GovernorError::InvalidProposalId)]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(
+        init,
+        payer = voter,
+        space = 8 + Vote::LEN,
+        seeds = [b"vote", governor.key().as_ref(), proposal.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub vote: Account<'info, Vote>,
+    #[account(
+        init_if_needed,
+        payer = voter,
+        space = 8 + VoterActivity::LEN,
+        seeds = [b"voter-activity", governor.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub voter_activity: Account<'info, VoterActivity>,
+    #[account(mut)]
+    pub voter: Signer<'info>,
+    /// Omitted by a voter without a governing token account, who votes with
+    /// delegated weight only.
+    #[account(
+        constraint = voter_token_account.owner == voter.key()
+            && voter_token_account.mint == governor.governing_mint
+            @ GovernorError::InvalidVotingTokenAccount
+    )]
+    pub voter_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: The instructions sysvar, checked by address
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: The voter's Delegation PDA, which need not exist; read by `get_votes`.
+    #[account(seeds = [b"delegation", governor.key().as_ref(), voter.key().as_ref()], bump)]
+    pub voter_delegation: UncheckedAccount<'info>,
+    /// The voter's VoteCheckpoints PDA, read by `vote_weight`; not needed for
+    /// `realtime_weight` types.
+    #[account(
+        seeds = [b"checkpoints", governor.key().as_ref(), voter.key().as_ref()],
+        bump = voter_checkpoints.bump
+    )]
+    pub voter_checkpoints: Option<Account<'info, VoteCheckpoints>>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct CommitVote<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(
+        init,
+        payer = voter,
+        space = 8 + VoteCommitment::LEN,
+        seeds = [b"vote-commitment", proposal.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub vote_commitment: Account<'info, VoteCommitment>,
+    #[account(mut)]
+    pub voter: Signer<'info>,
+    /// Omitted by a voter without a governing token account, who votes with
+    /// delegated weight only.
+    #[account(
+        constraint = voter_token_account.owner == voter.key()
+            && voter_token_account.mint == governor.governing_mint
+            @ GovernorError::InvalidVotingTokenAccount
+    )]
+    pub voter_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: The instructions sysvar, checked by address
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: The voter's Delegation PDA, which need not exist; read by `get_votes`.
+    #[account(seeds = [b"delegation", governor.key().as_ref(), voter.key().as_ref()], bump)]
+    pub voter_delegation: UncheckedAccount<'info>,
+    /// The voter's VoteCheckpoints PDA, read by `vote_weight`; not needed for
+    /// `realtime_weight` types.
+    #[account(
+        seeds = [b"checkpoints", governor.key().as_ref(), voter.key().as_ref()],
+        bump = voter_checkpoints.bump
+    )]
+    pub voter_checkpoints: Option<Account<'info, VoteCheckpoints>>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct RevealVote<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(
+        mut,
+        seeds = [b"vote-commitment", proposal.key().as_ref(), vote_commitment.voter.as_ref()],
+        bump = vote_commitment.bump
+    )]
+    pub vote_commitment: Account<'info, VoteCommitment>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ClaimVoteReward<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(
+        mut,
+        seeds = [b"vote", governor.key().as_ref(), proposal.key().as_ref(), voter.key().as_ref()],
+        bump = vote.bump,
+        has_one = voter @ GovernorError::Unauthorized,
+        has_one = governor @ GovernorError::InvalidProposalId,
+        constraint = vote.proposal_id == proposal_id @ GovernorError::InvalidProposalId
+    )]
+    pub vote: Account<'info, Vote>,
+    #[account(mut)]
+    pub voter: Signer<'info>,
+    #[account(mut, seeds = [b"treasury", governor.key().as_ref()], bump = governor.treasury_bump)]
+    pub treasury: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ClaimVoteStake<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(
+        mut,
+        seeds = [b"vote", governor.key().as_ref(), proposal.key().as_ref(), voter.key().as_ref()],
+        bump = vote.bump,
+        has_one = voter @ GovernorError::Unauthorized,
+        has_one = governor @ GovernorError::InvalidProposalId,
+        constraint = vote.proposal_id == proposal_id @ GovernorError::InvalidProposalId
+    )]
+    pub vote: Account<'info, Vote>,
+    #[account(mut)]
+    pub voter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct RageQuit<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    /// Closed, so each vote can only be used to exit once.
+    #[account(
+        mut,
+        close = voter,
+        seeds = [b"vote", governor.key().as_ref(), proposal.key().as_ref(), voter.key().as_ref()],
+        bump = vote.bump,
+        has_one = voter @ GovernorError::Unauthorized,
+        has_one = governor @ GovernorError::InvalidProposalId,
+        constraint = vote.proposal_id == proposal_id @ GovernorError::InvalidProposalId
+    )]
+    pub vote: Account<'info, Vote>,
+    #[account(mut)]
+    pub voter: Signer<'info>,
+    #[account(
+        mut,
+        constraint = voter_token_account.owner == voter.key()
+            && voter_token_account.mint == governor.governing_mint
+            @ GovernorError::InvalidVotingTokenAccount
+    )]
+    pub voter_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, address = governor.governing_mint @ GovernorError::InvalidVotingTokenAccount)]
+    pub governing_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: Governor authority PDA, owner of the treasury vaults.
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"treasury", governor.key().as_ref()], bump = governor.treasury_bump)]
+    pub treasury: SystemAccount<'info>,
+    /// Required to be paid out of any token vault.
+    #[account(has_one = governor)]
+    pub treasury_registry: Option<Account<'info, TreasuryRegistry>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct FinalizeProposal<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + ProposalDigest::LEN,
+        seeds = [b"digest", governor.key().as_ref(), proposal.content_hash.as_ref()],
+        bump
+    )]
+    pub proposal_digest: Account<'info, ProposalDigest>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + GovernorHistory::LEN,
+        seeds = [b"history", governor.key().as_ref()],
+        bump
+    )]
+    pub history: Account<'info, GovernorHistory>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct CancelProposal<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    pub canceler: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct BlockExecution<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    pub blocker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ExpirePending<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ExpireProposal<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct SweepForfeitedDeposit<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(mut, seeds = [b"treasury", governor.key().as_ref()], bump = governor.treasury_bump)]
+    pub treasury: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct CloseExpiredProposal<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        close = proposer,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId,
+        has_one = proposer
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    /// CHECK: Rent destination, validated against `proposal.proposer`
+    #[account(mut)]
+    pub proposer: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct QueueProposal<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    pub payer: Signer<'info>,
+    /// Required when `proposal.depends_on` is set.
+    pub dependency: Option<Account<'info, Proposal>>,
+    /// Required when the governor has a timelock and the proposal a payload.
+    #[account(mut)]
+    pub timelock: Option<Account<'info, Timelock>>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteProposal<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(mut, has_one = governor @ GovernorError::InvalidProposalId)]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(mut)]
+    pub executor: Signer<'info>,
+    /// Required when `proposal.depends_on` is set.
+    pub dependency: Option<Account<'info, Proposal>>,
+    /// Required when the proposal has `Mint` or `Burn` actions, together with the
+    /// authority and token program; the token accounts they name go in the remaining
+    /// accounts.
+    #[account(mut, address = governor.governing_mint @ GovernorError::InvalidVotingTokenAccount)]
+    pub governing_mint: Option<InterfaceAccount<'info, Mint>>,
+    /// CHECK: Governor authority PDA, mint authority of the governing mint.
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    /// Lamport treasury funding execution rewards. Anyone may top it up with a transfer.
+    #[account(mut, seeds = [b"treasury", governor.key().as_ref()], bump)]
+    pub treasury: SystemAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = executor,
+        space = 8 + GovernorHistory::LEN,
+        seeds = [b"history", governor.key().as_ref()],
+        bump
+    )]
+    pub history: Account<'info, GovernorHistory>,
+    pub system_program: Program<'info, System>,
+    /// Required when `proposal.external_actions` is non-zero.
+    #[account(
+        constraint = proposal_actions.load()?.proposal == proposal.key() @ GovernorError::InvalidProposalId
+    )]
+    pub proposal_actions: Option<AccountLoader<'info, ProposalActions>>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ExecuteTransaction<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(mut)]
+    pub executor: Signer<'info>,
+    /// Required when `proposal.depends_on` is set.
+    pub dependency: Option<Account<'info, Proposal>>,
+    /// Required when the proposal has `Mint` or `Burn` actions, together with the
+    /// authority and token program; the token accounts they name go in the remaining
+    /// accounts after the program.
+    #[account(mut, address = governor.governing_mint @ GovernorError::InvalidVotingTokenAccount)]
+    pub governing_mint: Option<InterfaceAccount<'info, Mint>>,
+    /// CHECK: Governor authority PDA, mint authority of the governing mint.
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    /// Required when the governor has a timelock.
+    #[account(mut)]
+    pub timelock: Option<Account<'info, Timelock>>,
+    /// CHECK: The governor's execution hook program, checked against
+    /// `Governor::execution_hook`; required when it is set.
+    pub execution_hook: Option<UncheckedAccount<'info>>,
+    /// Lamport treasury funding execution rewards.
+    #[account(mut, seeds = [b"treasury", governor.key().as_ref()], bump)]
+    pub treasury: SystemAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = executor,
+        space = 8 + GovernorHistory::LEN,
+        seeds = [b"history", governor.key().as_ref()],
+        bump
+    )]
+    pub history: Account<'info, GovernorHistory>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct CreatePayloadLookupTable<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId,
+        has_one = proposer @ GovernorError::Unauthorized
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    /// CHECK: Lookup table derived from the governor authority and `recent_slot`,
+    /// checked in the handler and created by the lookup table program.
+    #[account(mut)]
+    pub lookup_table: UncheckedAccount<'info>,
+    /// CHECK: Governor authority PDA, authority of the lookup table.
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: UncheckedAccount<'info>,
+    /// CHECK: The address lookup table program.
+    #[account(address = address_lookup_table::program::ID)]
+    pub address_lookup_table_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct RecordTransactionFailure<'info> {
+    #[account(
+        has_one = manager @ GovernorError::Unauthorized,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    pub manager: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct MarkTransactionSkipped<'info> {
+    #[account(
+        mut,
+        has_one = manager @ GovernorError::Unauthorized,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    /// Required when skipping the last instruction of a proposal with `Mint` or
+    /// `Burn` actions, together with the authority and token program.
+    #[account(mut, address = governor.governing_mint @ GovernorError::InvalidVotingTokenAccount)]
+    pub governing_mint: Option<InterfaceAccount<'info, Mint>>,
+    /// CHECK: Governor authority PDA, mint authority of the governing mint.
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    /// Lamport treasury funding execution rewards.
+    #[account(mut, seeds = [b"treasury", governor.key().as_ref()], bump)]
+    pub treasury: SystemAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = manager,
+        space = 8 + GovernorHistory::LEN,
+        seeds = [b"history", governor.key().as_ref()],
+        bump
+    )]
+    pub history: Account<'info, GovernorHistory>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SyncTotalSupply<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(address = governor.governing_mint @ GovernorError::InvalidVotingTokenAccount)]
+    pub governing_mint: InterfaceAccount<'info, Mint>,
+}
+
+#[derive(Accounts)]
+pub struct CreateTreasuryTokenAccount<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    /// CHECK: Governor authority PDA, owner of the new token account.
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: UncheckedAccount<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = governor_authority,
+        associated_token::token_program = token_program
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateTreasuryVault<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + TreasuryRegistry::LEN,
+        seeds = [b"treasury-registry", governor.key().as_ref()],
+        bump
+    )]
+    pub treasury_registry: Account<'info, TreasuryRegistry>,
+    /// CHECK: Governor authority PDA, owner of the vault.
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: UncheckedAccount<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = governor_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    /// The manager or the governor authority.
+    pub creator: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseTreasuryVault<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        seeds = [b"treasury-registry", governor.key().as_ref()],
+        bump = treasury_registry.bump
+    )]
+    pub treasury_registry: Account<'info, TreasuryRegistry>,
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: Signer<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = governor_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, seeds = [b"treasury", governor.key().as_ref()], bump = governor.treasury_bump)]
+    pub treasury: SystemAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct DepositToTreasury<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(mut, seeds = [b"treasury", governor.key().as_ref()], bump = governor.treasury_bump)]
+    pub treasury: SystemAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + TreasuryStats::LEN,
+        seeds = [b"treasury-stats", governor.key().as_ref()],
+        bump
+    )]
+    pub treasury_stats: Account<'info, TreasuryStats>,
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositTokenToTreasury<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    /// CHECK: Governor authority PDA, owner of the treasury token account.
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: UncheckedAccount<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint, token::token_program = token_program)]
+    pub depositor_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = governor_authority,
+        associated_token::token_program = token_program
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + TreasuryStats::LEN,
+        seeds = [b"treasury-stats", governor.key().as_ref()],
+        bump
+    )]
+    pub treasury_stats: Account<'info, TreasuryStats>,
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetTreasuryBalances<'info> {
+    pub governor: Account<'info, Governor>,
+    #[account(seeds = [b"treasury", governor.key().as_ref()], bump = governor.treasury_bump)]
+    pub treasury: SystemAccount<'info>,
+    /// CHECK: Governor authority PDA; token accounts passed must be owned by it.
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetTreasuryBalance<'info> {
+    pub governor: Account<'info, Governor>,
+    #[account(seeds = [b"treasury", governor.key().as_ref()], bump = governor.treasury_bump)]
+    pub treasury: SystemAccount<'info>,
+    /// CHECK: Governor authority PDA, owner of `treasury_token_account`.
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: UncheckedAccount<'info>,
+    #[account(
+        constraint = treasury_token_account.owner == governor_authority.key()
+            @ GovernorError::InvalidTreasuryTokenAccount
+    )]
+    pub treasury_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct EmergencyExecute<'info> {
+    #[account(
+        mut,
+        has_one = guardian @ GovernorError::Unauthorized,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(mut)]
+    pub guardian: Signer<'info>,
+    /// Required when `proposal.depends_on` is set.
+    pub dependency: Option<Account<'info, Proposal>>,
+    /// Required when the proposal has `Mint` or `Burn` actions, together with the
+    /// authority and token program; the token accounts they name go in the remaining
+    /// accounts.
+    #[account(mut, address = governor.governing_mint @ GovernorError::InvalidVotingTokenAccount)]
+    pub governing_mint: Option<InterfaceAccount<'info, Mint>>,
+    /// CHECK: Governor authority PDA, mint authority of the governing mint.
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    #[account(
+        init_if_needed,
+        payer = guardian,
+        space = 8 + GovernorHistory::LEN,
+        seeds = [b"history", governor.key().as_ref()],
+        bump
+    )]
+    pub history: Account<'info, GovernorHistory>,
+    pub system_program: Program<'info, System>,
+    /// Required when `proposal.external_actions` is non-zero.
+    #[account(
+        constraint = proposal_actions.load()?.proposal == proposal.key() @ GovernorError::InvalidProposalId
+    )]
+    pub proposal_actions: Option<AccountLoader<'info, ProposalActions>>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    schedule_id: u64,
+    proposal_type: u8,
+    description_hash: [u8; 32],
+    actions: Vec<ProposalAction>,
+    instructions: Vec<ProposalInstruction>
+)]
+pub struct CreateProposalSchedule<'info> {
+    #[account(
+        mut,
+        has_one = manager @ GovernorError::Unauthorized,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        init,
+        payer = manager,
+        space = 8 + ProposalSchedule::LEN + Proposal::payload_len(&instructions),
+        seeds = [b"schedule", governor.key().as_ref(), &schedule_id.to_le_bytes()],
+        bump
+    )]
+    pub schedule: Account<'info, ProposalSchedule>,
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    /// The governor's treasury registry, if it has one; transfers out of registered
+    /// vaults then count against caps and limits on their mint.
+    #[account(has_one = governor)]
+    pub treasury_registry: Option<Account<'info, TreasuryRegistry>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(schedule_id: u64)]
+pub struct CrankSchedule<'info> {
+    #[account(
+        mut,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        seeds = [b"schedule", governor.key().as_ref(), &schedule_id.to_le_bytes()],
+        bump = schedule.bump
+    )]
+    pub schedule: Account<'info, ProposalSchedule>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Proposal::LEN + schedule.payload_bytes as usize,
+        seeds = [b"proposal", governor.key().as_ref(), &governor.proposal_count.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// The governor's treasury registry, if it has one; transfers out of registered
+    /// vaults then count against caps and limits on their mint.
+    #[account(has_one = governor)]
+    pub treasury_registry: Option<Account<'info, TreasuryRegistry>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(schedule_id: u64)]
+pub struct UpdateProposalSchedule<'info> {
+    #[account(
+        has_one = manager @ GovernorError::Unauthorized,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        seeds = [b"schedule", governor.key().as_ref(), &schedule_id.to_le_bytes()],
+        bump = schedule.bump
+    )]
+    pub schedule: Account<'info, ProposalSchedule>,
+    pub manager: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(schedule_id: u64)]
+pub struct CancelProposalSchedule<'info> {
+    #[account(
+        mut,
+        has_one = manager @ GovernorError::Unauthorized,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        close = manager,
+        seeds = [b"schedule", governor.key().as_ref(), &schedule_id.to_le_bytes()],
+        bump = schedule.bump
+    )]
+    pub schedule: Account<'info, ProposalSchedule>,
+    #[account(mut)]
+    pub manager: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AdminUpdateGovernor<'info> {
+    #[account(
+        mut,
+        has_one = admin @ GovernorError::Unauthorized,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(
+        mut,
+        constraint = governor.pending_admin == Some(new_admin.key()) @ GovernorError::Unauthorized,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    pub new_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AddProposalType<'info> {
+    #[account(
+        mut,
+        has_one = admin @ GovernorError::Unauthorized,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProposalType<'info> {
+    #[account(
+        mut,
+        has_one = admin @ GovernorError::Unauthorized,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveProposalType<'info> {
+    #[account(
+        mut,
+        has_one = admin @ GovernorError::Unauthorized,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(delegatee: Pubkey)]
+pub struct Delegate<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        init,
+        payer = delegator,
+        space = 8 + Delegation::LEN,
+        seeds = [b"delegation", governor.key().as_ref(), delegator.key().as_ref()],
+        bump
+    )]
+    pub delegation: Account<'info, Delegation>,
+    #[account(
+        init_if_needed,
+        payer = delegator,
+        space = 8 + DelegateeIndex::LEN,
+        seeds = [b"delegatee-index", governor.key().as_ref(), delegatee.as_ref()],
+        bump
+    )]
+    pub delegatee_index: Account<'info, DelegateeIndex>,
+    #[account(mut)]
+    pub delegator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DelegateSplit<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        init,
+        payer = delegator,
+        space = 8 + Delegation::LEN,
+        seeds = [b"delegation", governor.key().as_ref(), delegator.key().as_ref()],
+        bump
+    )]
+    pub delegation: Account<'info, Delegation>,
+    #[account(mut)]
+    pub delegator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(delegatee: Pubkey)]
+pub struct InitDelegateeIndex<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + DelegateeIndex::LEN,
+        seeds = [b"delegatee-index", governor.key().as_ref(), delegatee.as_ref()],
+        bump
+    )]
+    pub delegatee_index: Account<'info, DelegateeIndex>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Undelegate<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        close = delegator,
+        seeds = [b"delegation", governor.key().as_ref(), delegator.key().as_ref()],
+        bump = delegation.bump,
+        has_one = delegator
+    )]
+    pub delegation: Account<'info, Delegation>,
+    #[account(mut)]
+    pub delegator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CheckpointVotes<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + VoteCheckpoints::LEN,
+        seeds = [b"checkpoints", governor.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub checkpoints: Account<'info, VoteCheckpoints>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    /// Omitted by an owner without a governing token account, who checkpoints
+    /// delegated weight only.
+    #[account(
+        constraint = owner_token_account.owner == owner.key()
+            && owner_token_account.mint == governor.governing_mint
+            @ GovernorError::InvalidVotingTokenAccount
+    )]
+    pub owner_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: The owner's Delegation PDA, which need not exist; read by `get_votes`.
+    #[account(seeds = [b"delegation", governor.key().as_ref(), owner.key().as_ref()], bump)]
+    pub owner_delegation: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(account: Pubkey)]
+pub struct GetPastVotes<'info> {
+    pub governor: Account<'info, Governor>,
+    #[account(
+        seeds = [b"checkpoints", governor.key().as_ref(), account.as_ref()],
+        bump = checkpoints.bump
+    )]
+    pub checkpoints: Account<'info, VoteCheckpoints>,
+}
+
+#[derive(Accounts)]
+pub struct GetProposalTypes<'info> {
+    pub governor: Account<'info, Governor>,
+}
+
+#[derive(Accounts)]
+pub struct GetTreasuryVaults<'info> {
+    pub governor: Account<'info, Governor>,
+    #[account(seeds = [b"treasury-registry", governor.key().as_ref()], bump = treasury_registry.bump)]
+    pub treasury_registry: Account<'info, TreasuryRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct GetHistory<'info> {
+    pub governor: Account<'info, Governor>,
+    #[account(seeds = [b"history", governor.key().as_ref()], bump = history.bump)]
+    pub history: Account<'info, GovernorHistory>,
+}
+
+#[derive(Accounts)]
+pub struct GetAggregateVotes<'info> {
+    pub governor: Account<'info, Governor>,
+}
+
+#[derive(Accounts)]
+#[instruction(delegatee: Pubkey)]
+pub struct GetDelegators<'info> {
+    pub governor: Account<'info, Governor>,
+    #[account(
+        seeds = [b"delegatee-index", governor.key().as_ref(), delegatee.as_ref()],
+        bump = delegatee_index.bump
+    )]
+    pub delegatee_index: Account<'info, DelegateeIndex>,
+}
+
+#[derive(Accounts)]
+pub struct GetEffectiveProposerThreshold<'info> {
+    pub governor: Account<'info, Governor>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct CheckQuorumProgress<'info> {
+    pub governor: Account<'info, Governor>,
+    #[account(
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ValidateProposalPayload<'info> {
+    pub governor: Account<'info, Governor>,
+    #[account(
+        constraint = proposal.id == proposal_id @ GovernorError::InvalidProposalId,
+        has_one = governor @ GovernorError::InvalidProposalId
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimStream<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        has_one = governor @ GovernorError::InvalidStream,
+        has_one = escrow @ GovernorError::InvalidStream,
+        has_one = mint @ GovernorError::InvalidStream
+    )]
+    pub stream: Account<'info, PaymentStream>,
+    #[account(mut)]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        constraint = recipient_token_account.owner == stream.recipient
+            && recipient_token_account.mint == stream.mint
+            @ GovernorError::InvalidStream
+    )]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Governor authority PDA, owner of the escrow.
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: UncheckedAccount<'info>,
+    #[account(address = mint.to_account_info().owner.key())]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint_or_native: Pubkey)]
+pub struct SetAllowance<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Allowance::LEN,
+        seeds = [b"allowance", governor.key().as_ref(), mint_or_native.as_ref()],
+        bump
+    )]
+    pub allowance: Account<'info, Allowance>,
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(to: Pubkey, mint_or_native: Pubkey)]
+pub struct SpendManagerAllowance<'info> {
+    #[account(
+        has_one = manager @ GovernorError::Unauthorized,
+        constraint = governor.is_idle() @ GovernorError::ReentrancyDetected
+    )]
+    pub governor: Account<'info, Governor>,
+    pub manager: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"allowance", governor.key().as_ref(), mint_or_native.as_ref()],
+        bump = allowance.bump
+    )]
+    pub allowance: Account<'info, Allowance>,
+    /// Required when the governor has a spending limit on `mint_or_native`.
+    #[account(
+        mut,
+        seeds = [b"spending-limit", governor.key().as_ref(), mint_or_native.as_ref()],
+        bump = spending_limit.bump
+    )]
+    pub spending_limit: Option<Account<'info, SpendingLimit>>,
+    #[account(mut, seeds = [b"treasury", governor.key().as_ref()], bump = governor.treasury_bump)]
+    pub treasury: SystemAccount<'info>,
+    /// CHECK: Lamport recipient or destination token account; only ever credited.
+    #[account(mut, address = to @ GovernorError::InvalidTransactionAccounts)]
+    pub recipient: UncheckedAccount<'info>,
+    /// Required for token spends, together with the mint, authority and token program.
+    #[account(mut)]
+    pub treasury_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    /// CHECK: Governor authority PDA, owner of the treasury token account.
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetTreasurer<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Treasurer::LEN,
+        seeds = [b"treasurer", governor.key().as_ref()],
+        bump
+    )]
+    pub treasurer_record: Account<'info, Treasurer>,
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeTreasurer<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        seeds = [b"treasurer", governor.key().as_ref()],
+        bump = treasurer_record.bump
+    )]
+    pub treasurer_record: Account<'info, Treasurer>,
+    pub revoker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(to: Pubkey)]
+pub struct SpendTreasurerBudget<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        seeds = [b"treasurer", governor.key().as_ref()],
+        bump = treasurer_record.bump,
+        constraint = treasurer_record.treasurer == treasurer.key() @ GovernorError::Unauthorized
+    )]
+    pub treasurer_record: Account<'info, Treasurer>,
+    pub treasurer: Signer<'info>,
+    /// Required when the governor has a spending limit on the budget's mint.
+    #[account(
+        mut,
+        seeds = [b"spending-limit", governor.key().as_ref(), treasurer_record.mint_or_native.as_ref()],
+        bump = spending_limit.bump
+    )]
+    pub spending_limit: Option<Account<'info, SpendingLimit>>,
+    #[account(mut, seeds = [b"treasury", governor.key().as_ref()], bump = governor.treasury_bump)]
+    pub treasury: SystemAccount<'info>,
+    /// CHECK: Lamport recipient or destination token account; only ever credited.
+    #[account(mut, address = to @ GovernorError::InvalidTransactionAccounts)]
+    pub recipient: UncheckedAccount<'info>,
+    /// Required for token budgets, together with the mint, authority and token program.
+    #[account(mut)]
+    pub treasury_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    /// CHECK: Governor authority PDA, owner of the treasury token account.
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Option<Pubkey>)]
+pub struct SetSpendingLimit<'info> {
+    #[account(mut, constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + SpendingLimit::LEN,
+        seeds = [b"spending-limit", governor.key().as_ref(), mint.unwrap_or_default().as_ref()],
+        bump
+    )]
+    pub spending_limit: Account<'info, SpendingLimit>,
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(grant_id: u64)]
+pub struct CreateGrant<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Grant::LEN,
+        seeds = [b"grant", governor.key().as_ref(), &grant_id.to_le_bytes()],
+        bump
+    )]
+    pub grant: Account<'info, Grant>,
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveMilestone<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(mut, has_one = governor @ GovernorError::InvalidGrant)]
+    pub grant: Account<'info, Grant>,
+    pub approver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimTranche<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(
+        mut,
+        has_one = governor @ GovernorError::InvalidGrant,
+        has_one = mint @ GovernorError::InvalidGrant
+    )]
+    pub grant: Account<'info, Grant>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        constraint = treasury_token_account.owner == governor_authority.key()
+            && treasury_token_account.mint == grant.mint
+            @ GovernorError::InvalidTreasuryTokenAccount
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = recipient_token_account.owner == grant.recipient
+            && recipient_token_account.mint == grant.mint
+            @ GovernorError::InvalidGrant
+    )]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Governor authority PDA, owner of the treasury token account.
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: UncheckedAccount<'info>,
+    #[account(address = mint.to_account_info().owner.key())]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CancelGrant<'info> {
+    #[account(constraint = governor.is_idle() @ GovernorError::ReentrancyDetected)]
+    pub governor: Account<'info, Governor>,
+    #[account(mut, has_one = governor @ GovernorError::InvalidGrant)]
+    pub grant: Account<'info, Grant>,
+    #[account(seeds = [b"governor-authority", governor.key().as_ref()], bump = governor.authority_bump)]
+    pub governor_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateTimelock<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Timelock::LEN,
+        seeds = [b"timelock", base.key().as_ref()],
+        bump
+    )]
+    pub timelock: Account<'info, Timelock>,
+    pub base: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct QueueOperation<'info> {
+    #[account(mut, seeds = [b"timelock", timelock.base.as_ref()], bump = timelock.bump)]
+    pub timelock: Account<'info, Timelock>,
+    pub proposer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteOperation<'info> {
+    #[account(mut, seeds = [b"timelock", timelock.base.as_ref()], bump = timelock.bump)]
+    pub timelock: Account<'info, Timelock>,
+    pub executor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateTimelockDelays<'info> {
+    #[account(mut, seeds = [b"timelock", timelock.base.as_ref()], bump = timelock.bump)]
+    pub timelock: Account<'info, Timelock>,
+    #[account(seeds = [b"timelock-vault", timelock.key().as_ref()], bump = timelock.vault_bump)]
+    pub vault: Signer<'info>,
+}
+
+#[account]
+#[derive(Default)]
+pub struct Governor {
+    pub admin: Pubkey,
+    pub manager: Pubkey,
+    pub guardian: Pubkey,
+    pub governing_mint: Pubkey,
+    pub voting_delay: u64,
+    pub voting_period: u64,
+    pub proposal_threshold: u64,
+    pub proposal_count: u64,
+    pub total_supply: u64,
+    pub timelock_delay: u64,
+    pub sponsorship_window: u64,
+    /// When set, proposers may only cancel before voting starts; the manager and
+    /// guardian can still cancel until execution.
+    pub cancel_before_active_only: bool,
+    /// Votes weighing less than this are rejected, so dust holders can't bloat state.
+    pub min_voting_weight: u64,
+    /// Lamports paid from the treasury to whoever executes a queued proposal.
+    pub execution_reward: u64,
+    /// When set, `cast_vote` must be a top-level instruction; votes cast through CPI
+    /// are rejected.
+    pub require_top_level_vote: bool,
+    /// Slots after a defeat during which identical content cannot be proposed again.
+    pub resubmission_cooldown_slots: u64,
+    /// Admin-managed windows during which new proposals are frozen, sorted by start.
+    pub blackout_windows: Vec<BlackoutWindow>,
+    pub blackout_manager_exempt: bool,
+    /// How many times a proposer may edit a Pending proposal's description.
+    pub max_proposal_edits: u64,
+    /// Non-refundable filing fee, in lamports, paid into the treasury on creation.
+    pub proposal_fee_lamports: u64,
+    /// Slots after creation at which an unactivated Draft may be expired. Zero disables.
+    pub draft_ttl_slots: u64,
+    /// When a tally exceeds `total_supply`, execution scales it down to the supply
+    /// instead of failing.
+    pub clamp_to_supply: bool,
+    /// Votes per window beyond which further votes earn no reward. Zero disables.
+    pub max_votes_per_window: u64,
+    pub vote_window_slots: u64,
+    /// Lamports paid from the treasury for each reward-eligible vote.
+    pub vote_reward_lamports: u64,
+    /// Also emit `SplGovernanceProposalV2` for indexers built around SPL Governance.
+    pub compat_events: bool,
+    /// Round voting windows out to whole epochs, starting on an epoch boundary.
+    pub align_to_epoch: bool,
+    /// Slots after `eta` within which a queued proposal must finish executing. Zero
+    /// disables.
+    pub execution_deadline_slots: u64,
+    /// Lamports escrowed in each new proposal's account, returned with its rent unless
+    /// the proposal fails quorum.
+    pub proposal_deposit_lamports: u64,
+    /// Pay for `CreateAtaIdempotent` payload instructions from the treasury rather than
+    /// the executor.
+    pub fund_ata_from_treasury: bool,
+    /// Weight a vote needs to count towards a proposal's `voter_count`, so splitting
+    /// tokens across many accounts can't inflate participation. Lighter votes still
+    /// count towards the tally. Zero counts every voter.
+    pub require_min_balance_per_voter: u64,
+    /// Approval, in bps of the votes cast, an emergency-capable proposal needs for
+    /// `emergency_execute` to skip the timelock.
+    pub emergency_bypass_threshold_bps: u16,
+    /// Turnout, in bps of `total_supply`, an emergency-capable proposal needs for
+    /// `emergency_execute` to skip the timelock.
+    pub emergency_quorum_bps: u16,
+    /// Fewest slots a proposal's voting window may span, checked whenever one is
+    /// scheduled, so a misconfigured timing can't rush a proposal through. Zero
+    /// disables.
+    pub min_active_slots: u64,
+    /// Store `cast_vote_with_reason` reasons on the `Vote` account as well as in
+    /// `VoteCast`. Off by default: reasons live only in logs, keeping votes small.
+    pub store_reasons: bool,
+    /// Longest reason `cast_vote_with_reason` accepts, in bytes, at most
+    /// `MAX_REASON_LENGTH`.
+    pub max_reason_length: u16,
+    /// Number proposals by `Proposal::hashed_id` through `create_hashed_proposal`
+    /// instead of sequentially. The sequential creators, from `create_proposal` and
+    /// the drafts to `crank_schedule`, then fail with `ProposalIdModeMismatch`.
+    pub hashed_proposal_ids: bool,
+    /// Share, in bps, of a vote's stake forfeited when it lands on the losing side
+    /// and paid to the winning side. Zero disables stakes.
+    pub loser_penalty_bps: u16,
+    /// Lamports each `cast_vote` escrows in the proposal account while
+    /// `loser_penalty_bps` is set.
+    pub vote_stake_lamports: u64,
+    /// Mint and `max_amount` of each `SpendingLimit`, at most `MAX_SPENDING_LIMITS`,
+    /// so payloads can be checked against them without the limit accounts.
+    pub spending_limits: Vec<SpendingCap>,
+    /// Bump of the `[b"governor-authority", governor]` PDA, which signs execution CPIs
+    /// and owns the governor's token vaults.
+    pub authority_bump: u8,
+    /// Bump of the `[b"treasury", governor]` lamport treasury.
+    pub treasury_bump: u8,
+    /// Addresses with no voting weight, whether held directly or delegated.
+    pub blocklist: Vec<Pubkey>,
+    /// Quorum multiplier per risk tier, in bps of the type's base quorum.
+    pub risk_tier_multipliers: Vec<u16>,
+    /// Set while `execute_transaction` is inside its CPI; every mutating instruction
+    /// rejects the governor meanwhile.
+    pub execution_in_progress: bool,
+    /// Admin named by `transfer_admin`, until it calls `accept_admin`.
+    pub pending_admin: Option<Pubkey>,
+    /// Set by `lock_admin`: the governor authority PDA stays admin for good.
+    pub admin_locked: bool,
+    /// Timelock that payloads are registered with on queueing, set by `set_timelock`.
+    pub timelock: Option<Pubkey>,
+    /// Program that may veto each payload instruction, set by `set_execution_hook`.
+    pub execution_hook: Option<Pubkey>,
+    /// Per proposal type, the proposals still in progress and the schedules that
+    /// reference it; kept in step with `proposal_types`.
+    pub active_proposal_counts: Vec<u32>,
+    pub proposal_types: Vec<ProposalType>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BlackoutWindow {
+    pub start_slot: u64,
+    pub end_slot: u64,
+}
+
+/// Instruction contexts hold proposals boxed: with a full payload the deserialized
+/// account is too large for the SBF stack frame.
+#[account]
+pub struct Proposal {
+    pub id: u64,
+    pub governor: Pubkey,
+    pub proposer: Pubkey,
+    pub description: String,
+    pub proposal_type: u8,
+    pub actions: Vec<ProposalAction>,
+    /// Actions stored in the proposal's `ProposalActions` account, run after `actions`.
+    pub external_actions: u16,
+    pub state: ProposalState,
+    pub created_slot: u64,
+    pub sponsor_weight: u64,
+    /// Wallets that sponsored the draft with `sponsor_proposal`, at most `MAX_SPONSORS`.
+    pub sponsors: Vec<Pubkey>,
+    pub endorsement_end: u64,
+    pub endorsement_weight: u64,
+    pub start_block: u64,
+    pub end_block: u64,
+    /// Last slot of the reveal window of a commit-reveal proposal; zero otherwise.
+    pub reveal_end: u64,
+    pub for_votes: u64,
+    pub against_votes: u64,
+    /// Tally with each vote scaled by `ProposalType::conviction_weight`; decides
+    /// `conviction_mode` proposals in place of the raw tally.
+    pub for_conviction: u64,
+    pub against_conviction: u64,
+    /// Votes cast with at least `Governor::require_min_balance_per_voter` weight.
+    pub voter_count: u32,
+    /// `Governor::total_supply` when a vote or `expire_proposal` first touched the
+    /// proposal after its start block; zero before. No more than this can vote.
+    pub snapshot_supply: u64,
+    /// Snapshot weight of everyone who has voted, which is at least what their votes
+    /// counted: power that cannot vote on this proposal again.
+    pub spent_weight: u64,
+    pub eta: u64,
+    /// Last slot at which a queued proposal may still execute; zero for no deadline.
+    pub expires_at: u64,
+    pub outcome: Option<ProposalState>,
+    pub executed: bool,
+    pub canceled: bool,
+    /// Set by `block_execution`: the proposal cannot execute until unblocked.
+    pub execution_blocked: bool,
+    /// Set when `emergency_execute` released the payload: it runs without waiting
+    /// on the governor's external timelock.
+    pub timelock_bypassed: bool,
+    /// Earlier proposal this one is a new version of, e.g. a defeated proposal resubmitted.
+    pub supersedes: Option<u64>,
+    /// Earlier proposal that must have executed before this one can be queued or executed.
+    pub depends_on: Option<u64>,
+    pub edit_count: u8,
+    pub fee_paid: u64,
+    /// Lamports escrowed in this account on creation, or on activation for drafts.
+    pub deposit: u64,
+    /// Set by `finalize_proposal` when the proposal failed quorum.
+    pub deposit_forfeited: bool,
+    /// Set once `sweep_forfeited_deposit` has moved the deposit to the treasury.
+    pub deposit_swept: bool,
+    /// Weight of the For and Against votes that posted a stake, which splits the
+    /// losing side's penalties among the winners.
+    pub for_stake_weight: u64,
+    pub against_stake_weight: u64,
+    /// Penalties forfeited by staked For and Against votes should their side lose.
+    pub for_stake_penalties: u64,
+    pub against_stake_penalties: u64,
+    /// Staked votes not yet settled by `claim_vote_stake` or `rage_quit`; the
+    /// stakes sit in this account until then.
+    pub unclaimed_stakes: u32,
+    pub bump: u8,
+    /// Number of payload instructions already run by `execute_transaction` or skipped.
+    pub executed_instructions: u16,
+    /// Bit `i` is set when instruction `i` was skipped by `mark_transaction_skipped`.
+    pub skipped_instructions: u16,
+    /// Set by the first vote that brings the tally to quorum.
+    pub quorum_reached: bool,
+    /// Quorum in bps set by the proposer in place of the type's, never lower than it.
+    pub quorum_override: Option<u16>,
+    /// The proposal type as it stood at activation. Voting, queueing and execution
+    /// read this rather than `Governor::proposal_types`.
+    pub type_snapshot: Option<ProposalType>,
+    /// Error code each payload instruction last failed with, as recorded by
+    /// `record_transaction_failure`; zero if it never failed.
+    pub instruction_error_codes: [u64; Proposal::MAX_INSTRUCTIONS],
+    /// Lookup table made by `create_payload_lookup_table` for executing the payload.
+    pub lookup_table: Option<Pubkey>,
+    /// Hash of the description, actions and instructions, fixed at activation.
+    pub content_hash: [u8; 32],
+    pub payload_bytes: u32,
+    /// Raw executable payload. Kept last: the account is reallocated as it grows.
+    pub instructions: Vec<ProposalInstruction>,
+}
+
+/// Execution status of one payload instruction, derived from
+/// `Proposal::executed_instructions`, `Proposal::skipped_instructions` and
+/// `Proposal::instruction_error_codes`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InstructionStatus {
+    NotExecuted,
+    Executed,
+    Skipped,
+    /// Pending with a recorded failure; it can still be retried or skipped.
+    Failed { error_code: u64 },
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct AccountMetaData {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ProposalInstruction {
+    pub index: u16,
+    pub program_id: Pubkey,
+    pub accounts: Vec<AccountMetaData>,
+    pub data: Vec<u8>,
+    /// Required for instructions targeting the governor program itself; such calls
+    /// run without the reentrancy guard.
+    pub self_call: bool,
+    /// Slots after the proposal's `eta` before this instruction may run.
+    pub hold_up_slots: u64,
+    pub kind: InstructionKind,
+}
+
+/// How the executor runs a payload instruction.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum InstructionKind {
+    /// Invoke `program_id` with the stored accounts and data.
+    Invoke,
+    /// Create `owner`'s associated token account for `mint` unless it exists, paid by
+    /// the executor or, with `Governor::fund_ata_from_treasury`, the treasury. Stored
+    /// with the associated token program as `program_id` and no accounts or data;
+    /// the next instruction must be a token transfer into that account.
+    CreateAtaIdempotent { owner: Pubkey, mint: Pubkey },
+    /// Create a `PaymentStream` vesting `total_amount` of `mint` to `recipient`
+    /// linearly from `start_slot` to `end_slot`, funded from a treasury token
+    /// account. Stored with the governor program as `program_id` and no accounts or
+    /// data.
+    CreateStream {
+        recipient: Pubkey,
+        mint: Pubkey,
+        total_amount: u64,
+        start_slot: u64,
+        end_slot: u64,
+    },
+    /// Stop `stream` vesting and return its unvested tokens to a treasury token
+    /// account. Stored like `CreateStream`.
+    CancelStream { stream: Pubkey },
+    /// Create a child `Governor` administered by this governor's authority, with
+    /// `config`'s settings and the Borsh-serialized `Vec<ProposalType>` in `data` as
+    /// its proposal types, and fund its treasury from this one. Stored with the
+    /// governor program as `program_id` and no accounts.
+    CreateSubGovernor { config: SubGovernorConfig },
+    /// Move `amount` lamports from the treasury into the governor authority's
+    /// associated token account for the native mint, creating it from the treasury
+    /// if needed, and sync its wSOL balance. Stored with the token program as
+    /// `program_id` and no accounts or data.
+    WrapTreasurySol { amount: u64 },
+    /// Close the governor authority's wSOL account, returning everything in it to
+    /// the treasury as lamports. Stored like `WrapTreasurySol`.
+    UnwrapTreasurySol,
+}
+
+/// Settings of a child governor created by a `CreateSubGovernor` payload
+/// instruction at `[b"sub-governor", governor authority, proposal, index]`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct SubGovernorConfig {
+    pub governing_mint: Pubkey,
+    pub manager: Pubkey,
+    pub guardian: Pubkey,
+    pub voting_delay: u64,
+    pub voting_period: u64,
+    pub proposal_threshold: u64,
+    pub timelock_delay: u64,
+    /// Lamports moved from this governor's treasury into the child's.
+    pub budget_lamports: u64,
+}
+
+/// A voter's ballot at `[b"vote", governor, proposal, voter]`: one per voter and
+/// proposal, and never valid under another governor.
+#[account]
+pub struct Vote {
+    pub governor: Pubkey,
+    pub voter: Pubkey,
+    pub proposal_id: u64,
+    pub support: bool,
+    pub weight: u64,
+    /// Slot the vote was cast, from which its conviction grows.
+    pub cast_slot: u64,
+    /// Set when the voter exceeded `Governor::max_votes_per_window`; the vote counts
+    /// but earns no reward.
+    pub reward_ineligible: bool,
+    pub reward_claimed: bool,
+    pub bump: u8,
+    /// Lamports escrowed in the proposal account with this vote, and the part of them
+    /// forfeited if it lands on the losing side.
+    pub stake: u64,
+    pub stake_penalty: u64,
+    pub stake_claimed: bool,
+    /// Rationale from `cast_vote_with_reason`, kept only with
+    /// `Governor::store_reasons`; the account grows to fit it.
+    pub reason: String,
+}
+
+/// Tokens vesting linearly to `recipient`, created by a `CreateStream` payload
+/// instruction at `[b"stream", proposal, index]`. The tokens sit in `escrow`, a token
+/// account at `[b"stream-escrow", stream]` owned by the governor authority.
+#[account]
+pub struct PaymentStream {
+    pub governor: Pubkey,
+    pub proposal: Pubkey,
+    /// Index of the creating instruction in the proposal's payload.
+    pub index: u16,
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub escrow: Pubkey,
+    pub total_amount: u64,
+    pub claimed: u64,
+    pub start_slot: u64,
+    pub end_slot: u64,
+    /// Slot at which a `CancelStream` instruction stopped vesting.
+    pub canceled_at: Option<u64>,
+    pub bump: u8,
+    pub escrow_bump: u8,
+}
+
+/// Milestone grant at `[b"grant", governor, grant_id]`: tranche `i` is paid from the
+/// treasury once milestone `i` is approved.
+#[account]
+pub struct Grant {
+    pub governor: Pubkey,
+    pub grant_id: u64,
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub tranches: Vec<u64>,
+    /// Lets the manager approve milestones as well as governance.
+    pub manager_attestation: bool,
+    /// Milestones approved so far; they are approved in order.
+    pub approved_count: u8,
+    /// Bit `i` is set once tranche `i` has been claimed.
+    pub claimed_tranches: u8,
+    pub canceled: bool,
+    pub bump: u8,
+}
+
+/// The manager's budget for spending one mint from the treasury without a
+/// proposal, at `[b"allowance", governor, mint_or_native]`.
+#[account]
+pub struct Allowance {
+    pub governor: Pubkey,
+    /// Token mint, or `TreasuryStats::NATIVE_MINT` for lamports.
+    pub mint_or_native: Pubkey,
+    pub window_slots: u64,
+    /// Most the manager may spend per window.
+    pub budget: u64,
+    /// First slot of the current window.
+    pub window_start: u64,
+    /// Spent so far in the current window.
+    pub spent: u64,
+    pub bump: u8,
+}
+
+/// The treasurer appointed by governance and their spending budget, at
+/// `[b"treasurer", governor]`. Revoking clears `treasurer`.
+#[account]
+pub struct Treasurer {
+    pub governor: Pubkey,
+    pub treasurer: Pubkey,
+    /// Token mint, or `TreasuryStats::NATIVE_MINT` for lamports.
+    pub mint_or_native: Pubkey,
+    pub window_slots: u64,
+    /// Most the treasurer may spend per window.
+    pub budget: u64,
+    /// First slot of the current window.
+    pub window_start: u64,
+    /// Spent so far in the current window.
+    pub spent: u64,
+    pub bump: u8,
+}
+
+/// Rolling cap on what payloads may move out of the treasury in one mint, at
+/// `[b"spending-limit", governor, mint]` with the default pubkey for lamports.
+#[account]
+pub struct SpendingLimit {
+    pub governor: Pubkey,
+    /// Token mint, or `None` for lamports from the treasury PDA.
+    pub mint: Option<Pubkey>,
+    pub window_slots: u64,
+    pub max_amount: u64,
+    /// First slot of the current window.
+    pub window_start: u64,
+    /// Spent so far in the current window.
+    pub spent: u64,
+    pub bump: u8,
+}
+
+/// A hidden vote on a commit-reveal proposal, at
+/// `[b"vote-commitment", proposal, voter]`.
+#[account]
+pub struct VoteCommitment {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub voter: Pubkey,
+    pub commitment: [u8; 32],
+    /// Voting weight at the proposal's snapshot, counted when revealed.
+    pub weight: u64,
+    pub revealed: bool,
+    pub bump: u8,
+}
+
+/// Per-voter count of votes cast in the current rate-limit window.
+#[account]
+pub struct VoterActivity {
+    pub governor: Pubkey,
+    pub voter: Pubkey,
+    pub window_start: u64,
+    pub votes_in_window: u64,
+    pub bump: u8,
+}
+
+/// Actions of a proposal beyond the `Proposal::MAX_ACTIONS` kept inline, in a
+/// fixed-capacity zero-copy layout so they are read without deserializing the
+/// proposal's other data. PDA of `[b"proposal-actions", proposal]`.
+#[account(zero_copy)]
+pub struct ProposalActions {
+    pub proposal: Pubkey,
+    pub len: u16,
+    pub bump: u8,
+    pub _padding: [u8; 5],
+    pub actions: [PackedAction; ProposalActions::CAPACITY],
+}
+
+/// A `ProposalAction` in a fixed-size slot: `kind` 0 is `ParameterChange` with
+/// `key` and `amount` as the value, 1 is `Mint` and 2 is `Burn`, both with
+/// `target` as the token account.
+#[zero_copy]
+pub struct PackedAction {
+    pub kind: u8,
+    pub key: u8,
+    pub _padding: [u8; 6],
+    pub target: Pubkey,
+    pub amount: u64,
+}
+
+#[account]
+pub struct SponsorReceipt {
+    pub proposal: Pubkey,
+    pub sponsor: Pubkey,
+    pub weight: u64,
+    pub bump: u8,
+}
+
+#[account]
+pub struct EndorsementReceipt {
+    pub proposal: Pubkey,
+    pub endorser: Pubkey,
+    pub weight: u64,
+    pub bump: u8,
+}
+
+#[account]
+pub struct Delegation {
+    pub governor: Pubkey,
+    pub delegator: Pubkey,
+    pub allocations: Vec<DelegationAllocation>,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DelegationAllocation {
+    pub delegatee: Pubkey,
+    pub bps: u16,
+}
+
+#[account]
+pub struct DelegateeIndex {
+    pub governor: Pubkey,
+    pub delegatee: Pubkey,
+    pub delegators: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+/// Running totals of deposits made through the treasury deposit instructions.
+/// Best-effort: direct transfers to the treasury are not counted.
+#[account]
+pub struct TreasuryStats {
+    pub governor: Pubkey,
+    pub deposit_count: u64,
+    pub totals: Vec<MintTotal>,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MintTotal {
+    /// `TreasuryStats::NATIVE_MINT` for SOL.
+    pub mint: Pubkey,
+    pub total_received: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TreasuryBalance {
+    pub mint_or_native: Pubkey,
+    pub amount: u64,
+}
+
+/// Timelock at `[b"timelock", base]`, shareable by several governors or a council.
+/// Its vault PDA, `[b"timelock-vault", timelock]`, signs executed operations and is
+/// the only signer that can change the delay bounds.
+#[account]
+pub struct Timelock {
+    pub base: Pubkey,
+    pub min_delay: u64,
+    pub max_delay: u64,
+    pub bump: u8,
+    pub vault_bump: u8,
+    /// Accounts allowed to queue and cancel operations.
+    pub proposers: Vec<Pubkey>,
+    pub operations: Vec<TimelockOperation>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TimelockOperation {
+    pub hash: [u8; 32],
+    /// Slot from which the operation can run.
+    pub eta: u64,
+}
+
+/// Token vaults of a governor's treasury, at `[b"treasury-registry", governor]`.
+/// Transfers out of a registered vault count against spending caps and limits on
+/// its mint.
+#[account]
+pub struct TreasuryRegistry {
+    pub governor: Pubkey,
+    pub bump: u8,
+    /// Kept last: the account grows and shrinks by one entry per vault.
+    pub vaults: Vec<TreasuryVault>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TreasuryVault {
+    pub mint: Pubkey,
+    /// The governor authority's associated token account for `mint`.
+    pub address: Pubkey,
+}
+
+/// Outcome ledger of every proposal a governor finalized or executed, oldest first,
+/// at `[b"history", governor]`.
+#[account]
+pub struct GovernorHistory {
+    pub governor: Pubkey,
+    pub bump: u8,
+    /// Kept last: the account grows by one record per append.
+    pub records: Vec<HistoryRecord>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct HistoryRecord {
+    pub proposal_id: u64,
+    pub proposal_type: u8,
+    pub for_votes: u64,
+    pub against_votes: u64,
+    /// State reached: a finalized outcome, or `Executed`.
+    pub outcome: ProposalState,
+    pub slot: u64,
+}
+
+/// Voting weight history of one account, oldest first.
+#[account]
+pub struct VoteCheckpoints {
+    pub governor: Pubkey,
+    pub account: Pubkey,
+    pub checkpoints: Vec<Checkpoint>,
+    /// Set once the oldest checkpoints have been dropped to make room.
+    pub truncated: bool,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Checkpoint {
+    pub slot: u64,
+    pub votes: u64,
+}
+
+/// Outcome record for a content hash, written whenever a proposal with that content
+/// is finalized. PDA: [b"digest", governor, content_hash].
+#[account]
+pub struct ProposalDigest {
+    pub governor: Pubkey,
+    pub content_hash: [u8; 32],
+    /// Most recently finalized proposal with this content.
+    pub proposal_id: u64,
+    /// Slot of the most recent defeat, if any.
+    pub defeated_slot: Option<u64>,
+    pub bump: u8,
+}
+
+/// Template for a recurring proposal, instantiated by `crank_schedule`.
+#[account]
+pub struct ProposalSchedule {
+    pub governor: Pubkey,
+    pub schedule_id: u64,
+    pub proposal_type: u8,
+    /// Hash of the off-chain description; instantiated proposals carry it hex-encoded.
+    pub description_hash: [u8; 32],
+    pub actions: Vec<ProposalAction>,
+    pub interval: u64,
+    pub next_run_slot: u64,
+    pub instance_count: u64,
+    pub paused: bool,
+    pub bump: u8,
+    pub payload_bytes: u32,
+    /// Kept last: the account is sized to the payload at creation.
+    pub instructions: Vec<ProposalInstruction>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ProposalState {
+    Pending,
+    Active,
+    Canceled,
+    Defeated,
+    Succeeded,
+    Queued,
+    Expired,
+    Executed,
+    Vetoed,
+    Sponsoring,
+    Endorsement,
+    EndorsementFailed,
+    Draft,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CancelReason {
+    /// Canceled by the proposer, manager or guardian.
+    Manual,
+    /// Still Pending `Proposal::MAX_PENDING_SLOTS` after creation.
+    StalePending,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExpiryReason {
+    /// A Draft older than `Governor::draft_ttl_slots`.
+    DraftTtl,
+    /// A proposal in its voting period that can no longer reach quorum.
+    QuorumUnreachable,
+}
+
+/// SPL Governance `ProposalState`, for `SplGovernanceProposalV2`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SplProposalState {
+    Draft,
+    SigningOff,
+    Voting,
+    Succeeded,
+    Executing,
+    Completed,
+    Cancelled,
+    Defeated,
+    ExecutingWithErrors,
+    Vetoed,
+}
+
+impl SplProposalState {
+    pub fn from_proposal(proposal: &Proposal) -> Self {
+        match proposal.state {
+            ProposalState::Draft | ProposalState::Sponsoring => Self::Draft,
+            ProposalState::Endorsement | ProposalState::Pending => Self::SigningOff,
+            ProposalState::Active => Self::Voting,
+            ProposalState::Succeeded => Self::Succeeded,
+            ProposalState::Queued if proposal.executed_instructions > 0 => Self::Executing,
+            ProposalState::Queued => Self::Succeeded,
+            ProposalState::Executed => Self::Completed,
+            ProposalState::Canceled => Self::Cancelled,
+            ProposalState::Defeated | ProposalState::Expired | ProposalState::EndorsementFailed => {
+                Self::Defeated
+            }
+            ProposalState::Vetoed => Self::Vetoed,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SplVoteType {
+    SingleChoice,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SplVoteThreshold {
+    YesVotePercentage(u8),
+    QuorumPercentage(u8),
+    Disabled,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SplOptionVoteResult {
+    None,
+    Succeeded,
+    Defeated,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct SplProposalOption {
+    pub label: String,
+    pub vote_weight: u64,
+    pub vote_result: SplOptionVoteResult,
+    pub transactions_executed_count: u16,
+    pub transactions_count: u16,
+}
+
+/// Governor settings that a `ParameterChange` action may update.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ParamKey {
+    VotingDelay,
+    VotingPeriod,
+    ProposalThreshold,
+    TimelockDelay,
+    SponsorshipWindow,
+    /// Boolean setting: 0 or 1.
+    CancelBeforeActiveOnly,
+    MinVotingWeight,
+    ExecutionReward,
+    /// Boolean setting: 0 or 1.
+    RequireTopLevelVote,
+    ResubmissionCooldownSlots,
+    MaxProposalEdits,
+    ProposalFeeLamports,
+    DraftTtlSlots,
+    /// Boolean setting: 0 or 1.
+    ClampToSupply,
+    MaxVotesPerWindow,
+    VoteWindowSlots,
+    VoteRewardLamports,
+    /// Boolean setting: 0 or 1.
+    CompatEvents,
+    /// Boolean setting: 0 or 1.
+    AlignToEpoch,
+    ExecutionDeadlineSlots,
+    ProposalDepositLamports,
+    /// Boolean setting: 0 or 1.
+    FundAtaFromTreasury,
+    RequireMinBalancePerVoter,
+    /// In bps, at most 10_000.
+    EmergencyBypassThresholdBps,
+    /// In bps, at most 10_000.
+    EmergencyQuorumBps,
+    MinActiveSlots,
+    /// Boolean setting: 0 or 1.
+    StoreReasons,
+    /// In bytes, at most `Governor::MAX_REASON_LENGTH`.
+    MaxReasonLength,
+    /// Boolean setting: 0 or 1.
+    HashedProposalIds,
+    /// In bps, at most 10_000.
+    LoserPenaltyBps,
+    VoteStakeLamports,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum ProposalAction {
+    ParameterChange { key: ParamKey, value: u64 },
+    /// Mints governing tokens to the token account `to`.
+    Mint { to: Pubkey, amount: u64 },
+    /// Burns governing tokens from `from`, which must be owned by the governor authority.
+    Burn { from: Pubkey, amount: u64 },
+}
+
+/// A proposal type as returned by `get_proposal_types`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ProposalTypeView {
+    pub index: u8,
+    pub proposal_type: ProposalType,
+    /// Proposals created under this type that have not yet reached a final state.
+    pub active_proposals: u32,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct ProposalType {
+    pub quorum: u16,
+    pub approval_threshold: u16,
+    pub name: String,
+    pub module: Option<Pubkey>,
+    pub emergency_bypass_allowed: bool,
+    /// Non-binding (temperature-check) types can only be finalized, never queued or executed.
+    pub binding: bool,
+    /// Slots a new proposal spends collecting endorsements before its voting delay
+    /// starts. Zero skips the endorsement phase.
+    pub endorsement_period: u64,
+    pub endorsement_quorum_bps: u16,
+    /// Overrides `Governor::proposal_threshold` for this type, e.g. to demand a larger
+    /// stake for constitutional changes.
+    pub proposer_threshold: Option<u64>,
+    /// Index into `Governor::risk_tier_multipliers` scaling this type's quorum.
+    pub risk_tier: u8,
+    /// Programs this type's payload instructions may call, at most
+    /// `MAX_ALLOWED_PROGRAMS`. Empty allows any program.
+    pub allowed_programs: Vec<Pubkey>,
+    /// Slots after voting ends for revealing votes committed with `commit_vote`.
+    /// Non-zero makes the type commit-reveal: tallies stay hidden until the reveal.
+    pub reveal_period: u64,
+    /// Ratification votes: the proposal passes, and still executes, once turnout
+    /// reaches quorum, whatever the For/Against split.
+    pub quorum_only: bool,
+    /// Most a payload may move out of the treasury per mint, at most
+    /// `MAX_SPENDING_CAPS` entries. Empty leaves spending uncapped, as does a mint
+    /// without an entry.
+    pub spending_cap: Vec<SpendingCap>,
+    /// Weigh votes by the voter's current weight rather than their checkpoint at the
+    /// proposal's start block. Weight then tracks balances during voting, but tokens
+    /// bought or borrowed mid-vote count too, and can be moved between wallets to
+    /// vote again with them. Off by default: snapshot weight.
+    pub realtime_weight: bool,
+    /// Overrides `Governor::execution_deadline_slots` for this type, e.g. a short
+    /// window for treasury transfers. `Some(0)` gives the type no deadline.
+    pub grace_period: Option<u64>,
+    /// Decide the outcome on conviction: each vote weighed by how long it stood
+    /// before voting ended, reaching full weight after `max_conviction_slots`. Not
+    /// available to commit-reveal types.
+    pub conviction_mode: bool,
+    pub max_conviction_slots: u64,
+    /// Set by `remove_proposal_type`. The entry keeps its index, so proposals and
+    /// history records holding it still resolve, but new proposals can't use it.
+    pub removed: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct SpendingCap {
+    /// Token mint, or `None` for lamports from the treasury PDA.
+    pub mint: Option<Pubkey>,
+    pub max_amount: u64,
+}
+
+/// An outflow from the treasury recognized in a payload instruction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TreasuryOutflow {
+    /// System program transfer from the lamport treasury.
+    Native { amount: u64 },
+    /// Token transfer signed by the governor authority that names its mint, or a
+    /// payment stream funded from the treasury.
+    Token { mint: Pubkey, amount: u64 },
+    /// Plain token `Transfer` signed by the governor authority; the mint is only
+    /// known from the source account's data.
+    UnknownMint { source: Pubkey, amount: u64 },
+}
+
+#[error_code]
+pub enum GovernorError {
+    #[msg("Proposer does not have enough votes to create a proposal")]
+    InsufficientProposerVotes,
+    #[msg("Invalid proposal type")]
+    InvalidProposalType,
+    #[msg("Voting period is not active")]
+    VotingPeriodInactive,
+    #[msg("Proposal has already been executed")]
+    ProposalAlreadyExecuted,
+    #[msg("Proposal has been canceled")]
+    ProposalCanceled,
+    #[msg("Voting period is still active")]
+    VotingPeriodActive,
+    #[msg("Quorum not reached")]
+    QuorumNotReached,
+    #[msg("Approval threshold not met")]
+    ApprovalThresholdNotMet,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Invalid proposal id")]
+    InvalidProposalId,
+    #[msg("Proposal has already been queued")]
+    ProposalAlreadyQueued,
+    #[msg("Proposal has not been queued")]
+    ProposalNotQueued,
+    #[msg("Timelock has not expired")]
+    TimelockNotExpired,
+    #[msg("Emergency bypass is not allowed for this proposal type")]
+    EmergencyBypassNotAllowed,
+    #[msg("Invalid delegatee")]
+    InvalidDelegatee,
+    #[msg("Delegatee index is full")]
+    DelegateeIndexFull,
+    #[msg("Proposal has already been finalized")]
+    ProposalAlreadyFinalized,
+    #[msg("Non-binding proposals cannot be executed")]
+    NonBindingProposal,
+    #[msg("Too many actions in proposal")]
+    TooManyActions,
+    #[msg("Parameter value is out of bounds")]
+    ParameterOutOfBounds,
+    #[msg("Proposal has not been activated")]
+    ProposalNotActivated,
+    #[msg("Proposal is not open for sponsorship")]
+    ProposalNotSponsoring,
+    #[msg("Sponsorship window has closed")]
+    SponsorshipWindowClosed,
+    #[msg("Proposal has already been sponsored by this account")]
+    AlreadySponsored,
+    #[msg("Draft has not expired")]
+    DraftNotExpired,
+    #[msg("Proposal lifetime exceeds the maximum")]
+    ProposalLifetimeTooLong,
+    #[msg("Proposal is not in its endorsement phase")]
+    ProposalNotInEndorsement,
+    #[msg("Endorsement period has ended")]
+    EndorsementPeriodEnded,
+    #[msg("Endorsement period is still active")]
+    EndorsementPeriodActive,
+    #[msg("Delegation allocations must be unique, non-zero and sum to 10000 bps")]
+    InvalidDelegationAllocations,
+    #[msg("Invalid delegatee index accounts")]
+    InvalidDelegateeIndexAccounts,
+    #[msg("Invalid delegation accounts")]
+    InvalidDelegationAccounts,
+    #[msg("Token account is not the owner's governing token account")]
+    InvalidVotingTokenAccount,
+    #[msg("Proposal is not a draft")]
+    ProposalNotDraft,
+    #[msg("Proposer can no longer cancel once voting has started")]
+    CancelWindowClosed,
+    #[msg("Instruction index has already been added")]
+    DuplicateInstructionIndex,
+    #[msg("Instruction index is out of order")]
+    InstructionIndexOutOfOrder,
+    #[msg("Proposal payload exceeds the maximum size")]
+    PayloadTooLarge,
+    #[msg("Voting weight is below the minimum")]
+    WeightBelowMinimum,
+    #[msg("No instruction at this index")]
+    InvalidInstructionIndex,
+    #[msg("Superseded proposal is missing or does not match")]
+    InvalidSupersededProposal,
+    #[msg("Superseded proposal has not reached a terminal state")]
+    SupersededProposalNotTerminal,
+    #[msg("Dependency proposal is missing or does not match")]
+    InvalidDependency,
+    #[msg("Dependency proposal has not been executed")]
+    DependencyNotExecuted,
+    #[msg("Schedule is paused")]
+    SchedulePaused,
+    #[msg("Schedule is not due yet")]
+    ScheduleNotDue,
+    #[msg("Votes must be cast in a top-level instruction")]
+    CpiVoteNotAllowed,
+    #[msg("Invalid proposal digest account")]
+    InvalidProposalDigest,
+    #[msg("Identical content was defeated too recently to be proposed again")]
+    ResubmissionTooSoon,
+    #[msg("New proposals are frozen during a blackout window")]
+    ProposalCreationBlackout,
+    #[msg("Blackout window is empty, overlaps another or does not exist")]
+    InvalidBlackoutWindow,
+    #[msg("Too many blackout windows")]
+    TooManyBlackoutWindows,
+    #[msg("Description is too long")]
+    DescriptionTooLong,
+    #[msg("Proposal can only be edited while Pending, before voting starts")]
+    ProposalNotEditable,
+    #[msg("Proposal has reached the maximum number of edits")]
+    TooManyEdits,
+    #[msg("Content hash does not match the edited proposal")]
+    ContentHashMismatch,
+    #[msg("Proposer cannot cover the proposal fee")]
+    InsufficientFeeBalance,
+    #[msg("Proposal is not a stale pending proposal")]
+    ProposalNotStale,
+    #[msg("Proposal does not meet any expiry condition")]
+    ProposalNotExpirable,
+    #[msg("Arithmetic overflow")]
+    ArithmeticOverflow,
+    #[msg("Lookup block must be in the past")]
+    FutureLookup,
+    #[msg("Checkpoint history no longer covers this block")]
+    CheckpointUnavailable,
+    #[msg("Proposal payload has too many instructions")]
+    TooManyInstructions,
+    #[msg("Proposals with a payload are executed with execute_transaction")]
+    ProposalHasPayload,
+    #[msg("Accounts do not match the stored instruction")]
+    InvalidTransactionAccounts,
+    #[msg("Address is already blocked")]
+    AddressAlreadyBlocked,
+    #[msg("Address is not blocked")]
+    AddressNotBlocked,
+    #[msg("Blocklist is full")]
+    BlocklistFull,
+    #[msg("Transfer would leave the treasury below rent exemption")]
+    TreasuryBelowRentExemption,
+    #[msg("Votes cast exceed the total supply")]
+    TallyExceedsSupply,
+    #[msg("Token transfers must come from an account owned by the governor authority")]
+    InvalidTreasuryTokenAccount,
+    #[msg("Proposal was finalized as defeated")]
+    ProposalDefeated,
+    #[msg("Vote rewards are disabled")]
+    VoteRewardDisabled,
+    #[msg("Vote exceeded the per-window cap and earns no reward")]
+    RewardIneligible,
+    #[msg("Vote reward has already been claimed")]
+    RewardAlreadyClaimed,
+    #[msg("Upgrade buffer must be a loader buffer owned by the governor authority")]
+    InvalidUpgradeBuffer,
+    #[msg("Risk tier is not defined")]
+    InvalidRiskTier,
+    #[msg("Governor is executing a proposal instruction")]
+    ReentrancyDetected,
+    #[msg("Instructions targeting the governor program must be flagged as self-calls")]
+    SelfCallNotFlagged,
+    #[msg("Governing mint's mint authority is not the governor authority")]
+    MintAuthorityNotGovernor,
+    #[msg("Mint and burn actions need the governing mint, governor authority, token program and token accounts")]
+    MissingSupplyAccounts,
+    #[msg("Proposal type does not allow calling this program")]
+    ProgramNotAllowed,
+    #[msg("Proposal's execution deadline has passed")]
+    ExecutionDeadlinePassed,
+    #[msg("Quorum override is below the proposal type's quorum")]
+    QuorumOverrideTooLow,
+    #[msg("Proposal's deposit is not forfeited")]
+    DepositNotForfeited,
+    #[msg("Proposal's deposit has already been swept")]
+    DepositAlreadySwept,
+    #[msg("Created associated token account is not the next transfer's destination")]
+    AtaTransferMismatch,
+    #[msg("Proposal uses commit-reveal voting: commit and reveal instead")]
+    CommitRevealRequired,
+    #[msg("Proposal does not use commit-reveal voting")]
+    NotCommitReveal,
+    #[msg("Reveal window is not open")]
+    RevealWindowInactive,
+    #[msg("Support and salt do not match the commitment")]
+    CommitmentMismatch,
+    #[msg("Vote has already been revealed")]
+    VoteAlreadyRevealed,
+    #[msg("Stream needs a non-zero amount and a start slot before its end slot")]
+    InvalidStreamTerms,
+    #[msg("Account does not match the payment stream")]
+    InvalidStream,
+    #[msg("Payment stream has already been canceled")]
+    StreamAlreadyCanceled,
+    #[msg("Nothing has vested since the last claim")]
+    NothingToClaim,
+    #[msg("Grant needs between one and eight non-zero tranches")]
+    InvalidGrantTranches,
+    #[msg("Account does not match the grant")]
+    InvalidGrant,
+    #[msg("Grant has been canceled")]
+    GrantAlreadyCanceled,
+    #[msg("Grant has no tranche at this index")]
+    InvalidTrancheIndex,
+    #[msg("Milestones must be approved in order")]
+    MilestoneOutOfOrder,
+    #[msg("Milestone has not been approved")]
+    MilestoneNotApproved,
+    #[msg("Tranche has already been claimed")]
+    TrancheAlreadyClaimed,
+    #[msg("Aggregate accounts must be unique, at most sixteen, and match the token accounts passed")]
+    InvalidAggregateAccounts,
+    #[msg("Payload moves more out of the treasury than the proposal type allows")]
+    SpendingCapExceeded,
+    #[msg("Treasury spending limit for this window would be exceeded")]
+    EpochSpendingLimitExceeded,
+    #[msg("Spending limit account for the transferred mint is missing")]
+    SpendingLimitAccountMissing,
+    #[msg("Spend exceeds the manager's allowance for this window")]
+    AllowanceExceeded,
+    #[msg("Spend exceeds the treasurer's budget for this window")]
+    TreasurerBudgetExceeded,
+    #[msg("No treasurer is appointed")]
+    NoTreasurer,
+    #[msg("Mint already has a treasury vault")]
+    TreasuryVaultExists,
+    #[msg("Treasury registry is full")]
+    TreasuryRegistryFull,
+    #[msg("Treasury vault still holds tokens")]
+    TreasuryVaultNotEmpty,
+    #[msg("Token account is not a registered treasury vault")]
+    TreasuryVaultNotRegistered,
+    #[msg("Proposal type, or a later one, is still used by a proposal or schedule")]
+    ProposalTypeInUse,
+    #[msg("Rage-quit is only open while the proposal is queued and before its timelock ends")]
+    RageQuitWindowClosed,
+    #[msg("Rage-quit requires an Against vote backed by governing tokens")]
+    RageQuitRequiresAgainstVote,
+    #[msg("Proposal execution is blocked")]
+    ExecutionBlocked,
+    #[msg("Proposal execution is not blocked")]
+    ExecutionNotBlocked,
+    #[msg("Only a Succeeded or Queued proposal can be blocked")]
+    ProposalNotBlockable,
+    #[msg("Proposal has not been finalized as Succeeded")]
+    ProposalNotFinalized,
+    #[msg("Approval is below the emergency bypass threshold")]
+    EmergencyApprovalNotMet,
+    #[msg("Turnout is below the emergency quorum")]
+    EmergencyQuorumNotMet,
+    #[msg("Voting window is shorter than the governor's minimum active slots")]
+    VotingWindowTooShort,
+    #[msg("Voter has no voting power")]
+    InsufficientVotingPower,
+    #[msg("The admin role is locked to the governor authority")]
+    AdminTransferLocked,
+    #[msg("Only the governor authority can be locked in as admin")]
+    AdminNotGovernorAuthority,
+    #[msg("Account is not a Squads v4 multisig")]
+    InvalidSquadsMultisig,
+    #[msg("Governor authority needs Initiate and Vote permissions on the multisig")]
+    NotSquadsMember,
+    #[msg("Timelock is missing, not the governor's, or misconfigured")]
+    InvalidTimelock,
+    #[msg("Delay is outside the timelock's bounds")]
+    TimelockDelayOutOfBounds,
+    #[msg("Signer is not a proposer of the timelock")]
+    TimelockProposerNotAllowed,
+    #[msg("Operation is already queued in the timelock")]
+    TimelockOperationAlreadyQueued,
+    #[msg("Operation is not queued in the timelock")]
+    TimelockOperationNotQueued,
+    #[msg("Timelock operation's eta has not passed")]
+    TimelockOperationNotReady,
+    #[msg("Timelock has no room for more operations")]
+    TimelockFull,
+    #[msg("Proposal already has a payload lookup table")]
+    LookupTableAlreadyCreated,
+    #[msg("Lookup table is not the one derived from the governor authority and slot")]
+    InvalidLookupTable,
+    #[msg("wSOL account is not the governor authority's associated token account for the native mint")]
+    InvalidWrappedSolAccount,
+    #[msg("Vote reason is longer than the governor's max_reason_length")]
+    ReasonTooLong,
+    #[msg("Governor numbers proposals the other way; use the matching create instruction")]
+    ProposalIdModeMismatch,
+    #[msg("A proposal with this id already exists")]
+    ProposalIdTaken,
+    #[msg("Execution hook account missing or not the governor's hook")]
+    InvalidExecutionHook,
+    #[msg("Vote posted no stake")]
+    NoVoteStake,
+    #[msg("Vote stake has already been claimed")]
+    StakeAlreadyClaimed,
+    #[msg("Proposal has not been finalized")]
+    OutcomePending,
+    #[msg("Proposal still holds vote stakes that have not been claimed")]
+    StakesUnclaimed,
+    #[msg("Governor already has the maximum number of proposal types")]
+    TooManyProposalTypes,
+    #[msg("Weight is read from checkpoints; pass the account's VoteCheckpoints PDA")]
+    CheckpointsRequired,
+    #[msg("Draft already has the maximum number of sponsors")]
+    TooManySponsors,
+    #[msg("Proposal has actions in its ProposalActions account; pass the account")]
+    ProposalActionsRequired,
+    #[msg("Actions in a ProposalActions account cannot be combined with a payload")]
+    ExternalActionsWithPayload,
+    #[msg("A recorded failure needs a nonzero error code")]
+    InvalidErrorCode,
+    #[msg("Proposal type has been removed")]
+    ProposalTypeRetired,
+}
+
+impl Governor {
+    pub const LEN: usize = 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 8 + 8 + 1 + 8
+        + 4 + Self::MAX_BLACKOUT_WINDOWS * BlackoutWindow::LEN + 1 + 8 + 8 + 8 + 1 + 8 + 8 + 8
+        + 1 + 1 + 8 + 1 + 1 + 4 + Self::MAX_BLOCKLIST_LEN * 32 + 4 + Self::MAX_RISK_TIERS * 2 + 1
+        + 8 + 1 + 8 + 2 + 2 + 8 + 1 + 2 + 1 + 4 + Self::MAX_SPENDING_LIMITS * SpendingCap::LEN + 32 + 4 + 33 + 1 + 33 + 33
+        + 2 + 8;
+
+    pub const SLOTS_PER_DAY: u64 = 216_000;
+    pub const MAX_VOTING_DELAY: u64 = 30 * Self::SLOTS_PER_DAY;
+    pub const MIN_VOTING_PERIOD: u64 = 9_000;
+    pub const MAX_VOTING_PERIOD: u64 = 30 * Self::SLOTS_PER_DAY;
+    pub const MAX_TIMELOCK_DELAY: u64 = 30 * Self::SLOTS_PER_DAY;
+    pub const DEFAULT_SPONSORSHIP_WINDOW: u64 = 7 * Self::SLOTS_PER_DAY;
+    pub const MAX_REASON_LENGTH: u64 = 1_000;
+    pub const DEFAULT_MAX_REASON_LENGTH: u16 = 280;
+    pub const DEFAULT_EMERGENCY_BYPASS_THRESHOLD_BPS: u16 = 9_000;
+    pub const DEFAULT_EMERGENCY_QUORUM_BPS: u16 = 3_000;
+    pub const MAX_SPONSORSHIP_WINDOW: u64 = 30 * Self::SLOTS_PER_DAY;
+    pub const MAX_RESUBMISSION_COOLDOWN: u64 = 90 * Self::SLOTS_PER_DAY;
+    pub const MAX_BLACKOUT_WINDOWS: usize = 4;
+    pub const MAX_BLOCKLIST_LEN: usize = 32;
+    pub const MAX_RISK_TIERS: usize = 8;
+    /// Type ids are a `u8`.
+    pub const MAX_PROPOSAL_TYPES: usize = u8::MAX as usize + 1;
+    /// Keeps `get_aggregate_votes` within a transaction's account and compute limits.
+    pub const MAX_AGGREGATE_ACCOUNTS: usize = 16;
+    pub const MAX_SPENDING_LIMITS: usize = 4;
+    /// Multiplier of the default tier 0: the type's base quorum, unscaled.
+    pub const BASE_RISK_MULTIPLIER: u16 = 10_000;
+    pub const DEFAULT_MAX_PROPOSAL_EDITS: u64 = 3;
+    pub const MAX_DRAFT_TTL: u64 = 365 * Self::SLOTS_PER_DAY;
+    pub const MAX_EXECUTION_DEADLINE: u64 = 365 * Self::SLOTS_PER_DAY;
+    pub const DEFAULT_VOTE_WINDOW: u64 = 30 * Self::SLOTS_PER_DAY;
+    pub const MAX_VOTE_WINDOW: u64 = 365 * Self::SLOTS_PER_DAY;
+    pub const MAX_PROPOSAL_EDITS: u64 = 10;
+    /// Upper bound on `end_block - creation slot`, independent of the timing settings.
+    pub const MAX_PROPOSAL_LIFETIME: u64 = 45 * Self::SLOTS_PER_DAY;
+
+    /// Rejects new proposals inside a blackout window, unless `proposer` is the manager
+    /// and `blackout_manager_exempt` is set.
+    pub fn check_blackout(&self, proposer: &Pubkey, slot: u64) -> Result<()> {
+        if self.blackout_manager_exempt && *proposer == self.manager {
+            return Ok(());
+        }
+        require!(
+            !self
+                .blackout_windows
+                .iter()
+                .any(|window| (window.start_slot..window.end_slot).contains(&slot)),
+            GovernorError::ProposalCreationBlackout
+        );
+        Ok(())
+    }
+
+    /// Counts a new proposal or schedule against `proposal_type`.
+    pub fn hold_proposal_type(&mut self, proposal_type: u8) {
+        if let Some(count) = self.active_proposal_counts.get_mut(proposal_type as usize) {
+            *count += 1;
+        }
+    }
+
+    /// Stops counting a proposal or schedule against `proposal_type`.
+    pub fn release_proposal_type(&mut self, proposal_type: u8) {
+        if let Some(count) = self.active_proposal_counts.get_mut(proposal_type as usize) {
+            *count = count.saturating_sub(1);
+        }
+    }
+
+    /// Releases `proposal`'s type once a transition leaves it no longer holding
+    /// the type; `held` is `holds_type()` from before the transition.
+    pub fn settle_proposal_type(&mut self, proposal: &Proposal, held: bool) {
+        if held && !proposal.holds_type() {
+            self.release_proposal_type(proposal.proposal_type);
+        }
+    }
+
+    /// The type's `proposer_threshold` when it sets one, else `proposal_threshold`.
+    pub fn proposal_threshold_for(&self, proposal_type: u8) -> Result<u64> {
+        let proposal_type_info = self
+            .proposal_types
+            .get(proposal_type as usize)
+            .ok_or(GovernorError::InvalidProposalType)?;
+        Ok(proposal_type_info.proposer_threshold.unwrap_or(self.proposal_threshold))
+    }
+
+    /// Requires `proposer` to hold at least the proposal type's threshold in votes,
+    /// both in its checkpoint at the slot before `slot` and currently. Reading the
+    /// previous slot keeps a checkpoint taken in the creating transaction from
+    /// counting. The manager is exempt.
+    #[allow(clippy::too_many_arguments)]
+    pub fn check_proposer_threshold(
+        &self,
+        governor: &Pubkey,
+        proposer: &Pubkey,
+        proposal_type: u8,
+        balance: u64,
+        proposer_delegation: &AccountInfo,
+        delegations: &[AccountInfo],
+        slot: u64,
+        checkpoints: Option<&VoteCheckpoints>,
+    ) -> Result<()> {
+        if *proposer == self.manager {
+            return Ok(());
+        }
+        let current_weight = self.get_votes(governor, proposer, balance, proposer_delegation, delegations)?;
+        let checkpoints = checkpoints.ok_or(GovernorError::CheckpointsRequired)?;
+        let votes = checkpoints.votes_at(slot.saturating_sub(1))?.min(current_weight);
+        require!(
+            votes >= self.proposal_threshold_for(proposal_type)?,
+            GovernorError::InsufficientProposerVotes
+        );
+        Ok(())
+    }
+
+    /// Checks the parts of a new proposal that do not depend on who is proposing it.
+    pub fn validate_new_proposal(&self, proposal_type: u8, actions: &[ProposalAction]) -> Result<()> {
+        let proposal_type_info = self
+            .proposal_types
+            .get(proposal_type as usize)
+            .ok_or(GovernorError::InvalidProposalType)?;
+        require!(!proposal_type_info.removed, GovernorError::ProposalTypeRetired);
+        require!(
+            proposal_type_info.binding || actions.is_empty(),
+            GovernorError::NonBindingProposal
+        );
+        require!(actions.len() <= Proposal::MAX_ACTIONS, GovernorError::TooManyActions);
+        for action in actions {
+            action.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Fails with `ProgramNotAllowed` if an instruction calls a program outside
+    /// `proposal_type`'s allowlist.
+    pub fn check_programs_allowed(
+        &self,
+        proposal_type: u8,
+        instructions: &[ProposalInstruction],
+    ) -> Result<()> {
+        let proposal_type_info = self
+            .proposal_types
+            .get(proposal_type as usize)
+            .ok_or(GovernorError::InvalidProposalType)?;
+        require!(
+            instructions
+                .iter()
+                .all(|instruction| proposal_type_info.allows_program(&instruction.program_id)),
+            GovernorError::ProgramNotAllowed
+        );
+        Ok(())
+    }
+
+    /// Fails with `SpendingCapExceeded` if the treasury transfers in `instructions`
+    /// add up to more than one of `proposal_type`'s spending caps, and with
+    /// `EpochSpendingLimitExceeded` if they alone would breach a `SpendingLimit`.
+    /// Transfers out of `vaults`, the governor's registered treasury vaults, count
+    /// against their mint.
+    pub fn check_spending_caps(
+        &self,
+        governor: &Pubkey,
+        proposal_type: u8,
+        instructions: &[ProposalInstruction],
+        vaults: &[TreasuryVault],
+    ) -> Result<()> {
+        let proposal_type_info = self
+            .proposal_types
+            .get(proposal_type as usize)
+            .ok_or(GovernorError::InvalidProposalType)?;
+        self.check_spending_caps_of(governor, proposal_type_info, instructions, vaults)
+    }
+
+    /// `check_spending_caps` against the caps of `proposal_type_info` directly, e.g. a
+    /// proposal's `type_snapshot`.
+    pub fn check_spending_caps_of(
+        &self,
+        governor: &Pubkey,
+        proposal_type_info: &ProposalType,
+        instructions: &[ProposalInstruction],
+        vaults: &[TreasuryVault],
+    ) -> Result<()> {
+        if proposal_type_info.spending_cap.is_empty() && self.spending_limits.is_empty() {
+            return Ok(());
+        }
+        let authority = Pubkey::create_program_address(&self.authority_seeds(governor), &crate::ID)
+            .map_err(|_| GovernorError::InvalidTransactionAccounts)?;
+        let treasury = Pubkey::create_program_address(&self.treasury_seeds(governor), &crate::ID)
+            .map_err(|_| GovernorError::InvalidTransactionAccounts)?;
+
+        proposal_type_info.check_spending_caps(instructions, &authority, &treasury, vaults)?;
+        require!(
+            SpendingCap::within(&self.spending_limits, instructions, &authority, &treasury, vaults),
+            GovernorError::EpochSpendingLimitExceeded
+        );
+        Ok(())
+    }
+
+    /// Signer seeds of the governor authority PDA, for `invoke_signed`.
+    pub fn authority_seeds<'a>(&'a self, governor: &'a Pubkey) -> [&'a [u8]; 3] {
+        [
+            b"governor-authority",
+            governor.as_ref(),
+            std::slice::from_ref(&self.authority_bump),
+        ]
+    }
+
+    /// Signer seeds of the lamport treasury, for `invoke_signed`.
+    pub fn treasury_seeds<'a>(&'a self, governor: &'a Pubkey) -> [&'a [u8]; 3] {
+        [b"treasury", governor.as_ref(), std::slice::from_ref(&self.treasury_bump)]
+    }
+
+    /// Rejects content identical to a proposal defeated less than
+    /// `resubmission_cooldown_slots` ago. `digest_info` must be the ProposalDigest PDA
+    /// for `content_hash`, which does not exist until such content is first finalized.
+    pub fn check_resubmission_cooldown(
+        &self,
+        governor: &Pubkey,
+        content_hash: &[u8; 32],
+        digest_info: &AccountInfo,
+        slot: u64,
+    ) -> Result<()> {
+        let (expected, _) = Pubkey::find_program_address(
+            &[b"digest", governor.as_ref(), content_hash.as_ref()],
+            &crate::ID,
+        );
+        require_keys_eq!(*digest_info.key, expected, GovernorError::InvalidProposalDigest);
+        if *digest_info.owner != crate::ID || digest_info.data_is_empty() {
+            return Ok(());
+        }
+
+        let digest = ProposalDigest::try_deserialize(&mut &digest_info.try_borrow_data()?[..])?;
+        if let Some(defeated_slot) = digest.defeated_slot {
+            require!(
+                slot >= defeated_slot.saturating_add(self.resubmission_cooldown_slots),
+                GovernorError::ResubmissionTooSoon
+            );
+        }
+        Ok(())
+    }
+
+    /// Sets the settings a new governor starts with that are not chosen at creation.
+    pub fn apply_defaults(&mut self) {
+        self.sponsorship_window = Self::DEFAULT_SPONSORSHIP_WINDOW;
+        self.max_proposal_edits = Self::DEFAULT_MAX_PROPOSAL_EDITS;
+        self.vote_window_slots = Self::DEFAULT_VOTE_WINDOW;
+        self.risk_tier_multipliers = vec![Self::BASE_RISK_MULTIPLIER];
+        self.emergency_bypass_threshold_bps = Self::DEFAULT_EMERGENCY_BYPASS_THRESHOLD_BPS;
+        self.emergency_quorum_bps = Self::DEFAULT_EMERGENCY_QUORUM_BPS;
+        self.max_reason_length = Self::DEFAULT_MAX_REASON_LENGTH;
+    }
+
+    /// Applies a validated parameter change and returns the previous value.
+    pub fn set_parameter(&mut self, key: ParamKey, value: u64) -> Result<u64> {
+        key.validate(value)?;
+        let old = match key {
+            ParamKey::VotingDelay => std::mem::replace(&mut self.voting_delay, value),
+            ParamKey::VotingPeriod => std::mem::replace(&mut self.voting_period, value),
+            ParamKey::ProposalThreshold => std::mem::replace(&mut self.proposal_threshold, value),
+            ParamKey::TimelockDelay => std::mem::replace(&mut self.timelock_delay, value),
+            ParamKey::SponsorshipWindow => std::mem::replace(&mut self.sponsorship_window, value),
+            ParamKey::CancelBeforeActiveOnly => {
+                std::mem::replace(&mut self.cancel_before_active_only, value != 0) as u64
+            }
+            ParamKey::MinVotingWeight => std::mem::replace(&mut self.min_voting_weight, value),
+            ParamKey::ExecutionReward => std::mem::replace(&mut self.execution_reward, value),
+            ParamKey::RequireTopLevelVote => {
+                std::mem::replace(&mut self.require_top_level_vote, value != 0) as u64
+            }
+            ParamKey::ResubmissionCooldownSlots => {
+                std::mem::replace(&mut self.resubmission_cooldown_slots, value)
+            }
+            ParamKey::MaxProposalEdits => std::mem::replace(&mut self.max_proposal_edits, value),
+            ParamKey::ProposalFeeLamports => std::mem::replace(&mut self.proposal_fee_lamports, value),
+            ParamKey::DraftTtlSlots => std::mem::replace(&mut self.draft_ttl_slots, value),
+            ParamKey::ClampToSupply => {
+                std::mem::replace(&mut self.clamp_to_supply, value != 0) as u64
+            }
+            ParamKey::MaxVotesPerWindow => std::mem::replace(&mut self.max_votes_per_window, value),
+            ParamKey::VoteWindowSlots => std::mem::replace(&mut self.vote_window_slots, value),
+            ParamKey::VoteRewardLamports => std::mem::replace(&mut self.vote_reward_lamports, value),
+            ParamKey::CompatEvents => std::mem::replace(&mut self.compat_events, value != 0) as u64,
+            ParamKey::AlignToEpoch => std::mem::replace(&mut self.align_to_epoch, value != 0) as u64,
+            ParamKey::ExecutionDeadlineSlots => {
+                std::mem::replace(&mut self.execution_deadline_slots, value)
+            }
+            ParamKey::ProposalDepositLamports => {
+                std::mem::replace(&mut self.proposal_deposit_lamports, value)
+            }
+            ParamKey::FundAtaFromTreasury => {
+                std::mem::replace(&mut self.fund_ata_from_treasury, value != 0) as u64
+            }
+            ParamKey::RequireMinBalancePerVoter => {
+                std::mem::replace(&mut self.require_min_balance_per_voter, value)
+            }
+            ParamKey::EmergencyBypassThresholdBps => {
+                std::mem::replace(&mut self.emergency_bypass_threshold_bps, value as u16) as u64
+            }
+            ParamKey::EmergencyQuorumBps => {
+                std::mem::replace(&mut self.emergency_quorum_bps, value as u16) as u64
+            }
+            ParamKey::MinActiveSlots => std::mem::replace(&mut self.min_active_slots, value),
+            ParamKey::StoreReasons => std::mem::replace(&mut self.store_reasons, value != 0) as u64,
+            ParamKey::MaxReasonLength => {
+                std::mem::replace(&mut self.max_reason_length, value as u16) as u64
+            }
+            ParamKey::HashedProposalIds => {
+                std::mem::replace(&mut self.hashed_proposal_ids, value != 0) as u64
+            }
+            ParamKey::LoserPenaltyBps => {
+                std::mem::replace(&mut self.loser_penalty_bps, value as u16) as u64
+            }
+            ParamKey::VoteStakeLamports => std::mem::replace(&mut self.vote_stake_lamports, value),
+        };
+        Ok(old)
+    }
+
+    /// Quorum, in votes, for a proposal of `proposal_type` given its risk tier. A
+    /// proposal's `quorum_override` replaces the type's base quorum.
+    pub fn quorum_votes(&self, proposal_type: &ProposalType, quorum_override: Option<u16>) -> Result<u64> {
+        let multiplier = self
+            .risk_tier_multipliers
+            .get(proposal_type.risk_tier as usize)
+            .ok_or(GovernorError::InvalidRiskTier)?;
+        match quorum_override {
+            Some(quorum) => ProposalType { quorum, ..proposal_type.clone() }
+                .quorum_votes(self.total_supply, *multiplier),
+            None => proposal_type.quorum_votes(self.total_supply, *multiplier),
+        }
+    }
+
+    /// Fails with `QuorumOverrideTooLow` if `quorum_override` would lower the quorum
+    /// of `proposal_type`; overrides may only raise it, up to 100%.
+    pub fn check_quorum_override(&self, proposal_type: u8, quorum_override: Option<u16>) -> Result<()> {
+        let Some(quorum) = quorum_override else {
+            return Ok(());
+        };
+        let proposal_type_info = self
+            .proposal_types
+            .get(proposal_type as usize)
+            .ok_or(GovernorError::InvalidProposalType)?;
+        require!(quorum >= proposal_type_info.quorum, GovernorError::QuorumOverrideTooLow);
+        require!(quorum <= 10_000, GovernorError::ParameterOutOfBounds);
+        Ok(())
+    }
+
+    /// With `require_top_level_vote`, fails with `CpiVoteNotAllowed` unless the current
+    /// top-level instruction of the transaction is this program's.
+    pub fn check_top_level_vote(&self, instructions: &AccountInfo) -> Result<()> {
+        if self.require_top_level_vote {
+            // Under CPI, the transaction's current top-level instruction belongs to the caller.
+            let current_index = load_current_index_checked(instructions)?;
+            let current = load_instruction_at_checked(current_index as usize, instructions)?;
+            require_keys_eq!(current.program_id, crate::ID, GovernorError::CpiVoteNotAllowed);
+        }
+        Ok(())
+    }
+
+    /// False while a proposal instruction is being executed.
+    pub fn is_idle(&self) -> bool {
+        !self.execution_in_progress
+    }
+
+    pub fn is_blocked(&self, account: &Pubkey) -> bool {
+        self.blocklist.contains(account)
+    }
+
+    /// (weight, spent) of a vote on `proposal`, given the voter's `current_weight`
+    /// from `get_votes`. `realtime_weight` types count the current weight as is, and
+    /// need no checkpoints. Other types count what the voter's `checkpoints` held at
+    /// the proposal's start block, capped at the current weight so tokens sold or
+    /// delegated away after the snapshot do not vote again in other hands; the
+    /// whole snapshot weight is spent either way.
+    pub fn vote_weight(
+        &self,
+        proposal: &Proposal,
+        checkpoints: Option<&VoteCheckpoints>,
+        current_weight: u64,
+    ) -> Result<(u64, u64)> {
+        if proposal.type_info(&self.proposal_types)?.realtime_weight {
+            return Ok((current_weight, current_weight));
+        }
+        let checkpoints = checkpoints.ok_or(GovernorError::CheckpointsRequired)?;
+        let snapshot_weight = checkpoints.votes_at(proposal.start_block)?;
+        Ok((snapshot_weight.min(current_weight), snapshot_weight))
+    }
+
+    /// Account size, discriminator included, of a governor holding `proposal_types`
+    /// and their `active_proposal_counts`.
+    pub fn space_with_types(proposal_types: &[ProposalType]) -> usize {
+        8 + Self::LEN
+            + proposal_types
+                .iter()
+                .map(|proposal_type| proposal_type.try_to_vec().map_or(0, |bytes| bytes.len()) + 4)
+                .sum::<usize>()
+    }
+
+    /// Voting weight of `account`: the part of its own governing-token `balance` it has
+    /// not delegated away, per `account_delegation`, plus its share of each delegator
+    /// passed in `delegations` as (Delegation, token account) pairs. `account_delegation`
+    /// must be the account's Delegation PDA, so delegated power counts for the
+    /// delegatees only. Pairs must be ordered by strictly increasing Delegation address
+    /// so no delegator is counted twice. Balances are read live, so this is the
+    /// current weight; `vote_weight` turns it into the weight a vote counts with.
+    /// Blocked accounts have no weight, and blocked delegators contribute none.
+    pub fn get_votes(
+        &self,
+        governor: &Pubkey,
+        account: &Pubkey,
+        balance: u64,
+        account_delegation: &AccountInfo,
+        delegations: &[AccountInfo],
+    ) -> Result<u64> {
+        if self.is_blocked(account) {
+            return Ok(0);
+        }
+
+        let pairs = delegations.chunks_exact(2);
+        require!(pairs.remainder().is_empty(), GovernorError::InvalidDelegationAccounts);
+
+        require_keys_eq!(
+            *account_delegation.key,
+            Delegation::address(governor, account).0,
+            GovernorError::InvalidDelegationAccounts
+        );
+        let mut votes = if *account_delegation.owner == crate::ID {
+            let delegation = Delegation::try_deserialize(&mut &account_delegation.try_borrow_data()?[..])?;
+            balance - delegation.delegated_amount(balance)
+        } else {
+            balance
+        };
+        let mut previous: Option<Pubkey> = None;
+        for pair in pairs {
+            let (delegation_info, token_info) = (&pair[0], &pair[1]);
+            if let Some(previous) = previous {
+                require!(previous < *delegation_info.key, GovernorError::InvalidDelegationAccounts);
+            }
+            previous = Some(*delegation_info.key);
+
+            require_keys_eq!(*delegation_info.owner, crate::ID, GovernorError::InvalidDelegationAccounts);
+            let delegation = Delegation::try_deserialize(&mut &delegation_info.try_borrow_data()?[..])?;
+            require_keys_eq!(delegation.governor, *governor, GovernorError::InvalidDelegationAccounts);
+
+            require!(
+                *token_info.owner == anchor_spl::token::ID
+                    || *token_info.owner == anchor_spl::token_2022::ID,
+                GovernorError::InvalidDelegationAccounts
+            );
+            let token_account = TokenAccount::try_deserialize(&mut &token_info.try_borrow_data()?[..])?;
+            require!(
+                token_account.owner == delegation.delegator && token_account.mint == self.governing_mint,
+                GovernorError::InvalidDelegationAccounts
+            );
+
+            if !self.is_blocked(&delegation.delegator) {
+                votes += delegation.votes_for(account, token_account.amount);
+            }
+        }
+        Ok(votes)
+    }
+}
+
+impl Proposal {
+    pub const MAX_ACTIONS: usize = 8;
+    pub const MAX_SPONSORS: usize = 16;
+    pub const LEN: usize =
+        8 + 32 + 32 + 4 + Self::MAX_DESCRIPTION_LEN + 1 + 4 + Self::MAX_ACTIONS * ProposalAction::LEN + 2 + 1 + 8 + 8
+            + 4 + Self::MAX_SPONSORS * 32 + 8 + 8 + 8
+            + 8 + 8 + 8 + 8 + 2 + 1 + 1 + 9 + 9 + 1 + 8 + 1 + 2 + 2 + 1 + 3 + 8 + 1 + 1 + 32 + 4 + 4 + 8 + 8
+            + 4 + 1 + ProposalType::LEN + 8 * Self::MAX_INSTRUCTIONS + 33 + 1 + 8 + 8 + 8 + 8 + 4 + 8 + 8 + 8 + 8 + 1;
+    pub const MAX_DESCRIPTION_LEN: usize = 196;
+    /// Longer than any voting delay plus endorsement phase a sane configuration allows.
+    pub const MAX_PENDING_SLOTS: u64 = 60 * Governor::SLOTS_PER_DAY;
+    /// Cap on the serialized size of `instructions`, excluding the vec length prefix.
+    pub const MAX_PAYLOAD_BYTES: usize = 8_192;
+    /// At most 16, so `skipped_instructions` has a bit for each.
+    pub const MAX_INSTRUCTIONS: usize = 16;
+    /// Compute units `execute_all_transactions` keeps in hand before starting another
+    /// instruction.
+    pub const BATCH_COMPUTE_RESERVE: u64 = 100_000;
+
+    pub fn payload_len(instructions: &[ProposalInstruction]) -> usize {
+        instructions.iter().map(ProposalInstruction::size).sum()
+    }
+
+    /// Checks a complete payload: indices contiguous from zero and both size caps.
+    /// Returns its size in bytes.
+    pub fn validate_payload(instructions: &[ProposalInstruction]) -> Result<usize> {
+        require!(
+            instructions.len() <= Self::MAX_INSTRUCTIONS,
+            GovernorError::TooManyInstructions
+        );
+        for (index, instruction) in instructions.iter().enumerate() {
+            require!(instruction.index as usize == index, GovernorError::InstructionIndexOutOfOrder);
+        }
+        let payload_bytes = Self::payload_len(instructions);
+        require!(payload_bytes <= Self::MAX_PAYLOAD_BYTES, GovernorError::PayloadTooLarge);
+        Ok(payload_bytes)
+    }
+
+    /// What `vote` is owed from its stake given whether the proposal `passed`: the
+    /// stake less its penalty on the losing side, or the stake plus a weighted share
+    /// of the losing side's penalties on the winning one, rounded down.
+    pub fn stake_payout(&self, vote: &Vote, passed: bool) -> u64 {
+        let (winning_weight, losing_penalties) = if passed {
+            (self.for_stake_weight, self.against_stake_penalties)
+        } else {
+            (self.against_stake_weight, self.for_stake_penalties)
+        };
+        if vote.support == passed {
+            vote.stake + pro_rata_share(losing_penalties, vote.weight, winning_weight)
+        } else {
+            vote.stake - vote.stake_penalty
+        }
+    }
+
+    /// Moves a newly created (or fully sponsored) proposal into its endorsement phase
+    /// when its type has one, otherwise straight into its voting schedule.
+    pub fn activate(&mut self, governor: &Governor, slot: u64) -> Result<()> {
+        let proposal_type_info = governor
+            .proposal_types
+            .get(self.proposal_type as usize)
+            .ok_or(GovernorError::InvalidProposalType)?;
+        require!(
+            proposal_type_info.binding
+                || (self.actions.is_empty() && self.external_actions == 0 && self.instructions.is_empty()),
+            GovernorError::NonBindingProposal
+        );
+        self.content_hash = self.compute_content_hash()?;
+        self.type_snapshot = Some(proposal_type_info.clone());
+
+        if proposal_type_info.endorsement_period > 0 {
+            self.state = ProposalState::Endorsement;
+            self.endorsement_end = slot + proposal_type_info.endorsement_period;
+            self.start_block = 0;
+            self.end_block = 0;
+            Ok(())
+        } else {
+            self.state = ProposalState::Pending;
+            self.schedule(governor, slot)
+        }
+    }
+
+    /// Sets the voting window from the governor's current timing, starting at `slot`.
+    /// With `Governor::align_to_epoch` the window is rounded out to whole epochs.
+    pub fn schedule(&mut self, governor: &Governor, slot: u64) -> Result<()> {
+        let start_block = slot + governor.voting_delay;
+        (self.start_block, self.end_block) = if governor.align_to_epoch {
+            epoch_aligned_window(&EpochSchedule::get()?, start_block, governor.voting_period)
+        } else {
+            (start_block, start_block + governor.voting_period)
+        };
+        require!(
+            self.end_block - slot <= Governor::MAX_PROPOSAL_LIFETIME,
+            GovernorError::ProposalLifetimeTooLong
+        );
+        require!(
+            self.end_block - self.start_block >= governor.min_active_slots,
+            GovernorError::VotingWindowTooShort
+        );
+        let reveal_period = self.type_info(&governor.proposal_types)?.reveal_period;
+        self.reveal_end = if reveal_period > 0 { self.end_block + reveal_period } else { 0 };
+        Ok(())
+    }
+
+    /// Parameters of the proposal's type: the snapshot taken at activation once there
+    /// is one, so later changes to `proposal_types` never affect a proposal in
+    /// flight, otherwise the live entry in `proposal_types`.
+    pub fn type_info<'a>(&'a self, proposal_types: &'a [ProposalType]) -> Result<&'a ProposalType> {
+        match &self.type_snapshot {
+            Some(snapshot) => Ok(snapshot),
+            None => proposal_types
+                .get(self.proposal_type as usize)
+                .ok_or_else(|| GovernorError::InvalidProposalType.into()),
+        }
+    }
+
+    /// Last slot at which the tally can still change: the end of the reveal window
+    /// for commit-reveal proposals, otherwise `end_block`.
+    pub fn tally_end(&self) -> u64 {
+        self.end_block.max(self.reveal_end)
+    }
+
+    /// Fails unless `slot` is within the voting period of an activated proposal.
+    pub fn check_voting_open(&self, slot: u64) -> Result<()> {
+        require!(self.is_activated(), GovernorError::ProposalNotActivated);
+        require!(
+            !self.is_terminal() && slot >= self.start_block && slot <= self.end_block,
+            GovernorError::VotingPeriodInactive
+        );
+        Ok(())
+    }
+
+    /// Id of a proposal created with `create_hashed_proposal`: the first 8 bytes,
+    /// little-endian, of keccak(governor, proposal_type, content_hash, payload_hash),
+    /// with the hashes from `compute_content_hash` and `compute_payload_hash`.
+    pub fn hashed_id(governor: &Pubkey, proposal_type: u8, content_hash: &[u8; 32], payload_hash: &[u8; 32]) -> u64 {
+        let hash = keccak::hashv(&[governor.as_ref(), &[proposal_type], content_hash, payload_hash]).to_bytes();
+        u64::from_le_bytes(hash[..8].try_into().unwrap())
+    }
+
+    pub fn compute_payload_hash(&self) -> Result<[u8; 32]> {
+        Ok(hashv(&[&self.instructions.try_to_vec()?]).to_bytes())
+    }
+
+    /// Accounts and programs named by the stored payload, without duplicates, in
+    /// order of first use.
+    pub fn payload_addresses(&self) -> Vec<Pubkey> {
+        let mut addresses: Vec<Pubkey> = vec![];
+        for instruction in &self.instructions {
+            for address in instruction
+                .accounts
+                .iter()
+                .map(|meta| meta.pubkey)
+                .chain(std::iter::once(instruction.program_id))
+            {
+                if !addresses.contains(&address) {
+                    addresses.push(address);
+                }
+            }
+        }
+        addresses
+    }
+
+    /// Hash under which `queue_proposal` registers the payload with the governor's
+    /// timelock, salted with the proposal's address so identical payloads differ.
+    pub fn timelock_operation_hash(&self) -> Result<[u8; 32]> {
+        Ok(hashv(&[self.governor.as_ref(), &self.id.to_le_bytes(), &self.compute_payload_hash()?]).to_bytes())
+    }
+
+    pub fn compute_content_hash(&self) -> Result<[u8; 32]> {
+        Ok(hashv(&[
+            self.description.as_bytes(),
+            &self.actions.try_to_vec()?,
+            &self.instructions.try_to_vec()?,
+        ])
+        .to_bytes())
+    }
+
+    pub fn validate_description(description: &str) -> Result<()> {
+        require!(
+            description.len() <= Self::MAX_DESCRIPTION_LEN,
+            GovernorError::DescriptionTooLong
+        );
+        Ok(())
+    }
+
+    /// Links this proposal to the earlier one it supersedes, which must belong to the
+    /// same governor and have reached a terminal state.
+    pub fn set_supersedes(&mut self, supersedes: Option<u64>, superseded: Option<&Proposal>) -> Result<()> {
+        if let Some(superseded_id) = supersedes {
+            let superseded = superseded.ok_or(GovernorError::InvalidSupersededProposal)?;
+            require!(
+                superseded.id == superseded_id
+                    && superseded.id < self.id
+                    && superseded.governor == self.governor,
+                GovernorError::InvalidSupersededProposal
+            );
+            require!(superseded.is_terminal(), GovernorError::SupersededProposalNotTerminal);
+        }
+        self.supersedes = supersedes;
+        Ok(())
+    }
+
+    /// `actions` followed by the ones in the proposal's `ProposalActions` account,
+    /// which must be passed when `external_actions` is non-zero.
+    pub fn all_actions(&self, proposal_actions: Option<&AccountLoader<ProposalActions>>) -> Result<Vec<ProposalAction>> {
+        let mut actions = self.actions.clone();
+        if self.external_actions > 0 {
+            let proposal_actions = proposal_actions.ok_or(GovernorError::ProposalActionsRequired)?.load()?;
+            actions.extend(proposal_actions.unpack()?);
+        }
+        Ok(actions)
+    }
+
+    /// Adds `sponsor` and its `weight` to the draft's pooled weight. The proposer and
+    /// wallets already listed cannot sponsor, and the list holds at most
+    /// `MAX_SPONSORS`.
+    pub fn add_sponsor(&mut self, sponsor: Pubkey, weight: u64) -> Result<()> {
+        require!(
+            sponsor != self.proposer && !self.sponsors.contains(&sponsor),
+            GovernorError::AlreadySponsored
+        );
+        require!(self.sponsors.len() < Self::MAX_SPONSORS, GovernorError::TooManySponsors);
+        self.sponsors.push(sponsor);
+        self.sponsor_weight = self.sponsor_weight.saturating_add(weight);
+        Ok(())
+    }
+
+    /// Records a dependency on an earlier proposal. Only lower ids are allowed, so
+    /// dependencies can never form a cycle.
+    pub fn set_depends_on(&mut self, depends_on: Option<u64>) -> Result<()> {
+        if let Some(dependency_id) = depends_on {
+            require!(dependency_id < self.id, GovernorError::InvalidDependency);
+        }
+        self.depends_on = depends_on;
+        Ok(())
+    }
+
+    /// Requires the proposal this one depends on, if any, to have been executed.
+    pub fn check_dependency(&self, dependency: Option<&Proposal>) -> Result<()> {
+        if let Some(dependency_id) = self.depends_on {
+            let dependency = dependency.ok_or(GovernorError::InvalidDependency)?;
+            require!(
+                dependency.id == dependency_id && dependency.governor == self.governor,
+                GovernorError::InvalidDependency
+            );
+            require!(
+                dependency.state == ProposalState::Executed,
+                GovernorError::DependencyNotExecuted
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether the proposal still counts towards its type's entry in
+    /// `Governor::active_proposal_counts`: until it reaches a final state, or
+    /// succeeds as a non-binding type and can go no further.
+    pub fn holds_type(&self) -> bool {
+        let non_binding_result = self.state == ProposalState::Succeeded
+            && self.type_snapshot.as_ref().is_some_and(|snapshot| !snapshot.binding);
+        !self.is_terminal() && !non_binding_result
+    }
+
+    /// True once the proposal can no longer change state.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self.state,
+            ProposalState::Canceled
+                | ProposalState::Defeated
+                | ProposalState::Expired
+                | ProposalState::Executed
+                | ProposalState::Vetoed
+                | ProposalState::EndorsementFailed
+        )
+    }
+
+    pub fn is_activated(&self) -> bool {
+        !matches!(
+            self.state,
+            ProposalState::Draft | ProposalState::Sponsoring | ProposalState::Endorsement
+        )
+    }
+
+    /// Checks the tally against the supermajority `emergency_execute` requires:
+    /// For votes of at least `approval_bps` of the votes cast and turnout of at least
+    /// `quorum_bps` of `total_supply`.
+    pub fn check_emergency_bypass(&self, approval_bps: u16, quorum_bps: u16, total_supply: u64) -> Result<()> {
+        let total_votes = self.for_votes as u128 + self.against_votes as u128;
+        require!(
+            total_votes * 10_000 >= total_supply as u128 * quorum_bps as u128,
+            GovernorError::EmergencyQuorumNotMet
+        );
+        require!(
+            self.for_votes as u128 * 10_000 >= total_votes * approval_bps as u128,
+            GovernorError::EmergencyApprovalNotMet
+        );
+        Ok(())
+    }
+
+    /// Records `total_supply` as `snapshot_supply` unless one was already taken.
+    /// Supply minted later is not checkpointed at the start block, so it can raise
+    /// `quorum_votes` but not vote.
+    pub fn take_supply_snapshot(&mut self, total_supply: u64) {
+        if self.snapshot_supply == 0 {
+            self.snapshot_supply = total_supply;
+        }
+    }
+
+    /// True when quorum stays out of reach even if all power that can still vote
+    /// does: the snapshot supply less `spent_weight`, which covers voters' tokens
+    /// that were capped away or rage-quit as well as those that counted.
+    /// `realtime_weight` types let tokens that voted move and vote again, so nothing
+    /// bounds them and this is always false.
+    pub fn quorum_unreachable(&self, proposal_type: &ProposalType, quorum_votes: u64) -> Result<bool> {
+        if proposal_type.realtime_weight {
+            return Ok(false);
+        }
+        let cast = self
+            .for_votes
+            .checked_add(self.against_votes)
+            .ok_or(GovernorError::ArithmeticOverflow)?;
+        let remaining = self.snapshot_supply.saturating_sub(self.spent_weight);
+        let reachable = cast as u128 + remaining as u128;
+        Ok(reachable < quorum_votes as u128)
+    }
+
+    pub fn check_execution_deadline(&self, slot: u64) -> Result<()> {
+        require!(
+            self.expires_at == 0 || slot <= self.expires_at,
+            GovernorError::ExecutionDeadlinePassed
+        );
+        Ok(())
+    }
+
+    /// Fails with `ProposalDefeated` when `finalize_proposal` recorded any outcome other
+    /// than Succeeded. The tallies behind that outcome are in `ProposalFinalized`.
+    pub fn check_not_defeated(&self) -> Result<()> {
+        if let Some(outcome) = self.outcome {
+            require!(outcome == ProposalState::Succeeded, GovernorError::ProposalDefeated);
+        }
+        Ok(())
+    }
+
+    /// Checks the tally against `quorum_votes`, from `Governor::quorum_votes`, and
+    /// the type's approval threshold.
+    pub fn check_succeeded(&self, proposal_type: &ProposalType, quorum_votes: u64) -> Result<()> {
+        let (for_votes, against_votes) = self.decisive_tally(proposal_type);
+        Self::check_tally(for_votes, against_votes, proposal_type, quorum_votes)
+    }
+
+    /// The (for, against) tally the outcome is decided on: the conviction tally for
+    /// `conviction_mode` types, the raw one otherwise.
+    pub fn decisive_tally(&self, proposal_type: &ProposalType) -> (u64, u64) {
+        if proposal_type.conviction_mode {
+            (self.for_conviction, self.against_conviction)
+        } else {
+            (self.for_votes, self.against_votes)
+        }
+    }
+
+    pub fn instruction_status(&self, index: u16) -> InstructionStatus {
+        if index >= self.executed_instructions {
+            match self.instruction_error_codes.get(index as usize) {
+                Some(&error_code) if error_code != 0 => InstructionStatus::Failed { error_code },
+                _ => InstructionStatus::NotExecuted,
+            }
+        } else if self.skipped_instructions & (1 << index) != 0 {
+            InstructionStatus::Skipped
+        } else {
+            InstructionStatus::Executed
+        }
+    }
+
+    /// Slot from which each payload instruction may run: `eta` plus its hold-up.
+    pub fn executable_at(&self) -> Vec<u64> {
+        self.instructions
+            .iter()
+            .map(|instruction| self.eta.saturating_add(instruction.hold_up_slots))
+            .collect()
+    }
+
+    /// Why the tally fails `check_succeeded`, or `None` if it passes.
+    pub fn defeat_reason(&self, proposal_type: &ProposalType, quorum_votes: u64) -> Option<DefeatReason> {
+        match self.check_succeeded(proposal_type, quorum_votes) {
+            Ok(()) => None,
+            Err(err) if err == GovernorError::QuorumNotReached.into() => {
+                Some(DefeatReason::QuorumNotReached)
+            }
+            Err(_) => Some(DefeatReason::ApprovalNotMet),
+        }
+    }
+
+    /// `check_succeeded` against the tally from `execution_tally`. Conviction types
+    /// are still held to the conviction tally, which never exceeds the raw one.
+    pub fn check_executable(
+        &self,
+        proposal_type: &ProposalType,
+        quorum_votes: u64,
+        total_supply: u64,
+        clamp_to_supply: bool,
+    ) -> Result<()> {
+        let (for_votes, against_votes) = self.execution_tally(total_supply, clamp_to_supply)?;
+        if proposal_type.conviction_mode {
+            return self.check_succeeded(proposal_type, quorum_votes);
+        }
+        Self::check_tally(for_votes, against_votes, proposal_type, quorum_votes)
+    }
+
+    /// The (for, against) tally, bounded by `total_supply`. A tally above the supply
+    /// means votes were double counted: with `clamp_to_supply` it is scaled down to
+    /// the supply keeping its for/against ratio, otherwise it is rejected.
+    pub fn execution_tally(&self, total_supply: u64, clamp_to_supply: bool) -> Result<(u64, u64)> {
+        let total_votes = self
+            .for_votes
+            .checked_add(self.against_votes)
+            .ok_or(GovernorError::ArithmeticOverflow)?;
+        if total_votes <= total_supply {
+            return Ok((self.for_votes, self.against_votes));
+        }
+        require!(clamp_to_supply, GovernorError::TallyExceedsSupply);
+
+        let for_votes = (self.for_votes as u128 * total_supply as u128 / total_votes as u128) as u64;
+        Ok((for_votes, total_supply - for_votes))
+    }
+
+    fn check_tally(
+        for_votes: u64,
+        against_votes: u64,
+        proposal_type: &ProposalType,
+        quorum_votes: u64,
+    ) -> Result<()> {
+        let total_votes = for_votes as u128 + against_votes as u128;
+        require!(total_votes >= quorum_votes as u128, GovernorError::QuorumNotReached);
+        if proposal_type.quorum_only {
+            return Ok(());
+        }
+
+        let approval = (for_votes as u128 * 10_000).checked_div(total_votes).unwrap_or(0);
+        require!(
+            approval >= proposal_type.approval_threshold as u128,
+            GovernorError::ApprovalThresholdNotMet
+        );
+
+        Ok(())
+    }
+}
+
+impl ParamKey {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ParamKey::VotingDelay => "voting_delay",
+            ParamKey::VotingPeriod => "voting_period",
+            ParamKey::ProposalThreshold => "proposal_threshold",
+            ParamKey::TimelockDelay => "timelock_delay",
+            ParamKey::SponsorshipWindow => "sponsorship_window",
+            ParamKey::CancelBeforeActiveOnly => "cancel_before_active_only",
+            ParamKey::MinVotingWeight => "min_voting_weight",
+            ParamKey::ExecutionReward => "execution_reward",
+            ParamKey::RequireTopLevelVote => "require_top_level_vote",
+            ParamKey::ResubmissionCooldownSlots => "resubmission_cooldown_slots",
+            ParamKey::MaxProposalEdits => "max_proposal_edits",
+            ParamKey::ProposalFeeLamports => "proposal_fee_lamports",
+            ParamKey::DraftTtlSlots => "draft_ttl_slots",
+            ParamKey::ClampToSupply => "clamp_to_supply",
+            ParamKey::MaxVotesPerWindow => "max_votes_per_window",
+            ParamKey::VoteWindowSlots => "vote_window_slots",
+            ParamKey::VoteRewardLamports => "vote_reward_lamports",
+            ParamKey::CompatEvents => "compat_events",
+            ParamKey::AlignToEpoch => "align_to_epoch",
+            ParamKey::ExecutionDeadlineSlots => "execution_deadline_slots",
+            ParamKey::ProposalDepositLamports => "proposal_deposit_lamports",
+            ParamKey::FundAtaFromTreasury => "fund_ata_from_treasury",
+            ParamKey::RequireMinBalancePerVoter => "require_min_balance_per_voter",
+            ParamKey::EmergencyBypassThresholdBps => "emergency_bypass_threshold_bps",
+            ParamKey::EmergencyQuorumBps => "emergency_quorum_bps",
+            ParamKey::MinActiveSlots => "min_active_slots",
+            ParamKey::StoreReasons => "store_reasons",
+            ParamKey::MaxReasonLength => "max_reason_length",
+            ParamKey::HashedProposalIds => "hashed_proposal_ids",
+            ParamKey::LoserPenaltyBps => "loser_penalty_bps",
+            ParamKey::VoteStakeLamports => "vote_stake_lamports",
+        }
+    }
+
+    pub fn validate(&self, value: u64) -> Result<()> {
+        let in_bounds = match self {
+            ParamKey::VotingDelay => value <= Governor::MAX_VOTING_DELAY,
+            ParamKey::VotingPeriod => {
+                (Governor::MIN_VOTING_PERIOD..=Governor::MAX_VOTING_PERIOD).contains(&value)
+            }
+            ParamKey::ProposalThreshold => true,
+            ParamKey::TimelockDelay => value <= Governor::MAX_TIMELOCK_DELAY,
+            ParamKey::SponsorshipWindow => value <= Governor::MAX_SPONSORSHIP_WINDOW,
+            ParamKey::CancelBeforeActiveOnly => value <= 1,
+            ParamKey::MinVotingWeight => true,
+            ParamKey::ExecutionReward => true,
+            ParamKey::RequireTopLevelVote => value <= 1,
+            ParamKey::ResubmissionCooldownSlots => value <= Governor::MAX_RESUBMISSION_COOLDOWN,
+            ParamKey::MaxProposalEdits => value <= Governor::MAX_PROPOSAL_EDITS,
+            ParamKey::ProposalFeeLamports => true,
+            ParamKey::DraftTtlSlots => value <= Governor::MAX_DRAFT_TTL,
+            ParamKey::ClampToSupply => value <= 1,
+            ParamKey::MaxVotesPerWindow => true,
+            ParamKey::VoteWindowSlots => (1..=Governor::MAX_VOTE_WINDOW).contains(&value),
+            ParamKey::VoteRewardLamports => true,
+            ParamKey::CompatEvents => value <= 1,
+            ParamKey::AlignToEpoch => value <= 1,
+            ParamKey::ExecutionDeadlineSlots => value <= Governor::MAX_EXECUTION_DEADLINE,
+            ParamKey::ProposalDepositLamports => true,
+            ParamKey::FundAtaFromTreasury => value <= 1,
+            ParamKey::RequireMinBalancePerVoter => true,
+            ParamKey::EmergencyBypassThresholdBps | ParamKey::EmergencyQuorumBps => value <= 10_000,
+            ParamKey::MinActiveSlots => value <= Governor::MAX_VOTING_PERIOD,
+            ParamKey::StoreReasons => value <= 1,
+            ParamKey::MaxReasonLength => value <= Governor::MAX_REASON_LENGTH,
+            ParamKey::HashedProposalIds => value <= 1,
+            ParamKey::LoserPenaltyBps => value <= 10_000,
+            ParamKey::VoteStakeLamports => true,
+        };
+        require!(in_bounds, GovernorError::ParameterOutOfBounds);
+        Ok(())
+    }
+}
+
+impl ProposalType {
+    pub const MAX_NAME_LEN: usize = 32;
+    pub const MAX_ALLOWED_PROGRAMS: usize = 8;
+    pub const MAX_SPENDING_CAPS: usize = 4;
+    pub const LEN: usize = 2 + 2 + 4 + Self::MAX_NAME_LEN + 33 + 1 + 1 + 8 + 2 + 9 + 1
+        + 4 + Self::MAX_ALLOWED_PROGRAMS * 32 + 8 + 1 + 4 + Self::MAX_SPENDING_CAPS * SpendingCap::LEN + 1 + 9
+        + 1 + 8 + 1;
+
+    /// Checks the type's bounds: bps values at most 10_000, the name and lists within
+    /// their caps, a deadline no longer than the governor's maximum, a conviction
+    /// period only for conviction types without reveal, `risk_tier` one of the
+    /// governor's `risk_tiers` and `removed` unset.
+    pub fn validate(&self, risk_tiers: usize) -> Result<()> {
+        require!(
+            self.quorum <= 10_000
+                && self.approval_threshold <= 10_000
+                && self.endorsement_quorum_bps <= 10_000
+                && self.name.len() <= Self::MAX_NAME_LEN
+                && self.allowed_programs.len() <= Self::MAX_ALLOWED_PROGRAMS
+                && self.spending_cap.len() <= Self::MAX_SPENDING_CAPS
+                && self.grace_period.unwrap_or(0) <= Governor::MAX_EXECUTION_DEADLINE
+                && (!self.conviction_mode || (self.max_conviction_slots > 0 && self.reveal_period == 0))
+                && !self.removed,
+            GovernorError::InvalidProposalType
+        );
+        require!((self.risk_tier as usize) < risk_tiers, GovernorError::InvalidRiskTier);
+        Ok(())
+    }
+
+    /// Slots after `eta` a queued proposal of this type has to finish executing:
+    /// `grace_period`, or the governor's `default_slots` without one. Zero means
+    /// no deadline.
+    pub fn execution_deadline_slots(&self, default_slots: u64) -> u64 {
+        self.grace_period.unwrap_or(default_slots)
+    }
+
+    /// Fails with `SpendingCapExceeded` if the treasury transfers in `instructions`
+    /// exceed one of the type's spending caps; see `SpendingCap::within`.
+    pub fn check_spending_caps(
+        &self,
+        instructions: &[ProposalInstruction],
+        authority: &Pubkey,
+        treasury: &Pubkey,
+        vaults: &[TreasuryVault],
+    ) -> Result<()> {
+        require!(
+            SpendingCap::within(&self.spending_cap, instructions, authority, treasury, vaults),
+            GovernorError::SpendingCapExceeded
+        );
+        Ok(())
+    }
+
+    /// `weight` of a vote that stood for `elapsed_slots` before voting ended: growing
+    /// linearly to the full weight at `max_conviction_slots`, rounded down. Unscaled
+    /// outside `conviction_mode`.
+    pub fn conviction_weight(&self, weight: u64, elapsed_slots: u64) -> u64 {
+        if !self.conviction_mode || self.max_conviction_slots == 0 {
+            return weight;
+        }
+        (weight as u128 * elapsed_slots.min(self.max_conviction_slots) as u128
+            / self.max_conviction_slots as u128) as u64
+    }
+
+    pub fn allows_program(&self, program_id: &Pubkey) -> bool {
+        self.allowed_programs.is_empty() || self.allowed_programs.contains(program_id)
+    }
+
+    /// Votes needed for quorum out of `total_supply`, with the base quorum scaled by
+    /// the type's risk tier multiplier (in bps, 10_000 = 1x).
+    pub fn quorum_votes(&self, total_supply: u64, tier_multiplier: u16) -> Result<u64> {
+        let quorum = total_supply as u128 * self.quorum as u128 * tier_multiplier as u128
+            / 10_000
+            / 10_000;
+        u64::try_from(quorum).map_err(|_| error!(GovernorError::ArithmeticOverflow))
+    }
+}
+
+impl SpendingCap {
+    pub const LEN: usize = 1 + 32 + 8;
+
+    /// Whether the treasury transfers in `instructions`, summed per mint, stay
+    /// within `caps`. Token transfers signed by `authority` must name their mint
+    /// (`TransferChecked`) or come out of one of `vaults` once any cap is set, since
+    /// an unattributed one could be moving a capped mint.
+    pub fn within(
+        caps: &[SpendingCap],
+        instructions: &[ProposalInstruction],
+        authority: &Pubkey,
+        treasury: &Pubkey,
+        vaults: &[TreasuryVault],
+    ) -> bool {
+        if caps.is_empty() {
+            return true;
+        }
+        let mut spent = vec![0u64; caps.len()];
+        for instruction in instructions {
+            let (mint, amount) = match TreasuryOutflow::decode(instruction, authority, treasury, vaults) {
+                None => continue,
+                Some(TreasuryOutflow::UnknownMint { .. }) => return false,
+                Some(outflow) => (outflow.mint(), outflow.amount()),
+            };
+            let Some(cap) = caps.iter().position(|cap| cap.mint == mint) else {
+                continue;
+            };
+            match spent[cap].checked_add(amount) {
+                Some(total) if total <= caps[cap].max_amount => spent[cap] = total,
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+impl TreasuryOutflow {
+    /// The mint moved, `None` for lamports and for an unattributed token transfer.
+    pub fn mint(&self) -> Option<Pubkey> {
+        match self {
+            Self::Token { mint, .. } => Some(*mint),
+            Self::Native { .. } | Self::UnknownMint { .. } => None,
+        }
+    }
+
+    pub fn amount(&self) -> u64 {
+        match self {
+            Self::Native { amount } | Self::Token { amount, .. } | Self::UnknownMint { amount, .. } => *amount,
+        }
+    }
+
+    const SYSTEM_TRANSFER: u32 = 2;
+    const TOKEN_TRANSFER: u8 = 3;
+    const TOKEN_TRANSFER_CHECKED: u8 = 12;
+
+    /// Decodes the treasury outflow `instruction` makes, if any: a system transfer
+    /// from `treasury`, a token `Transfer` or `TransferChecked` out of one of
+    /// `vaults` or signed by `authority`, a `CreateStream` or the budget of a
+    /// `CreateSubGovernor`. A plain `Transfer` out
+    /// of a registered vault is attributed to the vault's mint. Anything else,
+    /// including malformed data, decodes to `None`.
+    pub fn decode(
+        instruction: &ProposalInstruction,
+        authority: &Pubkey,
+        treasury: &Pubkey,
+        vaults: &[TreasuryVault],
+    ) -> Option<Self> {
+        if let InstructionKind::CreateStream { mint, total_amount, .. } = instruction.kind {
+            return Some(Self::Token { mint, amount: total_amount });
+        }
+        if let InstructionKind::CreateSubGovernor { config } = &instruction.kind {
+            return (config.budget_lamports > 0).then_some(Self::Native { amount: config.budget_lamports });
+        }
+        if instruction.kind != InstructionKind::Invoke {
+            return None;
+        }
+        let account = |index: usize| instruction.accounts.get(index).map(|meta| meta.pubkey);
+        let data = &instruction.data;
+
+        if instruction.program_id == system_program::ID {
+            let tag = u32::from_le_bytes(data.get(..4)?.try_into().ok()?);
+            let amount = u64::from_le_bytes(data.get(4..12)?.try_into().ok()?);
+            return (tag == Self::SYSTEM_TRANSFER && data.len() == 12 && account(0)? == *treasury)
+                .then_some(Self::Native { amount });
+        }
+        if instruction.program_id == anchor_spl::token::ID || instruction.program_id == anchor_spl::token_2022::ID {
+            let amount = u64::from_le_bytes(data.get(1..9)?.try_into().ok()?);
+            let source = account(0)?;
+            let vault = vaults.iter().find(|vault| vault.address == source);
+            return match *data.first()? {
+                Self::TOKEN_TRANSFER => match vault {
+                    Some(vault) => Some(Self::Token { mint: vault.mint, amount }),
+                    None if account(2)? == *authority => Some(Self::UnknownMint { source, amount }),
+                    None => None,
+                },
+                Self::TOKEN_TRANSFER_CHECKED if vault.is_some() || account(3)? == *authority => {
+                    Some(Self::Token { mint: account(1)?, amount })
+                }
+                _ => None,
+            };
+        }
+        None
+    }
+}
+
+impl BlackoutWindow {
+    pub const LEN: usize = 8 + 8;
+}
+
+impl ProposalAction {
+    pub const LEN: usize = 1 + 32 + 8;
+
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            ProposalAction::ParameterChange { key, value } => key.validate(*value),
+            ProposalAction::Mint { amount, .. } | ProposalAction::Burn { amount, .. } => {
+                require!(*amount > 0, GovernorError::ParameterOutOfBounds);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl InstructionKind {
+    /// Size of the largest variant, `CreateSubGovernor`.
+    pub const LEN: usize = 1 + SubGovernorConfig::LEN;
+
+    /// Address and initial state of the stream a `CreateStream` instruction at
+    /// `index` of `proposal` creates; `None` for other kinds.
+    pub fn payment_stream(&self, governor: Pubkey, proposal: Pubkey, index: u16) -> Option<(Pubkey, PaymentStream)> {
+        let InstructionKind::CreateStream { recipient, mint, total_amount, start_slot, end_slot } = *self
+        else {
+            return None;
+        };
+        let (stream, bump) = PaymentStream::address(&proposal, index);
+        let (escrow, escrow_bump) = PaymentStream::escrow_address(&stream);
+        Some((
+            stream,
+            PaymentStream {
+                governor,
+                proposal,
+                index,
+                recipient,
+                mint,
+                escrow,
+                total_amount,
+                claimed: 0,
+                start_slot,
+                end_slot,
+                canceled_at: None,
+                bump,
+                escrow_bump,
+            },
+        ))
+    }
+}
+
+impl ProposalInstruction {
+    /// Size of the payload built by `propose_program_upgrade`: seven accounts and the
+    /// four-byte `Upgrade` discriminant.
+    pub const PROGRAM_UPGRADE_LEN: usize = Self::serialized_len(7, 4);
+
+    /// Size of the payload built by `propose_squads_action` for a transaction message
+    /// of `message_len` bytes: the create, propose and approve instructions.
+    pub const fn squads_action_len(message_len: usize) -> usize {
+        Self::serialized_len(5, 8 + 2 + 4 + message_len + 1) + Self::serialized_len(5, 8 + 8 + 1) + Self::serialized_len(3, 8 + 1)
+    }
+
+    pub const fn serialized_len(accounts: usize, data: usize) -> usize {
+        2 + 32 + 4 + accounts * (32 + 1 + 1) + 4 + data + 1 + 8 + InstructionKind::LEN
+    }
+
+    pub fn from_instruction(index: u16, instruction: Instruction) -> Self {
+        Self {
+            index,
+            program_id: instruction.program_id,
+            accounts: instruction
+                .accounts
+                .into_iter()
+                .map(|meta| AccountMetaData {
+                    pubkey: meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: instruction.data,
+            self_call: false,
+            hold_up_slots: 0,
+            kind: InstructionKind::Invoke,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        Self::serialized_len(self.accounts.len(), self.data.len())
+    }
+
+    /// Accounts the executor expects for this instruction, before its program.
+    pub fn account_count(&self) -> usize {
+        match self.kind {
+            InstructionKind::Invoke => self.accounts.len(),
+            // Payer, associated account, owner, mint, system and token programs.
+            InstructionKind::CreateAtaIdempotent { .. } => 6,
+            // Payer, stream, escrow, mint, treasury token account, governor authority,
+            // token and system programs.
+            InstructionKind::CreateStream { .. } => 8,
+            // Stream, escrow, mint, treasury token account, governor authority and
+            // token program.
+            InstructionKind::CancelStream { .. } => 6,
+            // Payer, sub-governor, its governing mint, treasury, its treasury and
+            // system program.
+            InstructionKind::CreateSubGovernor { .. } => 6,
+            // Treasury, wSOL account, governor authority, native mint, system and
+            // associated token programs.
+            InstructionKind::WrapTreasurySol { .. } => 6,
+            // Treasury, wSOL account and governor authority.
+            InstructionKind::UnwrapTreasurySol => 3,
+        }
+    }
+
+    /// True for a raw instruction calling the governor program without `self_call`.
+    pub fn is_unflagged_self_call(&self) -> bool {
+        self.kind == InstructionKind::Invoke && self.program_id == crate::ID && !self.self_call
+    }
+
+    /// Destination account of an SPL token `Transfer` or `TransferChecked`, under
+    /// either token program.
+    pub fn token_transfer_destination(&self) -> Option<Pubkey> {
+        if self.program_id != anchor_spl::token::ID && self.program_id != anchor_spl::token_2022::ID {
+            return None;
+        }
+        let position = match self.data.first() {
+            Some(3) => 1,
+            Some(12) => 2,
+            _ => return None,
+        };
+        self.accounts.get(position).map(|meta| meta.pubkey)
+    }
+
+    /// Source account of an SPL token `Transfer` or `TransferChecked`, under either
+    /// token program.
+    pub fn token_transfer_source(&self) -> Option<Pubkey> {
+        if self.program_id != anchor_spl::token::ID && self.program_id != anchor_spl::token_2022::ID {
+            return None;
+        }
+        match self.data.first() {
+            Some(3) | Some(12) => self.accounts.first().map(|meta| meta.pubkey),
+            _ => None,
+        }
+    }
+
+    /// Decodes a system-program `Transfer` as (from, to, lamports).
+    pub fn system_transfer(&self) -> Option<(Pubkey, Pubkey, u64)> {
+        if self.program_id != system_program::ID || self.accounts.len() < 2 || self.data.len() != 12 {
+            return None;
+        }
+        let (tag, lamports) = self.data.split_at(4);
+        if u32::from_le_bytes(tag.try_into().ok()?) != 2 {
+            return None;
+        }
+        Some((
+            self.accounts[0].pubkey,
+            self.accounts[1].pubkey,
+            u64::from_le_bytes(lamports.try_into().ok()?),
+        ))
+    }
+}
+
+impl ProposalActions {
+    pub const CAPACITY: usize = 64;
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    /// Validates `actions` and stores them after the ones already held.
+    pub fn append(&mut self, actions: &[ProposalAction]) -> Result<()> {
+        let start = self.len as usize;
+        require!(start + actions.len() <= Self::CAPACITY, GovernorError::TooManyActions);
+        for (slot, action) in self.actions[start..].iter_mut().zip(actions) {
+            action.validate()?;
+            *slot = PackedAction::pack(action);
+        }
+        self.len += actions.len() as u16;
+        Ok(())
+    }
+
+    pub fn unpack(&self) -> Result<Vec<ProposalAction>> {
+        self.actions[..self.len as usize].iter().map(PackedAction::unpack).collect()
+    }
+}
+
+impl PackedAction {
+    pub fn pack(action: &ProposalAction) -> Self {
+        let (kind, key, target, amount) = match action {
+            ProposalAction::ParameterChange { key, value } => (0, *key as u8, Pubkey::default(), *value),
+            ProposalAction::Mint { to, amount } => (1, 0, *to, *amount),
+            ProposalAction::Burn { from, amount } => (2, 0, *from, *amount),
+        };
+        Self {
+            kind,
+            key,
+            _padding: [0; 6],
+            target,
+            amount,
+        }
+    }
+
+    pub fn unpack(&self) -> Result<ProposalAction> {
+        match self.kind {
+            0 => Ok(ProposalAction::ParameterChange {
+                key: ParamKey::try_from_slice(&[self.key])
+                    .map_err(|_| error!(anchor_lang::error::ErrorCode::AccountDidNotDeserialize))?,
+                value: self.amount,
+            }),
+            1 => Ok(ProposalAction::Mint {
+                to: self.target,
+                amount: self.amount,
+            }),
+            2 => Ok(ProposalAction::Burn {
+                from: self.target,
+                amount: self.amount,
+            }),
+            _ => err!(anchor_lang::error::ErrorCode::AccountDidNotDeserialize),
+        }
+    }
+}
+
+impl SponsorReceipt {
+    pub const LEN: usize = 32 + 32 + 8 + 1;
+}
+
+impl EndorsementReceipt {
+    pub const LEN: usize = 32 + 32 + 8 + 1;
+}
+
+impl Vote {
+    /// Size with an empty `reason`.
+    pub const LEN: usize = 32 + 32 + 8 + 1 + 8 + 8 + 1 + 1 + 1 + 8 + 8 + 1 + 4;
+}
+
+impl Allowance {
+    pub const LEN: usize = 32 + 32 + 8 + 8 + 8 + 8 + 1;
+
+    /// Spends `amount` from the window containing `slot` and returns what is left
+    /// of the budget.
+    pub fn spend(&mut self, amount: u64, slot: u64) -> Result<u64> {
+        roll_window(&mut self.window_start, &mut self.spent, self.window_slots, slot);
+        self.spent = self
+            .spent
+            .checked_add(amount)
+            .filter(|spent| *spent <= self.budget)
+            .ok_or(GovernorError::AllowanceExceeded)?;
+        Ok(self.budget - self.spent)
+    }
+}
+
+impl Treasurer {
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 8 + 8 + 1;
+
+    /// Spends `amount` from the window containing `slot` and returns what is left
+    /// of the budget.
+    pub fn spend(&mut self, amount: u64, slot: u64) -> Result<u64> {
+        roll_window(&mut self.window_start, &mut self.spent, self.window_slots, slot);
+        self.spent = self
+            .spent
+            .checked_add(amount)
+            .filter(|spent| *spent <= self.budget)
+            .ok_or(GovernorError::TreasurerBudgetExceeded)?;
+        Ok(self.budget - self.spent)
+    }
+}
+
+impl SpendingLimit {
+    pub const LEN: usize = 32 + 1 + 32 + 8 + 8 + 8 + 8 + 1;
+
+    /// Adds `amount` to the window containing `slot`, starting a new one once
+    /// `window_slots` have passed since the current one began.
+    pub fn record(&mut self, amount: u64, slot: u64) -> Result<()> {
+        roll_window(&mut self.window_start, &mut self.spent, self.window_slots, slot);
+        self.spent = self
+            .spent
+            .checked_add(amount)
+            .filter(|spent| *spent <= self.max_amount)
+            .ok_or(GovernorError::EpochSpendingLimitExceeded)?;
+        Ok(())
+    }
+}
+
+impl TreasuryRegistry {
+    /// Size with no vaults.
+    pub const LEN: usize = 32 + 1 + 4;
+    pub const MAX_VAULTS: usize = 16;
+
+    /// Vaults of `registry`, or none when the governor has no registry.
+    pub fn vaults_of<'a>(registry: &'a Option<Account<TreasuryRegistry>>) -> &'a [TreasuryVault] {
+        registry.as_ref().map_or(&[], |registry| &registry.vaults)
+    }
+}
+
+impl TreasuryVault {
+    pub const LEN: usize = 32 + 32;
+}
+
+impl Timelock {
+    pub const MAX_PROPOSERS: usize = 8;
+    pub const MAX_OPERATIONS: usize = 32;
+    pub const LEN: usize = 32 + 8 + 8 + 1 + 1 + 4 + Self::MAX_PROPOSERS * 32 + 4 + Self::MAX_OPERATIONS * (32 + 8);
+
+    /// Hash identifying the operation that runs one instruction.
+    pub fn operation_hash(program_id: &Pubkey, accounts: &[AccountMetaData], data: &[u8]) -> Result<[u8; 32]> {
+        Ok(hashv(&[program_id.as_ref(), &accounts.try_to_vec()?, data]).to_bytes())
+    }
+
+    pub fn check_delays(min_delay: u64, max_delay: u64) -> Result<()> {
+        require!(
+            min_delay <= max_delay && max_delay <= Governor::MAX_TIMELOCK_DELAY,
+            GovernorError::InvalidTimelock
+        );
+        Ok(())
+    }
+
+    /// Queues `hash` for `proposer` to become ready `delay` slots after `slot`,
+    /// returning its eta.
+    pub fn queue(&mut self, proposer: &Pubkey, hash: [u8; 32], delay: u64, slot: u64) -> Result<u64> {
+        require!(self.proposers.contains(proposer), GovernorError::TimelockProposerNotAllowed);
+        require!(
+            (self.min_delay..=self.max_delay).contains(&delay),
+            GovernorError::TimelockDelayOutOfBounds
+        );
+        require!(
+            self.operations.iter().all(|operation| operation.hash != hash),
+            GovernorError::TimelockOperationAlreadyQueued
+        );
+        require!(self.operations.len() < Self::MAX_OPERATIONS, GovernorError::TimelockFull);
+        let eta = slot + delay;
+        self.operations.push(TimelockOperation { hash, eta });
+        Ok(eta)
+    }
+
+    /// Removes ready operation `hash`, returning its eta.
+    pub fn take_ready(&mut self, hash: &[u8; 32], slot: u64) -> Result<u64> {
+        let position = self
+            .operations
+            .iter()
+            .position(|operation| operation.hash == *hash)
+            .ok_or(GovernorError::TimelockOperationNotQueued)?;
+        require!(slot >= self.operations[position].eta, GovernorError::TimelockOperationNotReady);
+        Ok(self.operations.remove(position).eta)
+    }
+}
+
+impl GovernorHistory {
+    /// Size with no records.
+    pub const LEN: usize = 32 + 1 + 4;
+    /// Records that fit in a view's return data.
+    pub const MAX_RECORDS_PER_PAGE: usize = 30;
+}
+
+impl HistoryRecord {
+    pub const LEN: usize = 8 + 1 + 8 + 8 + 1 + 8;
+}
+
+impl Grant {
+    /// Bounded by the width of `claimed_tranches`.
+    pub const MAX_TRANCHES: usize = 8;
+    pub const LEN: usize = 32 + 8 + 32 + 32 + 4 + Self::MAX_TRANCHES * 8 + 1 + 1 + 1 + 1 + 1;
+}
+
+impl SubGovernorConfig {
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8;
+
+    pub fn address(authority: &Pubkey, proposal: &Pubkey, index: u16) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[b"sub-governor", authority.as_ref(), proposal.as_ref(), &index.to_le_bytes()],
+            &crate::ID,
+        )
+    }
+}
+
+impl PaymentStream {
+    pub const LEN: usize = 32 + 32 + 2 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 9 + 1 + 1;
+    /// Size of the escrow, a token account without extensions: mints that require
+    /// account extensions cannot be streamed.
+    pub const ESCROW_LEN: usize = 165;
+
+    pub fn address(proposal: &Pubkey, index: u16) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[b"stream", proposal.as_ref(), &index.to_le_bytes()],
+            &crate::ID,
+        )
+    }
+
+    pub fn escrow_address(stream: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"stream-escrow", stream.as_ref()], &crate::ID)
+    }
+
+    /// Amount vested by `slot`, linear between `start_slot` and `end_slot` and frozen
+    /// at `canceled_at`.
+    pub fn vested_amount(&self, slot: u64) -> u64 {
+        let until = self
+            .canceled_at
+            .map_or(slot, |canceled_at| canceled_at.min(slot))
+            .min(self.end_slot);
+        if until <= self.start_slot {
+            return 0;
+        }
+        (self.total_amount as u128 * (until - self.start_slot) as u128
+            / (self.end_slot - self.start_slot) as u128) as u64
+    }
+}
+
+impl VoteCommitment {
+    pub const LEN: usize = 32 + 8 + 32 + 32 + 8 + 1 + 1;
+
+    /// Commitment to a vote: the hash of the support byte followed by the salt.
+    pub fn hash(support: bool, salt: &[u8; 32]) -> [u8; 32] {
+        hashv(&[&[support as u8], salt]).to_bytes()
+    }
+}
+
+impl VoterActivity {
+    pub const LEN: usize = 32 + 32 + 8 + 8 + 1;
+}
+
+impl Delegation {
+    pub const MAX_ALLOCATIONS: usize = 8;
+    pub const LEN: usize = 32 + 32 + 4 + Self::MAX_ALLOCATIONS * (32 + 2) + 1;
+
+    pub fn validate_allocations(allocations: &[DelegationAllocation], delegator: &Pubkey) -> Result<()> {
+        require!(
+            !allocations.is_empty() && allocations.len() <= Self::MAX_ALLOCATIONS,
+            GovernorError::InvalidDelegationAllocations
+        );
+        let mut total: u32 = 0;
+        for (i, allocation) in allocations.iter().enumerate() {
+            require!(allocation.delegatee != *delegator, GovernorError::InvalidDelegatee);
+            require!(
+                allocation.bps > 0
+                    && allocations[..i].iter().all(|other| other.delegatee != allocation.delegatee),
+                GovernorError::InvalidDelegationAllocations
+            );
+            total += allocation.bps as u32;
+        }
+        require!(total == 10_000, GovernorError::InvalidDelegationAllocations);
+        Ok(())
+    }
+
+    pub fn address(governor: &Pubkey, delegator: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"delegation", governor.as_ref(), delegator.as_ref()], &crate::ID)
+    }
+
+    /// The part of `amount` allocated to any delegatee, as `votes_for` each would
+    /// count it.
+    pub fn delegated_amount(&self, amount: u64) -> u64 {
+        self.allocations
+            .iter()
+            .map(|allocation| (amount as u128 * allocation.bps as u128 / 10_000) as u64)
+            .sum()
+    }
+
+    /// The part of `amount` this delegation allocates to `delegatee`, rounded down.
+    pub fn votes_for(&self, delegatee: &Pubkey, amount: u64) -> u64 {
+        self.allocations
+            .iter()
+            .filter(|allocation| allocation.delegatee == *delegatee)
+            .map(|allocation| (amount as u128 * allocation.bps as u128 / 10_000) as u64)
+            .sum()
+    }
+}
+
+impl DelegateeIndex {
+    pub const MAX_DELEGATORS: usize = 64;
+    // Return data is capped at 1024 bytes: a 4-byte length prefix plus 31 keys.
+    pub const MAX_DELEGATORS_PER_PAGE: usize = 31;
+    pub const LEN: usize = 32 + 32 + 4 + 32 * Self::MAX_DELEGATORS + 1;
+
+    pub fn add(&mut self, delegator: Pubkey) -> Result<()> {
+        require!(
+            self.delegators.len() < Self::MAX_DELEGATORS,
+            GovernorError::DelegateeIndexFull
+        );
+        self.delegators.push(delegator);
+        Ok(())
+    }
+}
+
+impl TreasuryStats {
+    pub const MAX_MINTS: usize = 16;
+    pub const LEN: usize = 32 + 8 + 4 + Self::MAX_MINTS * (32 + 8) + 1;
+    /// Stands in for a mint when the deposit is SOL.
+    pub const NATIVE_MINT: Pubkey = Pubkey::new_from_array([0; 32]);
+
+    /// Counts a deposit. Once `MAX_MINTS` mints are tracked, deposits of further
+    /// mints are still counted but get no running total.
+    pub fn record(&mut self, mint: Pubkey, amount: u64) {
+        self.deposit_count = self.deposit_count.saturating_add(1);
+        if let Some(total) = self.totals.iter_mut().find(|total| total.mint == mint) {
+            total.total_received = total.total_received.saturating_add(amount);
+        } else if self.totals.len() < Self::MAX_MINTS {
+            self.totals.push(MintTotal { mint, total_received: amount });
+        }
+    }
+}
+
+impl VoteCheckpoints {
+    pub const MAX_CHECKPOINTS: usize = 64;
+    pub const LEN: usize = 32 + 32 + 4 + Self::MAX_CHECKPOINTS * (8 + 8) + 1 + 1;
+
+    /// Appends a checkpoint, overwriting one already taken in the same slot and
+    /// dropping the oldest when full.
+    pub fn push(&mut self, slot: u64, votes: u64) {
+        if let Some(last) = self.checkpoints.last_mut() {
+            if last.slot == slot {
+                last.votes = votes;
+                return;
+            }
+        }
+        if self.checkpoints.len() == Self::MAX_CHECKPOINTS {
+            self.checkpoints.remove(0);
+            self.truncated = true;
+        }
+        self.checkpoints.push(Checkpoint { slot, votes });
+    }
 
-#[account]
-pub struct Vote {
+    /// Weight of the latest checkpoint at or before `block`; zero before the first.
+    pub fn votes_at(&self, block: u64) -> Result<u64> {
+        let position = self.checkpoints.partition_point(|checkpoint| checkpoint.slot <= block);
+        if position == 0 {
+            require!(!self.truncated, GovernorError::CheckpointUnavailable);
+            return Ok(0);
+        }
+        Ok(self.checkpoints[position - 1].votes)
+    }
+}
+
+impl ProposalDigest {
+    pub const LEN: usize = 32 + 32 + 8 + 9 + 1;
+}
+
+impl ProposalSchedule {
+    pub const LEN: usize = 32 + 8 + 1 + 32 + 4 + Proposal::MAX_ACTIONS * ProposalAction::LEN + 8
+        + 8 + 8 + 1 + 1 + 4 + 4;
+
+    pub fn description(&self) -> String {
+        self.description_hash.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+}
+
+/// Proposal snapshot shaped like SPL Governance's `ProposalV2`, emitted next to the
+/// native lifecycle events when `Governor::compat_events` is set. Against votes map
+/// to `deny_vote_weight`; quorum, which SPL Governance has no field for, is reported
+/// separately in `quorum_threshold`.
+#[event]
+pub struct SplGovernanceProposalV2 {
+    pub governance: Pubkey,
+    pub governing_token_mint: Pubkey,
+    pub proposal: Pubkey,
+    pub state: SplProposalState,
+    pub vote_type: SplVoteType,
+    pub options: Vec<SplProposalOption>,
+    pub deny_vote_weight: Option<u64>,
+    pub abstain_vote_weight: Option<u64>,
+    pub max_vote_weight: Option<u64>,
+    pub vote_threshold: Option<SplVoteThreshold>,
+    pub quorum_threshold: SplVoteThreshold,
+    pub voting_at_slot: Option<u64>,
+    pub name: String,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct ProposalCreated {
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub start_block: u64,
+    pub end_block: u64,
+    pub description: String,
+    pub proposal_type: u8,
+    pub supersedes: Option<u64>,
+    pub depends_on: Option<u64>,
+    /// Hash of the serialized executable payload.
+    pub payload_hash: [u8; 32],
+    /// Non-refundable `Governor::proposal_fee_lamports` the proposer paid into the
+    /// treasury.
+    pub fee_paid: u64,
+}
+
+#[event]
+pub struct ProposalEdited {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub content_hash: [u8; 32],
+    pub edit_count: u8,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct ProposalDrafted {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub sponsorship_deadline: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct ProposalSponsored {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub sponsor: Pubkey,
+    pub weight: u64,
+    pub total_sponsor_weight: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct DraftPromoted {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    /// Pooled weight of the proposer and sponsors that met the threshold.
+    pub sponsor_weight: u64,
+    pub sponsors: Vec<Pubkey>,
+    pub threshold: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct ProposalEndorsed {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub endorser: Pubkey,
+    pub weight: u64,
+    pub total_endorsement_weight: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct EndorsementQuorumReached {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub start_block: u64,
+    pub end_block: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct AllowanceSet {
+    pub governor: Pubkey,
+    pub mint_or_native: Pubkey,
+    pub window_slots: u64,
+    pub budget: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct ManagerSpend {
+    pub governor: Pubkey,
+    pub manager: Pubkey,
+    pub to: Pubkey,
+    pub mint_or_native: Pubkey,
+    pub amount: u64,
+    /// Budget left in the current window.
+    pub remaining: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct TreasurerSet {
+    pub governor: Pubkey,
+    pub treasurer: Pubkey,
+    pub mint_or_native: Pubkey,
+    pub budget: u64,
+    pub window_slots: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct TreasurerRevoked {
+    pub governor: Pubkey,
+    pub treasurer: Pubkey,
+    /// Governor authority or manager.
+    pub revoker: Pubkey,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct TreasurerSpend {
+    pub governor: Pubkey,
+    pub treasurer: Pubkey,
+    pub to: Pubkey,
+    pub mint_or_native: Pubkey,
+    pub amount: u64,
+    /// Budget left in the current window.
+    pub remaining: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct SpendingLimitSet {
+    pub governor: Pubkey,
+    pub mint: Option<Pubkey>,
+    pub window_slots: u64,
+    pub max_amount: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct GrantCreated {
+    pub governor: Pubkey,
+    pub grant: Pubkey,
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub tranches: Vec<u64>,
+    pub manager_attestation: bool,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct MilestoneApproved {
+    pub governor: Pubkey,
+    pub grant: Pubkey,
+    pub index: u8,
+    pub approver: Pubkey,
+    pub amount: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct TrancheClaimed {
+    pub governor: Pubkey,
+    pub grant: Pubkey,
+    pub index: u8,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct PayloadLookupTableCreated {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub lookup_table: Pubkey,
+    /// Entries written to the table.
+    pub addresses: u16,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct ExecutionHookSet {
+    pub governor: Pubkey,
+    pub execution_hook: Option<Pubkey>,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct GovernorTimelockSet {
+    pub governor: Pubkey,
+    pub timelock: Option<Pubkey>,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct TimelockOperationQueued {
+    pub timelock: Pubkey,
+    pub proposer: Pubkey,
+    pub hash: [u8; 32],
+    pub eta: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct TimelockOperationExecuted {
+    pub timelock: Pubkey,
+    pub hash: [u8; 32],
+    pub eta: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct TimelockOperationCanceled {
+    pub timelock: Pubkey,
+    pub proposer: Pubkey,
+    pub hash: [u8; 32],
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct TimelockDelaysUpdated {
+    pub timelock: Pubkey,
+    pub min_delay: u64,
+    pub max_delay: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct GrantCanceled {
+    pub governor: Pubkey,
+    pub grant: Pubkey,
+    /// Milestones approved before the cancellation; their tranches stay claimable.
+    pub approved_count: u8,
+    /// Sum of the tranches that will never be released.
+    pub retained: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct SubGovernorCreated {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub sub_governor: Pubkey,
+    pub governing_mint: Pubkey,
+    pub proposal_types: u8,
+    pub budget_lamports: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct StreamCreated {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub stream: Pubkey,
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub total_amount: u64,
+    pub start_slot: u64,
+    pub end_slot: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct StreamClaimed {
+    pub governor: Pubkey,
+    pub stream: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub total_claimed: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct TreasurySolWrapped {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub wsol_account: Pubkey,
+    pub amount: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct TreasurySolUnwrapped {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub wsol_account: Pubkey,
+    /// Lamports returned to the treasury, including the account's rent.
+    pub amount: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct StreamCanceled {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub stream: Pubkey,
+    /// Unvested tokens returned to the treasury token account.
+    pub returned: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct VoteCommitted {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub voter: Pubkey,
+    pub weight: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct VoteRevealed {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub voter: Pubkey,
+    pub support: bool,
+    pub weight: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct VoteCast {
+    pub voter: Pubkey,
+    pub proposal_id: u64,
+    pub support: bool,
+    pub weight: u64,
+    pub reward_ineligible: bool,
+    /// Given with `cast_vote_with_reason`, whether or not it was stored.
+    pub reason: Option<String>,
+}
+
+#[event]
+pub struct VoteStakeClaimed {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub voter: Pubkey,
+    pub amount: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct VoteRewardClaimed {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
     pub voter: Pubkey,
+    pub amount: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct MemberRageQuit {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub member: Pubkey,
+    /// Governing tokens burned.
+    pub burned: u64,
+    /// Lamports paid from the treasury.
+    pub lamports: u64,
+    pub tokens: Vec<RageQuitShare>,
+    pub unix_timestamp: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RageQuitShare {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct TreasuryVaultCreated {
+    pub governor: Pubkey,
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct TreasuryVaultClosed {
+    pub governor: Pubkey,
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct TreasuryDeposit {
+    pub governor: Pubkey,
+    pub from: Pubkey,
+    /// `TreasuryStats::NATIVE_MINT` for SOL.
+    pub mint_or_native: Pubkey,
+    pub amount: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct TreasuryBalanceSnapshot {
+    pub governor: Pubkey,
+    pub treasury: Pubkey,
+    pub lamports: u64,
+    /// Balance of the token account passed alongside, if any.
+    pub token: Option<TreasuryBalance>,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct TreasuryTransfer {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub to: Pubkey,
+    pub lamports: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct QuorumProgress {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    /// Votes cast so far, for and against.
+    pub current: u64,
+    pub required: u64,
+    /// Slots left until `end_block`; zero once voting has closed.
+    pub slots_remaining: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct QuorumReached {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub slot: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct ProposalExecuted {
+    pub proposal_id: u64,
+    pub executor: Pubkey,
+    /// Lamports actually paid; zero when the treasury could not cover the reward.
+    pub reward: u64,
+}
+
+#[event]
+pub struct TransactionSkipped {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub ix_index: u16,
+    pub skipped_by: Pubkey,
+    pub unix_timestamp: i64,
+}
+
+/// Emitted whenever a payload instruction runs, fails or is skipped.
+#[event]
+pub struct InstructionStatusChanged {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub ix_index: u16,
+    pub status: InstructionStatus,
+    pub unix_timestamp: i64,
+}
+
+/// Outcome of one payload instruction: emitted on every successful run and when
+/// `record_transaction_failure` records a failure.
+#[event]
+pub struct TransactionExecutionResult {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub ix_index: u16,
+    pub success: bool,
+    /// Code the instruction failed with, as in `Proposal::instruction_error_codes`;
+    /// zero on success.
+    pub error_code: u64,
+    /// Compute units the instruction took, including the governor's own checks;
+    /// zero for a recorded failure.
+    pub compute_consumed_estimate: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct TransactionExecuted {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub ix_index: u16,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct Delegated {
+    pub governor: Pubkey,
+    pub delegator: Pubkey,
+    pub allocations: Vec<DelegationAllocation>,
+}
+
+#[event]
+pub struct Undelegated {
+    pub governor: Pubkey,
+    pub delegator: Pubkey,
+    pub allocations: Vec<DelegationAllocation>,
+}
+
+// Lifecycle events below are decoded positionally by indexers: their field order is
+// part of the wire format. Never reorder or remove fields; append new ones at the end.
+
+#[event]
+pub struct EmergencyExecuted {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub guardian: Pubkey,
+    pub eta: u64,
+    pub slot: u64,
+    pub unix_timestamp: i64,
+}
+
+/// Loud record of a proposal executed, or its payload released, by
+/// `emergency_execute` without waiting out the timelock.
+#[event]
+pub struct TimelockBypassed {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub guardian: Pubkey,
+    pub for_votes: u64,
+    pub against_votes: u64,
+    pub total_supply: u64,
+    /// The governor's `timelock_delay` that the proposal did not wait out.
+    pub skipped_timelock_slots: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct ProposalCanceled {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub canceled_by: Pubkey,
+    pub slot: u64,
+    pub unix_timestamp: i64,
+    pub reason: CancelReason,
+}
+
+#[event]
+pub struct ProposalExecutionBlocked {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub blocked_by: Pubkey,
+    /// Hash of the off-chain rationale for the block.
+    pub reason_hash: [u8; 32],
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct ProposalExecutionUnblocked {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub unblocked_by: Pubkey,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct QueuedProposalCanceled {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub canceled_by: Pubkey,
+    /// Payload instructions that had already run when the proposal was canceled.
+    pub executed_instructions: u16,
+    pub total_instructions: u16,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct ProposalQueued {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub eta: u64,
+    /// Slot from which each payload instruction may run, by index.
+    pub executable_at: Vec<u64>,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct ProposalVetoed {
+    pub governor: Pubkey,
     pub proposal_id: u64,
-    pub support: bool,
-    pub weight: u64,
+    pub vetoed_by: Pubkey,
+    pub slot: u64,
+    pub unix_timestamp: i64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
-pub struct ProposalType {
-    pub quorum: u16,
-    pub approval_threshold: u16,
-    pub name: String,
-    pub module: Option<Pubkey>,
+#[event]
+pub struct ProposalFinalized {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub state: ProposalState,
+    pub for_votes: u64,
+    pub against_votes: u64,
+    pub unix_timestamp: i64,
 }
 
-#[error_code]
-pub enum GovernorError {
-    #[msg("Proposer does not have enough votes to create a proposal")]
-    InsufficientProposerVotes,
-    #[msg("Invalid proposal type")]
-    InvalidProposalType,
-    #[msg("Voting period is not active")]
-    VotingPeriodInactive,
-    #[msg("Proposal has already been executed")]
-    ProposalAlreadyExecuted,
-    #[msg("Proposal has been canceled")]
-    ProposalCanceled,
-    #[msg("Voting period is still active")]
-    VotingPeriodActive,
-    #[msg("Quorum not reached")]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DefeatReason {
     QuorumNotReached,
-    #[msg("Approval threshold not met")]
-    ApprovalThresholdNotMet,
+    ApprovalNotMet,
 }
 
-impl Governor {
-    pub const LEN: usize = 32 + 32 + 8 + 8 + 8 + 8 + 8 + 32;
+/// Emitted alongside `ProposalFinalized` when the vote is lost.
+#[event]
+pub struct ProposalDefeated {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub for_votes: u64,
+    pub against_votes: u64,
+    pub reason: DefeatReason,
+    pub unix_timestamp: i64,
+}
 
-    pub fn get_votes(&self, account: &Pubkey, block: u64) -> u64 {
-        // TODO: Implement logic to get votes for an account at a specific block
-        // This would typically involve querying a token account or stake account
-        0
-    }
+#[event]
+pub struct AggregateVotes {
+    pub governor: Pubkey,
+    pub accounts: Vec<Pubkey>,
+    /// Summed current weight of `accounts`, delegations included.
+    pub votes: u64,
+    /// Supply snapshot the weight can be compared against.
+    pub total_supply: u64,
+    pub unix_timestamp: i64,
 }
 
-impl Proposal {
-    pub const LEN: usize = 8 + 32 + 200 + 1 + 8 + 8 + 8 + 8 + 1 + 1;
+#[event]
+pub struct EffectiveProposerThreshold {
+    pub governor: Pubkey,
+    pub proposal_type: u8,
+    pub threshold: u64,
+    pub unix_timestamp: i64,
 }
 
-impl Vote {
-    pub const LEN: usize = 32 + 8 + 1 + 8;
+#[event]
+pub struct TotalSupplySynced {
+    pub governor: Pubkey,
+    pub old: u64,
+    pub new: u64,
+    pub unix_timestamp: i64,
 }
 
 #[event]
-pub struct ProposalCreated {
-    pub proposal_id: u64,
-    pub proposer: Pubkey,
-    pub start_block: u64,
-    pub end_block: u64,
-    pub description: String,
+pub struct AdminTransferStarted {
+    pub governor: Pubkey,
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct AdminTransferred {
+    pub governor: Pubkey,
+    pub previous_admin: Pubkey,
+    pub new_admin: Pubkey,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct AdminLocked {
+    pub governor: Pubkey,
+    pub admin: Pubkey,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct GovernorSettingsUpdated {
+    pub governor: Pubkey,
+    pub field: String,
+    pub old: u64,
+    pub new: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct BlackoutWindowAdded {
+    pub governor: Pubkey,
+    pub start_slot: u64,
+    pub end_slot: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct BlackoutWindowRemoved {
+    pub governor: Pubkey,
+    pub start_slot: u64,
+    pub end_slot: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct AddressBlocked {
+    pub governor: Pubkey,
+    pub address: Pubkey,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct AddressUnblocked {
+    pub governor: Pubkey,
+    pub address: Pubkey,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct ProposalTypeAdded {
+    pub governor: Pubkey,
     pub proposal_type: u8,
+    pub name: String,
+    pub quorum: u16,
+    pub approval_threshold: u16,
+    pub unix_timestamp: i64,
 }
 
 #[event]
-pub struct VoteCast {
-    pub voter: Pubkey,
+pub struct ProposalTypeUpdated {
+    pub governor: Pubkey,
+    pub proposal_type: u8,
+    pub name: String,
+    pub quorum: u16,
+    pub approval_threshold: u16,
+    pub allowed_programs: Vec<Pubkey>,
+    /// Rent returned to the admin when the new settings take less space.
+    pub refund: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct ProposalTypeRemoved {
+    pub governor: Pubkey,
+    pub proposal_type: u8,
+    pub name: String,
+    /// Rent returned to the admin.
+    pub refund: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct RiskTierMultipliersUpdated {
+    pub governor: Pubkey,
+    pub multipliers: Vec<u16>,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct ProposalExpiredEarly {
+    pub governor: Pubkey,
     pub proposal_id: u64,
-    pub support: bool,
-    pub weight: u64,
+    pub reason: ExpiryReason,
+    pub unix_timestamp: i64,
 }
 
 #[event]
-pub struct ProposalExecuted {
+pub struct DepositForfeited {
+    pub governor: Pubkey,
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub amount: u64,
+    pub unix_timestamp: i64,
+}
+
+#[event]
+pub struct ProposalExecutionExpired {
+    pub governor: Pubkey,
     pub proposal_id: u64,
-}
\ No newline at end of file
+    pub expires_at: u64,
+    /// Payload instructions that never ran.
+    pub unexecuted_instructions: u16,
+    pub unix_timestamp: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proposal_with_votes(for_votes: u64, against_votes: u64) -> Proposal {
+        Proposal {
+            id: 0,
+            governor: Pubkey::default(),
+            proposer: Pubkey::default(),
+            description: String::new(),
+            proposal_type: 0,
+            actions: vec![],
+            external_actions: 0,
+            state: ProposalState::Pending,
+            created_slot: 0,
+            sponsor_weight: 0,
+            sponsors: vec![],
+            endorsement_end: 0,
+            endorsement_weight: 0,
+            start_block: 0,
+            end_block: 0,
+            reveal_end: 0,
+            for_votes,
+            against_votes,
+            for_conviction: 0,
+            against_conviction: 0,
+            voter_count: 0,
+            snapshot_supply: 0,
+            spent_weight: 0,
+            eta: 0,
+            expires_at: 0,
+            outcome: None,
+            executed: false,
+            canceled: false,
+            execution_blocked: false,
+            timelock_bypassed: false,
+            supersedes: None,
+            depends_on: None,
+            edit_count: 0,
+            fee_paid: 0,
+            deposit: 0,
+            deposit_forfeited: false,
+            deposit_swept: false,
+            for_stake_weight: 0,
+            against_stake_weight: 0,
+            for_stake_penalties: 0,
+            against_stake_penalties: 0,
+            unclaimed_stakes: 0,
+            bump: 0,
+            executed_instructions: 0,
+            skipped_instructions: 0,
+            quorum_reached: false,
+            quorum_override: None,
+            type_snapshot: None,
+            instruction_error_codes: [0; Proposal::MAX_INSTRUCTIONS],
+            lookup_table: None,
+            content_hash: [0; 32],
+            payload_bytes: 0,
+            instructions: vec![],
+        }
+    }
+
+    fn quorum_votes(quorum: u16, total_supply: u64) -> u64 {
+        ProposalType {
+            quorum,
+            ..ProposalType::default()
+        }
+        .quorum_votes(total_supply, Governor::BASE_RISK_MULTIPLIER)
+        .unwrap()
+    }
+
+    /// A proposal over a supply of `snapshot_supply` whose voters spent exactly
+    /// what they cast.
+    fn proposal_with_snapshot(for_votes: u64, against_votes: u64, snapshot_supply: u64) -> Proposal {
+        let mut proposal = proposal_with_votes(for_votes, against_votes);
+        proposal.take_supply_snapshot(snapshot_supply);
+        proposal.spent_weight = for_votes + against_votes;
+        proposal
+    }
+
+    #[test]
+    fn quorum_reachable_at_exact_boundary() {
+        // 10_000 bps of 1_000 supply is 1_000: every token voting reaches it exactly.
+        let proposal = proposal_with_snapshot(300, 200, 1_000);
+        let proposal_type = ProposalType::default();
+        assert!(!proposal.quorum_unreachable(&proposal_type, quorum_votes(10_000, 1_000)).unwrap());
+    }
+
+    #[test]
+    fn quorum_unreachable_one_past_boundary() {
+        // 10_010 bps of 1_000 supply is 1_001, one more than the supply can cast.
+        let proposal = proposal_with_snapshot(300, 200, 1_000);
+        let proposal_type = ProposalType::default();
+        assert!(proposal.quorum_unreachable(&proposal_type, quorum_votes(10_010, 1_000)).unwrap());
+    }
+
+    #[test]
+    fn quorum_reachable_when_tallies_exceed_supply() {
+        let proposal = proposal_with_snapshot(1_500, 0, 1_000);
+        let proposal_type = ProposalType::default();
+        assert!(!proposal.quorum_unreachable(&proposal_type, quorum_votes(12_000, 1_000)).unwrap());
+    }
+
+    #[test]
+    fn quorum_unreachable_once_spent_power_is_capped_away() {
+        // A voter checkpointed at 600 sold 500 before voting: 100 counted, 600 spent.
+        let mut proposal = proposal_with_snapshot(100, 0, 1_000);
+        proposal.spent_weight = 600;
+        let proposal_type = ProposalType::default();
+        // At most 100 + 400 can be cast: 5_000 bps (500) is still reachable, 6_000 is not.
+        assert!(!proposal.quorum_unreachable(&proposal_type, quorum_votes(5_000, 1_000)).unwrap());
+        assert!(proposal.quorum_unreachable(&proposal_type, quorum_votes(6_000, 1_000)).unwrap());
+
+        let realtime = ProposalType {
+            realtime_weight: true,
+            ..ProposalType::default()
+        };
+        assert!(!proposal.quorum_unreachable(&realtime, quorum_votes(6_000, 1_000)).unwrap());
+    }
+
+    #[test]
+    fn quorum_unreachable_when_supply_minted_after_snapshot() {
+        let mut proposal = proposal_with_snapshot(0, 0, 1_000);
+        // The supply doubles after the snapshot; the first snapshot stands.
+        proposal.take_supply_snapshot(2_000);
+        assert_eq!(proposal.snapshot_supply, 1_000);
+        let proposal_type = ProposalType::default();
+        assert!(!proposal.quorum_unreachable(&proposal_type, quorum_votes(5_000, 2_000)).unwrap());
+        assert!(proposal.quorum_unreachable(&proposal_type, quorum_votes(6_000, 2_000)).unwrap());
+    }
+
+    #[test]
+    fn tally_near_u64_max_does_not_overflow() {
+        let proposal_type = ProposalType {
+            approval_threshold: 5_000,
+            ..ProposalType::default()
+        };
+        let proposal = proposal_with_votes(u64::MAX, u64::MAX / 2);
+        assert!(proposal.check_succeeded(&proposal_type, u64::MAX).is_ok());
+        let proposal = proposal_with_votes(u64::MAX / 2, u64::MAX);
+        assert!(proposal.check_succeeded(&proposal_type, u64::MAX).is_err());
+    }
+
+    #[test]
+    fn vote_weight_snapshots_unless_realtime() {
+        let mut governor = Governor {
+            proposal_types: vec![ProposalType::default()],
+            ..Governor::default()
+        };
+        let mut proposal = proposal_with_votes(0, 0);
+        proposal.start_block = 100;
+        let mut checkpoints = VoteCheckpoints {
+            governor: Pubkey::default(),
+            account: Pubkey::default(),
+            checkpoints: vec![],
+            truncated: false,
+            bump: 0,
+        };
+        checkpoints.push(50, 1_000);
+        checkpoints.push(150, 5_000);
+
+        assert_eq!(governor.vote_weight(&proposal, Some(&checkpoints), 5_000).unwrap(), (1_000, 1_000));
+        // Tokens moved away after the snapshot no longer count, but are spent.
+        assert_eq!(governor.vote_weight(&proposal, Some(&checkpoints), 300).unwrap(), (300, 1_000));
+        assert!(governor.vote_weight(&proposal, None, 5_000).is_err());
+
+        // Realtime types count the current weight, checkpoints or not.
+        governor.proposal_types[0].realtime_weight = true;
+        assert_eq!(governor.vote_weight(&proposal, Some(&checkpoints), 5_000).unwrap(), (5_000, 5_000));
+        assert_eq!(governor.vote_weight(&proposal, None, 300).unwrap(), (300, 300));
+    }
+
+    #[test]
+    fn proposal_actions_round_trip_and_cap() {
+        let actions = vec![
+            ProposalAction::ParameterChange {
+                key: ParamKey::VotingPeriod,
+                value: 9_000,
+            },
+            ProposalAction::Mint {
+                to: Pubkey::new_unique(),
+                amount: 5,
+            },
+            ProposalAction::Burn {
+                from: Pubkey::new_unique(),
+                amount: 7,
+            },
+        ];
+        let mut proposal_actions: ProposalActions = bytemuck::Zeroable::zeroed();
+        proposal_actions.append(&actions).unwrap();
+        assert_eq!(proposal_actions.unpack().unwrap(), actions);
+
+        let fill = vec![actions[0].clone(); ProposalActions::CAPACITY - actions.len()];
+        proposal_actions.append(&fill).unwrap();
+        assert!(proposal_actions.append(&actions[..1]).is_err());
+        assert_eq!(proposal_actions.len as usize, ProposalActions::CAPACITY);
+
+        let mut proposal = proposal_with_votes(0, 0);
+        proposal.actions = actions[..1].to_vec();
+        assert_eq!(proposal.all_actions(None).unwrap(), proposal.actions);
+        proposal.external_actions = 1;
+        assert!(proposal.all_actions(None).is_err());
+    }
+
+    #[test]
+    fn sponsors_are_listed_once_and_capped() {
+        let mut proposal = proposal_with_votes(0, 0);
+        proposal.proposer = Pubkey::new_unique();
+        assert!(proposal.add_sponsor(proposal.proposer, 10).is_err());
+
+        let sponsor = Pubkey::new_unique();
+        proposal.add_sponsor(sponsor, 10).unwrap();
+        assert!(proposal.add_sponsor(sponsor, 10).is_err());
+        assert_eq!(proposal.sponsor_weight, 10);
+
+        for _ in 1..Proposal::MAX_SPONSORS {
+            proposal.add_sponsor(Pubkey::new_unique(), 1).unwrap();
+        }
+        assert!(proposal.add_sponsor(Pubkey::new_unique(), 1).is_err());
+        assert_eq!(proposal.sponsors.len(), Proposal::MAX_SPONSORS);
+        assert_eq!(proposal.sponsor_weight, 10 + Proposal::MAX_SPONSORS as u64 - 1);
+    }
+
+    #[test]
+    fn type_snapshot_survives_removed_type() {
+        let mut proposal_types = vec![ProposalType {
+            quorum: 2_000,
+            binding: true,
+            ..ProposalType::default()
+        }];
+        let mut proposal = proposal_with_votes(0, 0);
+        assert_eq!(proposal.type_info(&proposal_types).unwrap().quorum, 2_000);
+
+        proposal.type_snapshot = Some(proposal_types[0].clone());
+        proposal_types.clear();
+        let snapshot = proposal.type_info(&proposal_types).unwrap();
+        assert!(snapshot.binding);
+        assert_eq!(snapshot.quorum, 2_000);
+
+        proposal.type_snapshot = None;
+        assert!(proposal.type_info(&proposal_types).is_err());
+    }
+
+    #[test]
+    fn proposal_holds_type_until_settled() {
+        let mut proposal = proposal_with_votes(0, 0);
+        assert!(proposal.holds_type());
+
+        proposal.type_snapshot = Some(ProposalType { binding: true, ..ProposalType::default() });
+        proposal.state = ProposalState::Succeeded;
+        assert!(proposal.holds_type());
+        proposal.state = ProposalState::Executed;
+        assert!(!proposal.holds_type());
+
+        proposal.type_snapshot = Some(ProposalType::default());
+        proposal.state = ProposalState::Succeeded;
+        assert!(!proposal.holds_type());
+    }
+
+    #[test]
+    fn emergency_bypass_approval_boundary() {
+        // 900 of 1_000 votes cast is exactly 90%; 899 falls just short.
+        assert!(proposal_with_votes(900, 100).check_emergency_bypass(9_000, 3_000, 1_000).is_ok());
+        assert!(proposal_with_votes(899, 101).check_emergency_bypass(9_000, 3_000, 1_000).is_err());
+    }
+
+    #[test]
+    fn emergency_bypass_quorum_boundary() {
+        // 300 of 1_000 supply is exactly 30% turnout; 299 falls just short.
+        assert!(proposal_with_votes(300, 0).check_emergency_bypass(9_000, 3_000, 1_000).is_ok());
+        assert!(proposal_with_votes(299, 0).check_emergency_bypass(9_000, 3_000, 1_000).is_err());
+    }
+
+    #[test]
+    fn grace_period_overrides_execution_deadline() {
+        let mut proposal_type = ProposalType::default();
+        assert_eq!(proposal_type.execution_deadline_slots(1_000), 1_000);
+        proposal_type.grace_period = Some(100);
+        assert_eq!(proposal_type.execution_deadline_slots(1_000), 100);
+        proposal_type.grace_period = Some(0);
+        assert_eq!(proposal_type.execution_deadline_slots(1_000), 0);
+    }
+
+    #[test]
+    fn removed_proposal_type_rejects_new_proposals() {
+        let mut governor = Governor {
+            proposal_types: vec![ProposalType { binding: true, ..ProposalType::default() }; 2],
+            ..Governor::default()
+        };
+        governor.proposal_types[0].removed = true;
+        assert!(governor.validate_new_proposal(0, &[]).is_err());
+        assert!(governor.validate_new_proposal(1, &[]).is_ok());
+        assert!(governor.proposal_types[0].validate(1).is_err());
+    }
+
+    #[test]
+    fn governor_space_fits_full_lists_and_types() {
+        let proposal_types = vec![
+            ProposalType {
+                name: "x".repeat(ProposalType::MAX_NAME_LEN),
+                module: Some(Pubkey::default()),
+                allowed_programs: vec![Pubkey::default(); ProposalType::MAX_ALLOWED_PROGRAMS],
+                ..ProposalType::default()
+            },
+            ProposalType::default(),
+        ];
+        let cap = SpendingCap { mint: Some(Pubkey::default()), max_amount: 0 };
+        let governor = Governor {
+            blackout_windows: vec![BlackoutWindow { start_slot: 0, end_slot: 0 }; Governor::MAX_BLACKOUT_WINDOWS],
+            blocklist: vec![Pubkey::default(); Governor::MAX_BLOCKLIST_LEN],
+            risk_tier_multipliers: vec![0; Governor::MAX_RISK_TIERS],
+            spending_limits: vec![cap; Governor::MAX_SPENDING_LIMITS],
+            pending_admin: Some(Pubkey::default()),
+            timelock: Some(Pubkey::default()),
+            execution_hook: Some(Pubkey::default()),
+            active_proposal_counts: vec![0; proposal_types.len()],
+            proposal_types: proposal_types.clone(),
+            ..Governor::default()
+        };
+        assert!(8 + governor.try_to_vec().unwrap().len() <= Governor::space_with_types(&proposal_types));
+    }
+
+    #[test]
+    fn schedule_rejects_window_below_min_active_slots() {
+        let mut governor = Governor {
+            voting_period: 100,
+            min_active_slots: 100,
+            proposal_types: vec![ProposalType::default()],
+            ..Governor::default()
+        };
+        let mut proposal = proposal_with_votes(0, 0);
+        assert!(proposal.schedule(&governor, 10).is_ok());
+        assert_eq!(proposal.end_block - proposal.start_block, 100);
+
+        governor.voting_period = 99;
+        assert!(proposal.schedule(&governor, 10).is_err());
+    }
+
+    #[test]
+    fn quorum_check_rejects_tally_overflow() {
+        let mut proposal = proposal_with_votes(u64::MAX, 1);
+        proposal.take_supply_snapshot(1_000);
+        assert!(proposal
+            .quorum_unreachable(&ProposalType::default(), quorum_votes(5_000, 1_000))
+            .is_err());
+    }
+
+    #[test]
+    fn risk_tier_multiplier_scales_quorum() {
+        let proposal_type = ProposalType {
+            quorum: 1_000,
+            ..ProposalType::default()
+        };
+        assert_eq!(proposal_type.quorum_votes(1_000_000, 10_000).unwrap(), 100_000);
+        assert_eq!(proposal_type.quorum_votes(1_000_000, 30_000).unwrap(), 300_000);
+    }
+
+    #[test]
+    fn quorum_only_type_ignores_approval_threshold() {
+        let mut proposal_type = ProposalType {
+            approval_threshold: 5_000,
+            ..ProposalType::default()
+        };
+        let proposal = proposal_with_votes(100, 900);
+        assert!(proposal.check_succeeded(&proposal_type, 1_000).is_err());
+
+        proposal_type.quorum_only = true;
+        assert!(proposal.check_succeeded(&proposal_type, 1_000).is_ok());
+        assert!(proposal.check_succeeded(&proposal_type, 1_001).is_err());
+    }
+
+    fn payload_instruction(program_id: Pubkey, accounts: &[Pubkey], data: Vec<u8>) -> ProposalInstruction {
+        ProposalInstruction {
+            index: 0,
+            program_id,
+            accounts: accounts
+                .iter()
+                .map(|pubkey| AccountMetaData { pubkey: *pubkey, is_signer: false, is_writable: true })
+                .collect(),
+            data,
+            self_call: false,
+            hold_up_slots: 0,
+            kind: InstructionKind::Invoke,
+        }
+    }
+
+    fn system_transfer(from: Pubkey, lamports: u64) -> ProposalInstruction {
+        let mut data = 2u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&lamports.to_le_bytes());
+        payload_instruction(system_program::ID, &[from, Pubkey::new_unique()], data)
+    }
+
+    fn token_transfer_checked(mint: Pubkey, authority: Pubkey, amount: u64) -> ProposalInstruction {
+        let mut data = vec![12];
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.push(6);
+        let accounts = [Pubkey::new_unique(), mint, Pubkey::new_unique(), authority];
+        payload_instruction(anchor_spl::token::ID, &accounts, data)
+    }
+
+    #[test]
+    fn decodes_treasury_transfers() {
+        let (authority, treasury, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let decode = |instruction: &ProposalInstruction| TreasuryOutflow::decode(instruction, &authority, &treasury, &[]);
+
+        assert_eq!(decode(&system_transfer(treasury, 5)), Some(TreasuryOutflow::Native { amount: 5 }));
+        assert_eq!(decode(&system_transfer(Pubkey::new_unique(), 5)), None);
+
+        let checked = token_transfer_checked(mint, authority, 7);
+        assert_eq!(decode(&checked), Some(TreasuryOutflow::Token { mint, amount: 7 }));
+        let mut checked_2022 = checked.clone();
+        checked_2022.program_id = anchor_spl::token_2022::ID;
+        assert_eq!(decode(&checked_2022), Some(TreasuryOutflow::Token { mint, amount: 7 }));
+        assert_eq!(decode(&token_transfer_checked(mint, Pubkey::new_unique(), 7)), None);
+
+        let source = Pubkey::new_unique();
+        let mut data = vec![3];
+        data.extend_from_slice(&9u64.to_le_bytes());
+        let plain = payload_instruction(anchor_spl::token::ID, &[source, Pubkey::new_unique(), authority], data);
+        assert_eq!(decode(&plain), Some(TreasuryOutflow::UnknownMint { source, amount: 9 }));
+
+        let mut stream = payload_instruction(crate::ID, &[], vec![]);
+        stream.kind = InstructionKind::CreateStream {
+            recipient: Pubkey::new_unique(),
+            mint,
+            total_amount: 11,
+            start_slot: 0,
+            end_slot: 1,
+        };
+        assert_eq!(decode(&stream), Some(TreasuryOutflow::Token { mint, amount: 11 }));
+
+        let mut sub_governor = payload_instruction(crate::ID, &[], vec![]);
+        sub_governor.kind = InstructionKind::CreateSubGovernor {
+            config: SubGovernorConfig {
+                governing_mint: mint,
+                manager: Pubkey::new_unique(),
+                guardian: Pubkey::new_unique(),
+                voting_delay: 0,
+                voting_period: 1,
+                proposal_threshold: 0,
+                timelock_delay: 0,
+                budget_lamports: 13,
+            },
+        };
+        assert_eq!(decode(&sub_governor), Some(TreasuryOutflow::Native { amount: 13 }));
+
+        // Wrapping only moves lamports between the treasury and the authority; a
+        // later transfer of the wSOL is the outflow.
+        let mut wrap = payload_instruction(anchor_spl::token::ID, &[], vec![]);
+        wrap.kind = InstructionKind::WrapTreasurySol { amount: 17 };
+        assert_eq!(decode(&wrap), None);
+    }
+
+    #[test]
+    fn ignores_malformed_and_unrelated_instructions() {
+        let (authority, treasury) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let decode = |instruction: &ProposalInstruction| TreasuryOutflow::decode(instruction, &authority, &treasury, &[]);
+
+        // Truncated amount, trailing bytes, and a non-transfer system instruction.
+        let mut truncated = system_transfer(treasury, 5);
+        truncated.data.truncate(8);
+        assert_eq!(decode(&truncated), None);
+        let mut trailing = system_transfer(treasury, 5);
+        trailing.data.push(0);
+        assert_eq!(decode(&trailing), None);
+        let mut assign = system_transfer(treasury, 5);
+        assign.data[0] = 1;
+        assert_eq!(decode(&assign), None);
+
+        // Token approve (tag 4) and a transfer missing its authority account.
+        let mut approve = token_transfer_checked(Pubkey::new_unique(), authority, 7);
+        approve.data[0] = 4;
+        assert_eq!(decode(&approve), None);
+        let mut short = token_transfer_checked(Pubkey::new_unique(), authority, 7);
+        short.accounts.truncate(3);
+        assert_eq!(decode(&short), None);
+
+        let other = payload_instruction(Pubkey::new_unique(), &[treasury], vec![2, 0, 0, 0]);
+        assert_eq!(decode(&other), None);
+    }
+
+    #[test]
+    fn spending_caps_sum_transfers_per_mint() {
+        let (authority, treasury, usdc) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let proposal_type = ProposalType {
+            spending_cap: vec![
+                SpendingCap { mint: None, max_amount: 100 },
+                SpendingCap { mint: Some(usdc), max_amount: 1_000 },
+            ],
+            ..ProposalType::default()
+        };
+        let check = |instructions: &[ProposalInstruction]| {
+            proposal_type.check_spending_caps(instructions, &authority, &treasury, &[])
+        };
+
+        assert!(check(&[system_transfer(treasury, 60), system_transfer(treasury, 40)]).is_ok());
+        assert!(check(&[system_transfer(treasury, 60), system_transfer(treasury, 41)]).is_err());
+        assert!(check(&[token_transfer_checked(usdc, authority, 1_000), system_transfer(treasury, 100)]).is_ok());
+        assert!(check(&[token_transfer_checked(usdc, authority, 600), token_transfer_checked(usdc, authority, 401)]).is_err());
+        // Uncapped mints and transfers out of other accounts don't count.
+        assert!(check(&[token_transfer_checked(Pubkey::new_unique(), authority, u64::MAX)]).is_ok());
+        assert!(check(&[system_transfer(Pubkey::new_unique(), u64::MAX)]).is_ok());
+        assert!(check(&[token_transfer_checked(usdc, Pubkey::new_unique(), u64::MAX)]).is_ok());
+    }
+
+    #[test]
+    fn spending_caps_reject_transfers_without_a_mint() {
+        let (authority, treasury) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut data = vec![3];
+        data.extend_from_slice(&1u64.to_le_bytes());
+        let plain = payload_instruction(
+            anchor_spl::token::ID,
+            &[Pubkey::new_unique(), Pubkey::new_unique(), authority],
+            data,
+        );
+        let mut proposal_type = ProposalType::default();
+        assert!(proposal_type.check_spending_caps(std::slice::from_ref(&plain), &authority, &treasury, &[]).is_ok());
+
+        proposal_type.spending_cap = vec![SpendingCap { mint: None, max_amount: 100 }];
+        assert!(proposal_type.check_spending_caps(std::slice::from_ref(&plain), &authority, &treasury, &[]).is_err());
+
+    }
+
+    #[test]
+    fn registered_vaults_attribute_plain_transfers() {
+        let (authority, treasury, usdc) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let vault = TreasuryVault { mint: usdc, address: Pubkey::new_unique() };
+        let plain = |amount: u64| {
+            let mut data = vec![3];
+            data.extend_from_slice(&amount.to_le_bytes());
+            payload_instruction(anchor_spl::token::ID, &[vault.address, Pubkey::new_unique(), authority], data)
+        };
+        assert_eq!(
+            TreasuryOutflow::decode(&plain(5), &authority, &treasury, &[vault]),
+            Some(TreasuryOutflow::Token { mint: usdc, amount: 5 })
+        );
+
+        let proposal_type = ProposalType {
+            spending_cap: vec![SpendingCap { mint: Some(usdc), max_amount: 100 }],
+            ..ProposalType::default()
+        };
+        let check = |amount: u64| {
+            proposal_type.check_spending_caps(&[plain(amount)], &authority, &treasury, &[vault])
+        };
+        assert!(check(100).is_ok());
+        assert!(check(101).is_err());
+    }
+
+    #[test]
+    fn spending_limit_window_rolls_over() {
+        let mut limit = SpendingLimit {
+            governor: Pubkey::new_unique(),
+            mint: None,
+            window_slots: 100,
+            max_amount: 1_000,
+            window_start: 50,
+            spent: 0,
+            bump: 0,
+        };
+        limit.record(600, 60).unwrap();
+        limit.record(400, 149).unwrap();
+        assert!(limit.record(1, 149).is_err());
+
+        // Windows stay aligned to the first one: slot 380 falls in [350, 450).
+        limit.record(1_000, 380).unwrap();
+        assert_eq!((limit.window_start, limit.spent), (350, 1_000));
+        assert!(limit.record(1, 449).is_err());
+    }
+
+    #[test]
+    fn history_record_len_matches_serialization() {
+        let record = HistoryRecord {
+            proposal_id: 1,
+            proposal_type: 0,
+            for_votes: 2,
+            against_votes: 3,
+            outcome: ProposalState::Succeeded,
+            slot: 4,
+        };
+        assert_eq!(record.try_to_vec().unwrap().len(), HistoryRecord::LEN);
+    }
+
+    #[test]
+    fn pro_rata_share_rounds_down() {
+        assert_eq!(pro_rata_share(1_000, 250, 1_000), 250);
+        assert_eq!(pro_rata_share(999, 1, 3), 333);
+        assert_eq!(pro_rata_share(u64::MAX, u64::MAX, u64::MAX), u64::MAX);
+        // Never more than the whole balance, and nothing from an empty supply.
+        assert_eq!(pro_rata_share(100, 200, 100), 100);
+        assert_eq!(pro_rata_share(100, 1, 0), 0);
+    }
+
+    #[test]
+    fn conviction_grows_to_full_weight() {
+        let proposal_type = ProposalType {
+            conviction_mode: true,
+            max_conviction_slots: 1_000,
+            ..ProposalType::default()
+        };
+        assert_eq!(proposal_type.conviction_weight(500, 0), 0);
+        assert_eq!(proposal_type.conviction_weight(500, 250), 125);
+        assert_eq!(proposal_type.conviction_weight(500, 999), 499);
+        assert_eq!(proposal_type.conviction_weight(500, 1_000), 500);
+        assert_eq!(proposal_type.conviction_weight(500, 5_000), 500);
+        assert_eq!(proposal_type.conviction_weight(u64::MAX, 1_000), u64::MAX);
+        // Outside conviction mode every vote counts in full.
+        assert_eq!(ProposalType::default().conviction_weight(500, 0), 500);
+    }
+
+    #[test]
+    fn conviction_tally_decides_conviction_types() {
+        let mut proposal = proposal_with_votes(600, 400);
+        proposal.for_conviction = 100;
+        proposal.against_conviction = 400;
+        let proposal_type = ProposalType {
+            approval_threshold: 5_000,
+            conviction_mode: true,
+            max_conviction_slots: 1_000,
+            ..ProposalType::default()
+        };
+        assert!(proposal.check_succeeded(&proposal_type, 0).is_err());
+        assert!(proposal.check_executable(&proposal_type, 0, 1_000, false).is_err());
+        let raw = ProposalType { conviction_mode: false, ..proposal_type.clone() };
+        assert!(proposal.check_succeeded(&raw, 0).is_ok());
+        // Conviction types still need quorum on the scaled tally.
+        proposal.for_conviction = 600;
+        assert!(proposal.check_succeeded(&proposal_type, 1_000).is_ok());
+        assert!(proposal.check_succeeded(&proposal_type, 1_001).is_err());
+    }
+
+    #[test]
+    fn stake_payout_moves_penalties_to_winners() {
+        let mut proposal = proposal_with_votes(300, 100);
+        proposal.for_stake_weight = 300;
+        proposal.against_stake_weight = 100;
+        proposal.for_stake_penalties = 30;
+        proposal.against_stake_penalties = 10;
+        let vote = |support, weight| Vote {
+            governor: Pubkey::default(),
+            voter: Pubkey::default(),
+            proposal_id: 0,
+            support,
+            weight,
+            cast_slot: 0,
+            reward_ineligible: false,
+            reward_claimed: false,
+            bump: 0,
+            stake: 100,
+            stake_penalty: 10,
+            stake_claimed: false,
+            reason: String::new(),
+        };
+        assert_eq!(proposal.stake_payout(&vote(true, 200), true), 106);
+        assert_eq!(proposal.stake_payout(&vote(true, 100), true), 103);
+        assert_eq!(proposal.stake_payout(&vote(false, 100), true), 90);
+        assert_eq!(proposal.stake_payout(&vote(false, 100), false), 130);
+        assert_eq!(proposal.stake_payout(&vote(true, 300), false), 90);
+    }
+
+    #[test]
+    fn allowance_resets_each_window() {
+        let mut allowance = Allowance {
+            governor: Pubkey::new_unique(),
+            mint_or_native: TreasuryStats::NATIVE_MINT,
+            window_slots: 100,
+            budget: 500,
+            window_start: 0,
+            spent: 0,
+            bump: 0,
+        };
+        assert_eq!(allowance.spend(200, 10).unwrap(), 300);
+        assert!(allowance.spend(301, 99).is_err());
+        assert_eq!(allowance.spend(500, 100).unwrap(), 0);
+
+        allowance.budget = 0;
+        assert!(allowance.spend(1, 250).is_err());
+    }
+
+    #[test]
+    fn execution_tally_clamps_keeping_ratio() {
+        let proposal = proposal_with_votes(1_500, 500);
+        assert_eq!(proposal.execution_tally(1_000, true).unwrap(), (750, 250));
+    }
+
+    #[test]
+    fn execution_tally_rejects_excess_without_clamping() {
+        let proposal = proposal_with_votes(1_500, 500);
+        assert!(proposal.execution_tally(1_000, false).is_err());
+        assert_eq!(proposal.execution_tally(2_000, false).unwrap(), (1_500, 500));
+    }
+
+    #[test]
+    fn stream_vests_linearly_until_end_or_cancel() {
+        let mut stream = PaymentStream {
+            governor: Pubkey::default(),
+            proposal: Pubkey::default(),
+            index: 0,
+            recipient: Pubkey::default(),
+            mint: Pubkey::default(),
+            escrow: Pubkey::default(),
+            total_amount: 1_000,
+            claimed: 0,
+            start_slot: 100,
+            end_slot: 200,
+            canceled_at: None,
+            bump: 0,
+            escrow_bump: 0,
+        };
+        assert_eq!(stream.vested_amount(50), 0);
+        assert_eq!(stream.vested_amount(125), 250);
+        assert_eq!(stream.vested_amount(500), 1_000);
+
+        stream.canceled_at = Some(150);
+        assert_eq!(stream.vested_amount(140), 400);
+        assert_eq!(stream.vested_amount(500), 500);
+    }
+
+    #[test]
+    fn program_upgrade_payload_fits_reserved_space() {
+        let key = Pubkey::new_unique();
+        let upgrade = ProposalInstruction::from_instruction(
+            0,
+            bpf_loader_upgradeable::upgrade(&key, &key, &key, &key),
+        );
+        assert_eq!(upgrade.size(), ProposalInstruction::PROGRAM_UPGRADE_LEN);
+    }
+
+    #[test]
+    fn execution_hook_call_matches_anchor_layout() {
+        let (hook, governor, proposal) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let args = execution_hook::CheckExecution {
+            proposal_id: 7,
+            index: 1,
+            program_id: system_program::ID,
+            instruction: vec![1, 2, 3],
+        };
+        let instruction = execution_hook::check_execution(hook, governor, proposal, &args).unwrap();
+        assert_eq!(instruction.program_id, hook);
+        assert_eq!(instruction.data[..8], [0xa6, 0x26, 0xed, 0xe9, 0x4b, 0xb7, 0x72, 0xd4]);
+        assert_eq!(execution_hook::CheckExecution::try_from_slice(&instruction.data[8..]).unwrap(), args);
+        assert!(instruction.accounts.iter().all(|meta| !meta.is_signer && !meta.is_writable));
+    }
+
+    #[test]
+    fn hashed_id_commits_to_governor_type_and_content() {
+        let governor = Pubkey::new_unique();
+        let (content, payload) = ([1u8; 32], [2u8; 32]);
+        let id = Proposal::hashed_id(&governor, 0, &content, &payload);
+        assert_eq!(id, Proposal::hashed_id(&governor, 0, &content, &payload));
+        let hash = keccak::hashv(&[governor.as_ref(), &[0], &content, &payload]).to_bytes();
+        assert_eq!(id.to_le_bytes(), hash[..8]);
+
+        assert_ne!(id, Proposal::hashed_id(&Pubkey::new_unique(), 0, &content, &payload));
+        assert_ne!(id, Proposal::hashed_id(&governor, 1, &content, &payload));
+        assert_ne!(id, Proposal::hashed_id(&governor, 0, &payload, &content));
+    }
+
+    #[test]
+    fn delegated_amount_matches_what_delegatees_count() {
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let delegation = Delegation {
+            governor: Pubkey::new_unique(),
+            delegator: Pubkey::new_unique(),
+            allocations: vec![
+                DelegationAllocation { delegatee: first, bps: 3_333 },
+                DelegationAllocation { delegatee: second, bps: 6_667 },
+            ],
+            bump: 0,
+        };
+        let amount = 1_001;
+        let delegated = delegation.delegated_amount(amount);
+        assert_eq!(delegated, delegation.votes_for(&first, amount) + delegation.votes_for(&second, amount));
+        // Rounding leaves the remainder with the delegator, never counting it twice.
+        assert_eq!(amount - delegated, 1);
+    }
+
+    #[test]
+    fn timelock_queues_within_bounds_and_releases_after_eta() {
+        let proposer = Pubkey::new_unique();
+        let mut timelock = Timelock {
+            base: Pubkey::new_unique(),
+            min_delay: 10,
+            max_delay: 100,
+            bump: 0,
+            vault_bump: 0,
+            proposers: vec![proposer],
+            operations: vec![],
+        };
+        assert!(timelock.queue(&proposer, [1; 32], 9, 0).is_err());
+        assert!(timelock.queue(&proposer, [1; 32], 101, 0).is_err());
+        assert!(timelock.queue(&Pubkey::new_unique(), [1; 32], 10, 0).is_err());
+        assert_eq!(timelock.queue(&proposer, [1; 32], 10, 5).unwrap(), 15);
+        assert!(timelock.queue(&proposer, [1; 32], 20, 5).is_err());
+
+        assert!(timelock.take_ready(&[1; 32], 14).is_err());
+        assert_eq!(timelock.take_ready(&[1; 32], 15).unwrap(), 15);
+        assert!(timelock.operations.is_empty());
+    }
+
+    #[test]
+    fn squads_action_payload_fits_reserved_space() {
+        let key = Pubkey::new_unique();
+        let instructions = squads_action_instructions(&key, 7, &key, &key, 0, vec![0xAB; 40]);
+        assert_eq!(
+            instructions[1].data[8..],
+            [7, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+        let size: usize = instructions
+            .into_iter()
+            .map(|instruction| ProposalInstruction::from_instruction(0, instruction).size())
+            .sum();
+        assert_eq!(size, ProposalInstruction::squads_action_len(40));
+    }
+
+    #[test]
+    fn squads_multisig_state_reads_membership() {
+        let member = Pubkey::new_unique();
+        let mut data = hashv(&[b"account:Multisig"]).to_bytes()[..8].to_vec();
+        data.extend_from_slice(&[0; 70]);
+        data.extend_from_slice(&5u64.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.extend_from_slice(&[0, 255]);
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.push(7);
+        data.extend_from_slice(member.as_ref());
+        data.push(3);
+        assert_eq!(squads_multisig_state(&data, &member), Some((5, 3)));
+        assert_eq!(squads_multisig_state(&data, &Pubkey::new_unique()), Some((5, 0)));
+
+        data[0] ^= 1;
+        assert_eq!(squads_multisig_state(&data, &member), None);
+    }
+
+    #[test]
+    fn buffer_authority_reads_loader_buffers() {
+        let authority = Pubkey::new_unique();
+        let mut data = vec![1, 0, 0, 0, 1];
+        data.extend_from_slice(authority.as_ref());
+        data.extend_from_slice(&[0xAA; 16]);
+        assert_eq!(buffer_authority(&data), Some(authority));
+
+        // No authority: the buffer is immutable.
+        data[4] = 0;
+        assert_eq!(buffer_authority(&data), None);
+
+        // ProgramData (variant 3) is not a buffer.
+        data[..5].copy_from_slice(&[3, 0, 0, 0, 1]);
+        assert_eq!(buffer_authority(&data), None);
+    }
+
+    #[test]
+    fn epoch_aligned_window_rounds_start_up_to_boundary() {
+        let epochs = EpochSchedule::custom(1_000, 1_000, false);
+        assert_eq!(epoch_aligned_window(&epochs, 1_001, 1_500), (2_000, 3_999));
+        assert_eq!(epoch_aligned_window(&epochs, 2_000, 1_000), (2_000, 2_999));
+        assert_eq!(epoch_aligned_window(&epochs, 2_000, 1_001), (2_000, 3_999));
+    }
+
+    #[test]
+    fn epoch_aligned_window_handles_warmup_epochs() {
+        // Warmup epochs double from 32 slots: [0, 32), [32, 96), [96, 224), ...
+        let epochs = EpochSchedule::custom(8_192, 8_192, true);
+        assert_eq!(epoch_aligned_window(&epochs, 40, 100), (96, 223));
+    }
+
+    #[test]
+    fn instruction_status_tracks_failures_until_run_or_skipped() {
+        let mut proposal = proposal_with_votes(0, 0);
+        assert_eq!(proposal.instruction_status(0), InstructionStatus::NotExecuted);
+        proposal.instruction_error_codes[0] = 1;
+        assert_eq!(proposal.instruction_status(0), InstructionStatus::Failed { error_code: 1 });
+
+        // A retry that succeeds marks it executed; the last error stays recorded.
+        proposal.executed_instructions = 1;
+        assert_eq!(proposal.instruction_status(0), InstructionStatus::Executed);
+        assert_eq!(proposal.instruction_error_codes[0], 1);
+
+        proposal.instruction_error_codes[1] = u64::from(ProgramError::InvalidArgument);
+        proposal.skipped_instructions = 0b10;
+        proposal.executed_instructions = 2;
+        assert_eq!(proposal.instruction_status(1), InstructionStatus::Skipped);
+    }
+}
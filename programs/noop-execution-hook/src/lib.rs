@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+
+declare_id!("EmCbhQik8Rgo8SpJBj9pYGsLYuTTMeUAXKSHqso7oFmC");
+
+/// Reference implementation of the agora-solana execution hook interface
+/// (`agora_solana::execution_hook`). It approves every payload instruction; a real
+/// hook would inspect the arguments and fail to veto.
+#[program]
+pub mod noop_execution_hook {
+    use super::*;
+
+    pub fn check_execution(
+        _ctx: Context<CheckExecution>,
+        _proposal_id: u64,
+        _index: u16,
+        _program_id: Pubkey,
+        _instruction: Vec<u8>,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct CheckExecution<'info> {
+    /// CHECK: The calling governor.
+    pub governor: UncheckedAccount<'info>,
+    /// CHECK: The proposal whose payload instruction is about to run.
+    pub proposal: UncheckedAccount<'info>,
+}
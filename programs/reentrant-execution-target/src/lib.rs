@@ -0,0 +1,42 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program::invoke;
+
+declare_id!("C6tj5bzgU9vby1eQjwp3bXZEPrRfArpsUJmkd9rWhvve");
+
+/// Malicious payload target for the agora-solana reentrancy tests. Run as a
+/// proposal instruction, it calls straight back into the governor with whatever
+/// instruction the proposal stored, which the governor must reject.
+#[program]
+pub mod reentrant_execution_target {
+    use super::*;
+
+    /// Invokes `target_program` with `data` and the remaining accounts, keeping
+    /// their signer and writable flags, so the governor authority's signature
+    /// carries over into the reentrant call.
+    pub fn reenter<'info>(ctx: Context<'_, '_, '_, 'info, Reenter<'info>>, data: Vec<u8>) -> Result<()> {
+        let instruction = Instruction {
+            program_id: ctx.accounts.target_program.key(),
+            accounts: ctx
+                .remaining_accounts
+                .iter()
+                .map(|info| AccountMeta {
+                    pubkey: *info.key,
+                    is_signer: info.is_signer,
+                    is_writable: info.is_writable,
+                })
+                .collect(),
+            data,
+        };
+        let mut infos = ctx.remaining_accounts.to_vec();
+        infos.push(ctx.accounts.target_program.to_account_info());
+        invoke(&instruction, &infos)?;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Reenter<'info> {
+    /// CHECK: The program called back into, normally the governor.
+    pub target_program: UncheckedAccount<'info>,
+}
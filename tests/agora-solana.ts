@@ -1,16 +1,4248 @@
 import * as anchor from "@coral-xyz/anchor";
-import { Program } from "@coral-xyz/anchor";
+import { Program, BN } from "@coral-xyz/anchor";
+import {
+  AddressLookupTableAccount,
+  AddressLookupTableProgram,
+  Keypair,
+  PublicKey,
+  SYSVAR_CLOCK_PUBKEY,
+  SYSVAR_RENT_PUBKEY,
+  SystemProgram,
+  Transaction,
+  TransactionInstruction,
+  TransactionMessage,
+  VersionedTransaction,
+} from "@solana/web3.js";
+import { assert } from "chai";
+import { createHash } from "crypto";
+import { keccak_256 } from "@noble/hashes/sha3";
+import { readFileSync } from "fs";
 import { AgoraSolana } from "../target/types/agora_solana";
+import { ReentrantExecutionTarget } from "../target/types/reentrant_execution_target";
+
+const TOKEN_PROGRAM_ID = new PublicKey(
+  "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"
+);
+const TOKEN_2022_PROGRAM_ID = new PublicKey(
+  "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb"
+);
+const ASSOCIATED_TOKEN_PROGRAM_ID = new PublicKey(
+  "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL"
+);
+const BPF_LOADER_UPGRADEABLE_ID = new PublicKey(
+  "BPFLoaderUpgradeab1e11111111111111111111111"
+);
+const MINT_SIZE = 82;
+const TOKEN_ACCOUNT_SIZE = 165;
 
 describe("agora-solana", () => {
   // Configure the client to use the local cluster.
-  anchor.setProvider(anchor.AnchorProvider.env());
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
 
   const program = anchor.workspace.AgoraSolana as Program<AgoraSolana>;
+  const payer = provider.wallet.publicKey;
+
+  // Minimal SPL token helpers, built from raw instructions.
+  async function createMint(
+    authority: PublicKey,
+    tokenProgram = TOKEN_PROGRAM_ID
+  ): Promise<PublicKey> {
+    const mint = Keypair.generate();
+    const lamports =
+      await provider.connection.getMinimumBalanceForRentExemption(MINT_SIZE);
+    // InitializeMint2: [20, decimals, mint_authority, freeze_authority option]
+    const data = Buffer.concat([
+      Buffer.from([20, 6]),
+      authority.toBuffer(),
+      Buffer.from([0]),
+      Buffer.alloc(32),
+    ]);
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: payer,
+          newAccountPubkey: mint.publicKey,
+          space: MINT_SIZE,
+          lamports,
+          programId: tokenProgram,
+        }),
+        new TransactionInstruction({
+          programId: tokenProgram,
+          keys: [{ pubkey: mint.publicKey, isSigner: false, isWritable: true }],
+          data,
+        })
+      ),
+      [mint]
+    );
+    return mint.publicKey;
+  }
+
+  // MintTo: [7, amount (u64 LE)], signed by the payer as mint authority.
+  function mintToInstruction(
+    mint: PublicKey,
+    account: PublicKey,
+    amount: number,
+    tokenProgram = TOKEN_PROGRAM_ID
+  ): TransactionInstruction {
+    const data = Buffer.alloc(9);
+    data.writeUInt8(7, 0);
+    data.writeBigUInt64LE(BigInt(amount), 1);
+    return new TransactionInstruction({
+      programId: tokenProgram,
+      keys: [
+        { pubkey: mint, isSigner: false, isWritable: true },
+        { pubkey: account, isSigner: false, isWritable: true },
+        { pubkey: payer, isSigner: true, isWritable: false },
+      ],
+      data,
+    });
+  }
+
+  async function createTokenAccount(
+    mint: PublicKey,
+    owner: PublicKey,
+    amount: number,
+    tokenProgram = TOKEN_PROGRAM_ID
+  ): Promise<PublicKey> {
+    const account = Keypair.generate();
+    const lamports =
+      await provider.connection.getMinimumBalanceForRentExemption(
+        TOKEN_ACCOUNT_SIZE
+      );
+    // InitializeAccount3: [18, owner]
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: payer,
+          newAccountPubkey: account.publicKey,
+          space: TOKEN_ACCOUNT_SIZE,
+          lamports,
+          programId: tokenProgram,
+        }),
+        new TransactionInstruction({
+          programId: tokenProgram,
+          keys: [
+            { pubkey: account.publicKey, isSigner: false, isWritable: true },
+            { pubkey: mint, isSigner: false, isWritable: false },
+          ],
+          data: Buffer.concat([Buffer.from([18]), owner.toBuffer()]),
+        }),
+        mintToInstruction(mint, account.publicKey, amount, tokenProgram)
+      ),
+      [account]
+    );
+    return account.publicKey;
+  }
+
+  function proposalType(name: string, quorum = 400, approvalThreshold = 5_000) {
+    return {
+      quorum,
+      approvalThreshold,
+      name,
+      module: null,
+      emergencyBypassAllowed: false,
+      binding: true,
+      endorsementPeriod: new BN(0),
+      endorsementQuorumBps: 0,
+      proposerThreshold: null,
+      riskTier: 0,
+      allowedPrograms: [],
+      revealPeriod: new BN(0),
+      quorumOnly: false,
+      spendingCap: [],
+      realtimeWeight: false,
+      gracePeriod: null,
+      convictionMode: false,
+      maxConvictionSlots: new BN(0),
+      removed: false,
+    };
+  }
+
+  async function initializeGovernor(
+    votingDelay = 10,
+    votingPeriod = 9_000,
+    timelockDelay = 0
+  ) {
+    const governor = Keypair.generate();
+    const governingMint = await createMint(payer);
+    const payerTokenAccount = await createTokenAccount(
+      governingMint,
+      payer,
+      1_000_000
+    );
+    await program.methods
+      .initialize(
+        new BN(votingDelay),
+        new BN(votingPeriod),
+        new BN(1),
+        new BN(timelockDelay),
+        [proposalType("standard")]
+      )
+      .accountsPartial({
+        governor: governor.publicKey,
+        admin: payer,
+        manager: payer,
+        guardian: payer,
+        governingMint,
+      })
+      .signers([governor])
+      .rpc();
+    await checkpointVotes(governor.publicKey, payerTokenAccount);
+    return { governor: governor.publicKey, governingMint, payerTokenAccount };
+  }
+
+  function encodeVec(name: string, items: any[]): Buffer {
+    const length = Buffer.alloc(4);
+    length.writeUInt32LE(items.length);
+    return Buffer.concat([
+      length,
+      ...items.map((item) => program.coder.types.encode(name, item)),
+    ]);
+  }
+
+  // Mirrors Proposal::compute_content_hash.
+  function contentHash(description: string, actions: any[], instructions: any[]): Buffer {
+    return createHash("sha256")
+      .update(description)
+      .update(encodeVec("ProposalAction", actions))
+      .update(encodeVec("ProposalInstruction", instructions))
+      .digest();
+  }
+
+  // Derives the ProposalDigest PDA of the given content.
+  function digestAddress(
+    governor: PublicKey,
+    description: string,
+    actions: any[] = [],
+    instructions: any[] = []
+  ): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("digest"), governor.toBuffer(), contentHash(description, actions, instructions)],
+      program.programId
+    )[0];
+  }
+
+  function voteAddress(governor: PublicKey, proposal: PublicKey, voter: PublicKey): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("vote"), governor.toBuffer(), proposal.toBuffer(), voter.toBuffer()],
+      program.programId
+    )[0];
+  }
+
+  // Mirrors Proposal::hashed_id.
+  function hashedProposalId(
+    governor: PublicKey,
+    proposalType: number,
+    description: string,
+    actions: any[] = [],
+    instructions: any[] = []
+  ): BN {
+    const payloadHash = createHash("sha256").update(encodeVec("ProposalInstruction", instructions)).digest();
+    const hash = keccak_256(
+      Buffer.concat([
+        governor.toBuffer(),
+        Buffer.from([proposalType]),
+        contentHash(description, actions, instructions),
+        payloadHash,
+      ])
+    );
+    return new BN(Buffer.from(hash.slice(0, 8)), "le");
+  }
+
+  function proposalAddress(governor: PublicKey, id: number | BN): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("proposal"),
+        governor.toBuffer(),
+        new BN(id).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+  }
+
+  async function draftDigestAddress(governor: PublicKey, proposal: PublicKey) {
+    const draft = await program.account.proposal.fetch(proposal);
+    return digestAddress(governor, draft.description, draft.actions, draft.instructions);
+  }
+
+  async function waitForSlot(slot: number) {
+    while ((await provider.connection.getSlot()) <= slot) {
+      await new Promise((resolve) => setTimeout(resolve, 400));
+    }
+  }
+
+  function delegationAddress(governor: PublicKey, delegator: PublicKey): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("delegation"), governor.toBuffer(), delegator.toBuffer()],
+      program.programId
+    )[0];
+  }
+
+  function checkpointsAddress(governor: PublicKey, owner: PublicKey): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("checkpoints"), governor.toBuffer(), owner.toBuffer()],
+      program.programId
+    )[0];
+  }
+
+  // Checkpoints the current weight of `owner` (the payer by default), counting the
+  // given (Delegation, token account) pairs. Votes are weighed by the checkpoint in
+  // force at the proposal's start block, so with no voting delay this has to run
+  // before the proposal is created.
+  async function checkpointVotes(
+    governor: PublicKey,
+    ownerTokenAccount: PublicKey | null,
+    owner: Keypair | null = null,
+    delegations: PublicKey[] = []
+  ) {
+    await program.methods
+      .checkpointVotes()
+      .accountsPartial({ governor, owner: owner ? owner.publicKey : payer, ownerTokenAccount })
+      .remainingAccounts(
+        delegations.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false }))
+      )
+      .signers(owner ? [owner] : [])
+      .rpc();
+  }
+
+  function authorityAddress(governor: PublicKey): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("governor-authority"), governor.toBuffer()],
+      program.programId
+    )[0];
+  }
+
+  // Creates proposal `id` carrying `instructions`, votes it through with the
+  // payer's tokens and queues it. The governor must have a zero voting delay.
+  async function queuePayloadProposal(
+    governor: PublicKey,
+    payerTokenAccount: PublicKey,
+    description: string,
+    instructions: any[],
+    id = 0
+  ): Promise<PublicKey> {
+    const proposal = proposalAddress(governor, id);
+    await program.methods
+      .createProposal(description, 0, [], null, null, instructions, null)
+      .accountsPartial({
+        governor,
+        proposal,
+        proposer: payer,
+        proposerTokenAccount: payerTokenAccount,
+        proposalDigest: digestAddress(governor, description, [], instructions),
+      })
+      .rpc();
+    await voteAndQueue(governor, payerTokenAccount, proposal, id);
+    return proposal;
+  }
+
+  // Votes proposal `id` through with the payer's tokens and queues it, registering
+  // its payload with `timelock` when the governor has one.
+  async function voteAndQueue(
+    governor: PublicKey,
+    payerTokenAccount: PublicKey,
+    proposal: PublicKey,
+    id = 0,
+    timelock: PublicKey | null = null
+  ) {
+    const vote = voteAddress(governor, proposal, payer);
+    await program.methods
+      .castVote(new BN(id), true)
+      .accountsPartial({
+        governor,
+        proposal,
+        vote,
+        voter: payer,
+        voterTokenAccount: payerTokenAccount,
+        voterCheckpoints: checkpointsAddress(governor, payer),
+      })
+      .rpc();
+    const { endBlock } = await program.account.proposal.fetch(proposal);
+    await waitForSlot(endBlock.toNumber());
+
+    await program.methods
+      .queueProposal(new BN(id))
+      .accountsPartial({ governor, proposal, payer, dependency: null, timelock })
+      .rpc();
+  }
+
+  // Runs payload instruction `index`; PDA signer flags are dropped from the outer
+  // transaction since the program signs for them.
+  function executeTransaction(
+    governor: PublicKey,
+    proposal: PublicKey,
+    index: number,
+    instruction: { programId: PublicKey; accounts: any[] }
+  ) {
+    return program.methods
+      .executeTransaction(new BN(0), index)
+      .accountsPartial({ governor, proposal, executor: payer, dependency: null })
+      .remainingAccounts([
+        ...instruction.accounts.map((meta) => ({ ...meta, isSigner: false })),
+        { pubkey: instruction.programId, isSigner: false, isWritable: false },
+      ])
+      .rpc();
+  }
+
+  // Queues proposal `id` carrying a single governor self-call and executes it.
+  async function governanceCall(
+    governor: PublicKey,
+    payerTokenAccount: PublicKey,
+    id: number,
+    description: string,
+    call: TransactionInstruction
+  ) {
+    const instruction = {
+      index: 0,
+      programId: call.programId,
+      accounts: call.keys,
+      data: call.data,
+      selfCall: true,
+      holdUpSlots: new BN(0),
+      kind: { invoke: {} },
+    };
+    const proposal = await queuePayloadProposal(
+      governor,
+      payerTokenAccount,
+      description,
+      [instruction],
+      id
+    );
+    await program.methods
+      .executeTransaction(new BN(id), 0)
+      .accountsPartial({ governor, proposal, executor: payer, dependency: null })
+      .remainingAccounts([
+        ...instruction.accounts.map((meta) => ({ ...meta, isSigner: false })),
+        { pubkey: instruction.programId, isSigner: false, isWritable: false },
+      ])
+      .rpc();
+  }
+
+  async function expectError(promise: Promise<unknown>, code: string) {
+    try {
+      await promise;
+      assert.fail(`expected ${code}`);
+    } catch (err) {
+      assert.include(String(err), code);
+    }
+  }
+
+  describe("aggregate votes", () => {
+    it("sums the weight of a coalition", async () => {
+      const { governor, governingMint, payerTokenAccount } = await initializeGovernor();
+      const member = Keypair.generate().publicKey;
+      const memberTokenAccount = await createTokenAccount(governingMint, member, 500_000);
+      const readonly = (pubkey: PublicKey) => ({ pubkey, isSigner: false, isWritable: false });
+      // Each account's token account, then its (possibly absent) Delegation PDA.
+      const accountsOf = (owner: PublicKey, tokenAccount: PublicKey) => [
+        readonly(tokenAccount),
+        readonly(delegationAddress(governor, owner)),
+      ];
+
+      const votes = await program.methods
+        .getAggregateVotes([payer, member])
+        .accountsPartial({ governor })
+        .remainingAccounts([
+          ...accountsOf(payer, payerTokenAccount),
+          ...accountsOf(member, memberTokenAccount),
+        ])
+        .view();
+      assert.equal(votes.toNumber(), 1_500_000);
+
+      await expectError(
+        program.methods
+          .getAggregateVotes([payer, payer])
+          .accountsPartial({ governor })
+          .remainingAccounts([
+            ...accountsOf(payer, payerTokenAccount),
+            ...accountsOf(payer, payerTokenAccount),
+          ])
+          .view(),
+        "InvalidAggregateAccounts"
+      );
+    });
+  });
+
+  describe("voting without a token account", () => {
+    it("rejects a voter with no voting power cleanly", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0);
+      const member = Keypair.generate();
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: payer,
+            toPubkey: member.publicKey,
+            lamports: 100_000_000,
+          })
+        )
+      );
+      const description = "vote without tokens";
+      const proposal = proposalAddress(governor, 0);
+      await program.methods
+        .createProposal(description, 0, [], null, null, [], null)
+        .accountsPartial({
+          governor,
+          proposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, description),
+        })
+        .rpc();
+
+      const vote = voteAddress(governor, proposal, member.publicKey);
+      await expectError(
+        program.methods
+          .castVote(new BN(0), true)
+          .accountsPartial({
+            governor,
+            proposal,
+            vote,
+            voter: member.publicKey,
+            voterTokenAccount: null,
+            voterCheckpoints: null,
+          })
+          .signers([member])
+          .rpc(),
+        "InsufficientVotingPower"
+      );
+    });
+  });
+
+  describe("hashed proposal ids", () => {
+    it("numbers proposals by their content once enabled", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const actions = [{ parameterChange: { key: { hashedProposalIds: {} }, value: new BN(1) } }];
+      const enable = proposalAddress(governor, 0);
+      await program.methods
+        .createProposal("hash proposal ids", 0, actions, null, null, [], null)
+        .accountsPartial({
+          governor,
+          proposal: enable,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, "hash proposal ids", actions),
+        })
+        .rpc();
+      await voteAndQueue(governor, payerTokenAccount, enable);
+      await program.methods
+        .executeProposal(new BN(0))
+        .accountsPartial({ governor, proposal: enable, executor: payer, dependency: null })
+        .rpc();
+
+      const description = "content-addressed";
+      const id = hashedProposalId(governor, 0, description);
+      const proposal = proposalAddress(governor, id);
+      const create = () =>
+        program.methods
+          .createHashedProposal(id, description, 0, [], [], null)
+          .accountsPartial({
+            governor,
+            proposal,
+            proposer: payer,
+            proposerTokenAccount: payerTokenAccount,
+            proposalDigest: digestAddress(governor, description),
+          })
+          .rpc();
+      await create();
+      const account = await program.account.proposal.fetch(proposal);
+      assert.ok(account.id.eq(id));
+      assert.equal(account.description, description);
+
+      await expectError(create(), "ProposalIdTaken");
+      await expectError(
+        program.methods
+          .createProposal("sequential", 0, [], null, null, [], null)
+          .accountsPartial({
+            governor,
+            proposal: proposalAddress(governor, 2),
+            proposer: payer,
+            proposerTokenAccount: payerTokenAccount,
+            proposalDigest: digestAddress(governor, "sequential"),
+          })
+          .rpc(),
+        "ProposalIdModeMismatch"
+      );
+      await expectError(
+        program.methods
+          .createProposalDraft("sequential draft", 0, [], null, null)
+          .accountsPartial({ governor, proposal: proposalAddress(governor, 2), proposer: payer })
+          .rpc(),
+        "ProposalIdModeMismatch"
+      );
+    });
+  });
+
+  describe("vote reasons", () => {
+    function castWithReason(governor: PublicKey, proposal: PublicKey, id: number, payerTokenAccount: PublicKey, reason: string) {
+      const vote = voteAddress(governor, proposal, payer);
+      const sent = program.methods
+        .castVoteWithReason(new BN(id), true, reason)
+        .accountsPartial({
+          governor,
+          proposal,
+          vote,
+          voter: payer,
+          voterTokenAccount: payerTokenAccount,
+          voterCheckpoints: checkpointsAddress(governor, payer),
+        })
+        .rpc();
+      return { vote, sent };
+    }
+
+    it("keeps reasons in logs only until store_reasons is set", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const actions = [{ parameterChange: { key: { storeReasons: {} }, value: new BN(1) } }];
+      const description = "store vote reasons";
+      const first = proposalAddress(governor, 0);
+      await program.methods
+        .createProposal(description, 0, actions, null, null, [], null)
+        .accountsPartial({
+          governor,
+          proposal: first,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, description, actions),
+        })
+        .rpc();
+
+      await expectError(
+        castWithReason(governor, first, 0, payerTokenAccount, "x".repeat(281)).sent,
+        "ReasonTooLong"
+      );
+      const logged = castWithReason(governor, first, 0, payerTokenAccount, "worth the storage");
+      await logged.sent;
+      let vote = await program.account.vote.fetch(logged.vote);
+      assert.equal(vote.reason, "");
+
+      const { endBlock } = await program.account.proposal.fetch(first);
+      await waitForSlot(endBlock.toNumber());
+      await program.methods
+        .queueProposal(new BN(0))
+        .accountsPartial({ governor, proposal: first, payer, dependency: null, timelock: null })
+        .rpc();
+      await program.methods
+        .executeProposal(new BN(0))
+        .accountsPartial({ governor, proposal: first, executor: payer, dependency: null })
+        .rpc();
+      assert.ok((await program.account.governor.fetch(governor)).storeReasons);
+
+      const second = proposalAddress(governor, 1);
+      await program.methods
+        .createProposal("stored rationale", 0, [], null, null, [], null)
+        .accountsPartial({
+          governor,
+          proposal: second,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, "stored rationale"),
+        })
+        .rpc();
+      const stored = castWithReason(governor, second, 1, payerTokenAccount, "on chain for good");
+      await stored.sent;
+      vote = await program.account.vote.fetch(stored.vote);
+      assert.equal(vote.reason, "on chain for good");
+    });
+  });
+
+  describe("vote stakes", () => {
+    it("pays losing voters' penalties to the winners", async () => {
+      const { governor, governingMint, payerTokenAccount } = await initializeGovernor(0, 5);
+      const actions = [
+        { parameterChange: { key: { loserPenaltyBps: {} }, value: new BN(1_000) } },
+        { parameterChange: { key: { voteStakeLamports: {} }, value: new BN(10_000_000) } },
+      ];
+      const setup = proposalAddress(governor, 0);
+      await program.methods
+        .createProposal("stake votes", 0, actions, null, null, [], null)
+        .accountsPartial({
+          governor,
+          proposal: setup,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, "stake votes", actions),
+        })
+        .rpc();
+      await voteAndQueue(governor, payerTokenAccount, setup);
+      await program.methods
+        .executeProposal(new BN(0))
+        .accountsPartial({ governor, proposal: setup, executor: payer, dependency: null })
+        .rpc();
+
+      const member = Keypair.generate();
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({ fromPubkey: payer, toPubkey: member.publicKey, lamports: 100_000_000 })
+        )
+      );
+      const memberTokenAccount = await createTokenAccount(governingMint, member.publicKey, 100_000);
+      await checkpointVotes(governor, memberTokenAccount, member);
+      const description = "contested";
+      const proposal = proposalAddress(governor, 1);
+      await program.methods
+        .createProposal(description, 0, [], null, null, [], null)
+        .accountsPartial({
+          governor,
+          proposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, description),
+        })
+        .rpc();
+      const winner = voteAddress(governor, proposal, payer);
+      const loser = voteAddress(governor, proposal, member.publicKey);
+      await program.methods
+        .castVote(new BN(1), true)
+        .accountsPartial({
+          governor,
+          proposal,
+          vote: winner,
+          voter: payer,
+          voterTokenAccount: payerTokenAccount,
+          voterCheckpoints: checkpointsAddress(governor, payer),
+        })
+        .rpc();
+      await program.methods
+        .castVote(new BN(1), false)
+        .accountsPartial({
+          governor,
+          proposal,
+          vote: loser,
+          voter: member.publicKey,
+          voterTokenAccount: memberTokenAccount,
+          voterCheckpoints: checkpointsAddress(governor, member.publicKey),
+        })
+        .signers([member])
+        .rpc();
+      const claim = (vote: PublicKey, voter: Keypair | null) =>
+        program.methods
+          .claimVoteStake(new BN(1))
+          .accountsPartial({ governor, proposal, vote, voter: voter ? voter.publicKey : payer })
+          .signers(voter ? [voter] : [])
+          .rpc();
+      await expectError(claim(loser, member), "OutcomePending");
+
+      const { endBlock } = await program.account.proposal.fetch(proposal);
+      await waitForSlot(endBlock.toNumber());
+      await program.methods
+        .finalizeProposal(new BN(1))
+        .accountsPartial({ governor, proposal, proposalDigest: digestAddress(governor, description), payer })
+        .rpc();
+
+      const before = await provider.connection.getBalance(member.publicKey);
+      await claim(loser, member);
+      assert.equal((await provider.connection.getBalance(member.publicKey)) - before, 9_000_000);
+      const held = await provider.connection.getBalance(proposal);
+      await claim(winner, null);
+      assert.equal(held - (await provider.connection.getBalance(proposal)), 11_000_000);
+      await expectError(claim(winner, null), "StakeAlreadyClaimed");
+      assert.equal((await program.account.proposal.fetch(proposal)).unclaimedStakes, 0);
+    });
+
+    it("refunds stakes in full once a proposal expires", async () => {
+      const { governor, governingMint, payerTokenAccount } = await initializeGovernor(0, 5);
+      const actions = [
+        { parameterChange: { key: { loserPenaltyBps: {} }, value: new BN(1_000) } },
+        { parameterChange: { key: { voteStakeLamports: {} }, value: new BN(10_000_000) } },
+        { parameterChange: { key: { votingPeriod: {} }, value: new BN(9_000) } },
+      ];
+      const setup = proposalAddress(governor, 0);
+      await program.methods
+        .createProposal("stake votes", 0, actions, null, null, [], null)
+        .accountsPartial({
+          governor,
+          proposal: setup,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, "stake votes", actions),
+        })
+        .rpc();
+      await voteAndQueue(governor, payerTokenAccount, setup);
+      await program.methods
+        .executeProposal(new BN(0))
+        .accountsPartial({ governor, proposal: setup, executor: payer, dependency: null })
+        .rpc();
+
+      const member = Keypair.generate();
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({ fromPubkey: payer, toPubkey: member.publicKey, lamports: 100_000_000 })
+        )
+      );
+      const memberTokenAccount = await createTokenAccount(governingMint, member.publicKey, 100_000);
+      await checkpointVotes(governor, memberTokenAccount, member);
+      // A 100% quorum: every token has to vote.
+      const description = "everyone must vote";
+      const proposal = proposalAddress(governor, 1);
+      await program.methods
+        .createProposal(description, 0, [], null, null, [], 10_000)
+        .accountsPartial({
+          governor,
+          proposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, description),
+        })
+        .rpc();
+      const memberVote = voteAddress(governor, proposal, member.publicKey);
+      await program.methods
+        .castVote(new BN(1), false)
+        .accountsPartial({
+          governor,
+          proposal,
+          vote: memberVote,
+          voter: member.publicKey,
+          voterTokenAccount: memberTokenAccount,
+          voterCheckpoints: checkpointsAddress(governor, member.publicKey),
+        })
+        .signers([member])
+        .rpc();
+
+      // The payer moves one token away before voting, so its checkpointed
+      // 1_000_000 is spent for 999_999 votes and the quorum is out of reach.
+      const elsewhere = await createTokenAccount(governingMint, Keypair.generate().publicKey, 0);
+      // Transfer: [3, amount]
+      const data = Buffer.alloc(9);
+      data.writeUInt8(3, 0);
+      data.writeBigUInt64LE(BigInt(1), 1);
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          new TransactionInstruction({
+            programId: TOKEN_PROGRAM_ID,
+            keys: [
+              { pubkey: payerTokenAccount, isSigner: false, isWritable: true },
+              { pubkey: elsewhere, isSigner: false, isWritable: true },
+              { pubkey: payer, isSigner: true, isWritable: false },
+            ],
+            data,
+          })
+        )
+      );
+      const payerVote = voteAddress(governor, proposal, payer);
+      await program.methods
+        .castVote(new BN(1), true)
+        .accountsPartial({
+          governor,
+          proposal,
+          vote: payerVote,
+          voter: payer,
+          voterTokenAccount: payerTokenAccount,
+          voterCheckpoints: checkpointsAddress(governor, payer),
+        })
+        .rpc();
+      await program.methods.expireProposal(new BN(1)).accountsPartial({ governor, proposal }).rpc();
+      assert.ok((await program.account.proposal.fetch(proposal)).state.expired);
+
+      const claim = (vote: PublicKey, voter: Keypair | null) =>
+        program.methods
+          .claimVoteStake(new BN(1))
+          .accountsPartial({ governor, proposal, vote, voter: voter ? voter.publicKey : payer })
+          .signers(voter ? [voter] : [])
+          .rpc();
+      const before = await provider.connection.getBalance(member.publicKey);
+      await claim(memberVote, member);
+      assert.equal((await provider.connection.getBalance(member.publicKey)) - before, 10_000_000);
+      const held = await provider.connection.getBalance(proposal);
+      await claim(payerVote, null);
+      assert.equal(held - (await provider.connection.getBalance(proposal)), 10_000_000);
+      assert.equal((await program.account.proposal.fetch(proposal)).unclaimedStakes, 0);
+    });
+  });
+
+  describe("delegation", () => {
+    it("counts delegated power for the delegatee only", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0);
+      const delegatee = Keypair.generate();
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: payer,
+            toPubkey: delegatee.publicKey,
+            lamports: 100_000_000,
+          })
+        )
+      );
+      await program.methods
+        .delegate(delegatee.publicKey)
+        .accountsPartial({ governor, delegator: payer })
+        .rpc();
+      await checkpointVotes(governor, null, delegatee, [
+        delegationAddress(governor, payer),
+        payerTokenAccount,
+      ]);
+      const proposal = proposalAddress(governor, 0);
+      await program.methods
+        .createProposal("delegate then vote", 0, [], null, null, [], null)
+        .accountsPartial({
+          governor,
+          proposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, "delegate then vote"),
+        })
+        .rpc();
+
+      // The delegator still holds the tokens but votes with none of them: its
+      // checkpoint from before delegating is capped at its current weight.
+      const direct = voteAddress(governor, proposal, payer);
+      await program.methods
+        .castVote(new BN(0), true)
+        .accountsPartial({
+          governor,
+          proposal,
+          vote: direct,
+          voter: payer,
+          voterTokenAccount: payerTokenAccount,
+          voterCheckpoints: checkpointsAddress(governor, payer),
+        })
+        .rpc();
+      assert.equal((await program.account.vote.fetch(direct)).weight.toNumber(), 0);
+
+      const delegated = voteAddress(governor, proposal, delegatee.publicKey);
+      await program.methods
+        .castVote(new BN(0), true)
+        .accountsPartial({
+          governor,
+          proposal,
+          vote: delegated,
+          voter: delegatee.publicKey,
+          voterTokenAccount: null,
+          voterCheckpoints: checkpointsAddress(governor, delegatee.publicKey),
+        })
+        .remainingAccounts([
+          { pubkey: delegationAddress(governor, payer), isSigner: false, isWritable: false },
+          { pubkey: payerTokenAccount, isSigner: false, isWritable: false },
+        ])
+        .signers([delegatee])
+        .rpc();
+      assert.equal(
+        (await program.account.vote.fetch(delegated)).weight.toNumber(),
+        1_000_000
+      );
+    });
+  });
+
+  describe("draft payload upload", () => {
+    it("uploads a payload over several transactions", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor();
+      const proposal = proposalAddress(governor, 0);
+
+      await program.methods
+        .createProposalDraft("multi-transaction payload", 0, [], null, null)
+        .accountsPartial({
+          governor,
+          proposal: proposal,
+          proposer: payer,
+        })
+        .rpc();
+
+      const proposalId = new BN(0);
+      const addInstruction = (index: number) =>
+        program.methods
+          .addProposalInstruction(
+            proposalId,
+            index,
+            SystemProgram.programId,
+            [{ pubkey: payer, isSigner: false, isWritable: true }],
+            Buffer.alloc(64, index),
+            false,
+            new BN(0),
+            { invoke: {} }
+          )
+          .accountsPartial({ governor, proposal: proposal, proposer: payer })
+          .rpc();
+
+      for (let index = 0; index < 3; index++) {
+        await addInstruction(index);
+      }
+      await expectError(addInstruction(1), "DuplicateInstructionIndex");
+      await expectError(addInstruction(5), "InstructionIndexOutOfOrder");
+
+      let account = await program.account.proposal.fetch(proposal);
+      assert.equal(account.instructions.length, 3);
+      assert.deepEqual(
+        account.instructions.map((ix) => ix.index),
+        [0, 1, 2]
+      );
+
+      await program.methods
+        .activateProposal(proposalId)
+        .accountsPartial({
+          governor,
+          proposal: proposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: await draftDigestAddress(governor, proposal),
+        })
+        .rpc();
+
+      account = await program.account.proposal.fetch(proposal);
+      assert.notDeepEqual(account.contentHash, new Array(32).fill(0));
+      await expectError(addInstruction(3), "ProposalNotDraft");
+    });
+
+    it("compacts indices on removal and freezes the payload on activation", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor();
+      const proposal = proposalAddress(governor, 0);
+      const proposalId = new BN(0);
+      const accounts = { governor, proposal: proposal, proposer: payer };
+
+      await program.methods
+        .createProposalDraft("editable payload", 0, [], null, null)
+        .accountsPartial(accounts)
+        .rpc();
+      for (let index = 0; index < 3; index++) {
+        await program.methods
+          .addProposalInstruction(
+            proposalId,
+            index,
+            SystemProgram.programId,
+            [],
+            Buffer.from([index]),
+            false,
+            new BN(0),
+            { invoke: {} }
+          )
+          .accountsPartial(accounts)
+          .rpc();
+      }
+
+      await program.methods
+        .removeProposalInstruction(proposalId, 1)
+        .accountsPartial(accounts)
+        .rpc();
+      let account = await program.account.proposal.fetch(proposal);
+      assert.deepEqual(
+        account.instructions.map((ix) => [ix.index, ix.data[0]]),
+        [
+          [0, 0],
+          [1, 2],
+        ]
+      );
+
+      await program.methods
+        .replaceProposalInstruction(
+          proposalId,
+          0,
+          SystemProgram.programId,
+          [],
+          Buffer.from([9, 9]),
+          false,
+          new BN(0),
+          { invoke: {} }
+        )
+        .accountsPartial(accounts)
+        .rpc();
+      account = await program.account.proposal.fetch(proposal);
+      assert.deepEqual([...account.instructions[0].data], [9, 9]);
+
+      await program.methods
+        .activateProposal(proposalId)
+        .accountsPartial({
+          ...accounts,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: await draftDigestAddress(governor, proposal),
+        })
+        .rpc();
+      await expectError(
+        program.methods
+          .removeProposalInstruction(proposalId, 0)
+          .accountsPartial(accounts)
+          .rpc(),
+        "ProposalNotDraft"
+      );
+      await expectError(
+        program.methods
+          .replaceProposalInstruction(proposalId, 0, SystemProgram.programId, [], Buffer.from([1]), false, new BN(0), { invoke: {} })
+          .accountsPartial(accounts)
+          .rpc(),
+        "ProposalNotDraft"
+      );
+    });
+  });
+
+  describe("zero-copy proposal actions", () => {
+    it("runs actions beyond the inline limit from a ProposalActions account", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const proposal = proposalAddress(governor, 0);
+      const proposalId = new BN(0);
+      const [proposalActions] = PublicKey.findProgramAddressSync(
+        [Buffer.from("proposal-actions"), proposal.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .createProposalDraft("many actions", 0, [], null, null)
+        .accountsPartial({ governor, proposal, proposer: payer })
+        .rpc();
+      await program.methods
+        .createProposalActions(proposalId)
+        .accountsPartial({ governor, proposal, proposalActions, proposer: payer })
+        .rpc();
+      // Twelve settings changes, more than the eight a proposal holds inline; the
+      // last voting delay wins.
+      const actions = Array.from({ length: 12 }, (_, delay) => ({
+        parameterChange: { key: { votingDelay: {} }, value: new BN(delay) },
+      }));
+      for (const chunk of [actions.slice(0, 6), actions.slice(6)]) {
+        await program.methods
+          .appendProposalActions(proposalId, chunk)
+          .accountsPartial({ governor, proposal, proposalActions, proposer: payer })
+          .rpc();
+      }
+      await expectError(
+        program.methods
+          .addProposalInstruction(
+            proposalId,
+            0,
+            SystemProgram.programId,
+            [],
+            Buffer.alloc(0),
+            false,
+            new BN(0),
+            { invoke: {} }
+          )
+          .accountsPartial({ governor, proposal, proposer: payer })
+          .rpc(),
+        "ExternalActionsWithPayload"
+      );
+
+      const stored = await program.account.proposalActions.fetch(proposalActions);
+      assert.equal(stored.len, 12);
+      assert.equal((await program.account.proposal.fetch(proposal)).externalActions, 12);
+
+      await program.methods
+        .activateProposal(proposalId)
+        .accountsPartial({
+          governor,
+          proposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: await draftDigestAddress(governor, proposal),
+        })
+        .rpc();
+      await expectError(
+        program.methods
+          .appendProposalActions(proposalId, actions.slice(0, 1))
+          .accountsPartial({ governor, proposal, proposalActions, proposer: payer })
+          .rpc(),
+        "ProposalNotDraft"
+      );
+      await voteAndQueue(governor, payerTokenAccount, proposal);
+
+      await expectError(
+        program.methods
+          .executeProposal(proposalId)
+          .accountsPartial({ governor, proposal, executor: payer, dependency: null })
+          .rpc(),
+        "ProposalActionsRequired"
+      );
+      await program.methods
+        .executeProposal(proposalId)
+        .accountsPartial({ governor, proposal, executor: payer, dependency: null, proposalActions })
+        .rpc();
+
+      const account = await program.account.governor.fetch(governor);
+      assert.equal(account.votingDelay.toNumber(), 11);
+      assert.ok((await program.account.proposal.fetch(proposal)).executed);
+    });
+  });
+
+  describe("proposal dependencies", () => {
+    // Creates a proposal, votes it through with the payer's tokens and waits for
+    // its voting period to end.
+    async function passProposal(
+      governor: PublicKey,
+      payerTokenAccount: PublicKey,
+      id: number,
+      dependsOn: number | null
+    ): Promise<PublicKey> {
+      const proposal = proposalAddress(governor, id);
+      await program.methods
+        .createProposal(
+          `proposal ${id}`,
+          0,
+          [],
+          null,
+          dependsOn === null ? null : new BN(dependsOn),
+          [],
+          null
+        )
+        .accountsPartial({
+          governor,
+          proposal: proposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, `proposal ${id}`),
+        })
+        .rpc();
+
+      const vote = voteAddress(governor, proposal, payer);
+      await program.methods
+        .castVote(new BN(id), true)
+        .accountsPartial({
+          governor,
+          proposal: proposal,
+          vote,
+          voter: payer,
+          voterTokenAccount: payerTokenAccount,
+          voterCheckpoints: checkpointsAddress(governor, payer),
+        })
+        .rpc();
+
+      const account = await program.account.proposal.fetch(proposal);
+      await waitForSlot(account.endBlock.toNumber());
+      return proposal;
+    }
+
+    it("queues a dependent proposal once its dependency has executed", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const dependency = await passProposal(governor, payerTokenAccount, 0, null);
+      const dependent = await passProposal(governor, payerTokenAccount, 1, 0);
+
+      await program.methods
+        .queueProposal(new BN(0))
+        .accountsPartial({ governor, proposal: dependency, payer, dependency: null })
+        .rpc();
+      await program.methods
+        .executeProposal(new BN(0))
+        .accountsPartial({ governor, proposal: dependency, executor: payer, dependency: null })
+        .rpc();
+
+      await program.methods
+        .queueProposal(new BN(1))
+        .accountsPartial({ governor, proposal: dependent, payer, dependency })
+        .rpc();
+      const account = await program.account.proposal.fetch(dependent);
+      assert.equal(account.dependsOn.toNumber(), 0);
+      assert.ok(account.state.queued);
+
+      const history = await program.methods
+        .getHistory(0, 10)
+        .accountsPartial({ governor })
+        .view();
+      assert.equal(history.length, 1);
+      assert.equal(history[0].proposalId.toNumber(), 0);
+      assert.ok(history[0].outcome.executed);
+    });
+
+    it("rejects a dependent proposal while its dependency has not executed", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const dependency = await passProposal(governor, payerTokenAccount, 0, null);
+      const dependent = await passProposal(governor, payerTokenAccount, 1, 0);
+
+      await expectError(
+        program.methods
+          .queueProposal(new BN(1))
+          .accountsPartial({ governor, proposal: dependent, payer, dependency })
+          .rpc(),
+        "DependencyNotExecuted"
+      );
+      await expectError(
+        program.methods
+          .queueProposal(new BN(1))
+          .accountsPartial({ governor, proposal: dependent, payer, dependency: null })
+          .rpc(),
+        "InvalidDependency"
+      );
+    });
+  });
+
+  describe("governor authority", () => {
+    it("signs an spl-token transfer out of a vault it owns", async () => {
+      const { governor, governingMint, payerTokenAccount } =
+        await initializeGovernor(0, 5);
+      const authority = authorityAddress(governor);
+      const vault = await createTokenAccount(governingMint, authority, 500);
+      const recipient = await createTokenAccount(governingMint, payer, 0);
+
+      // Transfer: [3, amount (u64 LE)]
+      const data = Buffer.alloc(9);
+      data.writeUInt8(3, 0);
+      data.writeBigUInt64LE(BigInt(200), 1);
+      const instruction = {
+        index: 0,
+        programId: TOKEN_PROGRAM_ID,
+        accounts: [
+          { pubkey: vault, isSigner: false, isWritable: true },
+          { pubkey: recipient, isSigner: false, isWritable: true },
+          { pubkey: authority, isSigner: true, isWritable: false },
+        ],
+        data,
+        selfCall: false,
+        holdUpSlots: new BN(0),
+        kind: { invoke: {} },
+      };
+
+      const proposal = await queuePayloadProposal(
+        governor,
+        payerTokenAccount,
+        "pay 200 from the vault",
+        [instruction]
+      );
+      await executeTransaction(governor, proposal, 0, instruction);
+
+      const balance = await provider.connection.getTokenAccountBalance(recipient);
+      assert.equal(balance.value.amount, "200");
+      const account = await program.account.proposal.fetch(proposal);
+      assert.ok(account.executed);
+    });
+  });
+
+  describe("treasury transfers", () => {
+    it("pays SOL out of the treasury through a passed proposal", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const [treasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), governor.toBuffer()],
+        program.programId
+      );
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: payer,
+            toPubkey: treasury,
+            lamports: 2_000_000_000,
+          })
+        )
+      );
+
+      const recipient = Keypair.generate().publicKey;
+      const transfer = SystemProgram.transfer({
+        fromPubkey: treasury,
+        toPubkey: recipient,
+        lamports: 1_000_000_000,
+      });
+      const instruction = {
+        index: 0,
+        programId: transfer.programId,
+        accounts: transfer.keys,
+        data: transfer.data,
+        selfCall: false,
+        holdUpSlots: new BN(0),
+        kind: { invoke: {} },
+      };
+
+      const proposal = await queuePayloadProposal(
+        governor,
+        payerTokenAccount,
+        "send 1 SOL",
+        [instruction]
+      );
+      await executeTransaction(governor, proposal, 0, instruction);
+
+      assert.equal(await provider.connection.getBalance(recipient), 1_000_000_000);
+      const account = await program.account.proposal.fetch(proposal);
+      assert.ok(account.executed);
+    });
+
+    it("pays SPL and token-2022 tokens out of treasury token accounts", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const authority = authorityAddress(governor);
+
+      const instructions = [];
+      const recipients = [];
+      for (const tokenProgram of [TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID]) {
+        const mint = await createMint(payer, tokenProgram);
+        const [vault] = PublicKey.findProgramAddressSync(
+          [authority.toBuffer(), tokenProgram.toBuffer(), mint.toBuffer()],
+          ASSOCIATED_TOKEN_PROGRAM_ID
+        );
+        await program.methods
+          .createTreasuryTokenAccount()
+          .accountsPartial({
+            governor,
+            governorAuthority: authority,
+            mint,
+            treasuryTokenAccount: vault,
+            payer,
+            tokenProgram,
+          })
+          .rpc();
+        await provider.sendAndConfirm(
+          new Transaction().add(mintToInstruction(mint, vault, 1_000, tokenProgram))
+        );
+        const recipient = await createTokenAccount(mint, payer, 0, tokenProgram);
+        recipients.push(recipient);
+
+        // Transfer: [3, amount (u64 LE)]
+        const data = Buffer.alloc(9);
+        data.writeUInt8(3, 0);
+        data.writeBigUInt64LE(BigInt(400), 1);
+        instructions.push({
+          index: instructions.length,
+          programId: tokenProgram,
+          accounts: [
+            { pubkey: vault, isSigner: false, isWritable: true },
+            { pubkey: recipient, isSigner: false, isWritable: true },
+            { pubkey: authority, isSigner: true, isWritable: false },
+          ],
+          data,
+          selfCall: false,
+          holdUpSlots: new BN(0),
+          kind: { invoke: {} },
+        });
+      }
+
+      const proposal = await queuePayloadProposal(
+        governor,
+        payerTokenAccount,
+        "pay 400 of each token",
+        instructions
+      );
+      for (const [index, instruction] of instructions.entries()) {
+        await executeTransaction(governor, proposal, index, instruction);
+      }
+
+      for (const recipient of recipients) {
+        const balance = await provider.connection.getTokenAccountBalance(recipient);
+        assert.equal(balance.value.amount, "400");
+      }
+    });
+
+    it("rejects token transfers from accounts the authority does not own", async () => {
+      const { governor, governingMint, payerTokenAccount } =
+        await initializeGovernor(0, 5);
+      const authority = authorityAddress(governor);
+      const victim = await createTokenAccount(governingMint, payer, 500);
+      const recipient = await createTokenAccount(governingMint, payer, 0);
+
+      // Approve: [4, amount (u64 LE)], delegating the victim's tokens to the authority.
+      const approve = Buffer.alloc(9);
+      approve.writeUInt8(4, 0);
+      approve.writeBigUInt64LE(BigInt(500), 1);
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          new TransactionInstruction({
+            programId: TOKEN_PROGRAM_ID,
+            keys: [
+              { pubkey: victim, isSigner: false, isWritable: true },
+              { pubkey: authority, isSigner: false, isWritable: false },
+              { pubkey: payer, isSigner: true, isWritable: false },
+            ],
+            data: approve,
+          })
+        )
+      );
+
+      const data = Buffer.alloc(9);
+      data.writeUInt8(3, 0);
+      data.writeBigUInt64LE(BigInt(500), 1);
+      const instruction = {
+        index: 0,
+        programId: TOKEN_PROGRAM_ID,
+        accounts: [
+          { pubkey: victim, isSigner: false, isWritable: true },
+          { pubkey: recipient, isSigner: false, isWritable: true },
+          { pubkey: authority, isSigner: true, isWritable: false },
+        ],
+        data,
+        selfCall: false,
+        holdUpSlots: new BN(0),
+        kind: { invoke: {} },
+      };
+      const proposal = await queuePayloadProposal(
+        governor,
+        payerTokenAccount,
+        "drain a delegated account",
+        [instruction]
+      );
+      await expectError(
+        executeTransaction(governor, proposal, 0, instruction),
+        "InvalidTreasuryTokenAccount"
+      );
+    });
+  });
+
+  describe("program upgrades", () => {
+    // Upgradeable loader instructions are bincode: a u32 LE variant, then fields.
+    function loaderInstruction(keys: any[], data: Buffer): TransactionInstruction {
+      return new TransactionInstruction({
+        programId: BPF_LOADER_UPGRADEABLE_ID,
+        keys,
+        data,
+      });
+    }
+
+    function variant(index: number, rest = Buffer.alloc(0)): Buffer {
+      const tag = Buffer.alloc(4);
+      tag.writeUInt32LE(index);
+      return Buffer.concat([tag, rest]);
+    }
+
+    async function setAuthority(account: PublicKey, newAuthority: PublicKey) {
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          loaderInstruction(
+            [
+              { pubkey: account, isSigner: false, isWritable: true },
+              { pubkey: payer, isSigner: true, isWritable: false },
+              { pubkey: newAuthority, isSigner: false, isWritable: false },
+            ],
+            variant(4)
+          )
+        )
+      );
+    }
+
+    // Writes `bytes` into a new buffer owned by the payer, then hands it to `authority`.
+    async function createBuffer(bytes: Buffer, authority: PublicKey): Promise<PublicKey> {
+      const buffer = Keypair.generate();
+      const space = 37 + bytes.length;
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.createAccount({
+            fromPubkey: payer,
+            newAccountPubkey: buffer.publicKey,
+            space,
+            lamports: await provider.connection.getMinimumBalanceForRentExemption(space),
+            programId: BPF_LOADER_UPGRADEABLE_ID,
+          }),
+          loaderInstruction(
+            [
+              { pubkey: buffer.publicKey, isSigner: false, isWritable: true },
+              { pubkey: payer, isSigner: false, isWritable: false },
+            ],
+            variant(0)
+          )
+        ),
+        [buffer]
+      );
+
+      const chunk = 900;
+      const writes = [];
+      for (let offset = 0; offset < bytes.length; offset += chunk) {
+        const part = bytes.subarray(offset, offset + chunk);
+        const header = Buffer.alloc(12);
+        header.writeUInt32LE(offset, 0);
+        header.writeBigUInt64LE(BigInt(part.length), 4);
+        writes.push(
+          provider.sendAndConfirm(
+            new Transaction().add(
+              loaderInstruction(
+                [
+                  { pubkey: buffer.publicKey, isSigner: false, isWritable: true },
+                  { pubkey: payer, isSigner: true, isWritable: false },
+                ],
+                variant(1, Buffer.concat([header, part]))
+              )
+            )
+          )
+        );
+      }
+      await Promise.all(writes);
+
+      if (!authority.equals(payer)) {
+        await setAuthority(buffer.publicKey, authority);
+      }
+      return buffer.publicKey;
+    }
+
+    function programDataAddress(programId: PublicKey): PublicKey {
+      return PublicKey.findProgramAddressSync(
+        [programId.toBuffer()],
+        BPF_LOADER_UPGRADEABLE_ID
+      )[0];
+    }
+
+    // Deploys `bytes` as a new upgradeable program whose upgrade authority is `authority`.
+    async function deployProgram(bytes: Buffer, authority: PublicKey): Promise<PublicKey> {
+      const buffer = await createBuffer(bytes, payer);
+      const programAccount = Keypair.generate();
+      const programData = programDataAddress(programAccount.publicKey);
+      const maxDataLen = Buffer.alloc(8);
+      maxDataLen.writeBigUInt64LE(BigInt(bytes.length * 2));
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.createAccount({
+            fromPubkey: payer,
+            newAccountPubkey: programAccount.publicKey,
+            space: 36,
+            lamports: await provider.connection.getMinimumBalanceForRentExemption(36),
+            programId: BPF_LOADER_UPGRADEABLE_ID,
+          }),
+          loaderInstruction(
+            [
+              { pubkey: payer, isSigner: true, isWritable: true },
+              { pubkey: programData, isSigner: false, isWritable: true },
+              { pubkey: programAccount.publicKey, isSigner: false, isWritable: true },
+              { pubkey: buffer, isSigner: false, isWritable: true },
+              { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false },
+              { pubkey: SYSVAR_CLOCK_PUBKEY, isSigner: false, isWritable: false },
+              { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+              { pubkey: payer, isSigner: true, isWritable: false },
+            ],
+            variant(2, maxDataLen)
+          )
+        ),
+        [programAccount]
+      );
+      await setAuthority(programData, authority);
+      return programAccount.publicKey;
+    }
+
+    // Any valid program will do as the dummy; this one is already built.
+    const dummyBytes = () => readFileSync("target/deploy/agora_solana.so");
+
+    it("upgrades a program whose upgrade authority is the governor", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const authority = authorityAddress(governor);
+      const target = await deployProgram(dummyBytes(), authority);
+      const buffer = await createBuffer(dummyBytes(), authority);
+
+      // Mirrors the payload propose_program_upgrade builds.
+      const upgrade = {
+        index: 0,
+        programId: BPF_LOADER_UPGRADEABLE_ID,
+        accounts: [
+          { pubkey: programDataAddress(target), isSigner: false, isWritable: true },
+          { pubkey: target, isSigner: false, isWritable: true },
+          { pubkey: buffer, isSigner: false, isWritable: true },
+          { pubkey: payer, isSigner: false, isWritable: true },
+          { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false },
+          { pubkey: SYSVAR_CLOCK_PUBKEY, isSigner: false, isWritable: false },
+          { pubkey: authority, isSigner: true, isWritable: false },
+        ],
+        data: variant(3),
+        selfCall: false,
+        holdUpSlots: new BN(0),
+        kind: { invoke: {} },
+      };
+      const description = "upgrade the dummy program";
+      const proposal = proposalAddress(governor, 0);
+      await program.methods
+        .proposeProgramUpgrade(description, 0, target, payer)
+        .accountsPartial({
+          governor,
+          proposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          buffer,
+          proposalDigest: digestAddress(governor, description, [], [upgrade]),
+        })
+        .rpc();
+
+      const account = await program.account.proposal.fetch(proposal);
+      assert.deepEqual(
+        account.instructions[0].accounts.map((meta) => meta.pubkey.toBase58()),
+        upgrade.accounts.map((meta) => meta.pubkey.toBase58())
+      );
+
+      const before = await provider.connection.getAccountInfo(programDataAddress(target));
+      await voteAndQueue(governor, payerTokenAccount, proposal);
+      await executeTransaction(governor, proposal, 0, upgrade);
+
+      // ProgramData starts with the u32 variant and the u64 slot of the last deploy.
+      const after = await provider.connection.getAccountInfo(programDataAddress(target));
+      assert.isAbove(
+        Number(after.data.readBigUInt64LE(4)),
+        Number(before.data.readBigUInt64LE(4))
+      );
+      assert.isNull(await provider.connection.getAccountInfo(buffer));
+    });
+
+    it("rejects a buffer the governor authority does not own", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const target = await deployProgram(dummyBytes(), authorityAddress(governor));
+      const buffer = await createBuffer(dummyBytes(), payer);
+
+      await expectError(
+        program.methods
+          .proposeProgramUpgrade("upgrade from a foreign buffer", 0, target, payer)
+          .accountsPartial({
+            governor,
+            proposal: proposalAddress(governor, 0),
+            proposer: payer,
+            proposerTokenAccount: payerTokenAccount,
+            buffer,
+            proposalDigest: PublicKey.default,
+          })
+          .rpc(),
+        "InvalidUpgradeBuffer"
+      );
+    });
+  });
+
+  describe("queued proposal cancellation", () => {
+    it("blocks the remaining transactions of a partly executed proposal", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const [treasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), governor.toBuffer()],
+        program.programId
+      );
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: payer,
+            toPubkey: treasury,
+            lamports: 1_000_000_000,
+          })
+        )
+      );
+
+      const recipient = Keypair.generate().publicKey;
+      const instructions = [0, 1].map((index) => {
+        const transfer = SystemProgram.transfer({
+          fromPubkey: treasury,
+          toPubkey: recipient,
+          lamports: 100_000_000,
+        });
+        return {
+          index,
+          programId: transfer.programId,
+          accounts: transfer.keys,
+          data: transfer.data,
+          selfCall: false,
+          holdUpSlots: new BN(0),
+          kind: { invoke: {} },
+        };
+      });
+      const proposal = await queuePayloadProposal(
+        governor,
+        payerTokenAccount,
+        "two payments",
+        instructions
+      );
+      await executeTransaction(governor, proposal, 0, instructions[0]);
+
+      await program.methods
+        .cancelQueuedProposal(new BN(0))
+        .accountsPartial({ governor, proposal, canceler: payer })
+        .rpc();
+
+      const account = await program.account.proposal.fetch(proposal);
+      assert.ok(account.canceled);
+      assert.equal(account.executedInstructions, 1);
+      await expectError(
+        executeTransaction(governor, proposal, 1, instructions[1]),
+        "ProposalCanceled"
+      );
+      assert.equal(await provider.connection.getBalance(recipient), 100_000_000);
+    });
+
+    it("cannot be finalized back to Succeeded once queued", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const description = "queued then finalized";
+      const proposal = await queuePayloadProposal(governor, payerTokenAccount, description, []);
+
+      await expectError(
+        program.methods
+          .finalizeProposal(new BN(0))
+          .accountsPartial({ governor, proposal, proposalDigest: digestAddress(governor, description), payer })
+          .rpc(),
+        "ProposalAlreadyFinalized"
+      );
+      const account = await program.account.proposal.fetch(proposal);
+      assert.deepEqual(account.state, { queued: {} });
+      assert.deepEqual(account.outcome, { succeeded: {} });
+
+      await program.methods
+        .cancelQueuedProposal(new BN(0))
+        .accountsPartial({ governor, proposal, canceler: payer })
+        .rpc();
+      assert.ok((await program.account.proposal.fetch(proposal)).canceled);
+    });
+  });
+
+  describe("reentrancy guard", () => {
+    // Payload instruction calling back into execute_transaction for proposal 0.
+    async function replayInstruction(governor: PublicKey, selfCall: boolean) {
+      const replay = await program.methods
+        .executeTransaction(new BN(0), 0)
+        .accountsPartial({
+          governor,
+          proposal: proposalAddress(governor, 0),
+          executor: authorityAddress(governor),
+          dependency: null,
+        })
+        .instruction();
+      return {
+        index: 0,
+        programId: replay.programId,
+        accounts: replay.keys,
+        data: replay.data,
+        selfCall,
+        holdUpSlots: new BN(0),
+        kind: { invoke: {} },
+      };
+    }
+
+    it("rejects a governor-targeted instruction not flagged as a self-call", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const instruction = await replayInstruction(governor, false);
+      const proposal = await queuePayloadProposal(
+        governor,
+        payerTokenAccount,
+        "unflagged self-call",
+        [instruction]
+      );
+
+      await expectError(
+        executeTransaction(governor, proposal, 0, instruction),
+        "SelfCallNotFlagged"
+      );
+    });
+
+    it("does not let a self-call replay the instruction being executed", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const instruction = await replayInstruction(governor, true);
+      const proposal = await queuePayloadProposal(
+        governor,
+        payerTokenAccount,
+        "replaying self-call",
+        [instruction]
+      );
+
+      await expectError(
+        executeTransaction(governor, proposal, 0, instruction),
+        "InstructionIndexOutOfOrder"
+      );
+      const account = await program.account.governor.fetch(governor);
+      assert.isFalse(account.executionInProgress);
+    });
+
+    it("rejects a payload program that calls back into the governor", async () => {
+      const reentrantTarget =
+        anchor.workspace.ReentrantExecutionTarget as Program<ReentrantExecutionTarget>;
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const replay = await replayInstruction(governor, false);
+      const reenter = await reentrantTarget.methods
+        .reenter(replay.data)
+        .accounts({ targetProgram: program.programId })
+        .remainingAccounts(replay.accounts)
+        .instruction();
+      const instruction = {
+        index: 0,
+        programId: reenter.programId,
+        accounts: reenter.keys,
+        data: reenter.data,
+        selfCall: false,
+        holdUpSlots: new BN(0),
+        kind: { invoke: {} },
+      };
+      const proposal = await queuePayloadProposal(
+        governor,
+        payerTokenAccount,
+        "reentrant payload",
+        [instruction]
+      );
+
+      await expectError(
+        executeTransaction(governor, proposal, 0, instruction),
+        "ReentrancyDetected"
+      );
+      const account = await program.account.proposal.fetch(proposal);
+      assert.equal(account.executedInstructions, 0);
+      assert.isFalse((await program.account.governor.fetch(governor)).executionInProgress);
+    });
+  });
+
+  describe("supply actions", () => {
+    // SetAuthority(MintTokens): [6, 0, 1, new_authority]
+    async function setMintAuthority(mint: PublicKey, authority: PublicKey) {
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          new TransactionInstruction({
+            programId: TOKEN_PROGRAM_ID,
+            keys: [
+              { pubkey: mint, isSigner: false, isWritable: true },
+              { pubkey: payer, isSigner: true, isWritable: false },
+            ],
+            data: Buffer.concat([Buffer.from([6, 0, 1]), authority.toBuffer()]),
+          })
+        )
+      );
+    }
+
+    async function queueActionProposal(
+      governor: PublicKey,
+      payerTokenAccount: PublicKey,
+      actions: any[]
+    ): Promise<PublicKey> {
+      const description = "adjust supply";
+      const proposal = proposalAddress(governor, 0);
+      await program.methods
+        .createProposal(description, 0, actions, null, null, [], null)
+        .accountsPartial({
+          governor,
+          proposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, description, actions),
+        })
+        .rpc();
+      await voteAndQueue(governor, payerTokenAccount, proposal);
+      return proposal;
+    }
+
+    function executeWithSupplyAccounts(
+      governor: PublicKey,
+      proposal: PublicKey,
+      governingMint: PublicKey,
+      tokenAccounts: PublicKey[]
+    ) {
+      return program.methods
+        .executeProposal(new BN(0))
+        .accountsPartial({
+          governor,
+          proposal,
+          executor: payer,
+          dependency: null,
+          governingMint,
+          governorAuthority: authorityAddress(governor),
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(
+          tokenAccounts.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true }))
+        )
+        .rpc();
+    }
+
+    it("mints and burns through the governor authority and resyncs the supply", async () => {
+      const { governor, governingMint, payerTokenAccount } =
+        await initializeGovernor(0, 5);
+      const authority = authorityAddress(governor);
+      const vault = await createTokenAccount(governingMint, authority, 500);
+      const recipient = await createTokenAccount(governingMint, payer, 0);
+      await setMintAuthority(governingMint, authority);
+      await program.methods
+        .syncTotalSupply()
+        .accountsPartial({ governor, governingMint })
+        .rpc();
+
+      const proposal = await queueActionProposal(governor, payerTokenAccount, [
+        { mint: { to: recipient, amount: new BN(1_000) } },
+        { burn: { from: vault, amount: new BN(200) } },
+      ]);
+      await executeWithSupplyAccounts(governor, proposal, governingMint, [recipient, vault]);
+
+      const account = await program.account.governor.fetch(governor);
+      assert.equal(account.totalSupply.toNumber(), 1_000_000 + 500 + 1_000 - 200);
+      const minted = await provider.connection.getTokenAccountBalance(recipient);
+      assert.equal(minted.value.amount, "1000");
+      const burned = await provider.connection.getTokenAccountBalance(vault);
+      assert.equal(burned.value.amount, "300");
+    });
+
+    it("rejects minting while the governor is not the mint authority", async () => {
+      const { governor, governingMint, payerTokenAccount } =
+        await initializeGovernor(0, 5);
+      const recipient = await createTokenAccount(governingMint, payer, 0);
+
+      const proposal = await queueActionProposal(governor, payerTokenAccount, [
+        { mint: { to: recipient, amount: new BN(1_000) } },
+      ]);
+      await expectError(
+        executeWithSupplyAccounts(governor, proposal, governingMint, [recipient]),
+        "MintAuthorityNotGovernor"
+      );
+    });
+  });
+
+  describe("instruction hold-ups", () => {
+    it("holds a later instruction until eta plus its hold-up", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const [treasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), governor.toBuffer()],
+        program.programId
+      );
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: payer,
+            toPubkey: treasury,
+            lamports: 1_000_000_000,
+          })
+        )
+      );
+
+      const recipient = Keypair.generate().publicKey;
+      const instructions = [0, 10_000].map((holdUp, index) => {
+        const transfer = SystemProgram.transfer({
+          fromPubkey: treasury,
+          toPubkey: recipient,
+          lamports: 100_000_000,
+        });
+        return {
+          index,
+          programId: transfer.programId,
+          accounts: transfer.keys,
+          data: transfer.data,
+          selfCall: false,
+          holdUpSlots: new BN(holdUp),
+          kind: { invoke: {} },
+        };
+      });
+      const proposal = await queuePayloadProposal(
+        governor,
+        payerTokenAccount,
+        "announce, then pay",
+        instructions
+      );
+
+      await executeTransaction(governor, proposal, 0, instructions[0]);
+      await expectError(
+        executeTransaction(governor, proposal, 1, instructions[1]),
+        "TimelockNotExpired"
+      );
+      const account = await program.account.proposal.fetch(proposal);
+      assert.equal(account.executedInstructions, 1);
+      assert.equal(account.instructions[1].holdUpSlots.toNumber(), 10_000);
+    });
+  });
+
+  describe("instruction skipping", () => {
+    it("lets the manager skip an instruction that keeps failing", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const [treasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), governor.toBuffer()],
+        program.programId
+      );
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: payer,
+            toPubkey: treasury,
+            lamports: 1_000_000_000,
+          })
+        )
+      );
+
+      const recipient = Keypair.generate().publicKey;
+      const instructions = [5_000_000_000, 100_000_000].map((lamports, index) => {
+        const transfer = SystemProgram.transfer({
+          fromPubkey: treasury,
+          toPubkey: recipient,
+          lamports,
+        });
+        return {
+          index,
+          programId: transfer.programId,
+          accounts: transfer.keys,
+          data: transfer.data,
+          selfCall: false,
+          holdUpSlots: new BN(0),
+          kind: { invoke: {} },
+        };
+      });
+      const proposal = await queuePayloadProposal(
+        governor,
+        payerTokenAccount,
+        "overdrawn payment",
+        instructions
+      );
+
+      // The overdrawn transfer fails and stays pending for a retry.
+      try {
+        await executeTransaction(governor, proposal, 0, instructions[0]);
+        assert.fail("expected the transfer to fail");
+      } catch (err) {
+        assert.notInclude(String(err), "expected the transfer to fail");
+      }
+      let account = await program.account.proposal.fetch(proposal);
+      assert.equal(account.executedInstructions, 0);
+
+      await expectError(
+        executeTransaction(governor, proposal, 1, instructions[1]),
+        "InstructionIndexOutOfOrder"
+      );
+      // The system program's ResultWithNegativeLamports.
+      await program.methods
+        .recordTransactionFailure(new BN(0), 0, new BN(1))
+        .accountsPartial({ governor, proposal, manager: payer })
+        .rpc();
+      account = await program.account.proposal.fetch(proposal);
+      assert.equal(account.executedInstructions, 0);
+      assert.equal(account.instructionErrorCodes[0].toNumber(), 1);
+      await expectError(
+        program.methods
+          .recordTransactionFailure(new BN(0), 0, new BN(0))
+          .accountsPartial({ governor, proposal, manager: payer })
+          .rpc(),
+        "InvalidErrorCode"
+      );
+      await program.methods
+        .markTransactionSkipped(new BN(0), 0)
+        .accountsPartial({ governor, proposal, manager: payer })
+        .rpc();
+      await executeTransaction(governor, proposal, 1, instructions[1]);
+
+      account = await program.account.proposal.fetch(proposal);
+      assert.ok(account.executed);
+      assert.equal(account.skippedInstructions, 0b01);
+      assert.equal(account.instructionErrorCodes[0].toNumber(), 1);
+      assert.equal(await provider.connection.getBalance(recipient), 100_000_000);
+      const [history] = PublicKey.findProgramAddressSync(
+        [Buffer.from("history"), governor.toBuffer()],
+        program.programId
+      );
+      const { records } = await program.account.governorHistory.fetch(history);
+      assert.equal(records.length, 1);
+      assert.equal(records[0].proposalId.toNumber(), 0);
+      assert.deepEqual(records[0].outcome, { executed: {} });
+    });
+  });
+
+  describe("batch execution", () => {
+    // Queues a proposal paying 0.1 SOL from the treasury to a fresh recipient per
+    // instruction.
+    async function queuePayments(count: number) {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const [treasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), governor.toBuffer()],
+        program.programId
+      );
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: payer,
+            toPubkey: treasury,
+            lamports: 1_000_000_000,
+          })
+        )
+      );
+      const instructions = [...Array(count).keys()].map((index) => {
+        const transfer = SystemProgram.transfer({
+          fromPubkey: treasury,
+          toPubkey: Keypair.generate().publicKey,
+          lamports: 100_000_000,
+        });
+        return {
+          index,
+          programId: transfer.programId,
+          accounts: transfer.keys,
+          data: transfer.data,
+          selfCall: false,
+          holdUpSlots: new BN(0),
+          kind: { invoke: {} },
+        };
+      });
+      const proposal = await queuePayloadProposal(
+        governor,
+        payerTokenAccount,
+        `${count} payments`,
+        instructions
+      );
+      return { governor, proposal, instructions };
+    }
+
+    // One group per instruction: its accounts, then its program.
+    function executeAll(governor: PublicKey, proposal: PublicKey, instructions: any[]) {
+      return program.methods
+        .executeAllTransactions(new BN(0))
+        .accountsPartial({ governor, proposal, executor: payer, dependency: null })
+        .remainingAccounts(
+          instructions.flatMap((instruction) => [
+            ...instruction.accounts.map((meta) => ({ ...meta, isSigner: false })),
+            { pubkey: instruction.programId, isSigner: false, isWritable: false },
+          ])
+        )
+        .rpc();
+    }
+
+    it("runs a small payload in one call", async () => {
+      const { governor, proposal, instructions } = await queuePayments(3);
+
+      await executeAll(governor, proposal, instructions);
+
+      const account = await program.account.proposal.fetch(proposal);
+      assert.equal(account.executedInstructions, 3);
+      assert.ok(account.executed);
+    });
+
+    it("resumes where a partial call stopped", async () => {
+      const { governor, proposal, instructions } = await queuePayments(3);
+
+      // Accounts for the first instruction only: the call stops after it.
+      await executeAll(governor, proposal, instructions.slice(0, 1));
+      let account = await program.account.proposal.fetch(proposal);
+      assert.equal(account.executedInstructions, 1);
+      assert.isFalse(account.executed);
+
+      await executeAll(governor, proposal, instructions.slice(1));
+      account = await program.account.proposal.fetch(proposal);
+      assert.equal(account.executedInstructions, 3);
+      assert.ok(account.executed);
+    });
+  });
+
+  describe("payload validation", () => {
+    it("flags instructions whose supplied accounts do not match", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 1_000);
+      const [treasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), governor.toBuffer()],
+        program.programId
+      );
+      const instructions = [0, 1].map((index) => {
+        const transfer = SystemProgram.transfer({
+          fromPubkey: treasury,
+          toPubkey: Keypair.generate().publicKey,
+          lamports: 1_000,
+        });
+        return {
+          index,
+          programId: transfer.programId,
+          accounts: transfer.keys,
+          data: transfer.data,
+          selfCall: false,
+          holdUpSlots: new BN(0),
+          kind: { invoke: {} },
+        };
+      });
+      const description = "two payments";
+      const proposal = proposalAddress(governor, 0);
+      await program.methods
+        .createProposal(description, 0, [], null, null, instructions, null)
+        .accountsPartial({
+          governor,
+          proposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, description, [], instructions),
+        })
+        .rpc();
+
+      // The second instruction's accounts are supplied in the wrong order.
+      const [first, second] = instructions.map((instruction) =>
+        instruction.accounts.map((meta) => ({ ...meta, isSigner: false }))
+      );
+      const systemProgram = { pubkey: SystemProgram.programId, isSigner: false, isWritable: false };
+      const valid = await program.methods
+        .validateProposalPayload(new BN(0))
+        .accountsPartial({ governor, proposal })
+        .remainingAccounts([...first, systemProgram, ...second.reverse(), systemProgram])
+        .view();
+
+      assert.equal(valid, 0b01);
+    });
+  });
+
+  describe("associated token account creation", () => {
+    // Queues [create the recipient's ATA, pay it 400 tokens from the treasury vault].
+    async function queueAtaPayout(ataExists: boolean) {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const authority = authorityAddress(governor);
+      const mint = await createMint(payer);
+      const [vault] = PublicKey.findProgramAddressSync(
+        [authority.toBuffer(), TOKEN_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+        ASSOCIATED_TOKEN_PROGRAM_ID
+      );
+      await program.methods
+        .createTreasuryTokenAccount()
+        .accountsPartial({
+          governor,
+          governorAuthority: authority,
+          mint,
+          treasuryTokenAccount: vault,
+          payer,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      await provider.sendAndConfirm(
+        new Transaction().add(mintToInstruction(mint, vault, 1_000))
+      );
+
+      const owner = Keypair.generate().publicKey;
+      const [ata] = PublicKey.findProgramAddressSync(
+        [owner.toBuffer(), TOKEN_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+        ASSOCIATED_TOKEN_PROGRAM_ID
+      );
+      const createAccounts = [
+        { pubkey: payer, isSigner: true, isWritable: true },
+        { pubkey: ata, isSigner: false, isWritable: true },
+        { pubkey: owner, isSigner: false, isWritable: false },
+        { pubkey: mint, isSigner: false, isWritable: false },
+        { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      ];
+      if (ataExists) {
+        // CreateIdempotent: [1]
+        await provider.sendAndConfirm(
+          new Transaction().add(
+            new TransactionInstruction({
+              programId: ASSOCIATED_TOKEN_PROGRAM_ID,
+              keys: createAccounts,
+              data: Buffer.from([1]),
+            })
+          )
+        );
+      }
+
+      // Transfer: [3, amount (u64 LE)]
+      const data = Buffer.alloc(9);
+      data.writeUInt8(3, 0);
+      data.writeBigUInt64LE(BigInt(400), 1);
+      const transfer = {
+        index: 1,
+        programId: TOKEN_PROGRAM_ID,
+        accounts: [
+          { pubkey: vault, isSigner: false, isWritable: true },
+          { pubkey: ata, isSigner: false, isWritable: true },
+          { pubkey: authority, isSigner: true, isWritable: false },
+        ],
+        data,
+        selfCall: false,
+        holdUpSlots: new BN(0),
+        kind: { invoke: {} },
+      };
+      const instructions = [
+        {
+          index: 0,
+          programId: ASSOCIATED_TOKEN_PROGRAM_ID,
+          accounts: [],
+          data: Buffer.alloc(0),
+          selfCall: false,
+          holdUpSlots: new BN(0),
+          kind: { createAtaIdempotent: { owner, mint } },
+        },
+        transfer,
+      ];
+      const proposal = await queuePayloadProposal(
+        governor,
+        payerTokenAccount,
+        "pay 400 into an ata",
+        instructions
+      );
+      return { governor, proposal, ata, createAccounts, transfer };
+    }
+
+    for (const ataExists of [false, true]) {
+      it(`pays out ${ataExists ? "into an existing" : "after creating the"} ATA`, async () => {
+        const { governor, proposal, ata, createAccounts, transfer } =
+          await queueAtaPayout(ataExists);
+
+        await program.methods
+          .executeTransaction(new BN(0), 0)
+          .accountsPartial({ governor, proposal, executor: payer, dependency: null })
+          .remainingAccounts([
+            ...createAccounts,
+            { pubkey: ASSOCIATED_TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+          ])
+          .rpc();
+        await executeTransaction(governor, proposal, 1, transfer);
+
+        const balance = await provider.connection.getTokenAccountBalance(ata);
+        assert.equal(balance.value.amount, "400");
+        const account = await program.account.proposal.fetch(proposal);
+        assert.ok(account.executed);
+      });
+    }
+  });
+
+  describe("execution hook", () => {
+    const noopHook: PublicKey = anchor.workspace.NoopExecutionHook.programId;
+
+    it("lets the hook veto payload instructions", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const [treasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), governor.toBuffer()],
+        program.programId
+      );
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({ fromPubkey: payer, toPubkey: treasury, lamports: 1_000_000_000 })
+        )
+      );
+      const recipient = Keypair.generate().publicKey;
+      const instructions = [0, 1].map((index) => {
+        const transfer = SystemProgram.transfer({
+          fromPubkey: treasury,
+          toPubkey: recipient,
+          lamports: 100_000_000,
+        });
+        return {
+          index,
+          programId: transfer.programId,
+          accounts: transfer.keys,
+          data: transfer.data,
+          selfCall: false,
+          holdUpSlots: new BN(0),
+          kind: { invoke: {} },
+        };
+      });
+      const proposal = await queuePayloadProposal(governor, payerTokenAccount, "hooked payments", instructions);
+      const execute = (index: number, executionHook: PublicKey | null) =>
+        program.methods
+          .executeTransaction(new BN(0), index)
+          .accountsPartial({ governor, proposal, executor: payer, dependency: null, executionHook })
+          .remainingAccounts([
+            ...instructions[index].accounts.map((meta) => ({ ...meta, isSigner: false })),
+            { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+          ])
+          .rpc();
+
+      await program.methods.setExecutionHook(noopHook).accountsPartial({ governor, admin: payer }).rpc();
+      await expectError(execute(0, null), "InvalidExecutionHook");
+      await execute(0, noopHook);
+      assert.equal(await provider.connection.getBalance(recipient), 100_000_000);
+
+      // The token program implements no check_execution, so it refuses every call.
+      await program.methods.setExecutionHook(TOKEN_PROGRAM_ID).accountsPartial({ governor, admin: payer }).rpc();
+      try {
+        await execute(1, TOKEN_PROGRAM_ID);
+        assert.fail("expected the hook to veto");
+      } catch (err) {
+        assert.notInclude(String(err), "expected the hook to veto");
+      }
+      const account = await program.account.proposal.fetch(proposal);
+      assert.equal(account.executedInstructions, 1);
+      assert.equal(await provider.connection.getBalance(recipient), 100_000_000);
+      assert.isFalse((await program.account.governor.fetch(governor)).executionInProgress);
+    });
+  });
+
+  describe("wrapped SOL", () => {
+    const NATIVE_MINT = new PublicKey("So11111111111111111111111111111111111111112");
+
+    it("wraps treasury SOL, pays wSOL out and unwraps the remainder", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const authority = authorityAddress(governor);
+      const [treasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), governor.toBuffer()],
+        program.programId
+      );
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({ fromPubkey: payer, toPubkey: treasury, lamports: 1_000_000_000 })
+        )
+      );
+      const [wsol] = PublicKey.findProgramAddressSync(
+        [authority.toBuffer(), TOKEN_PROGRAM_ID.toBuffer(), NATIVE_MINT.toBuffer()],
+        ASSOCIATED_TOKEN_PROGRAM_ID
+      );
+
+      // A plain wSOL account for the recipient; the native mint has no mint authority.
+      const recipient = Keypair.generate();
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.createAccount({
+            fromPubkey: payer,
+            newAccountPubkey: recipient.publicKey,
+            space: TOKEN_ACCOUNT_SIZE,
+            lamports: await provider.connection.getMinimumBalanceForRentExemption(TOKEN_ACCOUNT_SIZE),
+            programId: TOKEN_PROGRAM_ID,
+          }),
+          new TransactionInstruction({
+            programId: TOKEN_PROGRAM_ID,
+            keys: [
+              { pubkey: recipient.publicKey, isSigner: false, isWritable: true },
+              { pubkey: NATIVE_MINT, isSigner: false, isWritable: false },
+            ],
+            data: Buffer.concat([Buffer.from([18]), payer.toBuffer()]),
+          })
+        ),
+        [recipient]
+      );
+
+      const wrapAccounts = [
+        { pubkey: treasury, isSigner: false, isWritable: true },
+        { pubkey: wsol, isSigner: false, isWritable: true },
+        { pubkey: authority, isSigner: false, isWritable: false },
+        { pubkey: NATIVE_MINT, isSigner: false, isWritable: false },
+        { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        { pubkey: ASSOCIATED_TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      ];
+      const wrap = {
+        index: 0,
+        programId: TOKEN_PROGRAM_ID,
+        accounts: [],
+        data: Buffer.alloc(0),
+        selfCall: false,
+        holdUpSlots: new BN(0),
+        kind: { wrapTreasurySol: { amount: new BN(1_000_000) } },
+      };
+      // TransferChecked: [12, amount (u64 LE), decimals]
+      const data = Buffer.alloc(10);
+      data.writeUInt8(12, 0);
+      data.writeBigUInt64LE(BigInt(400_000), 1);
+      data.writeUInt8(9, 9);
+      const transfer = {
+        index: 1,
+        programId: TOKEN_PROGRAM_ID,
+        accounts: [
+          { pubkey: wsol, isSigner: false, isWritable: true },
+          { pubkey: NATIVE_MINT, isSigner: false, isWritable: false },
+          { pubkey: recipient.publicKey, isSigner: false, isWritable: true },
+          { pubkey: authority, isSigner: true, isWritable: false },
+        ],
+        data,
+        selfCall: false,
+        holdUpSlots: new BN(0),
+        kind: { invoke: {} },
+      };
+      const proposal = await queuePayloadProposal(governor, payerTokenAccount, "wrap and pay", [wrap, transfer]);
+      await executeTransaction(governor, proposal, 0, { programId: TOKEN_PROGRAM_ID, accounts: wrapAccounts });
+      await executeTransaction(governor, proposal, 1, transfer);
+
+      const paid = await provider.connection.getTokenAccountBalance(recipient.publicKey);
+      assert.equal(paid.value.amount, "400000");
+      const held = await provider.connection.getTokenAccountBalance(wsol);
+      assert.equal(held.value.amount, "600000");
+
+      const unwrap = await queuePayloadProposal(
+        governor,
+        payerTokenAccount,
+        "unwrap the remainder",
+        [{ ...wrap, kind: { unwrapTreasurySol: {} } }],
+        1
+      );
+      const unwrapAccounts = (account: PublicKey) => [
+        { pubkey: treasury, isSigner: false, isWritable: true },
+        { pubkey: account, isSigner: false, isWritable: true },
+        { pubkey: authority, isSigner: false, isWritable: false },
+        { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      ];
+      // A wSOL account other than the authority's ATA is rejected.
+      await expectError(
+        program.methods
+          .executeTransaction(new BN(1), 0)
+          .accountsPartial({ governor, proposal: unwrap, executor: payer, dependency: null })
+          .remainingAccounts(unwrapAccounts(recipient.publicKey))
+          .rpc(),
+        "InvalidWrappedSolAccount"
+      );
+
+      const wsolLamports = await provider.connection.getBalance(wsol);
+      const before = await provider.connection.getBalance(treasury);
+      await program.methods
+        .executeTransaction(new BN(1), 0)
+        .accountsPartial({ governor, proposal: unwrap, executor: payer, dependency: null })
+        .remainingAccounts(unwrapAccounts(wsol))
+        .rpc();
+
+      assert.equal(await provider.connection.getBalance(treasury), before + wsolLamports);
+      assert.isNull(await provider.connection.getAccountInfo(wsol));
+    });
+  });
+
+  describe("payment streams", () => {
+    const STREAM_SLOTS = 200;
+
+    // Queues and executes a proposal streaming 1_000 tokens from a treasury vault to
+    // a fresh recipient over STREAM_SLOTS slots starting now.
+    async function createStream() {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const authority = authorityAddress(governor);
+      const mint = await createMint(payer);
+      const [vault] = PublicKey.findProgramAddressSync(
+        [authority.toBuffer(), TOKEN_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+        ASSOCIATED_TOKEN_PROGRAM_ID
+      );
+      await program.methods
+        .createTreasuryTokenAccount()
+        .accountsPartial({
+          governor,
+          governorAuthority: authority,
+          mint,
+          treasuryTokenAccount: vault,
+          payer,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      await provider.sendAndConfirm(
+        new Transaction().add(mintToInstruction(mint, vault, 1_000))
+      );
+      const recipient = Keypair.generate().publicKey;
+      const recipientTokenAccount = await createTokenAccount(mint, recipient, 0);
+
+      const startSlot = await provider.connection.getSlot();
+      const endSlot = startSlot + STREAM_SLOTS;
+      const proposal = await queuePayloadProposal(governor, payerTokenAccount, "stream a grant", [
+        {
+          index: 0,
+          programId: program.programId,
+          accounts: [],
+          data: Buffer.alloc(0),
+          selfCall: false,
+          holdUpSlots: new BN(0),
+          kind: {
+            createStream: {
+              recipient,
+              mint,
+              totalAmount: new BN(1_000),
+              startSlot: new BN(startSlot),
+              endSlot: new BN(endSlot),
+            },
+          },
+        },
+      ]);
+      const [stream] = PublicKey.findProgramAddressSync(
+        [Buffer.from("stream"), proposal.toBuffer(), Buffer.from([0, 0])],
+        program.programId
+      );
+      const [escrow] = PublicKey.findProgramAddressSync(
+        [Buffer.from("stream-escrow"), stream.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .executeTransaction(new BN(0), 0)
+        .accountsPartial({ governor, proposal, executor: payer, dependency: null })
+        .remainingAccounts([
+          { pubkey: payer, isSigner: true, isWritable: true },
+          { pubkey: stream, isSigner: false, isWritable: true },
+          { pubkey: escrow, isSigner: false, isWritable: true },
+          { pubkey: mint, isSigner: false, isWritable: false },
+          { pubkey: vault, isSigner: false, isWritable: true },
+          { pubkey: authority, isSigner: false, isWritable: false },
+          { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+          { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+          { pubkey: program.programId, isSigner: false, isWritable: false },
+        ])
+        .rpc();
+
+      const claim = () =>
+        program.methods
+          .claimStream()
+          .accountsPartial({
+            governor,
+            stream,
+            escrow,
+            mint,
+            recipientTokenAccount,
+            governorAuthority: authority,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .rpc();
+      const balance = async (account: PublicKey) =>
+        Number((await provider.connection.getTokenAccountBalance(account)).value.amount);
+      return {
+        governor,
+        payerTokenAccount,
+        authority,
+        mint,
+        vault,
+        stream,
+        escrow,
+        endSlot,
+        recipientTokenAccount,
+        claim,
+        balance,
+      };
+    }
+
+    it("pays out the vested part on each claim", async () => {
+      const { claim, balance, recipientTokenAccount, escrow } = await createStream();
+
+      await claim();
+      const first = await balance(recipientTokenAccount);
+      assert.isAbove(first, 0);
+      assert.isBelow(first, 1_000);
+
+      await claim();
+      const second = await balance(recipientTokenAccount);
+      assert.isAbove(second, first);
+      assert.equal(second + (await balance(escrow)), 1_000);
+    });
+
+    it("pays out the full amount once the stream has ended", async () => {
+      const { claim, balance, recipientTokenAccount, escrow, endSlot } = await createStream();
+
+      await waitForSlot(endSlot);
+      await claim();
+      assert.equal(await balance(recipientTokenAccount), 1_000);
+      assert.equal(await balance(escrow), 0);
+      await expectError(claim(), "NothingToClaim");
+    });
+
+    it("returns unvested tokens to the treasury when canceled by a later proposal", async () => {
+      const stream = await createStream();
+      const proposal = await queuePayloadProposal(
+        stream.governor,
+        stream.payerTokenAccount,
+        "cancel the grant",
+        [
+          {
+            index: 0,
+            programId: program.programId,
+            accounts: [],
+            data: Buffer.alloc(0),
+            selfCall: false,
+            holdUpSlots: new BN(0),
+            kind: { cancelStream: { stream: stream.stream } },
+          },
+        ],
+        1
+      );
+      await program.methods
+        .executeTransaction(new BN(1), 0)
+        .accountsPartial({ governor: stream.governor, proposal, executor: payer, dependency: null })
+        .remainingAccounts([
+          { pubkey: stream.stream, isSigner: false, isWritable: true },
+          { pubkey: stream.escrow, isSigner: false, isWritable: true },
+          { pubkey: stream.mint, isSigner: false, isWritable: false },
+          { pubkey: stream.vault, isSigner: false, isWritable: true },
+          { pubkey: stream.authority, isSigner: false, isWritable: false },
+          { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+          { pubkey: program.programId, isSigner: false, isWritable: false },
+        ])
+        .rpc();
+
+      const account = await program.account.paymentStream.fetch(stream.stream);
+      assert.isNotNull(account.canceledAt);
+      const returned = await stream.balance(stream.vault);
+      assert.isAbove(returned, 0);
+      assert.isBelow(returned, 1_000);
+
+      // What vested before the cancellation stays claimable, and nothing more.
+      await waitForSlot(stream.endSlot);
+      await stream.claim();
+      assert.equal(await stream.balance(stream.recipientTokenAccount) + returned, 1_000);
+      assert.equal(await stream.balance(stream.escrow), 0);
+    });
+  });
+
+  describe("sub-governors", () => {
+    it("creates a funded sub-DAO that passes its own proposals", async () => {
+      const { governor, governingMint, payerTokenAccount } = await initializeGovernor(0, 5);
+      const authority = authorityAddress(governor);
+      const [treasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), governor.toBuffer()],
+        program.programId
+      );
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: payer,
+            toPubkey: treasury,
+            lamports: 1_000_000_000,
+          })
+        )
+      );
+
+      const workingGroup = proposalType("working group", 1_000);
+      const typeCount = Buffer.alloc(4);
+      typeCount.writeUInt32LE(1);
+      const budget = 200_000_000;
+      const proposal = await queuePayloadProposal(governor, payerTokenAccount, "spin up a working group", [
+        {
+          index: 0,
+          programId: program.programId,
+          accounts: [],
+          data: Buffer.concat([typeCount, program.coder.types.encode("ProposalType", workingGroup)]),
+          selfCall: false,
+          holdUpSlots: new BN(0),
+          kind: {
+            createSubGovernor: {
+              config: {
+                governingMint,
+                manager: payer,
+                guardian: payer,
+                votingDelay: new BN(0),
+                votingPeriod: new BN(5),
+                proposalThreshold: new BN(1),
+                timelockDelay: new BN(0),
+                budgetLamports: new BN(budget),
+              },
+            },
+          },
+        },
+      ]);
+      const [child] = PublicKey.findProgramAddressSync(
+        [Buffer.from("sub-governor"), authority.toBuffer(), proposal.toBuffer(), Buffer.from([0, 0])],
+        program.programId
+      );
+      const [childTreasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), child.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .executeTransaction(new BN(0), 0)
+        .accountsPartial({ governor, proposal, executor: payer, dependency: null })
+        .remainingAccounts([
+          { pubkey: payer, isSigner: true, isWritable: true },
+          { pubkey: child, isSigner: false, isWritable: true },
+          { pubkey: governingMint, isSigner: false, isWritable: false },
+          { pubkey: treasury, isSigner: false, isWritable: true },
+          { pubkey: childTreasury, isSigner: false, isWritable: true },
+          { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+          { pubkey: program.programId, isSigner: false, isWritable: false },
+        ])
+        .rpc();
+
+      const account = await program.account.governor.fetch(child);
+      assert.ok(account.admin.equals(authority));
+      assert.equal(account.proposalTypes[0].name, "working group");
+      assert.equal(account.totalSupply.toNumber(), 1_000_000);
+      assert.equal(await provider.connection.getBalance(childTreasury), budget);
+
+      const description = "first working group proposal";
+      const childProposal = proposalAddress(child, 0);
+      await program.methods
+        .createProposal(description, 0, [], null, null, [], null)
+        .accountsPartial({
+          governor: child,
+          proposal: childProposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(child, description),
+        })
+        .rpc();
+      await voteAndQueue(child, payerTokenAccount, childProposal);
+      const { state } = await program.account.proposal.fetch(childProposal);
+      assert.ok(state.queued);
+    });
+  });
+
+  describe("treasury spending limits", () => {
+    it("rejects a payload that alone exceeds a limit set by governance", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const [treasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), governor.toBuffer()],
+        program.programId
+      );
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: payer,
+            toPubkey: treasury,
+            lamports: 1_000_000_000,
+          })
+        )
+      );
+      const [spendingLimit] = PublicKey.findProgramAddressSync(
+        [Buffer.from("spending-limit"), governor.toBuffer(), PublicKey.default.toBuffer()],
+        program.programId
+      );
+      await governanceCall(
+        governor,
+        payerTokenAccount,
+        0,
+        "limit lamport spending",
+        await program.methods
+          .setSpendingLimit(null, new BN(1_000_000), new BN(100_000_000))
+          .accountsPartial({
+            governor,
+            spendingLimit,
+            governorAuthority: authorityAddress(governor),
+            payer: treasury,
+          })
+          .instruction()
+      );
+      const limit = await program.account.spendingLimit.fetch(spendingLimit);
+      assert.equal(limit.maxAmount.toNumber(), 100_000_000);
+
+      const transfer = SystemProgram.transfer({
+        fromPubkey: treasury,
+        toPubkey: Keypair.generate().publicKey,
+        lamports: 100_000_001,
+      });
+      const instructions = [
+        {
+          index: 0,
+          programId: transfer.programId,
+          accounts: transfer.keys,
+          data: transfer.data,
+          selfCall: false,
+          holdUpSlots: new BN(0),
+          kind: { invoke: {} },
+        },
+      ];
+      const description = "overspend";
+      await expectError(
+        program.methods
+          .createProposal(description, 0, [], null, null, instructions, null)
+          .accountsPartial({
+            governor,
+            proposal: proposalAddress(governor, 1),
+            proposer: payer,
+            proposerTokenAccount: payerTokenAccount,
+            proposalDigest: digestAddress(governor, description, [], instructions),
+          })
+          .rpc(),
+        "EpochSpendingLimitExceeded"
+      );
+    });
+  });
+
+  describe("treasury vaults", () => {
+    it("registers a vault and closes it through governance once empty", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const authority = authorityAddress(governor);
+      const mint = await createMint(payer);
+      const [vault] = PublicKey.findProgramAddressSync(
+        [authority.toBuffer(), TOKEN_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+        ASSOCIATED_TOKEN_PROGRAM_ID
+      );
+      const [treasuryRegistry] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury-registry"), governor.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .createTreasuryVault()
+        .accountsPartial({
+          governor,
+          treasuryRegistry,
+          governorAuthority: authority,
+          mint,
+          vault,
+          creator: payer,
+          payer,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      let registry = await program.account.treasuryRegistry.fetch(treasuryRegistry);
+      assert.equal(registry.vaults.length, 1);
+      assert.ok(registry.vaults[0].address.equals(vault));
+
+      await expectError(
+        program.methods
+          .createTreasuryVault()
+          .accountsPartial({
+            governor,
+            treasuryRegistry,
+            governorAuthority: authority,
+            mint,
+            vault,
+            creator: payer,
+            payer,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .rpc(),
+        "TreasuryVaultExists"
+      );
+
+      await governanceCall(
+        governor,
+        payerTokenAccount,
+        0,
+        "close the empty vault",
+        await program.methods
+          .closeTreasuryVault()
+          .accountsPartial({
+            governor,
+            treasuryRegistry,
+            governorAuthority: authority,
+            mint,
+            vault,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .instruction()
+      );
+      registry = await program.account.treasuryRegistry.fetch(treasuryRegistry);
+      assert.equal(registry.vaults.length, 0);
+      assert.isNull(await provider.connection.getAccountInfo(vault));
+    });
+  });
+
+  describe("admin settings", () => {
+    it("lets only the admin retime future proposals", async () => {
+      const governor = Keypair.generate();
+      const manager = Keypair.generate();
+      const governingMint = await createMint(payer);
+      const payerTokenAccount = await createTokenAccount(governingMint, payer, 1_000_000);
+      await program.methods
+        .initialize(new BN(0), new BN(9_000), new BN(1), new BN(0), [proposalType("standard")])
+        .accountsPartial({
+          governor: governor.publicKey,
+          admin: payer,
+          manager: manager.publicKey,
+          guardian: payer,
+          governingMint,
+        })
+        .signers([governor])
+        .rpc();
+      // The payer is not the manager here, so it needs a checkpoint from an
+      // earlier slot to meet the proposal threshold.
+      await checkpointVotes(governor.publicKey, payerTokenAccount);
+      await waitForSlot(await provider.connection.getSlot());
+      const create = (id: number) =>
+        program.methods
+          .createProposal(`retimed ${id}`, 0, [], null, null, [], null)
+          .accountsPartial({
+            governor: governor.publicKey,
+            proposal: proposalAddress(governor.publicKey, id),
+            proposer: payer,
+            proposerTokenAccount: payerTokenAccount,
+            proposerCheckpoints: checkpointsAddress(governor.publicKey, payer),
+            proposalDigest: digestAddress(governor.publicKey, `retimed ${id}`),
+          })
+          .rpc();
+      await create(0);
+
+      const outsider = Keypair.generate();
+      for (const signer of [manager, outsider]) {
+        await expectError(
+          program.methods
+            .setVotingPeriod(new BN(18_000))
+            .accountsPartial({ governor: governor.publicKey, admin: signer.publicKey })
+            .signers([signer])
+            .rpc(),
+          "Unauthorized"
+        );
+        await expectError(
+          program.methods
+            .setProposalThreshold(new BN(0))
+            .accountsPartial({ governor: governor.publicKey, admin: signer.publicKey })
+            .signers([signer])
+            .rpc(),
+          "Unauthorized"
+        );
+      }
+      await expectError(
+        program.methods
+          .setVotingDelay(new BN(31 * 216_000))
+          .accountsPartial({ governor: governor.publicKey, admin: payer })
+          .rpc(),
+        "ParameterOutOfBounds"
+      );
+
+      await program.methods
+        .setVotingDelay(new BN(5))
+        .accountsPartial({ governor: governor.publicKey, admin: payer })
+        .rpc();
+      await program.methods
+        .setVotingPeriod(new BN(18_000))
+        .accountsPartial({ governor: governor.publicKey, admin: payer })
+        .rpc();
+      await program.methods
+        .setProposalThreshold(new BN(500_000))
+        .accountsPartial({ governor: governor.publicKey, admin: payer })
+        .rpc();
+      const account = await program.account.governor.fetch(governor.publicKey);
+      assert.equal(account.votingDelay.toNumber(), 5);
+      assert.equal(account.votingPeriod.toNumber(), 18_000);
+      assert.equal(account.proposalThreshold.toNumber(), 500_000);
+
+      await create(1);
+      const before = await program.account.proposal.fetch(proposalAddress(governor.publicKey, 0));
+      const after = await program.account.proposal.fetch(proposalAddress(governor.publicKey, 1));
+      assert.equal(before.endBlock.sub(before.startBlock).toNumber(), 9_000);
+      assert.equal(after.endBlock.sub(after.startBlock).toNumber(), 18_000);
+      assert.equal(after.startBlock.sub(after.createdSlot).toNumber(), 5);
+    });
+  });
+
+  describe("proposal types", () => {
+    it("starts with the initial types and lets the admin add more", async () => {
+      const governor = Keypair.generate();
+      const manager = Keypair.generate();
+      const governingMint = await createMint(payer);
+      const payerTokenAccount = await createTokenAccount(governingMint, payer, 1_000_000);
+      await program.methods
+        .initialize(new BN(0), new BN(9_000), new BN(1), new BN(0), [proposalType("routine")])
+        .accountsPartial({
+          governor: governor.publicKey,
+          admin: payer,
+          manager: manager.publicKey,
+          guardian: payer,
+          governingMint,
+        })
+        .signers([governor])
+        .rpc();
+      // The payer is not the manager here, so it needs a checkpoint from an
+      // earlier slot to meet the proposal threshold.
+      await checkpointVotes(governor.publicKey, payerTokenAccount);
+      await waitForSlot(await provider.connection.getSlot());
+      const create = (id: number, type: number) =>
+        program.methods
+          .createProposal(`typed ${id}`, type, [], null, null, [], null)
+          .accountsPartial({
+            governor: governor.publicKey,
+            proposal: proposalAddress(governor.publicKey, id),
+            proposer: payer,
+            proposerTokenAccount: payerTokenAccount,
+            proposerCheckpoints: checkpointsAddress(governor.publicKey, payer),
+            proposalDigest: digestAddress(governor.publicKey, `typed ${id}`),
+          })
+          .rpc();
+      await create(0, 0);
+      await expectError(create(1, 1), "InvalidProposalType");
+
+      await expectError(
+        program.methods
+          .addProposalType(proposalType("treasury", 2_000, 6_000))
+          .accountsPartial({ governor: governor.publicKey, admin: manager.publicKey })
+          .signers([manager])
+          .rpc(),
+        "Unauthorized"
+      );
+      await expectError(
+        program.methods
+          .addProposalType(proposalType("treasury", 10_001, 6_000))
+          .accountsPartial({ governor: governor.publicKey, admin: payer })
+          .rpc(),
+        "InvalidProposalType"
+      );
+      await expectError(
+        program.methods
+          .addProposalType(proposalType("x".repeat(33), 2_000, 6_000))
+          .accountsPartial({ governor: governor.publicKey, admin: payer })
+          .rpc(),
+        "InvalidProposalType"
+      );
+      // Every setting is checked, not just the bps values and name.
+      await expectError(
+        program.methods
+          .addProposalType({ ...proposalType("treasury", 2_000, 6_000), riskTier: 1 })
+          .accountsPartial({ governor: governor.publicKey, admin: payer })
+          .rpc(),
+        "InvalidRiskTier"
+      );
+      await expectError(
+        program.methods
+          .addProposalType({ ...proposalType("treasury", 2_000, 6_000), convictionMode: true })
+          .accountsPartial({ governor: governor.publicKey, admin: payer })
+          .rpc(),
+        "InvalidProposalType"
+      );
+      await program.methods
+        .addProposalType({
+          ...proposalType("treasury", 2_000, 6_000),
+          allowedPrograms: [SystemProgram.programId],
+        })
+        .accountsPartial({ governor: governor.publicKey, admin: payer })
+        .rpc();
+
+      const account = await program.account.governor.fetch(governor.publicKey);
+      assert.deepEqual(account.proposalTypes.map((type) => type.name), ["routine", "treasury"]);
+      assert.equal(account.proposalTypes[1].quorum, 2_000);
+      assert.equal(account.proposalTypes[1].approvalThreshold, 6_000);
+      assert.deepEqual(
+        account.proposalTypes[1].allowedPrograms.map((program) => program.toBase58()),
+        [SystemProgram.programId.toBase58()]
+      );
+      assert.deepEqual(account.activeProposalCounts, [1, 0]);
+      await create(1, 1);
+      assert.equal((await program.account.proposal.fetch(proposalAddress(governor.publicKey, 1))).proposalType, 1);
+    });
+
+    it("lets the admin update a type's settings and allowlist", async () => {
+      const governor = Keypair.generate();
+      const manager = Keypair.generate();
+      const governingMint = await createMint(payer);
+      const payerTokenAccount = await createTokenAccount(governingMint, payer, 1_000_000);
+      await program.methods
+        .initialize(new BN(0), new BN(9_000), new BN(1), new BN(0), [
+          proposalType("routine"),
+          proposalType("operational"),
+        ])
+        .accountsPartial({
+          governor: governor.publicKey,
+          admin: payer,
+          manager: manager.publicKey,
+          guardian: payer,
+          governingMint,
+        })
+        .signers([governor])
+        .rpc();
+      await checkpointVotes(governor.publicKey, payerTokenAccount);
+      await waitForSlot(await provider.connection.getSlot());
+      const update = (id: number, type: ReturnType<typeof proposalType>, admin = payer) =>
+        program.methods
+          .updateProposalType(id, type)
+          .accountsPartial({ governor: governor.publicKey, admin })
+          .rpc();
+      const operational = {
+        ...proposalType("operational", 1_000, 6_000),
+        allowedPrograms: [SystemProgram.programId],
+      };
+
+      await expectError(
+        program.methods
+          .updateProposalType(1, operational)
+          .accountsPartial({ governor: governor.publicKey, admin: manager.publicKey })
+          .signers([manager])
+          .rpc(),
+        "Unauthorized"
+      );
+      await expectError(update(2, operational), "InvalidProposalType");
+      await expectError(update(1, { ...operational, riskTier: 1 }), "InvalidRiskTier");
+      await expectError(
+        update(1, { ...operational, allowedPrograms: Array(9).fill(SystemProgram.programId) }),
+        "InvalidProposalType"
+      );
+      await update(1, operational);
+
+      let account = await program.account.governor.fetch(governor.publicKey);
+      assert.equal(account.proposalTypes[1].quorum, 1_000);
+      assert.equal(account.proposalTypes[1].approvalThreshold, 6_000);
+      assert.deepEqual(
+        account.proposalTypes[1].allowedPrograms.map((program) => program.toBase58()),
+        [SystemProgram.programId.toBase58()]
+      );
+
+      // The new allowlist applies to proposals created from now on.
+      const instruction = {
+        index: 0,
+        programId: TOKEN_PROGRAM_ID,
+        accounts: [],
+        data: Buffer.alloc(0),
+        selfCall: false,
+        holdUpSlots: new BN(0),
+        kind: { invoke: {} },
+      };
+      const create = (description: string) =>
+        program.methods
+          .createProposal(description, 1, [], null, null, [instruction], null)
+          .accountsPartial({
+            governor: governor.publicKey,
+            proposal: proposalAddress(governor.publicKey, 0),
+            proposer: payer,
+            proposerTokenAccount: payerTokenAccount,
+            proposerCheckpoints: checkpointsAddress(governor.publicKey, payer),
+            proposalDigest: digestAddress(governor.publicKey, description),
+          })
+          .rpc();
+      await expectError(create("call the token program"), "ProgramNotAllowed");
+
+      // Clearing the allowlist shrinks the account again and lifts the restriction.
+      const before = (await provider.connection.getAccountInfo(governor.publicKey)).data.length;
+      await update(1, proposalType("operational", 1_000, 6_000));
+      const after = (await provider.connection.getAccountInfo(governor.publicKey)).data.length;
+      assert.equal(before - after, 32);
+      await create("call the token program");
+      account = await program.account.governor.fetch(governor.publicKey);
+      assert.deepEqual(account.proposalTypes[1].allowedPrograms, []);
+    });
+  });
+
+  describe("self-governance", () => {
+    it("hands the admin role to the governor authority for good", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const authority = authorityAddress(governor);
+      await program.methods
+        .transferAdmin(authority)
+        .accountsPartial({ governor, admin: payer })
+        .rpc();
+      await governanceCall(
+        governor,
+        payerTokenAccount,
+        0,
+        "accept the admin role",
+        await program.methods
+          .acceptAdmin()
+          .accountsPartial({ governor, newAdmin: authority })
+          .instruction()
+      );
+      await governanceCall(
+        governor,
+        payerTokenAccount,
+        1,
+        "lock the admin role",
+        await program.methods
+          .lockAdmin()
+          .accountsPartial({ governor, admin: authority })
+          .instruction()
+      );
+      let account = await program.account.governor.fetch(governor);
+      assert.ok(account.admin.equals(authority));
+      assert.ok(account.adminLocked);
+
+      // Settings now only change through proposals.
+      await expectError(
+        program.methods
+          .setBlackoutManagerExempt(true)
+          .accountsPartial({ governor, admin: payer })
+          .rpc(),
+        "Unauthorized"
+      );
+      await governanceCall(
+        governor,
+        payerTokenAccount,
+        2,
+        "exempt the manager from blackouts",
+        await program.methods
+          .setBlackoutManagerExempt(true)
+          .accountsPartial({ governor, admin: authority })
+          .instruction()
+      );
+      const actions = [
+        { parameterChange: { key: { votingPeriod: {} }, value: new BN(9_000) } },
+      ];
+      const description = "lengthen voting";
+      const proposal = proposalAddress(governor, 3);
+      await program.methods
+        .createProposal(description, 0, actions, null, null, [], null)
+        .accountsPartial({
+          governor,
+          proposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, description, actions),
+        })
+        .rpc();
+      await voteAndQueue(governor, payerTokenAccount, proposal, 3);
+      await program.methods
+        .executeProposal(new BN(3))
+        .accountsPartial({ governor, proposal, executor: payer, dependency: null })
+        .rpc();
+      account = await program.account.governor.fetch(governor);
+      assert.ok(account.blackoutManagerExempt);
+      assert.equal(account.votingPeriod.toNumber(), 9_000);
+
+      await expectError(
+        governanceCall(
+          governor,
+          payerTokenAccount,
+          4,
+          "hand the admin role back",
+          await program.methods
+            .transferAdmin(payer)
+            .accountsPartial({ governor, admin: authority })
+            .instruction()
+        ),
+        "AdminTransferLocked"
+      );
+    });
+  });
+
+  describe("execution block", () => {
+    it("freezes execution without reviving a proposal that expired meanwhile", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const actions = [
+        { parameterChange: { key: { executionDeadlineSlots: {} }, value: new BN(10) } },
+      ];
+      const description = "set an execution deadline";
+      const deadline = proposalAddress(governor, 0);
+      await program.methods
+        .createProposal(description, 0, actions, null, null, [], null)
+        .accountsPartial({
+          governor,
+          proposal: deadline,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, description, actions),
+        })
+        .rpc();
+      await voteAndQueue(governor, payerTokenAccount, deadline);
+      await program.methods
+        .executeProposal(new BN(0))
+        .accountsPartial({ governor, proposal: deadline, executor: payer, dependency: null })
+        .rpc();
+
+      const proposal = proposalAddress(governor, 1);
+      await program.methods
+        .createProposal("under investigation", 0, [], null, null, [], null)
+        .accountsPartial({
+          governor,
+          proposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, "under investigation"),
+        })
+        .rpc();
+      await voteAndQueue(governor, payerTokenAccount, proposal, 1);
+
+      await program.methods
+        .blockExecution(new BN(1), Array(32).fill(7))
+        .accountsPartial({ governor, proposal, blocker: payer })
+        .rpc();
+      let account = await program.account.proposal.fetch(proposal);
+      assert.ok(account.executionBlocked);
+      assert.ok(account.state.queued);
+      await expectError(
+        program.methods
+          .executeProposal(new BN(1))
+          .accountsPartial({ governor, proposal, executor: payer, dependency: null })
+          .rpc(),
+        "ExecutionBlocked"
+      );
+
+      await waitForSlot(account.expiresAt.toNumber() + 1);
+      await program.methods
+        .expireProposal(new BN(1))
+        .accountsPartial({ governor, proposal })
+        .rpc();
+      await program.methods
+        .unblockExecution(new BN(1))
+        .accountsPartial({ governor, proposal, blocker: payer })
+        .rpc();
+      account = await program.account.proposal.fetch(proposal);
+      assert.notOk(account.executionBlocked);
+      assert.ok(account.state.expired);
+      // Expiry recorded an outcome, which execution rejects.
+      await expectError(
+        program.methods
+          .executeProposal(new BN(1))
+          .accountsPartial({ governor, proposal, executor: payer, dependency: null })
+          .rpc(),
+        "ProposalDefeated"
+      );
+    });
+  });
+
+  describe("early expiry", () => {
+    it("expires a proposal once the power left to vote cannot reach quorum", async () => {
+      const { governor, governingMint, payerTokenAccount } = await initializeGovernor(0);
+      const description = "out of reach";
+      const proposal = proposalAddress(governor, 0);
+      await program.methods
+        .createProposal(description, 0, [], null, null, [], null)
+        .accountsPartial({
+          governor,
+          proposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, description),
+        })
+        .rpc();
+      const expire = () =>
+        program.methods.expireProposal(new BN(0)).accountsPartial({ governor, proposal }).rpc();
+      await expectError(expire(), "ProposalNotExpirable");
+
+      // The payer checkpointed all 1_000_000 tokens but moves 990_000 of them to a
+      // wallet without a checkpoint before voting. Only 10_000 count while the
+      // whole 1_000_000 is spent, which leaves the 40_000 quorum out of reach.
+      const elsewhere = await createTokenAccount(governingMint, Keypair.generate().publicKey, 0);
+      // Transfer: [3, amount]
+      const data = Buffer.alloc(9);
+      data.writeUInt8(3, 0);
+      data.writeBigUInt64LE(BigInt(990_000), 1);
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          new TransactionInstruction({
+            programId: TOKEN_PROGRAM_ID,
+            keys: [
+              { pubkey: payerTokenAccount, isSigner: false, isWritable: true },
+              { pubkey: elsewhere, isSigner: false, isWritable: true },
+              { pubkey: payer, isSigner: true, isWritable: false },
+            ],
+            data,
+          })
+        )
+      );
+      const vote = voteAddress(governor, proposal, payer);
+      await program.methods
+        .castVote(new BN(0), true)
+        .accountsPartial({
+          governor,
+          proposal,
+          vote,
+          voter: payer,
+          voterTokenAccount: payerTokenAccount,
+          voterCheckpoints: checkpointsAddress(governor, payer),
+        })
+        .rpc();
+      assert.equal((await program.account.vote.fetch(vote)).weight.toNumber(), 10_000);
+
+      await expire();
+      const account = await program.account.proposal.fetch(proposal);
+      assert.ok(account.state.expired);
+      assert.equal(account.spentWeight.toNumber(), 1_000_000);
+    });
+  });
+
+  describe("payload lookup tables", () => {
+    it("executes a payload whose accounts only fit through its lookup table", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const proposal = proposalAddress(governor, 0);
+      const proposalId = new BN(0);
+      const [treasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), governor.toBuffer()],
+        program.programId
+      );
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({ fromPubkey: payer, toPubkey: treasury, lamports: 10_000_000 })
+        )
+      );
+
+      // Four treasury transfers, each dragging twelve extra accounts the system
+      // program ignores: too many keys for one legacy transaction.
+      await program.methods
+        .createProposalDraft("wide payload", 0, [], null, null)
+        .accountsPartial({ governor, proposal, proposer: payer })
+        .rpc();
+      const transfer = Buffer.alloc(12);
+      transfer.writeUInt32LE(2);
+      transfer.writeBigUInt64LE(1_000n, 4);
+      const payload = [];
+      for (let index = 0; index < 4; index++) {
+        const accounts = [
+          { pubkey: treasury, isSigner: true, isWritable: true },
+          { pubkey: payer, isSigner: false, isWritable: true },
+          ...Array.from({ length: 12 }, () => ({
+            pubkey: Keypair.generate().publicKey,
+            isSigner: false,
+            isWritable: false,
+          })),
+        ];
+        payload.push(accounts);
+        await program.methods
+          .addProposalInstruction(
+            proposalId,
+            index,
+            SystemProgram.programId,
+            accounts,
+            transfer,
+            false,
+            new BN(0),
+            { invoke: {} }
+          )
+          .accountsPartial({ governor, proposal, proposer: payer })
+          .rpc();
+      }
+      await program.methods
+        .activateProposal(proposalId)
+        .accountsPartial({
+          governor,
+          proposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: await draftDigestAddress(governor, proposal),
+        })
+        .rpc();
+
+      const recentSlot = await provider.connection.getSlot();
+      const slotBytes = Buffer.alloc(8);
+      slotBytes.writeBigUInt64LE(BigInt(recentSlot));
+      const [lookupTable] = PublicKey.findProgramAddressSync(
+        [authorityAddress(governor).toBuffer(), slotBytes],
+        AddressLookupTableProgram.programId
+      );
+      await program.methods
+        .createPayloadLookupTable(proposalId, new BN(recentSlot))
+        .accountsPartial({ governor, proposal, proposer: payer, lookupTable })
+        .rpc();
+      const account = await program.account.proposal.fetch(proposal);
+      assert.equal(account.lookupTable.toBase58(), lookupTable.toBase58());
+
+      await voteAndQueue(governor, payerTokenAccount, proposal);
+      // Entries become usable the slot after they were added.
+      await waitForSlot((await provider.connection.getSlot()) + 1);
+
+      const execute = await program.methods
+        .executeAllTransactions(proposalId)
+        .accountsPartial({ governor, proposal, executor: payer, dependency: null })
+        .remainingAccounts(
+          payload.flatMap((accounts) => [
+            ...accounts.map((meta) => ({ ...meta, isSigner: false })),
+            { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+          ])
+        )
+        .instruction();
+      const { blockhash } = await provider.connection.getLatestBlockhash();
+      const message = (tables: AddressLookupTableAccount[] = []) =>
+        new TransactionMessage({
+          payerKey: payer,
+          recentBlockhash: blockhash,
+          instructions: [execute],
+        }).compileToV0Message(tables);
+
+      assert.throws(() => new VersionedTransaction(message()).serialize());
+      const table = (await provider.connection.getAddressLookupTable(lookupTable)).value;
+      const transaction = await provider.wallet.signTransaction(
+        new VersionedTransaction(message([table]))
+      );
+      await provider.connection.confirmTransaction(
+        await provider.connection.sendTransaction(transaction)
+      );
+
+      assert.ok((await program.account.proposal.fetch(proposal)).executed);
+    });
+  });
+
+  describe("timelock", () => {
+    async function createTimelock(minDelay: number, maxDelay: number, proposers: PublicKey[]) {
+      const base = Keypair.generate();
+      const [timelock] = PublicKey.findProgramAddressSync(
+        [Buffer.from("timelock"), base.publicKey.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .createTimelock(new BN(minDelay), new BN(maxDelay), proposers)
+        .accountsPartial({ timelock, base: base.publicKey, payer })
+        .signers([base])
+        .rpc();
+      return timelock;
+    }
+
+    it("holds a governor's payload until its operation is ready", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5, 20);
+      const timelock = await createTimelock(10, 100, [authorityAddress(governor)]);
+      await program.methods
+        .setTimelock(timelock)
+        .accountsPartial({ governor, admin: payer })
+        .rpc();
+      const [treasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), governor.toBuffer()],
+        program.programId
+      );
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({ fromPubkey: payer, toPubkey: treasury, lamports: 10_000_000 })
+        )
+      );
+      const transfer = SystemProgram.transfer({
+        fromPubkey: treasury,
+        toPubkey: payer,
+        lamports: 1_000,
+      });
+      const instruction = {
+        index: 0,
+        programId: transfer.programId,
+        accounts: transfer.keys,
+        data: transfer.data,
+        selfCall: false,
+        holdUpSlots: new BN(0),
+        kind: { invoke: {} },
+      };
+      const description = "pay through the timelock";
+      const proposal = proposalAddress(governor, 0);
+      await program.methods
+        .createProposal(description, 0, [], null, null, [instruction], null)
+        .accountsPartial({
+          governor,
+          proposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, description, [], [instruction]),
+        })
+        .rpc();
+      await voteAndQueue(governor, payerTokenAccount, proposal, 0, timelock);
+
+      const { eta } = await program.account.proposal.fetch(proposal);
+      let account = await program.account.timelock.fetch(timelock);
+      assert.equal(account.operations.length, 1);
+      assert.equal(account.operations[0].eta.toNumber(), eta.toNumber());
+
+      await waitForSlot(eta.toNumber());
+      await expectError(
+        executeTransaction(governor, proposal, 0, instruction),
+        "InvalidTimelock"
+      );
+      await program.methods
+        .executeTransaction(new BN(0), 0)
+        .accountsPartial({ governor, proposal, executor: payer, dependency: null, timelock })
+        .remainingAccounts([
+          ...instruction.accounts.map((meta) => ({ ...meta, isSigner: false })),
+          { pubkey: instruction.programId, isSigner: false, isWritable: false },
+        ])
+        .rpc();
+      account = await program.account.timelock.fetch(timelock);
+      assert.isEmpty(account.operations);
+    });
+
+    it("changes its delay bounds only through an executed operation", async () => {
+      const timelock = await createTimelock(0, 100, [payer]);
+      const [vault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("timelock-vault"), timelock.toBuffer()],
+        program.programId
+      );
+      const update = await program.methods
+        .updateTimelockDelays(new BN(5), new BN(50))
+        .accountsPartial({ timelock, vault })
+        .instruction();
+      const accounts = update.keys.map(({ pubkey, isSigner, isWritable }) => ({
+        pubkey,
+        isSigner,
+        isWritable,
+      }));
+      const length = Buffer.alloc(4);
+      length.writeUInt32LE(accounts.length);
+      const hash = createHash("sha256")
+        .update(program.programId.toBuffer())
+        .update(length)
+        .update(
+          Buffer.concat(accounts.map((meta) => program.coder.types.encode("AccountMetaData", meta)))
+        )
+        .update(update.data)
+        .digest();
+
+      await program.methods
+        .queueOperation(Array.from(hash), new BN(0))
+        .accountsPartial({ timelock, proposer: payer })
+        .rpc();
+      await program.methods
+        .executeOperation(program.programId, accounts, update.data)
+        .accountsPartial({ timelock, executor: payer })
+        .remainingAccounts([
+          ...accounts.map((meta) => ({ ...meta, isSigner: false })),
+          { pubkey: program.programId, isSigner: false, isWritable: false },
+        ])
+        .rpc();
+
+      const account = await program.account.timelock.fetch(timelock);
+      assert.equal(account.minDelay.toNumber(), 5);
+      assert.equal(account.maxDelay.toNumber(), 50);
+      assert.isEmpty(account.operations);
+      await expectError(
+        program.methods
+          .queueOperation(Array.from(hash), new BN(0))
+          .accountsPartial({ timelock, proposer: payer })
+          .rpc(),
+        "TimelockDelayOutOfBounds"
+      );
+    });
+  });
+
+  describe("rage-quit", () => {
+    it("pays an Against voter their share before the proposal executes", async () => {
+      const { governor, governingMint, payerTokenAccount } =
+        await initializeGovernor(0, 5, 1_000);
+      const [treasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), governor.toBuffer()],
+        program.programId
+      );
+      const member = Keypair.generate();
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: payer,
+            toPubkey: treasury,
+            lamports: 1_100_000_000,
+          }),
+          SystemProgram.transfer({
+            fromPubkey: payer,
+            toPubkey: member.publicKey,
+            lamports: 100_000_000,
+          })
+        )
+      );
+      // The member holds 100_000 of the 1_100_000 supply.
+      const memberTokenAccount = await createTokenAccount(
+        governingMint,
+        member.publicKey,
+        100_000
+      );
+      await checkpointVotes(governor, memberTokenAccount, member);
+
+      const description = "spend over a minority's objection";
+      const proposal = proposalAddress(governor, 0);
+      await program.methods
+        .createProposal(description, 0, [], null, null, [], null)
+        .accountsPartial({
+          governor,
+          proposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          proposalDigest: digestAddress(governor, description),
+        })
+        .rpc();
+      const vote = voteAddress(governor, proposal, member.publicKey);
+      await program.methods
+        .castVote(new BN(0), false)
+        .accountsPartial({
+          governor,
+          proposal,
+          vote,
+          voter: member.publicKey,
+          voterTokenAccount: memberTokenAccount,
+          voterCheckpoints: checkpointsAddress(governor, member.publicKey),
+        })
+        .signers([member])
+        .rpc();
+      await voteAndQueue(governor, payerTokenAccount, proposal);
+
+      const spare =
+        (await provider.connection.getBalance(treasury)) -
+        (await provider.connection.getMinimumBalanceForRentExemption(0));
+      const before = await provider.connection.getBalance(member.publicKey);
+      await program.methods
+        .rageQuit(new BN(0))
+        .accountsPartial({
+          governor,
+          proposal,
+          vote,
+          voter: member.publicKey,
+          voterTokenAccount: memberTokenAccount,
+          governingMint,
+          treasuryRegistry: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([member])
+        .rpc();
+
+      const balance = await provider.connection.getTokenAccountBalance(memberTokenAccount);
+      assert.equal(balance.value.amount, "0");
+      const governorAccount = await program.account.governor.fetch(governor);
+      assert.equal(governorAccount.totalSupply.toNumber(), 1_000_000);
+      const { againstVotes } = await program.account.proposal.fetch(proposal);
+      assert.equal(againstVotes.toNumber(), 0);
+      // Its share of the treasury plus the closed vote's rent.
+      const share = Math.floor((spare * 100_000) / 1_100_000);
+      assert.isAtLeast(
+        (await provider.connection.getBalance(member.publicKey)) - before,
+        share
+      );
+      assert.isNull(await provider.connection.getAccountInfo(vote));
+    });
+  });
+
+  describe("manager allowance", () => {
+    it("lets the manager spend up to the budget set by governance", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const [treasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), governor.toBuffer()],
+        program.programId
+      );
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: payer,
+            toPubkey: treasury,
+            lamports: 1_000_000_000,
+          })
+        )
+      );
+      const native = PublicKey.default;
+      await governanceCall(
+        governor,
+        payerTokenAccount,
+        0,
+        "manager budget",
+        await program.methods
+          .setAllowance(native, new BN(1_000_000), new BN(50_000_000))
+          .accountsPartial({
+            governor,
+            governorAuthority: authorityAddress(governor),
+            payer: treasury,
+          })
+          .instruction()
+      );
+
+      const recipient = Keypair.generate().publicKey;
+      const spend = (lamports: number) =>
+        program.methods
+          .managerSpend(recipient, native, new BN(lamports))
+          .accountsPartial({
+            governor,
+            manager: payer,
+            spendingLimit: null,
+            recipient,
+            treasuryTokenAccount: null,
+            mint: null,
+            governorAuthority: null,
+            tokenProgram: null,
+          })
+          .rpc();
+
+      await spend(30_000_000);
+      assert.equal(await provider.connection.getBalance(recipient), 30_000_000);
+      await expectError(spend(20_000_001), "AllowanceExceeded");
+      await spend(20_000_000);
+      assert.equal(await provider.connection.getBalance(recipient), 50_000_000);
+    });
+  });
+
+  describe("treasurer", () => {
+    it("spends within the budget until revoked by the manager", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const [treasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), governor.toBuffer()],
+        program.programId
+      );
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: payer,
+            toPubkey: treasury,
+            lamports: 1_000_000_000,
+          })
+        )
+      );
+      const treasurer = Keypair.generate();
+      await governanceCall(
+        governor,
+        payerTokenAccount,
+        0,
+        "appoint a treasurer",
+        await program.methods
+          .setTreasurer(treasurer.publicKey, PublicKey.default, new BN(40_000_000), new BN(1_000_000))
+          .accountsPartial({
+            governor,
+            governorAuthority: authorityAddress(governor),
+            payer: treasury,
+          })
+          .instruction()
+      );
+
+      const recipient = Keypair.generate().publicKey;
+      const spend = (lamports: number) =>
+        program.methods
+          .treasurerSpend(recipient, new BN(lamports))
+          .accountsPartial({
+            governor,
+            treasurer: treasurer.publicKey,
+            spendingLimit: null,
+            recipient,
+            treasuryTokenAccount: null,
+            mint: null,
+            governorAuthority: null,
+            tokenProgram: null,
+          })
+          .signers([treasurer])
+          .rpc();
+
+      await spend(25_000_000);
+      assert.equal(await provider.connection.getBalance(recipient), 25_000_000);
+      await expectError(spend(15_000_001), "TreasurerBudgetExceeded");
+
+      await program.methods
+        .revokeTreasurer()
+        .accountsPartial({ governor, revoker: payer })
+        .rpc();
+      await expectError(spend(1_000_000), "Unauthorized");
+    });
+  });
+
+  describe("milestone grants", () => {
+    // Funds a treasury vault with 600 tokens and passes proposal 0, creating a grant
+    // of 100, 200 and 300 tokens to a fresh recipient.
+    async function createGrant(managerAttestation: boolean) {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const authority = authorityAddress(governor);
+      const [treasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), governor.toBuffer()],
+        program.programId
+      );
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: payer,
+            toPubkey: treasury,
+            lamports: 1_000_000_000,
+          })
+        )
+      );
+      const mint = await createMint(payer);
+      const [vault] = PublicKey.findProgramAddressSync(
+        [authority.toBuffer(), TOKEN_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+        ASSOCIATED_TOKEN_PROGRAM_ID
+      );
+      await program.methods
+        .createTreasuryTokenAccount()
+        .accountsPartial({
+          governor,
+          governorAuthority: authority,
+          mint,
+          treasuryTokenAccount: vault,
+          payer,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      await provider.sendAndConfirm(
+        new Transaction().add(mintToInstruction(mint, vault, 600))
+      );
+      const recipient = Keypair.generate().publicKey;
+      const recipientTokenAccount = await createTokenAccount(mint, recipient, 0);
+
+      const [grant] = PublicKey.findProgramAddressSync(
+        [Buffer.from("grant"), governor.toBuffer(), new BN(0).toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      await governanceCall(
+        governor,
+        payerTokenAccount,
+        0,
+        "fund a grant",
+        await program.methods
+          .createGrant(
+            new BN(0),
+            recipient,
+            mint,
+            [new BN(100), new BN(200), new BN(300)],
+            managerAttestation
+          )
+          .accountsPartial({ governor, grant, governorAuthority: authority, payer: treasury })
+          .instruction()
+      );
+
+      const approve = (index: number, approver: PublicKey = payer) =>
+        program.methods
+          .approveMilestone(index)
+          .accountsPartial({ governor, grant, approver })
+          .instruction();
+      const claim = (index: number) =>
+        program.methods
+          .claimTranche(index)
+          .accountsPartial({
+            governor,
+            grant,
+            mint,
+            treasuryTokenAccount: vault,
+            recipientTokenAccount,
+            governorAuthority: authority,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .rpc();
+      const balance = async (account: PublicKey) =>
+        Number((await provider.connection.getTokenAccountBalance(account)).value.amount);
+      return {
+        governor,
+        payerTokenAccount,
+        authority,
+        grant,
+        vault,
+        recipientTokenAccount,
+        approve,
+        claim,
+        balance,
+      };
+    }
+
+    it("releases each tranche only after its milestone is approved in order", async () => {
+      const { approve, claim, balance, recipientTokenAccount } = await createGrant(true);
+
+      await expectError(
+        provider.sendAndConfirm(new Transaction().add(await approve(1))),
+        "MilestoneOutOfOrder"
+      );
+      await expectError(claim(0), "MilestoneNotApproved");
+
+      await provider.sendAndConfirm(new Transaction().add(await approve(0)));
+      await claim(0);
+      assert.equal(await balance(recipientTokenAccount), 100);
+      await expectError(claim(0), "TrancheAlreadyClaimed");
+      await expectError(claim(1), "MilestoneNotApproved");
+    });
+
+    it("requires a follow-up proposal when manager attestation is off", async () => {
+      const grant = await createGrant(false);
+
+      await expectError(
+        provider.sendAndConfirm(new Transaction().add(await grant.approve(0))),
+        "Unauthorized"
+      );
+      await governanceCall(
+        grant.governor,
+        grant.payerTokenAccount,
+        1,
+        "approve the first milestone",
+        await grant.approve(0, grant.authority)
+      );
+      await grant.claim(0);
+      assert.equal(await grant.balance(grant.recipientTokenAccount), 100);
+    });
+
+    it("keeps unreleased tranches in the treasury once canceled", async () => {
+      const grant = await createGrant(true);
+      await provider.sendAndConfirm(new Transaction().add(await grant.approve(0)));
+
+      await governanceCall(
+        grant.governor,
+        grant.payerTokenAccount,
+        1,
+        "cancel the grant",
+        await program.methods
+          .cancelGrant()
+          .accountsPartial({
+            governor: grant.governor,
+            grant: grant.grant,
+            governorAuthority: grant.authority,
+          })
+          .instruction()
+      );
+      await expectError(
+        provider.sendAndConfirm(new Transaction().add(await grant.approve(1))),
+        "GrantAlreadyCanceled"
+      );
+
+      // The milestone approved before the cancellation stays claimable.
+      await grant.claim(0);
+      assert.equal(await grant.balance(grant.recipientTokenAccount), 100);
+      assert.equal(await grant.balance(grant.vault), 500);
+    });
+  });
+
+  // Needs the Squads v4 program and its program config, cloned from mainnet in Anchor.toml.
+  describe("squads adapter", () => {
+    const SQUADS_PROGRAM_ID = new PublicKey(
+      "SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf"
+    );
+    const squadsAddress = (...seeds: Buffer[]) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("multisig"), ...seeds],
+        SQUADS_PROGRAM_ID
+      )[0];
+    const sighash = (name: string) =>
+      createHash("sha256").update(`global:${name}`).digest().subarray(0, 8);
+    const u32 = (value: number) => {
+      const buffer = Buffer.alloc(4);
+      buffer.writeUInt32LE(value);
+      return buffer;
+    };
+
+    // A threshold-one multisig whose members are given as [key, permission mask].
+    async function createMultisig(members: [PublicKey, number][]) {
+      const createKey = Keypair.generate();
+      const programConfig = squadsAddress(Buffer.from("program_config"));
+      const config = await provider.connection.getAccountInfo(programConfig);
+      const multisig = squadsAddress(
+        Buffer.from("multisig"),
+        createKey.publicKey.toBuffer()
+      );
+      // MultisigCreateArgsV2: no config authority, threshold 1, members, no
+      // time lock, rent collector or memo.
+      const data = Buffer.concat([
+        sighash("multisig_create_v2"),
+        Buffer.from([0, 1, 0]),
+        u32(members.length),
+        ...members.map(([key, mask]) =>
+          Buffer.concat([key.toBuffer(), Buffer.from([mask])])
+        ),
+        u32(0),
+        Buffer.from([0, 0]),
+      ]);
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          new TransactionInstruction({
+            programId: SQUADS_PROGRAM_ID,
+            keys: [
+              { pubkey: programConfig, isSigner: false, isWritable: false },
+              // ProgramConfig: discriminator, authority, creation fee, treasury.
+              {
+                pubkey: new PublicKey(config.data.subarray(48, 80)),
+                isSigner: false,
+                isWritable: true,
+              },
+              { pubkey: multisig, isSigner: false, isWritable: true },
+              { pubkey: createKey.publicKey, isSigner: true, isWritable: false },
+              { pubkey: payer, isSigner: true, isWritable: true },
+              { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+            ],
+            data,
+          })
+        ),
+        [createKey]
+      );
+      return multisig;
+    }
+
+    // A vault transaction message sending one lamport from vault 0 to the payer.
+    function transferMessage(multisig: PublicKey): Buffer {
+      const vault = squadsAddress(multisig.toBuffer(), Buffer.from("vault"), Buffer.from([0]));
+      const transfer = Buffer.alloc(12);
+      transfer.writeUInt32LE(2);
+      transfer.writeBigUInt64LE(1n, 4);
+      return Buffer.concat([
+        Buffer.from([1, 1, 1, 3]),
+        vault.toBuffer(),
+        payer.toBuffer(),
+        SystemProgram.programId.toBuffer(),
+        Buffer.from([1, 2, 2, 0, 1, 12, 0]),
+        transfer,
+        Buffer.from([0]),
+      ]);
+    }
+
+    it("creates and approves a vault transaction as an ordinary member", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const authority = authorityAddress(governor);
+      const [treasury] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury"), governor.toBuffer()],
+        program.programId
+      );
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: payer,
+            toPubkey: treasury,
+            lamports: 100_000_000,
+          })
+        )
+      );
+      // Initiate and Vote only; the payer keeps Execute.
+      const multisig = await createMultisig([
+        [authority, 0b011],
+        [payer, 0b111],
+      ]);
+      const message = transferMessage(multisig);
+      const index = Buffer.alloc(8);
+      index.writeBigUInt64LE(1n);
+      const transaction = squadsAddress(multisig.toBuffer(), Buffer.from("transaction"), index);
+      const squadsProposal = squadsAddress(
+        multisig.toBuffer(),
+        Buffer.from("transaction"),
+        index,
+        Buffer.from("proposal")
+      );
+
+      // Mirrors the payload propose_squads_action builds.
+      const meta = (pubkey: PublicKey, isSigner: boolean, isWritable: boolean) => ({
+        pubkey,
+        isSigner,
+        isWritable,
+      });
+      const payload = [
+        {
+          accounts: [
+            meta(multisig, false, true),
+            meta(transaction, false, true),
+            meta(authority, true, false),
+            meta(treasury, true, true),
+            meta(SystemProgram.programId, false, false),
+          ],
+          data: Buffer.concat([
+            sighash("vault_transaction_create"),
+            Buffer.from([0, 0]),
+            u32(message.length),
+            message,
+            Buffer.from([0]),
+          ]),
+        },
+        {
+          accounts: [
+            meta(multisig, false, false),
+            meta(squadsProposal, false, true),
+            meta(authority, true, false),
+            meta(treasury, true, true),
+            meta(SystemProgram.programId, false, false),
+          ],
+          data: Buffer.concat([sighash("proposal_create"), index, Buffer.from([0])]),
+        },
+        {
+          accounts: [
+            meta(multisig, false, false),
+            meta(authority, true, true),
+            meta(squadsProposal, false, true),
+          ],
+          data: Buffer.concat([sighash("proposal_approve"), Buffer.from([0])]),
+        },
+      ].map((instruction, i) => ({
+        index: i,
+        programId: SQUADS_PROGRAM_ID,
+        ...instruction,
+        selfCall: false,
+        holdUpSlots: new BN(0),
+        kind: { invoke: {} },
+      }));
+      const description = "approve a squads vault transfer";
+      const proposal = proposalAddress(governor, 0);
+      await program.methods
+        .proposeSquadsAction(description, 0, 0, message)
+        .accountsPartial({
+          governor,
+          proposal,
+          proposer: payer,
+          proposerTokenAccount: payerTokenAccount,
+          multisig,
+          proposalDigest: digestAddress(governor, description, [], payload),
+        })
+        .rpc();
+
+      await voteAndQueue(governor, payerTokenAccount, proposal);
+      for (const instruction of payload) {
+        await executeTransaction(governor, proposal, instruction.index, instruction);
+      }
+
+      // Squads Proposal: discriminator, multisig, transaction index, then the status
+      // variant, where 3 is Approved.
+      const approved = await provider.connection.getAccountInfo(squadsProposal);
+      assert.equal(approved.data[48], 3);
+      assert.isNotNull(await provider.connection.getAccountInfo(transaction));
+    });
+
+    it("rejects a multisig the governor authority cannot vote on", async () => {
+      const { governor, payerTokenAccount } = await initializeGovernor(0, 5);
+      const multisig = await createMultisig([
+        [authorityAddress(governor), 0b001],
+        [payer, 0b111],
+      ]);
 
-  it("Is initialized!", async () => {
-    // Add your test here.
-    const tx = await program.methods.initialize().rpc();
-    console.log("Your transaction signature", tx);
+      await expectError(
+        program.methods
+          .proposeSquadsAction("initiate only", 0, 0, transferMessage(multisig))
+          .accountsPartial({
+            governor,
+            proposal: proposalAddress(governor, 0),
+            proposer: payer,
+            proposerTokenAccount: payerTokenAccount,
+            multisig,
+            proposalDigest: PublicKey.default,
+          })
+          .rpc(),
+        "NotSquadsMember"
+      );
+    });
   });
 });